@@ -1,13 +1,21 @@
 //! OVATool CLI - Export VMware VMs to OVA format.
 
 use std::path::PathBuf;
+use std::process::ExitCode;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::{Parser, Subcommand, ValueEnum};
 use indicatif::{ProgressBar, ProgressStyle};
+use ovatool_core::compare::compare_ovas;
+use ovatool_core::diagnose::{diagnose, Severity};
+use ovatool_core::ovf::GUEST_OS_MAPPINGS;
+use ovatool_core::pipeline::ADAPTIVE_RATIO_THRESHOLD;
+use ovatool_core::vmdk::{verify_stream_vmdk, ExtentAccess};
 use ovatool_core::{
-    export_vm, get_vm_info, CompressionLevel, ExportOptions, ExportPhase, ExportProgress,
+    export_vm, export_vm_with_ovf, get_ova_info, get_vm_info, import_ova, plan_export,
+    CompressionLevel, ExportOptions, ExportPhase, ExportProgress, ManifestAlgorithm, OvaOrder,
 };
 
 /// Fast, multithreaded tool for exporting VMware VMs to OVA format.
@@ -20,6 +28,8 @@ struct Cli {
 }
 
 #[derive(Subcommand)]
+// Parsed once per run, so boxing the export arguments would buy nothing
+#[allow(clippy::large_enum_variant)]
 enum Commands {
     /// Export a VMware VM to OVA format.
     Export {
@@ -30,10 +40,15 @@ enum Commands {
         #[arg(short, long)]
         output: Option<PathBuf>,
 
-        /// Compression level (fast, balanced, max).
+        /// Compression level (fast, balanced, max, adaptive).
         #[arg(short, long, value_enum, default_value = "balanced")]
         compression: CompressionArg,
 
+        /// Compressed-to-original size ratio above which adaptive compression
+        /// drops to fast for the rest of a disk.
+        #[arg(long, value_name = "RATIO", default_value_t = ADAPTIVE_RATIO_THRESHOLD)]
+        adaptive_threshold: f64,
+
         /// Number of threads to use (0 = auto-detect).
         #[arg(short, long, default_value = "0")]
         threads: usize,
@@ -45,13 +60,119 @@ enum Commands {
         /// Suppress progress output.
         #[arg(short, long)]
         quiet: bool,
+
+        /// Show detailed output, including time spent in each phase.
+        #[arg(short, long, conflicts_with = "quiet")]
+        verbose: bool,
+
+        /// Export a zero-filled placeholder for disks whose backing file is missing.
+        #[arg(long)]
+        synthesize_missing_disks: bool,
+
+        /// Storage policy name to reference in the OVF.
+        #[arg(long)]
+        storage_policy: Option<String>,
+
+        /// Refuse disk files that are symbolic links instead of following them.
+        #[arg(long)]
+        no_follow_symlinks: bool,
+
+        /// Package this OVF descriptor verbatim instead of generating one.
+        #[arg(long, value_name = "OVF_FILE")]
+        ovf: Option<PathBuf>,
+
+        /// Abort if any disk's capacity exceeds this many gigabytes.
+        #[arg(long, value_name = "GB")]
+        max_disk_size: Option<u64>,
+
+        /// Write a JSON timing and I/O profile of the export to this file.
+        #[arg(long, value_name = "FILE")]
+        profile: Option<PathBuf>,
+
+        /// Package an extra file, such as a README or license, into the OVA.
+        /// Can be given more than once.
+        #[arg(long = "include", value_name = "FILE")]
+        include: Vec<PathBuf>,
+
+        /// Export the VM under this name instead of its display name.
+        #[arg(long, value_name = "NAME")]
+        rename_vm: Option<String>,
+
+        /// Compress the VM's disks concurrently instead of one at a time.
+        #[arg(long)]
+        parallel_disks: bool,
+
+        /// Grain size of the exported disks in kilobytes (a power of two, at least 4).
+        #[arg(long, value_name = "KB", default_value = "64")]
+        grain_size: u64,
+
+        /// Where the OVF descriptor goes in the OVA (ovf-first, ovf-last).
+        #[arg(long, value_enum, default_value = "ovf-first")]
+        ova_order: OvaOrderArg,
+
+        /// Digest algorithm of the manifest (sha1, sha256, sha512).
+        #[arg(long, value_enum, default_value = "sha256")]
+        manifest_algorithm: ManifestAlgorithmArg,
+
+        /// Package this image as the appliance's icon (PNG, JPEG, GIF, SVG or ICO).
+        #[arg(long, value_name = "FILE")]
+        icon: Option<PathBuf>,
+
+        /// Check the OVF's structure (disk, file and InstanceID references)
+        /// before packaging it.
+        #[arg(long)]
+        validate_ovf: bool,
+
+        /// Also record each disk's decompressed SHA256 in a
+        /// `<name>.logical.sha256` sidecar inside the OVA.
+        #[arg(long)]
+        logical_manifest: bool,
+
+        /// List the files that would be read and written, and any problems,
+        /// without exporting.
+        #[arg(long)]
+        dry_run: bool,
     },
 
-    /// Display information about a VMware VM.
+    /// Display information about a VMware VM or an exported OVA.
     Info {
+        /// Path to the VMX or OVA file.
+        file: PathBuf,
+    },
+
+    /// Check a streamOptimized VMDK by decompressing every grain.
+    VerifyDisk {
+        /// Path to the VMDK file.
+        file: PathBuf,
+    },
+
+    /// List the recognized guest OS identifiers and their OVF mappings.
+    ListGuestOs,
+
+    /// Compare two OVAs: file lists, sizes, manifest digests and OVF fields.
+    Compare {
+        /// Path to the first OVA.
+        first: PathBuf,
+
+        /// Path to the second OVA.
+        second: PathBuf,
+    },
+
+    /// List every reason a VM can't be exported, without exporting it.
+    Diagnose {
         /// Path to the VMX file.
         vmx_file: PathBuf,
     },
+
+    /// Import an OVA exported by ovatool back into a VMX and flat VMDKs.
+    Import {
+        /// Path to the OVA file.
+        ova_file: PathBuf,
+
+        /// Directory to write the VM into. Defaults to the current directory.
+        #[arg(short, long)]
+        output_dir: Option<PathBuf>,
+    },
 }
 
 /// Compression level argument mapping.
@@ -63,6 +184,8 @@ enum CompressionArg {
     Balanced,
     /// Maximum compression (zlib level 9).
     Max,
+    /// Maximum compression, dropping to fast for disks that barely compress.
+    Adaptive,
 }
 
 impl From<CompressionArg> for CompressionLevel {
@@ -71,26 +194,189 @@ impl From<CompressionArg> for CompressionLevel {
             CompressionArg::Fast => CompressionLevel::Fast,
             CompressionArg::Balanced => CompressionLevel::Balanced,
             CompressionArg::Max => CompressionLevel::Max,
+            CompressionArg::Adaptive => CompressionLevel::Adaptive,
         }
     }
 }
 
-fn main() -> Result<()> {
+/// OVA file order argument mapping.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum OvaOrderArg {
+    /// OVF descriptor first, as the OVF specification requires.
+    OvfFirst,
+    /// OVF descriptor after the disks.
+    OvfLast,
+}
+
+impl From<OvaOrderArg> for OvaOrder {
+    fn from(arg: OvaOrderArg) -> Self {
+        match arg {
+            OvaOrderArg::OvfFirst => OvaOrder::OvfFirst,
+            OvaOrderArg::OvfLast => OvaOrder::OvfLast,
+        }
+    }
+}
+
+/// Manifest digest algorithm argument mapping.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ManifestAlgorithmArg {
+    /// SHA-1, for older importers.
+    Sha1,
+    /// SHA-256.
+    Sha256,
+    /// SHA-512.
+    Sha512,
+}
+
+impl From<ManifestAlgorithmArg> for ManifestAlgorithm {
+    fn from(arg: ManifestAlgorithmArg) -> Self {
+        match arg {
+            ManifestAlgorithmArg::Sha1 => ManifestAlgorithm::Sha1,
+            ManifestAlgorithmArg::Sha256 => ManifestAlgorithm::Sha256,
+            ManifestAlgorithmArg::Sha512 => ManifestAlgorithm::Sha512,
+        }
+    }
+}
+
+/// Process exit codes, part of the CLI's stable contract for automation.
+mod exit_code {
+    /// Unclassified failure.
+    pub const FAILURE: u8 = 1;
+    /// The VMX or a VMDK could not be parsed.
+    pub const PARSE: u8 = 2;
+    /// A file could not be read or written.
+    pub const IO: u8 = 3;
+    /// The VM uses a feature that can't be exported.
+    pub const UNSUPPORTED: u8 = 4;
+    /// The input or an option failed validation.
+    pub const VALIDATION: u8 = 5;
+}
+
+fn main() -> ExitCode {
     let cli = Cli::parse();
 
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {:?}", err);
+            ExitCode::from(exit_code_for(&err))
+        }
+    }
+}
+
+/// Map an error to its process exit code.
+///
+/// The first core library error in the chain decides the code; errors that
+/// don't originate in the library are reported as unclassified failures.
+fn exit_code_for(err: &anyhow::Error) -> u8 {
+    let core_error = err
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<ovatool_core::Error>());
+
+    match core_error {
+        Some(ovatool_core::Error::VmxParse { .. }) | Some(ovatool_core::Error::Vmdk { .. }) => {
+            exit_code::PARSE
+        }
+        Some(ovatool_core::Error::Io { .. }) => exit_code::IO,
+        Some(ovatool_core::Error::Unsupported { .. }) => exit_code::UNSUPPORTED,
+        Some(ovatool_core::Error::Validation { .. }) => exit_code::VALIDATION,
+        Some(_) | None => {
+            // I/O failures raised outside the library (e.g. reading --ovf)
+            if err.chain().any(|cause| cause.is::<std::io::Error>()) {
+                exit_code::IO
+            } else {
+                exit_code::FAILURE
+            }
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
     match cli.command {
         Commands::Export {
             vmx_file,
             output,
             compression,
+            adaptive_threshold,
             threads,
             chunk_size,
             quiet,
+            verbose,
+            synthesize_missing_disks,
+            storage_policy,
+            no_follow_symlinks,
+            ovf,
+            max_disk_size,
+            profile,
+            include,
+            rename_vm,
+            parallel_disks,
+            grain_size,
+            ova_order,
+            manifest_algorithm,
+            icon,
+            validate_ovf,
+            logical_manifest,
+            dry_run,
         } => {
-            run_export(&vmx_file, output.as_deref(), compression, threads, chunk_size, quiet)?;
+            // Create export options
+            let chunk_size_bytes = chunk_size * 1024 * 1024;
+            let mut options = ExportOptions::new(compression.into(), chunk_size_bytes, threads);
+            options.adaptive_ratio_threshold = adaptive_threshold;
+            options.synthesize_missing_disks = synthesize_missing_disks;
+            options.storage_policy = storage_policy;
+            options.follow_symlinks = !no_follow_symlinks;
+            options.max_disk_bytes = max_disk_size.map(|gb| gb.saturating_mul(1024 * 1024 * 1024));
+            options.extra_files = include;
+            options.display_name_override = rename_vm;
+            options.parallel_disks = parallel_disks;
+            options.grain_size = grain_size.saturating_mul(1024);
+            options.ova_order = ova_order.into();
+            options.manifest_algorithm = manifest_algorithm.into();
+            options.icon = icon;
+            options.validate_ovf = validate_ovf;
+            options.logical_manifest = logical_manifest;
+            // The progress bar redraws at a fixed rate, so finer updates are wasted
+            options.progress_interval = Duration::from_millis(50);
+
+            if dry_run {
+                return show_plan(&vmx_file, &options);
+            }
+
+            let custom_ovf = ovf
+                .map(|path| {
+                    std::fs::read_to_string(&path)
+                        .with_context(|| format!("Failed to read OVF file {}", path.display()))
+                })
+                .transpose()?;
+
+            run_export(
+                &vmx_file,
+                output.as_deref(),
+                options,
+                custom_ovf.as_deref(),
+                profile.as_deref(),
+                quiet,
+                verbose,
+            )?;
+        }
+        Commands::Info { file } => {
+            show_info(&file)?;
+        }
+        Commands::VerifyDisk { file } => {
+            verify_disk(&file)?;
+        }
+        Commands::ListGuestOs => {
+            list_guest_os();
+        }
+        Commands::Compare { first, second } => {
+            compare(&first, &second)?;
         }
-        Commands::Info { vmx_file } => {
-            show_info(&vmx_file)?;
+        Commands::Diagnose { vmx_file } => {
+            run_diagnose(&vmx_file)?;
+        }
+        Commands::Import { ova_file, output_dir } => {
+            import(&ova_file, output_dir.as_deref())?;
         }
     }
 
@@ -100,13 +386,17 @@ fn main() -> Result<()> {
 fn run_export(
     vmx_file: &std::path::Path,
     output: Option<&std::path::Path>,
-    compression: CompressionArg,
-    threads: usize,
-    chunk_size_mb: usize,
+    options: ExportOptions,
+    custom_ovf: Option<&str>,
+    profile: Option<&std::path::Path>,
     quiet: bool,
+    verbose: bool,
 ) -> Result<()> {
     // Get VM info first to show details and determine output path
-    let vm_info = get_vm_info(vmx_file)?;
+    let mut vm_info = get_vm_info(vmx_file)?;
+    if let Some(name) = &options.display_name_override {
+        vm_info.name = name.clone();
+    }
 
     // Determine output path
     let output_path = match output {
@@ -131,26 +421,31 @@ fn run_export(
         );
         println!();
         println!("Output:    {}", output_path.display());
-        println!(
-            "Compression: {:?}",
-            CompressionLevel::from(compression)
-        );
+        println!("Compression: {:?}", options.compression);
         println!();
     }
 
-    // Create export options
-    let chunk_size_bytes = chunk_size_mb * 1024 * 1024;
-    let options = ExportOptions::new(compression.into(), chunk_size_bytes, threads);
-
     // Set up progress tracking
     let progress_bar: Option<Arc<Mutex<ProgressBar>>> = if quiet {
         None
     } else {
-        let pb = ProgressBar::new(vm_info.total_disk_size);
-        let style = ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")?
-            .progress_chars("#>-");
-        pb.set_style(style);
+        let pb = if vm_info.total_disk_size == 0 && !vm_info.disks.is_empty() {
+            // Disk sizes are unknown, so there's no total to show progress against
+            let pb = ProgressBar::new_spinner();
+            pb.set_style(
+                ProgressStyle::default_spinner()
+                    .template("{spinner:.green} [{elapsed_precise}] {bytes} ({bytes_per_sec}) {msg}")?,
+            );
+            pb.enable_steady_tick(std::time::Duration::from_millis(100));
+            pb
+        } else {
+            let pb = ProgressBar::new(vm_info.total_disk_size);
+            let style = ProgressStyle::default_bar()
+                .template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")?
+                .progress_chars("#>-");
+            pb.set_style(style);
+            pb
+        };
         Some(Arc::new(Mutex::new(pb)))
     };
 
@@ -158,7 +453,13 @@ fn run_export(
     let callback: Option<ovatool_core::ProgressCallback> = if let Some(pb_arc) = progress_bar.clone() {
         Some(Box::new(move |progress: ExportProgress| {
             let pb = pb_arc.lock().unwrap();
-            pb.set_position(progress.bytes_processed);
+            // Track the compressed bytes written against the estimated compressed total
+            if progress.compressed_bytes_total > 0 && !progress.is_indeterminate() {
+                pb.set_length(progress.compressed_bytes_total);
+                pb.set_position(progress.compressed_bytes_written);
+            } else {
+                pb.set_position(progress.bytes_processed);
+            }
 
             // Update message based on phase
             let phase_msg = match progress.phase {
@@ -181,7 +482,10 @@ fn run_export(
     };
 
     // Run the export
-    export_vm(vmx_file, &output_path, options, callback)?;
+    let report = match custom_ovf {
+        Some(ovf_xml) => export_vm_with_ovf(vmx_file, &output_path, options, ovf_xml, callback)?,
+        None => export_vm(vmx_file, &output_path, options, callback)?,
+    };
 
     // Finish progress bar
     if let Some(pb_arc) = progress_bar {
@@ -189,25 +493,62 @@ fn run_export(
         pb.finish_with_message("Complete!");
     }
 
+    if let Some(profile_path) = profile {
+        std::fs::write(profile_path, report.to_json()).with_context(|| {
+            format!("Failed to write profile {}", profile_path.display())
+        })?;
+    }
+
+    // Warnings are always shown, even in quiet mode
+    for warning in &report.warnings {
+        eprintln!("WARNING: {}", warning);
+    }
+
     if !quiet {
         println!();
         println!("Export completed successfully: {}", output_path.display());
 
-        // Show output file size
+        // Show output file size; there's no ratio without the disk sizes
         if let Ok(metadata) = std::fs::metadata(&output_path) {
-            println!(
-                "Output size: {} (compression ratio: {:.1}%)",
-                format_bytes(metadata.len()),
-                (metadata.len() as f64 / vm_info.total_disk_size as f64) * 100.0
-            );
+            if vm_info.total_disk_size > 0 {
+                println!(
+                    "Output size: {} (compression ratio: {:.1}%)",
+                    format_bytes(metadata.len()),
+                    (metadata.len() as f64 / vm_info.total_disk_size as f64) * 100.0
+                );
+            } else {
+                println!("Output size: {}", format_bytes(metadata.len()));
+            }
+        }
+    }
+
+    if verbose {
+        println!();
+        println!("Phase timings:");
+        for phase in [
+            ExportPhase::Parsing,
+            ExportPhase::Compressing,
+            ExportPhase::Writing,
+            ExportPhase::Finalizing,
+        ] {
+            if let Some(duration) = report.phase_timings.get(&phase) {
+                println!("  {:<12} {:>10.3}s", phase.to_string(), duration.as_secs_f64());
+            }
         }
     }
 
     Ok(())
 }
 
-fn show_info(vmx_file: &std::path::Path) -> Result<()> {
-    let vm_info = get_vm_info(vmx_file)?;
+fn show_info(file: &std::path::Path) -> Result<()> {
+    let is_ova = file
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("ova"));
+    let vm_info = if is_ova {
+        get_ova_info(file)?
+    } else {
+        get_vm_info(file)?
+    };
 
     println!("VM Information");
     println!("==============");
@@ -216,6 +557,9 @@ fn show_info(vmx_file: &std::path::Path) -> Result<()> {
     println!("Guest OS:  {}", vm_info.guest_os);
     println!("CPUs:      {}", vm_info.cpus);
     println!("Memory:    {} MB", vm_info.memory_mb);
+    if let Some(hardware_version) = &vm_info.hardware_version {
+        println!("Hardware:  {}", hardware_version);
+    }
     println!();
 
     if vm_info.disks.is_empty() {
@@ -223,12 +567,19 @@ fn show_info(vmx_file: &std::path::Path) -> Result<()> {
     } else {
         println!("Disks:");
         for (i, disk) in vm_info.disks.iter().enumerate() {
+            let access = match disk.access {
+                ExtentAccess::ReadWrite => "",
+                ExtentAccess::ReadOnly => ", read-only",
+                ExtentAccess::NoAccess => ", no access",
+            };
             println!(
-                "  {}. {} - {} ({})",
+                "  {}. {} - {} ({}{}{})",
                 i + 1,
                 disk.filename,
                 format_bytes(disk.size_bytes),
-                disk.create_type
+                disk.create_type,
+                access,
+                if disk.deletable { "" } else { ", not deletable" }
             );
         }
         println!();
@@ -241,6 +592,142 @@ fn show_info(vmx_file: &std::path::Path) -> Result<()> {
     Ok(())
 }
 
+fn show_plan(vmx_file: &std::path::Path, options: &ExportOptions) -> Result<()> {
+    let plan = plan_export(vmx_file, options)?;
+
+    println!("Export Plan");
+    println!("-----------");
+    println!("Name:      {}", plan.name);
+    println!();
+    println!("Disks:");
+    for (i, disk) in plan.disks.iter().enumerate() {
+        let mut kind = format!("{:?}", disk.layout).to_lowercase();
+        if disk.split {
+            kind.push_str(", split");
+        }
+        if disk.delta {
+            kind.push_str(", snapshot delta");
+        }
+        println!(
+            "  {}. {} - {} ({}, {})",
+            i + 1,
+            disk.file_name,
+            format_bytes(disk.capacity_bytes),
+            disk.create_type,
+            kind
+        );
+        for path in &disk.source_paths {
+            println!("       reads {}", path.display());
+        }
+    }
+    println!();
+    println!("OVA contents:");
+    for name in &plan.output_files {
+        println!("  {}", name);
+    }
+
+    for warning in &plan.warnings {
+        eprintln!("WARNING: {}", warning);
+    }
+    if !plan.is_exportable() {
+        return Err(ovatool_core::Error::validation("The VM can't be exported as planned").into());
+    }
+
+    Ok(())
+}
+
+fn verify_disk(file: &std::path::Path) -> Result<()> {
+    let report = verify_stream_vmdk(file)
+        .with_context(|| format!("Failed to verify {}", file.display()))?;
+
+    println!("Disk Verification");
+    println!("=================");
+    println!();
+    println!("File:      {}", file.display());
+    println!("Capacity:  {}", format_bytes(report.capacity_bytes));
+    println!("Grain:     {}", format_bytes(report.grain_size_bytes));
+    println!(
+        "Grains:    {} allocated, {} zero ({} total)",
+        report.allocated_grains, report.zero_grains, report.total_grains
+    );
+    println!();
+    println!("All grains decompressed successfully.");
+
+    Ok(())
+}
+
+fn list_guest_os() {
+    println!("{:<24} {:>6}  OS type", "Guest OS", "OVF id");
+    for mapping in GUEST_OS_MAPPINGS {
+        println!(
+            "{:<24} {:>6}  {}",
+            mapping.guest_os, mapping.os_id, mapping.os_type
+        );
+    }
+}
+
+fn compare(first: &std::path::Path, second: &std::path::Path) -> Result<()> {
+    let differences = compare_ovas(first, second).with_context(|| {
+        format!("Failed to compare {} and {}", first.display(), second.display())
+    })?;
+
+    if differences.is_empty() {
+        println!("No differences.");
+    } else {
+        for difference in &differences {
+            println!("{}", difference);
+        }
+        println!();
+        println!("{} difference(s).", differences.len());
+    }
+
+    Ok(())
+}
+
+fn run_diagnose(vmx_file: &std::path::Path) -> Result<()> {
+    let diagnostics = diagnose(vmx_file)
+        .with_context(|| format!("Failed to diagnose {}", vmx_file.display()))?;
+
+    if diagnostics.is_empty() {
+        println!("No problems found.");
+        return Ok(());
+    }
+    for diagnostic in &diagnostics {
+        println!("{}", diagnostic);
+    }
+    let blockers = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Blocker)
+        .count();
+    println!();
+    println!(
+        "{} blocker(s), {} warning(s).",
+        blockers,
+        diagnostics.len() - blockers
+    );
+    if blockers > 0 {
+        return Err(ovatool_core::Error::validation("The VM can't be exported").into());
+    }
+
+    Ok(())
+}
+
+fn import(ova_file: &std::path::Path, output_dir: Option<&std::path::Path>) -> Result<()> {
+    let output_dir = output_dir.unwrap_or_else(|| std::path::Path::new("."));
+    let report = import_ova(ova_file, output_dir)
+        .with_context(|| format!("Failed to import {}", ova_file.display()))?;
+
+    for warning in &report.warnings {
+        eprintln!("WARNING: {}", warning);
+    }
+    println!("Imported VM: {}", report.vmx_path.display());
+    for disk in &report.disk_paths {
+        println!("  Disk: {}", disk.display());
+    }
+
+    Ok(())
+}
+
 /// Format bytes as human-readable string.
 fn format_bytes(bytes: u64) -> String {
     const KB: u64 = 1024;
@@ -0,0 +1,334 @@
+//! Tests for the CLI's process-level behavior.
+
+use std::path::Path;
+use std::process::Command;
+
+/// Run the `ovatool` binary with the given arguments.
+fn ovatool(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_ovatool"))
+        .args(args)
+        .output()
+        .expect("Failed to run ovatool")
+}
+
+/// Write a single-disk VMX and a descriptor with the given extent line.
+fn write_vm(dir: &Path, create_type: &str, extent_line: &str) {
+    std::fs::write(
+        dir.join("test.vmx"),
+        r#"displayName = "CLI Test"
+guestOS = "ubuntu-64"
+memsize = "512"
+numvcpus = "1"
+scsi0.present = "TRUE"
+scsi0:0.present = "TRUE"
+scsi0:0.fileName = "test.vmdk"
+"#,
+    )
+    .unwrap();
+
+    std::fs::write(
+        dir.join("test.vmdk"),
+        format!(
+            "# Disk DescriptorFile\nversion=1\nCID=fffffffe\nparentCID=ffffffff\ncreateType=\"{}\"\n\n{}\n",
+            create_type, extent_line
+        ),
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_exit_code_nonexistent_vmx() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let vmx = dir.path().join("missing.vmx");
+
+    let output = ovatool(&["export", "-q", vmx.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(3), "I/O errors exit with 3");
+}
+
+#[test]
+fn test_exit_code_unsupported_create_type() {
+    let dir = tempfile::TempDir::new().unwrap();
+    write_vm(dir.path(), "vmfsRaw", r#"RW 2048 VMFSRAW "/vmfs/devices/disks/naa.1" 0"#);
+    let output_path = dir.path().join("out.ova");
+
+    let output = ovatool(&[
+        "export",
+        "-q",
+        "-o",
+        output_path.to_str().unwrap(),
+        dir.path().join("test.vmx").to_str().unwrap(),
+    ]);
+    assert_eq!(
+        output.status.code(),
+        Some(4),
+        "Unsupported disks exit with 4: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn test_exit_code_malformed_descriptor() {
+    let dir = tempfile::TempDir::new().unwrap();
+    write_vm(dir.path(), "monolithicFlat", r#"RW 2048 BOGUS "test-flat.vmdk" 0"#);
+
+    let output = ovatool(&["info", dir.path().join("test.vmx").to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(2), "Parse errors exit with 2");
+}
+
+#[test]
+fn test_exit_code_missing_flat_extent() {
+    let dir = tempfile::TempDir::new().unwrap();
+    write_vm(dir.path(), "monolithicFlat", r#"RW 2048 FLAT "test-flat.vmdk" 0"#);
+    let output_path = dir.path().join("out.ova");
+
+    let output = ovatool(&[
+        "export",
+        "-q",
+        "-o",
+        output_path.to_str().unwrap(),
+        dir.path().join("test.vmx").to_str().unwrap(),
+    ]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(3), "Missing files exit with 3: {}", stderr);
+    assert!(stderr.contains("test-flat.vmdk' of disk 'test.vmdk' not found"), "{}", stderr);
+}
+
+#[test]
+fn test_exit_code_success() {
+    let dir = tempfile::TempDir::new().unwrap();
+    write_vm(dir.path(), "monolithicFlat", r#"RW 2048 FLAT "test-flat.vmdk" 0"#);
+    std::fs::write(dir.path().join("test-flat.vmdk"), vec![0u8; 1024 * 1024]).unwrap();
+    let output_path = dir.path().join("out.ova");
+
+    let output = ovatool(&[
+        "export",
+        "-q",
+        "-o",
+        output_path.to_str().unwrap(),
+        dir.path().join("test.vmx").to_str().unwrap(),
+    ]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output_path.exists());
+}
+
+#[test]
+fn test_verify_disk_rejects_flat_vmdk() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let disk = dir.path().join("flat.vmdk");
+    std::fs::write(&disk, vec![0u8; 4096]).unwrap();
+
+    let output = ovatool(&["verify-disk", disk.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(2), "Invalid VMDKs exit with 2");
+}
+
+#[test]
+fn test_profile_json_written() {
+    let dir = tempfile::TempDir::new().unwrap();
+    write_vm(dir.path(), "monolithicFlat", r#"RW 2048 FLAT "test-flat.vmdk" 0"#);
+    std::fs::write(dir.path().join("test-flat.vmdk"), vec![0x5Au8; 1024 * 1024]).unwrap();
+    let output_path = dir.path().join("out.ova");
+    let profile_path = dir.path().join("profile.json");
+
+    let output = ovatool(&[
+        "export",
+        "-q",
+        "-o",
+        output_path.to_str().unwrap(),
+        "--profile",
+        profile_path.to_str().unwrap(),
+        dir.path().join("test.vmx").to_str().unwrap(),
+    ]);
+    assert_eq!(output.status.code(), Some(0));
+
+    let profile = std::fs::read_to_string(&profile_path).expect("Profile should be written");
+    assert!(profile.contains("\"phase_timings\""));
+    assert!(profile.contains("\"compressing\":"));
+    assert!(profile.contains("\"file_name\": \"test.vmdk\""));
+    assert!(profile.contains("\"capacity_bytes\": 1048576"));
+}
+
+#[test]
+fn test_list_guest_os() {
+    let output = ovatool(&["list-guest-os"]);
+    assert_eq!(output.status.code(), Some(0));
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line_for = |guest_os: &str| {
+        stdout
+            .lines()
+            .find(|line| line.split_whitespace().next() == Some(guest_os))
+            .unwrap_or_else(|| panic!("{} not listed", guest_os))
+            .split_whitespace()
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(line_for("ubuntu-64"), vec!["ubuntu-64", "96", "ubuntu64Guest"]);
+    assert_eq!(line_for("windows10-64"), vec!["windows10-64", "109", "windows9_64Guest"]);
+}
+
+#[test]
+fn test_compare_ovas() {
+    let dir = tempfile::TempDir::new().unwrap();
+    write_vm(dir.path(), "monolithicFlat", r#"RW 2048 FLAT "test-flat.vmdk" 0"#);
+    std::fs::write(dir.path().join("test-flat.vmdk"), vec![0x5Au8; 1024 * 1024]).unwrap();
+    let vmx = dir.path().join("test.vmx");
+    let export = |output: &Path| {
+        let output = ovatool(&[
+            "export",
+            "-q",
+            "-o",
+            output.to_str().unwrap(),
+            vmx.to_str().unwrap(),
+        ]);
+        assert_eq!(output.status.code(), Some(0));
+    };
+
+    let original = dir.path().join("original.ova");
+    export(&original);
+
+    let output = ovatool(&["compare", original.to_str().unwrap(), original.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "No differences.");
+
+    // Re-export with more memory, which only changes the OVF
+    let content = std::fs::read_to_string(&vmx).unwrap();
+    std::fs::write(&vmx, content.replace("memsize = \"512\"", "memsize = \"2048\"")).unwrap();
+    let modified = dir.path().join("modified.ova");
+    export(&modified);
+
+    let output = ovatool(&["compare", original.to_str().unwrap(), modified.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("digest of CLI_Test.ovf: "), "{}", stdout);
+    assert!(stdout.contains("OVF memory_mb: 512 -> 2048"), "{}", stdout);
+    assert!(!stdout.contains("test.vmdk"), "{}", stdout);
+}
+
+#[test]
+fn test_export_includes_extra_files() {
+    let dir = tempfile::TempDir::new().unwrap();
+    write_vm(dir.path(), "monolithicFlat", r#"RW 2048 FLAT "test-flat.vmdk" 0"#);
+    std::fs::write(dir.path().join("test-flat.vmdk"), vec![0x5Au8; 1024 * 1024]).unwrap();
+    let readme = dir.path().join("README.txt");
+    std::fs::write(&readme, "Log in as admin.\n").unwrap();
+    let output_path = dir.path().join("out.ova");
+
+    let output = ovatool(&[
+        "export",
+        "-q",
+        "-o",
+        output_path.to_str().unwrap(),
+        "--include",
+        readme.to_str().unwrap(),
+        dir.path().join("test.vmx").to_str().unwrap(),
+    ]);
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let file = std::fs::File::open(&output_path).unwrap();
+    let mut reader = ovatool_core::ova::OvaReader::new(file).unwrap();
+    assert_eq!(reader.read_file("README.txt").unwrap(), b"Log in as admin.\n");
+    assert!(reader.manifest_digests().unwrap().contains_key("README.txt"));
+    assert!(reader.verify().unwrap().is_empty());
+}
+
+#[test]
+fn test_export_rename_vm_default_output() {
+    let dir = tempfile::TempDir::new().unwrap();
+    write_vm(dir.path(), "monolithicFlat", r#"RW 2048 FLAT "test-flat.vmdk" 0"#);
+    std::fs::write(dir.path().join("test-flat.vmdk"), vec![0x5Au8; 1024 * 1024]).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ovatool"))
+        .current_dir(dir.path())
+        .args(["export", "-q", "--rename-vm", "Web Server", "test.vmx"])
+        .output()
+        .expect("Failed to run ovatool");
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(!dir.path().join("CLI_Test.ova").exists());
+
+    let file = std::fs::File::open(dir.path().join("Web_Server.ova")).unwrap();
+    let mut reader = ovatool_core::ova::OvaReader::new(file).unwrap();
+    let ovf = String::from_utf8(reader.read_file("Web_Server.ovf").unwrap()).unwrap();
+    assert!(ovf.contains("<ovf:Name>Web Server</ovf:Name>"), "{}", ovf);
+}
+
+#[test]
+fn test_export_blank_display_name_default_output() {
+    let dir = tempfile::TempDir::new().unwrap();
+    write_vm(dir.path(), "monolithicFlat", r#"RW 2048 FLAT "test-flat.vmdk" 0"#);
+    std::fs::write(dir.path().join("test-flat.vmdk"), vec![0u8; 1024 * 1024]).unwrap();
+    let vmx = std::fs::read_to_string(dir.path().join("test.vmx")).unwrap();
+    std::fs::write(dir.path().join("test.vmx"), vmx.replace("\"CLI Test\"", "\"\"")).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ovatool"))
+        .current_dir(dir.path())
+        .args(["export", "-q", "test.vmx"])
+        .output()
+        .expect("Failed to run ovatool");
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(!dir.path().join(".ova").exists());
+    assert!(dir.path().join("test.ova").exists());
+}
+
+#[test]
+fn test_export_zero_size_disk_warns() {
+    let dir = tempfile::TempDir::new().unwrap();
+    write_vm(dir.path(), "monolithicFlat", r#"RW 0 FLAT "test-flat.vmdk" 0"#);
+    std::fs::write(dir.path().join("test-flat.vmdk"), b"").unwrap();
+    let output_path = dir.path().join("out.ova");
+
+    let output = ovatool(&[
+        "export",
+        "-o",
+        output_path.to_str().unwrap(),
+        dir.path().join("test.vmx").to_str().unwrap(),
+    ]);
+    assert_eq!(output.status.code(), Some(0));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("WARNING: Disk sizes could not be determined"), "{}", stderr);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("compression ratio"), "{}", stdout);
+}
+
+#[test]
+fn test_export_dry_run() {
+    let dir = tempfile::TempDir::new().unwrap();
+    write_vm(dir.path(), "monolithicFlat", r#"RW 2048 FLAT "test-flat.vmdk" 0"#);
+    let output_path = dir.path().join("out.ova");
+    let dry_run = || {
+        ovatool(&[
+            "export",
+            "--dry-run",
+            "-o",
+            output_path.to_str().unwrap(),
+            dir.path().join("test.vmx").to_str().unwrap(),
+        ])
+    };
+
+    // The flat extent is missing
+    let output = dry_run();
+    assert_eq!(output.status.code(), Some(5));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("test-flat.vmdk' not found"), "{}", stderr);
+
+    std::fs::write(dir.path().join("test-flat.vmdk"), vec![0u8; 1024 * 1024]).unwrap();
+    let output = dry_run();
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("test.vmdk - 1.00 MB (monolithicFlat, flat)"), "{}", stdout);
+    assert!(stdout.contains("CLI_Test.ovf"), "{}", stdout);
+    assert!(!output_path.exists());
+}
@@ -0,0 +1,267 @@
+//! Changed-block tracking support for incremental exports.
+//!
+//! A [`ChangedBlockMap`] lists the byte ranges of a disk that changed since a
+//! previous backup. Wrapping a disk's source in a [`ChangedBlocksSource`]
+//! makes the exporter write only those ranges; everything else is left
+//! unallocated in the output VMDK and reads back as zeros. Changed ranges
+//! are widened to whole grains, and changed grains are written even when
+//! they are all zeros, so they overwrite the older data on restore.
+//!
+//! The output is not a VMDK delta disk: its descriptor doesn't reference the
+//! previous backup, so it is not bootable on its own. Restoring it means
+//! writing its allocated grains over a copy of the previous backup's disk.
+
+use std::ops::Range;
+
+use crate::error::Result;
+use crate::source::DiskSource;
+
+/// Set of changed byte ranges within a disk.
+///
+/// Ranges are kept sorted and merged, so lookups are cheap regardless of the
+/// order they were added in.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChangedBlockMap {
+    ranges: Vec<Range<u64>>,
+}
+
+impl ChangedBlockMap {
+    /// Create an empty map (no changes).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a map from ranges of grain indices.
+    ///
+    /// # Arguments
+    ///
+    /// * `grain_size_bytes` - Size of one grain in bytes.
+    /// * `grain_ranges` - Changed grains as half-open index ranges.
+    pub fn from_grain_ranges(
+        grain_size_bytes: u64,
+        grain_ranges: impl IntoIterator<Item = Range<u64>>,
+    ) -> Self {
+        let mut map = Self::new();
+        for grains in grain_ranges {
+            let offset = grains.start * grain_size_bytes;
+            let len = (grains.end.saturating_sub(grains.start)) * grain_size_bytes;
+            map.add(offset, len);
+        }
+        map
+    }
+
+    /// Mark `len` bytes starting at `offset` as changed.
+    pub fn add(&mut self, offset: u64, len: u64) {
+        if len == 0 {
+            return;
+        }
+
+        let mut new_range = offset..offset + len;
+
+        // Absorb every existing range that overlaps or touches the new one
+        let mut merged = Vec::with_capacity(self.ranges.len() + 1);
+        for range in self.ranges.drain(..) {
+            if range.end < new_range.start || range.start > new_range.end {
+                merged.push(range);
+            } else {
+                new_range.start = new_range.start.min(range.start);
+                new_range.end = new_range.end.max(range.end);
+            }
+        }
+
+        let position = merged
+            .iter()
+            .position(|r| r.start > new_range.start)
+            .unwrap_or(merged.len());
+        merged.insert(position, new_range);
+        self.ranges = merged;
+    }
+
+    /// Returns `true` if any byte in `offset..offset + len` changed.
+    pub fn is_changed(&self, offset: u64, len: u64) -> bool {
+        let end = offset + len;
+        self.ranges
+            .iter()
+            .any(|range| range.start < end && range.end > offset)
+    }
+
+    /// Widen every range to whole blocks of `block_size` bytes.
+    ///
+    /// The exporter aligns maps to the output grain size, so each grain is
+    /// either fully changed or fully unchanged.
+    pub fn aligned(&self, block_size: u64) -> Self {
+        let mut map = Self::new();
+        for range in &self.ranges {
+            let start = range.start / block_size * block_size;
+            let end = range.end.div_ceil(block_size) * block_size;
+            map.add(start, end - start);
+        }
+        map
+    }
+
+    /// Returns the changed ranges in ascending order.
+    pub fn ranges(&self) -> &[Range<u64>] {
+        &self.ranges
+    }
+
+    /// Returns the total number of changed bytes.
+    pub fn changed_bytes(&self) -> u64 {
+        self.ranges.iter().map(|range| range.end - range.start).sum()
+    }
+}
+
+/// A disk source restricted to the ranges listed in a [`ChangedBlockMap`].
+///
+/// Unchanged ranges read as zeros and are reported as unallocated, so the
+/// exporter skips them. Changed ranges read as the inner source and must be
+/// written even where they are zeros, since those zeros replace the previous
+/// backup's data. Align the map to the output grain size with
+/// [`ChangedBlockMap::aligned`] first, so no grain mixes the two.
+pub struct ChangedBlocksSource {
+    inner: Box<dyn DiskSource>,
+    changed: ChangedBlockMap,
+}
+
+impl ChangedBlocksSource {
+    /// Wrap `inner` so only the ranges in `changed` are exported.
+    pub fn new(inner: Box<dyn DiskSource>, changed: ChangedBlockMap) -> Self {
+        Self { inner, changed }
+    }
+}
+
+impl DiskSource for ChangedBlocksSource {
+    fn size(&self) -> u64 {
+        self.inner.size()
+    }
+
+    fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        let mut data = self.inner.read_at(offset, len)?;
+        let end = offset + data.len() as u64;
+
+        // Zero everything between the changed ranges
+        let mut pos = offset;
+        for range in self.changed.ranges() {
+            if range.start >= end {
+                break;
+            }
+            if range.start > pos {
+                data[(pos - offset) as usize..(range.start - offset) as usize].fill(0);
+            }
+            pos = pos.max(range.end);
+        }
+        if pos < end {
+            data[(pos - offset) as usize..].fill(0);
+        }
+        Ok(data)
+    }
+
+    fn is_unallocated(&self, offset: u64, len: usize) -> bool {
+        !self.changed.is_changed(offset, len as u64)
+    }
+
+    fn must_write_zeros(&self, offset: u64, len: usize) -> bool {
+        self.changed.is_changed(offset, len as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::ZeroDiskSource;
+
+    /// A disk of the given size whose bytes all read as 1.
+    struct Ones(u64);
+
+    impl DiskSource for Ones {
+        fn size(&self) -> u64 {
+            self.0
+        }
+
+        fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>> {
+            let available = self.0.saturating_sub(offset);
+            Ok(vec![1u8; std::cmp::min(len as u64, available) as usize])
+        }
+    }
+
+    #[test]
+    fn test_add_merges_overlapping_and_adjacent() {
+        let mut map = ChangedBlockMap::new();
+        map.add(100, 50);
+        map.add(0, 10);
+        map.add(140, 20);
+        map.add(10, 5);
+
+        assert_eq!(map.ranges(), &[0..15, 100..160]);
+        assert_eq!(map.changed_bytes(), 75);
+    }
+
+    #[test]
+    fn test_add_bridges_ranges() {
+        let mut map = ChangedBlockMap::new();
+        map.add(0, 10);
+        map.add(20, 10);
+        map.add(5, 20);
+
+        assert_eq!(map.ranges().len(), 1);
+        assert_eq!(map.ranges()[0], 0..30);
+    }
+
+    #[test]
+    fn test_add_empty_range_ignored() {
+        let mut map = ChangedBlockMap::new();
+        map.add(10, 0);
+        assert!(map.ranges().is_empty());
+    }
+
+    #[test]
+    fn test_from_grain_ranges() {
+        let map = ChangedBlockMap::from_grain_ranges(4096, [1..3, 5..6]);
+        assert_eq!(map.ranges(), &[4096..12288, 20480..24576]);
+    }
+
+    #[test]
+    fn test_is_changed() {
+        let map = ChangedBlockMap::from_grain_ranges(100, std::iter::once(1..2));
+        assert!(!map.is_changed(0, 100));
+        assert!(map.is_changed(50, 100));
+        assert!(map.is_changed(150, 10));
+        assert!(!map.is_changed(200, 100));
+    }
+
+    #[test]
+    fn test_aligned() {
+        let mut map = ChangedBlockMap::new();
+        map.add(10, 5);
+        map.add(250, 100);
+        map.add(390, 1);
+
+        assert_eq!(map.aligned(100).ranges(), &[0..100, 200..400]);
+    }
+
+    #[test]
+    fn test_changed_blocks_source() {
+        let source = ChangedBlocksSource::new(
+            Box::new(ZeroDiskSource::new(1000)),
+            ChangedBlockMap::from_grain_ranges(100, std::iter::once(2..3)),
+        );
+
+        assert_eq!(source.size(), 1000);
+        assert!(source.is_unallocated(0, 100));
+        assert!(!source.must_write_zeros(0, 100));
+        // Changed to zeros: still written, to overwrite the older data
+        assert!(!source.is_unallocated(200, 100));
+        assert!(source.must_write_zeros(150, 100));
+        assert_eq!(source.read_at(200, 10).unwrap(), vec![0u8; 10]);
+    }
+
+    #[test]
+    fn test_changed_blocks_source_reads_unchanged_as_zeros() {
+        let mut changed = ChangedBlockMap::new();
+        changed.add(2, 3);
+        changed.add(8, 4);
+        let source = ChangedBlocksSource::new(Box::new(Ones(10)), changed);
+
+        assert_eq!(source.read_at(0, 10).unwrap(), [0, 0, 1, 1, 1, 0, 0, 0, 1, 1]);
+        assert_eq!(source.read_at(3, 3).unwrap(), [1, 1, 0]);
+    }
+}
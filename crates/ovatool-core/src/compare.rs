@@ -0,0 +1,263 @@
+//! Structural comparison of OVA archives.
+//!
+//! This module compares two OVAs without unpacking their disks: the archive
+//! file lists and sizes, the manifest digests, and the key fields of the OVF
+//! descriptors. It is meant for tracking down why a re-export differs.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::ova::OvaReader;
+use crate::ovf::{parse_ovf_summary, OvfSummary};
+
+/// A single structural difference between two OVAs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OvaDifference {
+    /// A file present only in the first archive.
+    OnlyInFirst(String),
+    /// A file present only in the second archive.
+    OnlyInSecond(String),
+    /// A file whose size differs between the archives.
+    Size {
+        /// Filename within the archives.
+        name: String,
+        /// Size in the first archive, in bytes.
+        first: u64,
+        /// Size in the second archive, in bytes.
+        second: u64,
+    },
+    /// A file whose manifest digest differs between the archives.
+    Digest {
+        /// Filename within the archives.
+        name: String,
+        /// Digest in the first manifest, or `-` if it lists none.
+        first: String,
+        /// Digest in the second manifest, or `-` if it lists none.
+        second: String,
+    },
+    /// An OVF field that differs between the descriptors.
+    OvfField {
+        /// Name of the field (e.g. `memory_mb`, `disk[0].capacity`).
+        field: String,
+        /// Value in the first descriptor.
+        first: String,
+        /// Value in the second descriptor.
+        second: String,
+    },
+}
+
+impl fmt::Display for OvaDifference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OvaDifference::OnlyInFirst(name) => write!(f, "only in first: {}", name),
+            OvaDifference::OnlyInSecond(name) => write!(f, "only in second: {}", name),
+            OvaDifference::Size {
+                name,
+                first,
+                second,
+            } => write!(f, "size of {}: {} -> {} bytes", name, first, second),
+            OvaDifference::Digest {
+                name,
+                first,
+                second,
+            } => write!(f, "digest of {}: {} -> {}", name, first, second),
+            OvaDifference::OvfField {
+                field,
+                first,
+                second,
+            } => write!(f, "OVF {}: {} -> {}", field, first, second),
+        }
+    }
+}
+
+/// Compare two OVA archives structurally.
+///
+/// # Arguments
+///
+/// * `first` - Path to the first OVA.
+/// * `second` - Path to the second OVA.
+///
+/// # Returns
+///
+/// The differences found, in the order: file lists and sizes, manifest
+/// digests, then OVF fields. Empty if the archives match.
+pub fn compare_ovas(first: &Path, second: &Path) -> Result<Vec<OvaDifference>> {
+    let first = ArchiveSummary::read(first)?;
+    let second = ArchiveSummary::read(second)?;
+
+    let mut differences = Vec::new();
+    compare_files(&first.files, &second.files, &mut differences);
+    compare_digests(&first.digests, &second.digests, &mut differences);
+    compare_ovf(&first.ovf, &second.ovf, &mut differences);
+    Ok(differences)
+}
+
+/// The parts of an OVA that are compared.
+struct ArchiveSummary {
+    /// File sizes by name.
+    files: BTreeMap<String, u64>,
+    /// Manifest digests by file name.
+    digests: BTreeMap<String, String>,
+    ovf: OvfSummary,
+}
+
+impl ArchiveSummary {
+    fn read(path: &Path) -> Result<Self> {
+        let file = File::open(path).map_err(|e| Error::io(e, path))?;
+        let mut reader = OvaReader::new(file)?;
+
+        let files = reader
+            .entries()
+            .iter()
+            .map(|entry| (entry.name.clone(), entry.size))
+            .collect();
+        let digests = reader.manifest_digests()?;
+        let ovf = parse_ovf_summary(&reader.read_ovf()?)?;
+
+        Ok(Self {
+            files,
+            digests,
+            ovf,
+        })
+    }
+}
+
+fn compare_files(
+    first: &BTreeMap<String, u64>,
+    second: &BTreeMap<String, u64>,
+    differences: &mut Vec<OvaDifference>,
+) {
+    for (name, &first_size) in first {
+        match second.get(name) {
+            None => differences.push(OvaDifference::OnlyInFirst(name.clone())),
+            Some(&second_size) if second_size != first_size => {
+                differences.push(OvaDifference::Size {
+                    name: name.clone(),
+                    first: first_size,
+                    second: second_size,
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    for name in second.keys().filter(|name| !first.contains_key(*name)) {
+        differences.push(OvaDifference::OnlyInSecond(name.clone()));
+    }
+}
+
+fn compare_digests(
+    first: &BTreeMap<String, String>,
+    second: &BTreeMap<String, String>,
+    differences: &mut Vec<OvaDifference>,
+) {
+    let mut names: Vec<&String> = first.keys().chain(second.keys()).collect();
+    names.sort();
+    names.dedup();
+
+    for name in names {
+        let first_digest = first.get(name);
+        let second_digest = second.get(name);
+        let same = match (first_digest, second_digest) {
+            (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+            _ => false,
+        };
+        if !same {
+            differences.push(OvaDifference::Digest {
+                name: name.clone(),
+                first: first_digest.map_or("-", String::as_str).to_string(),
+                second: second_digest.map_or("-", String::as_str).to_string(),
+            });
+        }
+    }
+}
+
+fn compare_ovf(first: &OvfSummary, second: &OvfSummary, differences: &mut Vec<OvaDifference>) {
+    let mut field = |field: String, a: String, b: String| {
+        if a != b {
+            differences.push(OvaDifference::OvfField {
+                field,
+                first: a,
+                second: b,
+            });
+        }
+    };
+    let optional = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_string());
+
+    field("name".into(), first.name.clone(), second.name.clone());
+    field("os_type".into(), first.os_type.clone(), second.os_type.clone());
+    field("cpus".into(), first.cpus.to_string(), second.cpus.to_string());
+    field(
+        "memory_mb".into(),
+        first.memory_mb.to_string(),
+        second.memory_mb.to_string(),
+    );
+    field(
+        "hardware_version".into(),
+        optional(&first.hardware_version),
+        optional(&second.hardware_version),
+    );
+    field(
+        "disks".into(),
+        first.disks.len().to_string(),
+        second.disks.len().to_string(),
+    );
+
+    for (i, (a, b)) in first.disks.iter().zip(&second.disks).enumerate() {
+        field(format!("disk[{}].file", i), a.file_name.clone(), b.file_name.clone());
+        field(
+            format!("disk[{}].capacity", i),
+            a.capacity.to_string(),
+            b.capacity.to_string(),
+        );
+        field(format!("disk[{}].format", i), a.format.clone(), b.format.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_files() {
+        let first = BTreeMap::from([("a.vmdk".to_string(), 10), ("b.vmdk".to_string(), 20)]);
+        let second = BTreeMap::from([("b.vmdk".to_string(), 25), ("c.vmdk".to_string(), 5)]);
+
+        let mut differences = Vec::new();
+        compare_files(&first, &second, &mut differences);
+        assert_eq!(
+            differences,
+            vec![
+                OvaDifference::OnlyInFirst("a.vmdk".to_string()),
+                OvaDifference::Size {
+                    name: "b.vmdk".to_string(),
+                    first: 20,
+                    second: 25,
+                },
+                OvaDifference::OnlyInSecond("c.vmdk".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compare_digests_ignores_case() {
+        let first = BTreeMap::from([("vm.ovf".to_string(), "ABCD".to_string())]);
+        let second = BTreeMap::from([
+            ("vm.ovf".to_string(), "abcd".to_string()),
+            ("disk.vmdk".to_string(), "1234".to_string()),
+        ]);
+
+        let mut differences = Vec::new();
+        compare_digests(&first, &second, &mut differences);
+        assert_eq!(
+            differences,
+            vec![OvaDifference::Digest {
+                name: "disk.vmdk".to_string(),
+                first: "-".to_string(),
+                second: "1234".to_string(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,109 @@
+//! Pre-flight diagnosis of VMs before export.
+//!
+//! An export stops at the first problem it hits. This module runs the
+//! checks an export would make up front and reports every problem at once,
+//! split into blockers, which would make the export fail, and warnings.
+
+use std::fmt;
+use std::path::Path;
+
+use crate::error::Result;
+use crate::export::{plan_export, ExportOptions};
+
+/// How serious a diagnosed problem is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The export would fail.
+    Blocker,
+    /// The export would succeed, but the OVA may not be what was expected.
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Blocker => write!(f, "blocker"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem found by [`diagnose`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Whether the problem blocks the export.
+    pub severity: Severity,
+    /// Filename in the VMX of the disk concerned, if it concerns one disk.
+    pub disk: Option<String>,
+    /// Description of the problem.
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.disk {
+            Some(disk) => write!(f, "{}: disk '{}': {}", self.severity, disk, self.message),
+            None => write!(f, "{}: {}", self.severity, self.message),
+        }
+    }
+}
+
+/// Check whether a VM can be exported, without exporting it.
+///
+/// Reports encrypted VMs and, for each disk, missing descriptors and
+/// extents, unsupported createTypes such as VMFS extents, and broken
+/// snapshot chains, along with the warnings an export would give. Checks
+/// are made with the default [`ExportOptions`].
+///
+/// # Arguments
+///
+/// * `vmx_path` - Path to the VMX file.
+///
+/// # Returns
+///
+/// Every problem found, blockers first; empty if the VM exports cleanly.
+/// Fails only if the VMX itself can't be read or parsed.
+pub fn diagnose(vmx_path: &Path) -> Result<Vec<Diagnostic>> {
+    let plan = plan_export(vmx_path, &ExportOptions::default())?;
+    let mut diagnostics = Vec::new();
+    let mut blocker_warnings = Vec::new();
+
+    for problem in &plan.problems {
+        blocker_warnings.push(problem.clone());
+        diagnostics.push(Diagnostic {
+            severity: Severity::Blocker,
+            disk: None,
+            message: problem.clone(),
+        });
+    }
+    for disk in &plan.disks {
+        for problem in &disk.problems {
+            blocker_warnings.push(format!("disk '{}': {}", disk.file_name, problem));
+            diagnostics.push(Diagnostic {
+                severity: Severity::Blocker,
+                disk: Some(disk.file_name.clone()),
+                message: problem.clone(),
+            });
+        }
+    }
+
+    if plan.disks.is_empty() {
+        diagnostics.push(Diagnostic {
+            severity: Severity::Warning,
+            disk: None,
+            message: "the VM has no disks".to_string(),
+        });
+    }
+    diagnostics.extend(
+        plan.warnings
+            .into_iter()
+            .filter(|warning| !blocker_warnings.contains(warning))
+            .map(|message| Diagnostic {
+                severity: Severity::Warning,
+                disk: None,
+                message,
+            }),
+    );
+
+    Ok(diagnostics)
+}
@@ -31,6 +31,14 @@ pub enum Error {
     /// Error in the export pipeline.
     #[error("Pipeline error: {message}")]
     Pipeline { message: String },
+
+    /// The input is valid but uses a feature this tool can't export.
+    #[error("Unsupported: {message}")]
+    Unsupported { message: String },
+
+    /// The input or a caller-supplied setting failed a validation check.
+    #[error("Validation error: {message}")]
+    Validation { message: String },
 }
 
 /// A specialized Result type for OVATool operations.
@@ -84,6 +92,20 @@ impl Error {
             message: message.into(),
         }
     }
+
+    /// Create an unsupported-feature error.
+    pub fn unsupported(message: impl Into<String>) -> Self {
+        Self::Unsupported {
+            message: message.into(),
+        }
+    }
+
+    /// Create a validation error.
+    pub fn validation(message: impl Into<String>) -> Self {
+        Self::Validation {
+            message: message.into(),
+        }
+    }
 }
 
 impl From<std::io::Error> for Error {
@@ -151,4 +173,17 @@ mod tests {
         let err: Error = io_err.into();
         assert!(matches!(err, Error::Io { path: None, .. }));
     }
+
+    #[test]
+    fn test_unsupported_error() {
+        let err = Error::unsupported("raw device mapping");
+        assert!(err.to_string().contains("Unsupported"));
+        assert!(err.to_string().contains("raw device mapping"));
+    }
+
+    #[test]
+    fn test_validation_error() {
+        let err = Error::validation("href mismatch");
+        assert!(err.to_string().contains("Validation error"));
+    }
 }
@@ -2,7 +2,8 @@
 //!
 //! This module coordinates the full export pipeline:
 //! 1. Parse VMX to get VM configuration and disk paths
-//! 2. For each disk, read it in chunks, compress, and write streamOptimized VMDK
+//! 2. For each disk, read it in chunks from its [`DiskSource`], compress, and
+//!    write streamOptimized VMDK
 //! 3. Package everything into OVA with OVF descriptor and manifest
 //!
 //! # Example
@@ -18,40 +19,287 @@
 //! export_vm(vmx_path, output_path, options, None).unwrap();
 //! ```
 
+use std::collections::{HashMap, VecDeque};
 use std::fs::{self, File};
-use std::io::Cursor;
-use std::path::Path;
-
+use flate2::read::DeflateDecoder;
+use sha2::{Digest, Sha256};
+use std::io::{Cursor, Read, Seek, Write};
+use std::ops::Range;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::cbt::{ChangedBlockMap, ChangedBlocksSource};
 use crate::error::{Error, Result};
-use crate::ova::OvaWriter;
-use crate::ovf::{DiskInfo, OvfBuilder};
-use crate::pipeline::{CompressionLevel, Pipeline, PipelineConfig};
+use crate::ova::{
+    ManifestAlgorithm, ManifestSigner, OvaOrder, OvaReader, OvaWriter, CERT_FILE_NAME,
+    MANIFEST_FILE_NAME, MAX_TAR_ENTRY_SIZE,
+};
+use crate::ovf::{
+    parse_file_references, parse_ovf_summary, validate_ovf, DiskFormat, DiskInfo, OvfBuilder,
+};
+use crate::pipeline::{
+    AdaptiveCompressor, CompressionLevel, OrderedDrain, OrderedSender, Pipeline, PipelineConfig,
+    ADAPTIVE_RATIO_THRESHOLD,
+};
+use crate::source::{
+    ConcatDiskSource, DiskSource, OverlayDiskSource, PaddedDiskSource, TruncatedDiskSource,
+    ZeroDiskSource,
+};
 use crate::vmdk::{
-    compress_grain, is_sparse_vmdk, parse_descriptor, Extent, ExtentType, SparseVmdkReader,
-    StreamVmdkWriter, VmdkReader,
+    check_grain_size, compress_grain, is_sparse_vmdk, is_zero_grain, locate_parent,
+    parse_descriptor, read_descriptor, snapshot_base_name, CreateType, Extent, ExtentAccess,
+    ExtentType, SparseVmdkReader, SparseVmdkWriter, StreamVmdkWriter, VmdkDescriptor, VmdkReader,
+    WindowedVmdkReader, DEFAULT_GRAIN_SIZE, SECTOR_SIZE,
 };
 use crate::vmx::{parse_vmx, VmxConfig};
 
 /// Default chunk size for processing (64 MB).
 pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024 * 1024;
 
+/// Size of the buffers disks written in parallel hand to the OVA writer.
+const PARALLEL_DISK_BUFFER_SIZE: usize = 4 * 1024 * 1024;
+
+/// Buffers each disk written in parallel may have waiting for the OVA writer.
+const PARALLEL_DISK_BUFFERS: usize = 4;
+
+/// Grains per disk past which a grain size smaller than the default draws a
+/// warning; each grain adds a marker and a grain table entry.
+const EXCESSIVE_GRAIN_COUNT: u64 = 16 * 1024 * 1024;
+
+/// Suffix of the sidecar listing the disks' logical SHA256 digests, for
+/// [`ExportOptions::logical_manifest`].
+const LOGICAL_MANIFEST_SUFFIX: &str = ".logical.sha256";
+
+/// Why an encrypted VM can't be exported.
+const ENCRYPTED_VM_PROBLEM: &str = "the VM is encrypted; decrypt it before exporting";
+
+/// Boundary disk capacities are rounded up to before export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CapacityRounding {
+    /// Keep each disk's exact capacity.
+    #[default]
+    None,
+    /// Round up to a whole number of mebibytes.
+    Mb,
+    /// Round up to a whole number of gibibytes.
+    Gb,
+}
+
+impl CapacityRounding {
+    /// Round `capacity_bytes` up to this policy's boundary.
+    pub fn round(&self, capacity_bytes: u64) -> u64 {
+        let boundary: u64 = match self {
+            CapacityRounding::None => return capacity_bytes,
+            CapacityRounding::Mb => 1024 * 1024,
+            CapacityRounding::Gb => 1024 * 1024 * 1024,
+        };
+        capacity_bytes
+            .checked_next_multiple_of(boundary)
+            .unwrap_or(capacity_bytes)
+    }
+}
+
 /// Options for the export process.
 #[derive(Debug, Clone)]
 pub struct ExportOptions {
     /// Compression level for VMDK output.
     pub compression: CompressionLevel,
+    /// Compressed-to-original ratio above which [`CompressionLevel::Adaptive`]
+    /// drops to fast compression (default [`ADAPTIVE_RATIO_THRESHOLD`]).
+    ///
+    /// Must be a positive number. Raising it keeps maximum compression for
+    /// disks that compress only slightly.
+    pub adaptive_ratio_threshold: f64,
     /// Size of chunks to process (default 64 MB).
     pub chunk_size: usize,
     /// Number of threads to use (0 = auto).
     pub num_threads: usize,
+    /// Emit a zero-filled disk when a descriptor's backing file is missing.
+    ///
+    /// The placeholder has the capacity declared by the descriptor, so the
+    /// OVA structure stays intact. A warning is recorded in the [`ExportReport`].
+    pub synthesize_missing_disks: bool,
+    /// Storage policy name to reference from the OVF (omitted when `None`).
+    pub storage_policy: Option<String>,
+    /// Follow symbolic links when resolving disk files (default `true`).
+    ///
+    /// Disk paths are always canonicalized. When disabled, a descriptor or
+    /// extent file that is itself a symlink is rejected instead of followed.
+    pub follow_symlinks: bool,
+    /// Changed-block maps keyed by disk file name, for incremental exports.
+    ///
+    /// A disk with a map is exported with only its changed ranges, widened to
+    /// whole grains; all other grains are left unallocated and read back as
+    /// zeros. Changed grains are written even when they are all zeros, so
+    /// they overwrite the older data. The result is not
+    /// a VMDK delta: it has no `parentCID` or `parentFileNameHint` linking it
+    /// to the previous export, and is not bootable on its own. Restoring it
+    /// means writing its allocated grains over a copy of that export. Disks
+    /// without a map are exported in full.
+    pub changed_blocks_only: HashMap<String, ChangedBlockMap>,
+    /// Minimum time between per-grain progress callbacks (default zero).
+    ///
+    /// Updates arriving sooner than this after the previous callback are
+    /// coalesced into the next one. Phase changes are always reported.
+    pub progress_interval: Duration,
+    /// Largest disk capacity, in bytes, the export will accept (no limit when `None`).
+    ///
+    /// Checked before any disk is read, so a descriptor declaring an absurd
+    /// capacity fails fast instead of running for hours.
+    pub max_disk_bytes: Option<u64>,
+    /// Advise the kernel that disk files will be read sequentially (default `false`).
+    ///
+    /// Uses `madvise(MADV_SEQUENTIAL)` on Unix and is ignored elsewhere.
+    /// Mostly helps on spinning disks.
+    pub sequential_read_hint: bool,
+    /// Format of the VMDKs written to the OVA (default streamOptimized).
+    ///
+    /// The generated OVF declares the same format. Sparse disks are written
+    /// uncompressed, so `compression` does not apply to them.
+    pub disk_format: DiskFormat,
+    /// Map flat extents this many bytes at a time (whole file when `None`).
+    ///
+    /// Bounds resident memory when exporting very large flat disks on hosts
+    /// with little RAM, at the cost of remapping as the export progresses.
+    pub mmap_window: Option<usize>,
+    /// Carry the VMX's `guestinfo.*` variables into the OVF (default `false`).
+    ///
+    /// Guest variables often hold passwords or tokens, so only the VMware
+    /// Tools settings are preserved unless this is enabled.
+    pub include_guestinfo: bool,
+    /// Carry the VMX's NUMA topology settings (`numa.*` and
+    /// `cpuid.coresPerSocket`) into the OVF (default `false`).
+    ///
+    /// Large VMs tuned for their host's NUMA layout keep that tuning after
+    /// import, but a node affinity only suits hosts with as many nodes.
+    pub include_numa: bool,
+    /// Store disks whose VMDKs come out byte-identical only once (default `false`).
+    ///
+    /// The OVF then references the single file from every disk using it.
+    /// Disks the same size as another are read up front to compare their
+    /// contents, so duplicates are never compressed or written. Ignored with
+    /// a custom OVF, whose References must list every disk file.
+    pub dedup_disks: bool,
+    /// Round disk capacities up to a clean boundary (default none).
+    ///
+    /// Applies to the capacity declared in the OVF and the VMDK header; the
+    /// added tail reads as zeros.
+    pub capacity_rounding: CapacityRounding,
+    /// Check that every streamOptimized disk decompresses to exactly the
+    /// source data (default `false`).
+    ///
+    /// The source and the decompressed grains are hashed as two logical byte
+    /// streams covering the whole disk, and the export fails if they differ.
+    /// Costs a decompression of every grain. Sparse VMDKs store their grains
+    /// uncompressed and are not checked.
+    pub end_to_end_verify: bool,
+    /// Record the SHA256 of each disk's logical (decompressed) contents in a
+    /// sidecar file (default `false`).
+    ///
+    /// The sidecar, `<name>.logical.sha256`, is packaged after the disks and
+    /// uses the manifest's `SHA256(file)= digest` lines. The digests don't go
+    /// in the standard manifest, whose lines must match the files in the OVA.
+    /// Only streamOptimized disks are hashed.
+    pub logical_manifest: bool,
+    /// OVF OS id and `vmw:osType` to declare instead of looking up the VMX's
+    /// `guestOS` (default none).
+    ///
+    /// For guest OSes the built-in table maps to `otherGuest`.
+    pub os_type_override: Option<(u32, String)>,
+    /// Files such as a README or license to package after the disks
+    /// (default none).
+    ///
+    /// Each is stored under its file name and listed in the manifest. The
+    /// export fails if a name is repeated or matches a disk, the OVF
+    /// descriptor, the manifest or the certificate.
+    pub extra_files: Vec<PathBuf>,
+    /// Signs the manifest into a `manifest.cert` file written after it
+    /// (default none, leaving the OVA unsigned).
+    pub manifest_signer: Option<Arc<dyn ManifestSigner>>,
+    /// Digest algorithm of the manifest (default SHA256).
+    ///
+    /// Some older importers only accept `SHA1(file)=` manifest lines.
+    pub manifest_algorithm: ManifestAlgorithm,
+    /// Name to export the VM under instead of its `displayName` (default
+    /// none).
+    ///
+    /// Used for the OVF's `<ovf:Name>`, for the VirtualSystem id of VMs
+    /// without a UUID, and for the OVF descriptor's file name.
+    pub display_name_override: Option<String>,
+    /// Compress the disks of a multi-disk VM concurrently (default false).
+    ///
+    /// Each disk is compressed on its own thread while the OVA is still
+    /// written one disk after another, in disk order. Disks ahead of the one
+    /// being written wait once a few buffers of their output are pending,
+    /// which bounds the memory used. The disks share the `num_threads`
+    /// compression threads, and progress counts the data processed across
+    /// all of them. Only applies to [`DiskFormat::StreamOptimized`].
+    pub parallel_disks: bool,
+    /// Grain size of streamOptimized disks in bytes (default 64 KB).
+    ///
+    /// Must be a power of two of at least 4 KB. Smaller grains skip more
+    /// unused space but add a marker and a grain table entry per grain.
+    pub grain_size: u64,
+    /// Where the OVF descriptor goes in the OVA (default first).
+    pub ova_order: OvaOrder,
+    /// Compression level of each disk, in disk order, overriding
+    /// `compression` (default none).
+    ///
+    /// Lets an incompressible disk, such as an encrypted data disk, be
+    /// compressed quickly while the OS disk is compressed hard. The export
+    /// fails if the number of levels doesn't match the number of disks.
+    pub per_disk_compression: Option<Vec<CompressionLevel>>,
+    /// Image shown as the appliance's logo by deployment wizards (default
+    /// none).
+    ///
+    /// Packaged like the `extra_files`, which may also list it, and
+    /// referenced by an `ovf:Icon` in the ProductSection. Must be a PNG,
+    /// JPEG, GIF, SVG or ICO file, judging by its extension.
+    pub icon: Option<PathBuf>,
+    /// Check the OVF with [`validate_ovf`] before packaging it, failing the
+    /// export if it breaks any rule (default false).
+    ///
+    /// Mostly useful with a caller-supplied OVF, since a generated one is
+    /// valid by construction.
+    pub validate_ovf: bool,
 }
 
 impl Default for ExportOptions {
     fn default() -> Self {
         Self {
             compression: CompressionLevel::Balanced,
+            adaptive_ratio_threshold: ADAPTIVE_RATIO_THRESHOLD,
             chunk_size: DEFAULT_CHUNK_SIZE,
             num_threads: 0,
+            synthesize_missing_disks: false,
+            storage_policy: None,
+            follow_symlinks: true,
+            changed_blocks_only: HashMap::new(),
+            progress_interval: Duration::ZERO,
+            max_disk_bytes: None,
+            sequential_read_hint: false,
+            disk_format: DiskFormat::default(),
+            mmap_window: None,
+            include_guestinfo: false,
+            include_numa: false,
+            dedup_disks: false,
+            capacity_rounding: CapacityRounding::None,
+            end_to_end_verify: false,
+            logical_manifest: false,
+            os_type_override: None,
+            extra_files: Vec::new(),
+            manifest_signer: None,
+            manifest_algorithm: ManifestAlgorithm::default(),
+            display_name_override: None,
+            parallel_disks: false,
+            grain_size: DEFAULT_GRAIN_SIZE * SECTOR_SIZE,
+            ova_order: OvaOrder::default(),
+            per_disk_compression: None,
+            icon: None,
+            validate_ovf: false,
         }
     }
 }
@@ -63,6 +311,7 @@ impl ExportOptions {
             compression,
             chunk_size,
             num_threads,
+            ..Self::default()
         }
     }
 
@@ -70,8 +319,7 @@ impl ExportOptions {
     pub fn fast() -> Self {
         Self {
             compression: CompressionLevel::Fast,
-            chunk_size: DEFAULT_CHUNK_SIZE,
-            num_threads: 0,
+            ..Self::default()
         }
     }
 
@@ -79,14 +327,110 @@ impl ExportOptions {
     pub fn max_compression() -> Self {
         Self {
             compression: CompressionLevel::Max,
-            chunk_size: DEFAULT_CHUNK_SIZE,
-            num_threads: 0,
+            ..Self::default()
         }
     }
+
+    /// Returns the compression level of the disk at `disk_index`.
+    ///
+    /// Taken from `per_disk_compression` when set, otherwise `compression`.
+    pub fn disk_compression(&self, disk_index: usize) -> CompressionLevel {
+        self.per_disk_compression
+            .as_ref()
+            .and_then(|levels| levels.get(disk_index).copied())
+            .unwrap_or(self.compression)
+    }
+
+    /// Start building options, beginning from the defaults.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ovatool_core::{CompressionLevel, ExportOptions};
+    ///
+    /// let options = ExportOptions::builder()
+    ///     .compression(CompressionLevel::Max)
+    ///     .threads(4)
+    ///     .build();
+    /// assert_eq!(options.num_threads, 4);
+    /// ```
+    pub fn builder() -> ExportOptionsBuilder {
+        ExportOptionsBuilder::default()
+    }
+}
+
+/// Builder for [`ExportOptions`], created by [`ExportOptions::builder`].
+///
+/// Options that aren't set keep their default values. Settings without a
+/// method here can be changed on the built options.
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptionsBuilder {
+    options: ExportOptions,
+}
+
+impl ExportOptionsBuilder {
+    /// Set the compression level for VMDK output.
+    pub fn compression(mut self, compression: CompressionLevel) -> Self {
+        self.options.compression = compression;
+        self
+    }
+
+    /// Set the ratio threshold of adaptive compression.
+    pub fn adaptive_ratio_threshold(mut self, threshold: f64) -> Self {
+        self.options.adaptive_ratio_threshold = threshold;
+        self
+    }
+
+    /// Set the size of the chunks disks are read in.
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.options.chunk_size = chunk_size;
+        self
+    }
+
+    /// Set the number of compression threads (0 = auto).
+    pub fn threads(mut self, num_threads: usize) -> Self {
+        self.options.num_threads = num_threads;
+        self
+    }
+
+    /// Set the format of the VMDKs written to the OVA.
+    pub fn disk_format(mut self, disk_format: DiskFormat) -> Self {
+        self.options.disk_format = disk_format;
+        self
+    }
+
+    /// Set the grain size of streamOptimized disks in bytes.
+    pub fn grain_size(mut self, grain_size: u64) -> Self {
+        self.options.grain_size = grain_size;
+        self
+    }
+
+    /// Compress the disks of a multi-disk VM concurrently.
+    pub fn parallel_disks(mut self, parallel_disks: bool) -> Self {
+        self.options.parallel_disks = parallel_disks;
+        self
+    }
+
+    /// Set where the OVF descriptor goes in the OVA.
+    pub fn ova_order(mut self, ova_order: OvaOrder) -> Self {
+        self.options.ova_order = ova_order;
+        self
+    }
+
+    /// Set the minimum time between per-grain progress callbacks.
+    pub fn progress_interval(mut self, interval: Duration) -> Self {
+        self.options.progress_interval = interval;
+        self
+    }
+
+    /// Finish building the options.
+    pub fn build(self) -> ExportOptions {
+        self.options
+    }
 }
 
 /// Phase of the export process.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ExportPhase {
     /// Parsing VMX and VMDK descriptors.
     Parsing,
@@ -117,14 +461,26 @@ impl std::fmt::Display for ExportPhase {
 pub struct ExportProgress {
     /// Current phase of the export.
     pub phase: ExportPhase,
-    /// Bytes processed so far.
+    /// Uncompressed disk bytes processed so far.
     pub bytes_processed: u64,
-    /// Total bytes to process.
+    /// Total uncompressed disk bytes to process.
     pub bytes_total: u64,
+    /// Compressed disk bytes written to the OVA so far.
+    ///
+    /// Advances as each chunk of a disk is written.
+    pub compressed_bytes_written: u64,
+    /// Total compressed disk bytes to write.
+    ///
+    /// Disks are written as they are compressed, so until the finalizing
+    /// phase this is an estimate: it starts at the uncompressed size and is
+    /// refined from the compression ratio of the data written so far.
+    pub compressed_bytes_total: u64,
     /// Current disk being processed (1-indexed).
     pub current_disk: usize,
     /// Total number of disks.
     pub total_disks: usize,
+    /// Time since the export started.
+    pub elapsed: Duration,
 }
 
 impl ExportProgress {
@@ -134,12 +490,39 @@ impl ExportProgress {
             phase,
             bytes_processed: 0,
             bytes_total: total_bytes,
+            compressed_bytes_written: 0,
+            compressed_bytes_total: 0,
             current_disk: 0,
             total_disks,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    /// Re-estimate [`compressed_bytes_total`](Self::compressed_bytes_total)
+    /// from the compression ratio of the data processed so far.
+    fn estimate_compressed_total(&mut self) {
+        if self.bytes_processed == 0 {
+            return;
         }
+        let ratio = self.compressed_bytes_written as f64 / self.bytes_processed as f64;
+        let remaining = self.bytes_total.saturating_sub(self.bytes_processed) as f64;
+        self.compressed_bytes_total = self.compressed_bytes_written + (remaining * ratio) as u64;
+    }
+
+    /// Returns true if there are disks but their total size is 0, so
+    /// progress can't be expressed as a fraction of the disk data.
+    ///
+    /// Progress displays should fall back to an indeterminate indicator,
+    /// such as a spinner, instead of a percentage.
+    pub fn is_indeterminate(&self) -> bool {
+        self.bytes_total == 0 && self.total_disks > 0
     }
 
     /// Calculate overall percentage complete.
+    ///
+    /// Based on uncompressed bytes; see [`percent_written`](Self::percent_written)
+    /// for the writing phase. When [indeterminate](Self::is_indeterminate),
+    /// this stays at 0 until the export completes.
     pub fn percent_complete(&self) -> f64 {
         if self.bytes_total == 0 {
             return match self.phase {
@@ -149,11 +532,297 @@ impl ExportProgress {
         }
         (self.bytes_processed as f64 / self.bytes_total as f64) * 100.0
     }
+
+    /// Calculate the percentage of compressed data written to the OVA.
+    pub fn percent_written(&self) -> f64 {
+        if self.compressed_bytes_total == 0 {
+            return match self.phase {
+                ExportPhase::Complete => 100.0,
+                _ => 0.0,
+            };
+        }
+        (self.compressed_bytes_written as f64 / self.compressed_bytes_total as f64) * 100.0
+    }
+
+    /// Average rate at which disk data has been processed, in uncompressed
+    /// bytes per second.
+    ///
+    /// Returns 0 before any time has elapsed.
+    pub fn bytes_per_sec(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs == 0.0 {
+            return 0.0;
+        }
+        self.bytes_processed as f64 / secs
+    }
+
+    /// Estimated time until all disk data is processed, at the average rate
+    /// so far.
+    ///
+    /// Returns `None` until some data has been processed, since there is no
+    /// rate to go by, and zero once everything is processed.
+    pub fn eta(&self) -> Option<Duration> {
+        let remaining = self.bytes_total.saturating_sub(self.bytes_processed);
+        if remaining == 0 {
+            return Some(Duration::ZERO);
+        }
+        let rate = self.bytes_per_sec();
+        (rate > 0.0).then(|| Duration::from_secs_f64(remaining as f64 / rate))
+    }
 }
 
 /// Type alias for the progress callback function.
+///
+/// A panic in the callback doesn't abort the export: it is caught, the
+/// update is skipped and the export reports it in
+/// [`ExportReport::warnings`].
 pub type ProgressCallback = Box<dyn Fn(ExportProgress) + Send>;
 
+/// Summary of a completed export.
+#[derive(Debug, Clone, Default)]
+pub struct ExportReport {
+    /// Non-fatal problems encountered during the export.
+    pub warnings: Vec<String>,
+    /// Wall-clock time spent in each phase.
+    ///
+    /// [`ExportPhase::Complete`] is a terminal state and has no entry.
+    pub phase_timings: HashMap<ExportPhase, Duration>,
+    /// Per-disk sizes, in the order the disks were exported.
+    pub disks: Vec<DiskStats>,
+    /// Number of worker threads used for compression.
+    pub threads: usize,
+    /// Estimated peak memory held for disk data, in bytes.
+    ///
+    /// StreamOptimized disks are written into the OVA as they are compressed,
+    /// so this counts one batch of chunks (two per compression thread) and
+    /// their compressed grains, regardless of disk size. Sparse VMDKs are
+    /// assembled in memory, so for them it counts the disk's data plus its
+    /// VMDK. Pipeline overhead is not included.
+    pub peak_memory_bytes: u64,
+}
+
+impl ExportReport {
+    /// Render the report as a JSON object, for machine-readable profiles.
+    ///
+    /// Phase durations are given in seconds.
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        json.push_str("{\n");
+        json.push_str(&format!("  \"threads\": {},\n", self.threads));
+        json.push_str(&format!(
+            "  \"peak_memory_bytes\": {},\n",
+            self.peak_memory_bytes
+        ));
+
+        json.push_str("  \"phase_timings\": {");
+        let phases: Vec<String> = [
+            ExportPhase::Parsing,
+            ExportPhase::Compressing,
+            ExportPhase::Writing,
+            ExportPhase::Finalizing,
+        ]
+        .iter()
+        .filter_map(|phase| {
+            self.phase_timings.get(phase).map(|duration| {
+                format!(
+                    "\n    {}: {:.6}",
+                    json_string(&phase.to_string().to_lowercase()),
+                    duration.as_secs_f64()
+                )
+            })
+        })
+        .collect();
+        json.push_str(&phases.join(","));
+        json.push_str(if phases.is_empty() { "},\n" } else { "\n  },\n" });
+
+        json.push_str("  \"disks\": [");
+        let disks: Vec<String> = self
+            .disks
+            .iter()
+            .map(|disk| {
+                let fields = [
+                    format!("\"file_name\": {}", json_string(&disk.file_name)),
+                    format!("\"capacity_bytes\": {}", disk.capacity_bytes),
+                    format!("\"bytes_read\": {}", disk.bytes_read),
+                    format!("\"compressed_bytes\": {}", disk.compressed_bytes),
+                    format!("\"compression_ratio\": {:.6}", disk.compression_ratio()),
+                    format!(
+                        "\"logical_sha256\": {}",
+                        disk.logical_sha256.as_deref().map_or("null".to_string(), json_string)
+                    ),
+                ];
+                format!("\n    {{\n      {}\n    }}", fields.join(",\n      "))
+            })
+            .collect();
+        json.push_str(&disks.join(","));
+        json.push_str(if disks.is_empty() { "],\n" } else { "\n  ],\n" });
+
+        let warnings: Vec<String> = self.warnings.iter().map(|w| json_string(w)).collect();
+        json.push_str(&format!("  \"warnings\": [{}]\n", warnings.join(", ")));
+        json.push_str("}\n");
+        json
+    }
+}
+
+/// Size statistics for one exported disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskStats {
+    /// Disk filename within the OVA.
+    pub file_name: String,
+    /// Virtual disk capacity in bytes.
+    pub capacity_bytes: u64,
+    /// Bytes read from the source; unallocated ranges are skipped.
+    pub bytes_read: u64,
+    /// Size of the VMDK written to the OVA.
+    pub compressed_bytes: u64,
+    /// SHA256 of the whole logical disk, set when
+    /// [`ExportOptions::end_to_end_verify`] checked it or
+    /// [`ExportOptions::logical_manifest`] recorded it.
+    pub logical_sha256: Option<String>,
+    /// Compression level the disk was written with; `None` for sparse
+    /// VMDKs, which are stored uncompressed.
+    pub compression: Option<CompressionLevel>,
+}
+
+impl DiskStats {
+    /// Compressed size as a fraction of the capacity (0.0 for an empty disk).
+    pub fn compression_ratio(&self) -> f64 {
+        if self.capacity_bytes == 0 {
+            return 0.0;
+        }
+        self.compressed_bytes as f64 / self.capacity_bytes as f64
+    }
+}
+
+/// Quote and escape a string for JSON output.
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Accumulates the wall-clock time spent in each export phase.
+struct PhaseTimer {
+    current: ExportPhase,
+    started: Instant,
+    timings: HashMap<ExportPhase, Duration>,
+}
+
+impl PhaseTimer {
+    /// Start timing the given phase.
+    fn start(phase: ExportPhase) -> Self {
+        Self {
+            current: phase,
+            started: Instant::now(),
+            timings: HashMap::new(),
+        }
+    }
+
+    /// Close the current phase and start timing `phase`.
+    fn enter(&mut self, phase: ExportPhase) {
+        let now = Instant::now();
+        *self.timings.entry(self.current).or_default() += now - self.started;
+        self.current = phase;
+        self.started = now;
+    }
+
+    /// Close the current phase and return the accumulated timings.
+    fn finish(mut self) -> HashMap<ExportPhase, Duration> {
+        self.enter(ExportPhase::Complete);
+        self.timings.remove(&ExportPhase::Complete);
+        self.timings
+    }
+}
+
+/// Delivers progress updates to the caller's callback, rate-limiting
+/// updates that arrive in quick succession.
+struct ProgressReporter<'a> {
+    callback: &'a Option<ProgressCallback>,
+    interval: Duration,
+    last_report: Option<Instant>,
+    /// When the export started, for [`ExportProgress::elapsed`].
+    started: Instant,
+    /// Number of updates the callback panicked on.
+    panics: usize,
+    /// Message of the first panic.
+    first_panic: Option<String>,
+}
+
+impl<'a> ProgressReporter<'a> {
+    /// Create a reporter firing throttled updates at most once per `interval`.
+    fn new(callback: &'a Option<ProgressCallback>, interval: Duration) -> Self {
+        Self {
+            callback,
+            interval,
+            last_report: None,
+            started: Instant::now(),
+            panics: 0,
+            first_panic: None,
+        }
+    }
+
+    /// Report an update unconditionally (phase changes, completion).
+    ///
+    /// A panic in the callback is caught and counted, so a faulty progress
+    /// display can't abort the export.
+    fn report(&mut self, progress: &ExportProgress) {
+        if let Some(callback) = self.callback {
+            let progress = ExportProgress {
+                elapsed: self.started.elapsed(),
+                ..progress.clone()
+            };
+            let outcome = std::panic::catch_unwind(AssertUnwindSafe(|| callback(progress)));
+            if let Err(payload) = outcome {
+                self.panics += 1;
+                self.first_panic.get_or_insert_with(|| panic_message(payload.as_ref()));
+            }
+            self.last_report = Some(Instant::now());
+        }
+    }
+
+    /// Warning describing the callback's panics, if it panicked.
+    fn panic_warning(&self) -> Option<String> {
+        self.first_panic.as_ref().map(|message| {
+            format!(
+                "Progress callback panicked on {} update(s), first with '{}'; those updates \
+                 were skipped",
+                self.panics, message
+            )
+        })
+    }
+
+    /// Report an update unless one was reported within the interval.
+    fn report_throttled(&mut self, progress: &ExportProgress) {
+        let due = self
+            .last_report
+            .is_none_or(|last| last.elapsed() >= self.interval);
+        if due {
+            self.report(progress);
+        }
+    }
+}
+
+/// Text of a caught panic's payload.
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "non-string panic payload".to_string())
+}
+
 /// Detail information about a disk.
 #[derive(Debug, Clone)]
 pub struct DiskDetail {
@@ -161,8 +830,14 @@ pub struct DiskDetail {
     pub filename: String,
     /// Size of the disk in bytes.
     pub size_bytes: u64,
-    /// VMDK create type (e.g., "monolithicFlat", "twoGbMaxExtentSparse").
-    pub create_type: String,
+    /// VMDK create type (e.g., `monolithicFlat`, `twoGbMaxExtentSparse`).
+    pub create_type: CreateType,
+    /// Most restrictive access mode of the disk's extents.
+    pub access: ExtentAccess,
+    /// Whether VMware may delete the disk along with the VM, from
+    /// `ddb.deletable`. Template base disks shared by linked clones are not
+    /// deletable.
+    pub deletable: bool,
 }
 
 /// Summary information about a VM.
@@ -180,6 +855,8 @@ pub struct VmInfo {
     pub disks: Vec<DiskDetail>,
     /// Total size of all disks in bytes.
     pub total_disk_size: u64,
+    /// Virtual hardware version (e.g. `vmx-21`), if known.
+    pub hardware_version: Option<String>,
 }
 
 /// Get information about a VM without exporting it.
@@ -204,18 +881,28 @@ pub fn get_vm_info(vmx_path: &Path) -> Result<VmInfo> {
         let vmdk_path = vmx_dir.join(&disk_config.file_name);
 
         // Try to read the VMDK descriptor or sparse header
-        let (size_bytes, create_type) = if vmdk_path.exists() {
+        let (size_bytes, create_type, access, deletable) = if vmdk_path.exists() {
             // Check if this is a sparse VMDK (binary) or text descriptor
             if is_sparse_vmdk(&vmdk_path)? {
                 // Sparse VMDK - read capacity from header
                 let sparse_reader = SparseVmdkReader::open(&vmdk_path)?;
-                (sparse_reader.capacity(), "monolithicSparse".to_string())
+                (
+                    sparse_reader.capacity(),
+                    CreateType::MonolithicSparse,
+                    ExtentAccess::ReadWrite,
+                    true,
+                )
             } else {
                 // Text descriptor
                 let content = fs::read_to_string(&vmdk_path)
                     .map_err(|e| Error::io(e, &vmdk_path))?;
                 let descriptor = parse_descriptor(&content)?;
-                (descriptor.disk_size_bytes(), descriptor.create_type.clone())
+                (
+                    descriptor.disk_size_bytes(),
+                    descriptor.create_type.clone(),
+                    descriptor.access_mode(),
+                    descriptor.deletable,
+                )
             }
         } else {
             // If descriptor doesn't exist, check for flat file
@@ -224,9 +911,14 @@ pub fn get_vm_info(vmx_path: &Path) -> Result<VmInfo> {
             if flat_path.exists() {
                 let metadata = fs::metadata(&flat_path)
                     .map_err(|e| Error::io(e, &flat_path))?;
-                (metadata.len(), "monolithicFlat".to_string())
+                (metadata.len(), CreateType::MonolithicFlat, ExtentAccess::ReadWrite, true)
             } else {
-                (0, "unknown".to_string())
+                (
+                    0,
+                    CreateType::Unknown("unknown".to_string()),
+                    ExtentAccess::ReadWrite,
+                    true,
+                )
             }
         };
 
@@ -235,6 +927,8 @@ pub fn get_vm_info(vmx_path: &Path) -> Result<VmInfo> {
             filename: disk_config.file_name.clone(),
             size_bytes,
             create_type,
+            access,
+            deletable,
         });
     }
 
@@ -245,9 +939,388 @@ pub fn get_vm_info(vmx_path: &Path) -> Result<VmInfo> {
         cpus: config.num_cpus,
         disks,
         total_disk_size,
+        hardware_version: config.hardware_version(),
+    })
+}
+
+/// Get information about a VM packaged in an OVA archive.
+///
+/// Reads only the OVF descriptor; disk sizes are the virtual capacities
+/// it declares.
+///
+/// # Arguments
+///
+/// * `ova_path` - Path to the OVA file.
+///
+/// # Returns
+///
+/// A `VmInfo` struct with details about the packaged VM.
+pub fn get_ova_info(ova_path: &Path) -> Result<VmInfo> {
+    let file = File::open(ova_path).map_err(|e| Error::io(e, ova_path))?;
+    let ovf = OvaReader::new(file)?.read_ovf()?;
+    let summary = parse_ovf_summary(&ovf)?;
+
+    let disks: Vec<DiskDetail> = summary
+        .disks
+        .into_iter()
+        .map(|disk| DiskDetail {
+            filename: disk.file_name,
+            size_bytes: disk.capacity,
+            create_type: CreateType::parse(&disk.format),
+            access: ExtentAccess::ReadWrite,
+            deletable: true,
+        })
+        .collect();
+    let total_disk_size = disks.iter().map(|disk| disk.size_bytes).sum();
+
+    Ok(VmInfo {
+        name: summary.name,
+        guest_os: summary.os_type,
+        memory_mb: summary.memory_mb,
+        cpus: summary.cpus,
+        disks,
+        total_disk_size,
+        hardware_version: summary.hardware_version,
+    })
+}
+
+/// How a disk's data is stored, as far as planning an export can tell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiskLayout {
+    /// Raw data in flat extent files.
+    Flat,
+    /// Grain-allocated data in hosted sparse or streamOptimized files.
+    Sparse,
+    /// The descriptor or a data file is missing.
+    Missing,
+    /// The disk can't be exported, e.g. it only lives on a VMFS datastore.
+    Unsupported,
+}
+
+/// A disk as an export would read it.
+#[derive(Debug, Clone)]
+pub struct DiskPlan {
+    /// Filename of the disk in the VMX, also its name in the OVA.
+    pub file_name: String,
+    /// Files the export reads: descriptors and extents, the parents of
+    /// snapshot deltas included.
+    pub source_paths: Vec<PathBuf>,
+    /// Create type of the disk's own VMDK.
+    pub create_type: CreateType,
+    /// Virtual capacity in bytes; 0 if unknown.
+    pub capacity_bytes: u64,
+    /// How the disk's data is stored.
+    pub layout: DiskLayout,
+    /// Whether the data is split over several extent files.
+    pub split: bool,
+    /// Whether the disk is a snapshot delta layered over a parent.
+    pub delta: bool,
+    /// Problems that would keep the disk from being exported; also listed
+    /// in [`ExportPlan::warnings`].
+    pub problems: Vec<String>,
+}
+
+/// What an export would read and write, worked out without writing.
+#[derive(Debug, Clone)]
+pub struct ExportPlan {
+    /// Name the VM would be exported under.
+    pub name: String,
+    /// The disks, in VMX order.
+    pub disks: Vec<DiskPlan>,
+    /// Files the OVA would hold, in archive order.
+    pub output_files: Vec<String>,
+    /// Total virtual capacity of the disks in bytes.
+    pub total_capacity_bytes: u64,
+    /// Problems with the VM as a whole that would make the export fail,
+    /// such as encryption; those of one disk are in [`DiskPlan::problems`].
+    pub problems: Vec<String>,
+    /// Problems found, including those that would make the export fail.
+    pub warnings: Vec<String>,
+}
+
+impl ExportPlan {
+    /// Returns true if the VM has no problems as a whole and every disk can
+    /// be read, possibly by synthesizing missing ones as the options allow.
+    pub fn is_exportable(&self) -> bool {
+        self.problems.is_empty()
+            && self.disks.iter().all(|disk| match disk.layout {
+                DiskLayout::Flat | DiskLayout::Sparse => true,
+                DiskLayout::Missing | DiskLayout::Unsupported => false,
+            })
+    }
+}
+
+/// Work out what exporting a VM would do, without reading disk data or
+/// writing anything.
+///
+/// Only descriptors and sparse headers are read. Problems that would make
+/// [`export_vm`] fail, such as encryption, a missing flat extent or an
+/// unsupported createType, are reported in [`ExportPlan::warnings`] rather
+/// than as errors, so every problem is listed at once.
+///
+/// # Arguments
+///
+/// * `vmx_path` - Path to the VMX file.
+/// * `options` - Export options the export would use.
+///
+/// # Returns
+///
+/// The export plan, or an error if the VMX can't be parsed.
+pub fn plan_export(vmx_path: &Path, options: &ExportOptions) -> Result<ExportPlan> {
+    let config = parse_vmx(vmx_path)?;
+    let vmx_dir = vmx_path
+        .parent()
+        .ok_or_else(|| Error::vmx_parse("VMX path has no parent directory"))?;
+    let name = options
+        .display_name_override
+        .clone()
+        .unwrap_or_else(|| config.display_name.clone());
+
+    let mut warnings = Vec::new();
+    let mut problems = Vec::new();
+    if config.is_encrypted() {
+        problems.push(ENCRYPTED_VM_PROBLEM.to_string());
+        warnings.push(ENCRYPTED_VM_PROBLEM.to_string());
+    }
+    if config.has_blank_display_name() {
+        warnings.push(blank_display_name_warning(&config));
+    }
+    let disks: Vec<DiskPlan> = config
+        .disks
+        .iter()
+        .map(|disk_config| {
+            let mut plan = DiskPlan {
+                file_name: disk_config.file_name.clone(),
+                source_paths: Vec::new(),
+                create_type: CreateType::Unknown(String::new()),
+                capacity_bytes: 0,
+                layout: DiskLayout::Missing,
+                split: false,
+                delta: false,
+                problems: Vec::new(),
+            };
+            let path = vmx_dir.join(&disk_config.file_name);
+            plan_vmdk(vmx_dir, &path, options, &mut plan, &mut warnings, &mut Vec::new());
+            plan
+        })
+        .collect();
+
+    let nvram_name = config.nvram.as_ref().and_then(|nvram| {
+        let nvram_path = vmx_dir.join(nvram);
+        if nvram_path.is_file() {
+            archive_file_name(&nvram_path).ok()
+        } else {
+            warnings.push(format!(
+                "NVRAM file '{}' not found; exported without it",
+                nvram_path.display()
+            ));
+            None
+        }
+    });
+
+    let ovf_filename = format!("{}.ovf", sanitize_filename(&name));
+    let mut output_files = Vec::new();
+    if options.ova_order == OvaOrder::OvfFirst {
+        output_files.push(ovf_filename.clone());
+    }
+    output_files.extend(disks.iter().map(|disk| disk.file_name.clone()));
+    output_files.extend(nvram_name);
+    output_files.extend(options.extra_files.iter().filter_map(|path| archive_file_name(path).ok()));
+    if let Some(icon) = options.icon.as_ref().filter(|icon| !options.extra_files.contains(icon)) {
+        output_files.extend(archive_file_name(icon).ok());
+    }
+    if options.logical_manifest {
+        output_files.push(format!("{}{}", sanitize_filename(&name), LOGICAL_MANIFEST_SUFFIX));
+    }
+    if options.ova_order == OvaOrder::OvfLast {
+        output_files.push(ovf_filename);
+    }
+    output_files.push(MANIFEST_FILE_NAME.to_string());
+    if options.manifest_signer.is_some() {
+        output_files.push(CERT_FILE_NAME.to_string());
+    }
+
+    Ok(ExportPlan {
+        name,
+        total_capacity_bytes: disks.iter().map(|disk| disk.capacity_bytes).sum(),
+        disks,
+        output_files,
+        problems,
+        warnings,
     })
 }
 
+/// Plan one VMDK of a disk's snapshot chain, following [`open_vmdk`]
+/// without reading any disk data.
+///
+/// The top of the chain decides the disk's create type, capacity and
+/// layout; problems anywhere in the chain override the layout.
+fn plan_vmdk(
+    extent_dir: &Path,
+    path: &Path,
+    options: &ExportOptions,
+    plan: &mut DiskPlan,
+    warnings: &mut Vec<String>,
+    chain: &mut Vec<PathBuf>,
+) {
+    let vmdk_path = match resolve_disk_path(path, options.follow_symlinks) {
+        Ok(vmdk_path) if vmdk_path.exists() => vmdk_path,
+        Ok(vmdk_path) => {
+            let message = format!("'{}' not found", vmdk_path.display());
+            return plan_problem(plan, warnings, DiskLayout::Missing, message);
+        }
+        Err(e) => return plan_problem(plan, warnings, DiskLayout::Unsupported, e.to_string()),
+    };
+    if chain.contains(&vmdk_path) {
+        let message = format!("snapshot chain loops back to '{}'", vmdk_path.display());
+        return plan_problem(plan, warnings, DiskLayout::Unsupported, message);
+    }
+    chain.push(vmdk_path.clone());
+    plan.source_paths.push(vmdk_path.clone());
+    let is_top = chain.len() == 1;
+
+    let descriptor = match is_sparse_vmdk(&vmdk_path) {
+        // Sparse VMDK - the file itself contains the data
+        Ok(true) => {
+            let reader = match SparseVmdkReader::open(&vmdk_path) {
+                Ok(reader) => reader,
+                Err(e) => {
+                    return plan_problem(plan, warnings, DiskLayout::Unsupported, e.to_string())
+                }
+            };
+            if is_top {
+                plan.create_type = if reader.is_stream_optimized() {
+                    CreateType::StreamOptimized
+                } else {
+                    CreateType::MonolithicSparse
+                };
+                plan.capacity_bytes = reader.capacity();
+                plan.layout = DiskLayout::Sparse;
+            }
+            match reader.embedded_descriptor() {
+                Ok(Some(text)) => parse_descriptor(&text).ok(),
+                _ => None,
+            }
+        }
+        // Text descriptor - check the data files it names
+        Ok(false) => {
+            let descriptor = fs::read_to_string(&vmdk_path)
+                .map_err(|e| Error::io(e, &vmdk_path))
+                .and_then(|content| parse_descriptor(&content));
+            let descriptor = match descriptor {
+                Ok(descriptor) => descriptor,
+                Err(e) => {
+                    return plan_problem(plan, warnings, DiskLayout::Unsupported, e.to_string())
+                }
+            };
+            if is_top {
+                plan.create_type = descriptor.create_type.clone();
+                plan.capacity_bytes = descriptor.disk_size_bytes();
+            }
+            plan_extents(extent_dir, &descriptor, options, plan, warnings, is_top);
+            Some(descriptor)
+        }
+        Err(e) => return plan_problem(plan, warnings, DiskLayout::Unsupported, e.to_string()),
+    };
+
+    let Some(descriptor) = descriptor.filter(VmdkDescriptor::is_delta) else {
+        return;
+    };
+    plan.delta = true;
+    match locate_parent(&vmdk_path, &descriptor) {
+        Ok(parent_path) => {
+            let parent_dir = parent_path.parent().unwrap_or_else(|| Path::new(""));
+            plan_vmdk(parent_dir, &parent_path, options, plan, warnings, chain);
+        }
+        Err(Error::Vmdk { message }) => {
+            let message = snapshot_guidance(path, &message).to_string();
+            plan_problem(plan, warnings, DiskLayout::Missing, message);
+        }
+        Err(e) => plan_problem(plan, warnings, DiskLayout::Missing, e.to_string()),
+    }
+}
+
+/// Plan the data extents a text descriptor names.
+fn plan_extents(
+    extent_dir: &Path,
+    descriptor: &VmdkDescriptor,
+    options: &ExportOptions,
+    plan: &mut DiskPlan,
+    warnings: &mut Vec<String>,
+    is_top: bool,
+) {
+    warnings.extend(
+        descriptor
+            .warnings
+            .iter()
+            .map(|warning| format!("disk '{}': {}", plan.file_name, warning)),
+    );
+
+    // Flat extents are read in preference to sparse ones, as by open_vmdk
+    let flat: Vec<&Extent> = descriptor
+        .extents
+        .iter()
+        .filter(|e| e.extent_type == ExtentType::Flat)
+        .collect();
+    let (extents, layout) = if !flat.is_empty() {
+        (flat, DiskLayout::Flat)
+    } else {
+        let sparse: Vec<&Extent> = descriptor
+            .extents
+            .iter()
+            .filter(|e| e.extent_type == ExtentType::Sparse)
+            .collect();
+        if sparse.is_empty() {
+            let message = descriptor.vmfs_clone_guidance().unwrap_or_else(|| {
+                format!("Unsupported VMDK type: {}", descriptor.create_type)
+            });
+            return plan_problem(plan, warnings, DiskLayout::Unsupported, message);
+        }
+        (sparse, DiskLayout::Sparse)
+    };
+    if is_top {
+        plan.layout = layout;
+        plan.split = extents.len() > 1;
+    }
+
+    for extent in extents {
+        let extent_path = extent_dir.join(&extent.filename);
+        let extent_path = match resolve_disk_path(&extent_path, options.follow_symlinks) {
+            Ok(extent_path) => extent_path,
+            Err(e) => return plan_problem(plan, warnings, DiskLayout::Unsupported, e.to_string()),
+        };
+        if extent_path.exists() {
+            let checked = match layout {
+                DiskLayout::Flat => {
+                    check_flat_extent(&plan.file_name, &extent_path, extent.size_sectors)
+                }
+                _ => Ok(()),
+            };
+            if let Err(e) = checked {
+                plan_problem(plan, warnings, DiskLayout::Unsupported, e.to_string());
+            }
+            plan.source_paths.push(extent_path);
+        } else if options.synthesize_missing_disks {
+            warnings.push(missing_disk_warning(&plan.file_name, &extent_path, plan.capacity_bytes));
+        } else {
+            let message = format!("extent '{}' not found", extent_path.display());
+            plan_problem(plan, warnings, DiskLayout::Missing, message);
+        }
+    }
+}
+
+/// Record a problem that would keep a disk from being exported.
+fn plan_problem(
+    plan: &mut DiskPlan,
+    warnings: &mut Vec<String>,
+    layout: DiskLayout,
+    message: String,
+) {
+    plan.layout = layout;
+    warnings.push(format!("disk '{}': {}", plan.file_name, message));
+    plan.problems.push(message);
+}
+
 /// Export a VMware VM to OVA format.
 ///
 /// This is the main entry point for the export process. It:
@@ -270,7 +1343,7 @@ pub fn get_vm_info(vmx_path: &Path) -> Result<VmInfo> {
 ///
 /// # Returns
 ///
-/// `Ok(())` on success, or an error if export fails.
+/// An [`ExportReport`] on success, or an error if export fails.
 ///
 /// # Example
 ///
@@ -292,442 +1365,1665 @@ pub fn export_vm(
     output_path: &Path,
     options: ExportOptions,
     progress_callback: Option<ProgressCallback>,
-) -> Result<()> {
-    // Helper to call progress callback if provided
-    let report_progress = |progress: ExportProgress| {
-        if let Some(ref callback) = progress_callback {
-            callback(progress);
-        }
-    };
+) -> Result<ExportReport> {
+    let output = FileOutput(output_path);
+    export_vm_impl(vmx_path, output, options, None, progress_callback)
+}
 
-    // Phase 1: Parsing
-    let config = parse_vmx(vmx_path)?;
-    let vmx_dir = vmx_path
-        .parent()
-        .ok_or_else(|| Error::vmx_parse("VMX path has no parent directory"))?;
+/// Export a VMware VM to OVA format, writing the archive to `writer`.
+///
+/// Works like [`export_vm`], but the OVA goes to any seekable sink, such as
+/// an in-memory `Cursor` or an upload buffer, instead of a new file. The
+/// archive is written from the writer's position on entry, leaving any bytes
+/// before it untouched, and ends at its position on return.
+///
+/// # Arguments
+///
+/// * `vmx_path` - Path to the VMX file.
+/// * `writer` - Sink for the OVA archive.
+/// * `options` - Export options (compression level, chunk size, etc.).
+/// * `progress_callback` - Optional callback for progress updates.
+///
+/// # Returns
+///
+/// An [`ExportReport`] on success, or an error if export fails.
+///
+/// # Example
+///
+/// ```no_run
+/// use ovatool_core::{export_vm_to_writer, ExportOptions};
+/// use std::io::Cursor;
+/// use std::path::Path;
+///
+/// let mut ova = Cursor::new(Vec::new());
+/// export_vm_to_writer(Path::new("/path/to/vm.vmx"), &mut ova, ExportOptions::default(), None)
+///     .unwrap();
+/// ```
+pub fn export_vm_to_writer<W: Write + Seek>(
+    vmx_path: &Path,
+    writer: W,
+    options: ExportOptions,
+    progress_callback: Option<ProgressCallback>,
+) -> Result<ExportReport> {
+    let output = WriterOutput(Some(writer));
+    export_vm_impl(vmx_path, output, options, None, progress_callback)
+}
 
-    // Calculate total disk size for progress tracking
-    let total_disk_size = calculate_total_disk_size(&config, vmx_dir)?;
-    let total_disks = config.disks.len();
+/// Export a VMware VM to OVA format using a caller-supplied OVF descriptor.
+///
+/// Disks are converted exactly as in [`export_vm`], but instead of generating
+/// an OVF the provided document is packaged verbatim. It must be well-formed
+/// and its `ovf:File` hrefs must name exactly the disks being exported.
+///
+/// # Arguments
+///
+/// * `vmx_path` - Path to the VMX file.
+/// * `output_path` - Path for the output OVA file.
+/// * `options` - Export options (compression level, chunk size, etc.).
+/// * `ovf_xml` - The OVF descriptor to include in the OVA.
+/// * `progress_callback` - Optional callback for progress updates.
+///
+/// # Returns
+///
+/// An [`ExportReport`] on success, or an error if the OVF is rejected or
+/// export fails.
+pub fn export_vm_with_ovf(
+    vmx_path: &Path,
+    output_path: &Path,
+    options: ExportOptions,
+    ovf_xml: &str,
+    progress_callback: Option<ProgressCallback>,
+) -> Result<ExportReport> {
+    let output = FileOutput(output_path);
+    export_vm_impl(vmx_path, output, options, Some(ovf_xml), progress_callback)
+}
 
-    let mut progress = ExportProgress::new(ExportPhase::Parsing, total_disk_size, total_disks);
-    report_progress(progress.clone());
+/// Where an export writes its OVA archive.
+trait ExportOutput {
+    /// The sink the archive is written to.
+    type Writer: Write + Seek;
 
-    // Create the pipeline for parallel compression
-    let pipeline_config = PipelineConfig::new(
-        options.chunk_size,
-        options.compression,
-        options.num_threads,
-    );
-    let pipeline = Pipeline::new(pipeline_config);
-    let compression_level = pipeline.compression_level();
+    /// Open the sink, once the export's inputs have been checked.
+    fn open(&mut self) -> Result<Self::Writer>;
 
-    // Create output file and OVA writer
-    let output_file = File::create(output_path)
-        .map_err(|e| Error::io(e, output_path))?;
-    let mut ova_writer = OvaWriter::new(output_file)?;
+    /// Attach context to an error writing to the sink.
+    fn error(&self, e: std::io::Error) -> Error;
+}
 
-    // Process each disk
-    let mut disk_infos: Vec<DiskInfo> = Vec::new();
-    let mut vmdk_buffers: Vec<(String, Vec<u8>, u64)> = Vec::new(); // (filename, compressed data, capacity)
+/// A new file at the given path.
+struct FileOutput<'a>(&'a Path);
 
-    for (disk_index, disk_config) in config.disks.iter().enumerate() {
-        progress.phase = ExportPhase::Compressing;
-        progress.current_disk = disk_index + 1;
-        report_progress(progress.clone());
+impl ExportOutput for FileOutput<'_> {
+    type Writer = File;
 
-        // Get the VMDK path
-        let vmdk_path = vmx_dir.join(&disk_config.file_name);
+    fn open(&mut self) -> Result<File> {
+        File::create(self.0).map_err(|e| self.error(e))
+    }
 
-        // Check if this is a sparse VMDK (binary) or a descriptor file (text)
-        // and determine which processing method to use
-        enum DiskType {
-            /// Single monolithic sparse VMDK file
-            MonolithicSparse(std::path::PathBuf, u64),
-            /// Flat VMDK with separate data file
-            Flat(std::path::PathBuf, u64),
-            /// Split sparse VMDK (twoGbMaxExtentSparse) with multiple extent files
-            SplitSparse(Vec<Extent>, std::path::PathBuf, u64),
-        }
-
-        let disk_type = if is_sparse_vmdk(&vmdk_path)? {
-            // Sparse VMDK - the file itself contains the data
-            let sparse_reader = SparseVmdkReader::open(&vmdk_path)?;
-            let capacity = sparse_reader.capacity();
-            DiskType::MonolithicSparse(vmdk_path.clone(), capacity)
-        } else {
-            // Text descriptor - parse it to find the data file(s)
-            let descriptor_content = fs::read_to_string(&vmdk_path)
-                .map_err(|e| Error::io(e, &vmdk_path))?;
-            let descriptor = parse_descriptor(&descriptor_content)?;
-            let capacity = descriptor.disk_size_bytes();
-
-            // Check for flat extent first (monolithicFlat, twoGbMaxExtentFlat)
-            if let Some(flat_extent) = descriptor
-                .extents
-                .iter()
-                .find(|e| e.extent_type == ExtentType::Flat)
-            {
-                let flat_path = vmx_dir.join(&flat_extent.filename);
-                DiskType::Flat(flat_path, capacity)
+    fn error(&self, e: std::io::Error) -> Error {
+        Error::io(e, self.0)
+    }
+}
+
+/// A caller-supplied writer, handed out once.
+struct WriterOutput<W>(W);
+
+impl<W: Write + Seek> ExportOutput for WriterOutput<Option<W>> {
+    type Writer = W;
+
+    fn open(&mut self) -> Result<W> {
+        self.0
+            .take()
+            .ok_or_else(|| Error::pipeline("Export output was already opened"))
+    }
+
+    fn error(&self, e: std::io::Error) -> Error {
+        Error::io_simple(e)
+    }
+}
+
+/// Shared implementation of [`export_vm`], [`export_vm_with_ovf`] and
+/// [`export_vm_to_writer`].
+fn export_vm_impl(
+    vmx_path: &Path,
+    output: impl ExportOutput,
+    options: ExportOptions,
+    custom_ovf: Option<&str>,
+    progress_callback: Option<ProgressCallback>,
+) -> Result<ExportReport> {
+    let parse_start = Instant::now();
+    let mut config = parse_vmx(vmx_path)?;
+    if config.is_encrypted() {
+        return Err(Error::unsupported(ENCRYPTED_VM_PROBLEM));
+    }
+    let vmx_dir = vmx_path
+        .parent()
+        .ok_or_else(|| Error::vmx_parse("VMX path has no parent directory"))?;
+    apply_descriptor_adapter_types(&mut config, vmx_dir);
+
+    // Resolve each disk to the source its data is read from
+    let mut warnings = Vec::new();
+    if config.has_blank_display_name() {
+        warnings.push(blank_display_name_warning(&config));
+    }
+    let sources = config
+        .disks
+        .iter()
+        .map(|disk_config| open_disk_source(vmx_dir, &disk_config.file_name, &options, &mut warnings))
+        .collect::<Result<Vec<_>>>()?;
+
+    // The NVRAM file is packaged when the OVF is generated, which references it
+    let nvram = match (&config.nvram, custom_ovf) {
+        (Some(nvram), None) => {
+            let nvram_path = vmx_dir.join(nvram);
+            if nvram_path.is_file() {
+                Some(nvram_path)
             } else {
-                // Check for sparse extents (twoGbMaxExtentSparse, etc.)
-                let sparse_extents: Vec<Extent> = descriptor
-                    .extents
-                    .iter()
-                    .filter(|e| e.extent_type == ExtentType::Sparse)
-                    .cloned()
-                    .collect();
-
-                if !sparse_extents.is_empty() {
-                    DiskType::SplitSparse(sparse_extents, vmx_dir.to_path_buf(), capacity)
-                } else {
-                    return Err(Error::vmdk(
-                        "No supported extent type found in VMDK descriptor (expected FLAT or SPARSE)"
-                    ));
-                }
+                warnings.push(format!(
+                    "NVRAM file '{}' not found; exported without it",
+                    nvram_path.display()
+                ));
+                None
             }
+        }
+        _ => None,
+    };
+
+    let parse_time = parse_start.elapsed();
+
+    let mut report = export_sources_impl(
+        &config,
+        sources,
+        output,
+        options,
+        custom_ovf,
+        nvram.as_deref(),
+        progress_callback,
+    )?;
+    warnings.append(&mut report.warnings);
+    report.warnings = warnings;
+    *report.phase_timings.entry(ExportPhase::Parsing).or_default() += parse_time;
+
+    Ok(report)
+}
+
+/// SCSI adapter models a descriptor's `ddb.adapterType` can name.
+const SCSI_ADAPTER_TYPES: &[&str] = &["lsilogic", "buslogic", "lsisas1068", "pvscsi"];
+
+/// Fills in the model of SCSI controllers the VMX leaves unspecified.
+///
+/// Each takes the `ddb.adapterType` of the first disk attached to it. Disks
+/// whose descriptor can't be read keep the default model; opening them
+/// reports the problem.
+fn apply_descriptor_adapter_types(config: &mut VmxConfig, vmx_dir: &Path) {
+    for controller in config.controllers.iter_mut() {
+        if controller.virtual_dev.is_some() {
+            continue;
+        }
+        let Some(disk) = config.disks.iter().find(|d| d.controller == controller.name) else {
+            continue;
         };
+        let adapter_type = read_descriptor(&vmx_dir.join(&disk.file_name))
+            .ok()
+            .flatten()
+            .map(|descriptor| descriptor.adapter_type.to_ascii_lowercase());
+        controller.virtual_dev =
+            adapter_type.filter(|adapter| SCSI_ADAPTER_TYPES.contains(&adapter.as_str()));
+    }
+}
 
-        // Read and compress the disk data based on disk type
-        let (compressed_vmdk, capacity_bytes) = match disk_type {
-            DiskType::MonolithicSparse(path, capacity) => {
-                let vmdk = process_sparse_disk(
-                    &path,
-                    capacity,
-                    &pipeline,
-                    compression_level,
-                    options.chunk_size,
-                    &mut progress,
-                    &progress_callback,
-                )?;
-                (vmdk, capacity)
+/// Export a VM whose disks are read from arbitrary [`DiskSource`]s.
+///
+/// This is the backend of [`export_vm`], exposed so disks can be read from
+/// places other than the local filesystem. Each source provides the data for
+/// the disk at the same index in `config.disks`; the disk's `file_name` is
+/// used as its name inside the OVA.
+///
+/// # Arguments
+///
+/// * `config` - VM configuration used to build the OVF descriptor.
+/// * `sources` - One data source per entry in `config.disks`.
+/// * `output_path` - Path for the output OVA file.
+/// * `options` - Export options (compression level, chunk size, etc.).
+/// * `progress_callback` - Optional callback for progress updates.
+///
+/// # Returns
+///
+/// An [`ExportReport`] on success, or an error if export fails.
+pub fn export_sources(
+    config: &VmxConfig,
+    sources: Vec<Box<dyn DiskSource>>,
+    output_path: &Path,
+    options: ExportOptions,
+    progress_callback: Option<ProgressCallback>,
+) -> Result<ExportReport> {
+    let output = FileOutput(output_path);
+    export_sources_impl(config, sources, output, options, None, None, progress_callback)
+}
+
+/// Shared implementation of the export entry points.
+///
+/// Packages `custom_ovf` verbatim when given, otherwise generates the OVF.
+/// The `nvram` file, if any, is packaged after the disks.
+fn export_sources_impl(
+    config: &VmxConfig,
+    sources: Vec<Box<dyn DiskSource>>,
+    mut output: impl ExportOutput,
+    options: ExportOptions,
+    custom_ovf: Option<&str>,
+    nvram: Option<&Path>,
+    progress_callback: Option<ProgressCallback>,
+) -> Result<ExportReport> {
+    let mut report = ExportReport::default();
+    let mut timer = PhaseTimer::start(ExportPhase::Parsing);
+
+    if sources.len() != config.disks.len() {
+        return Err(Error::pipeline(format!(
+            "Expected {} disk sources, got {}",
+            config.disks.len(),
+            sources.len()
+        )));
+    }
+
+    // Reject a custom OVF before spending time on the disks
+    if let Some(ovf_xml) = custom_ovf {
+        validate_custom_ovf(ovf_xml, config)?;
+    }
+
+    let renamed;
+    let config = match &options.display_name_override {
+        Some(name) => {
+            renamed = VmxConfig {
+                display_name: name.clone(),
+                ..config.clone()
+            };
+            &renamed
+        }
+        None => config,
+    };
+
+    // OVF filename is based on VM name
+    let ovf_filename = format!("{}.ovf", sanitize_filename(&config.display_name));
+    let nvram_name = nvram.map(archive_file_name).transpose()?;
+    let logical_manifest_name = options.logical_manifest.then(|| {
+        format!("{}{}", sanitize_filename(&config.display_name), LOGICAL_MANIFEST_SUFFIX)
+    });
+    let mut reserved_names = vec![ovf_filename.as_str()];
+    reserved_names.extend(nvram_name.as_deref());
+    reserved_names.extend(logical_manifest_name.as_deref());
+    let mut extra_files = options.extra_files.clone();
+    let icon = match &options.icon {
+        Some(path) => {
+            let mime_type = icon_mime_type(path)?;
+            if !extra_files.contains(path) {
+                extra_files.push(path.clone());
             }
-            DiskType::Flat(path, capacity) => {
-                let vmdk = process_disk(
-                    &path,
-                    capacity,
-                    &pipeline,
-                    compression_level,
-                    options.chunk_size,
-                    &mut progress,
-                    &progress_callback,
-                )?;
-                (vmdk, capacity)
+            Some((archive_file_name(path)?, mime_type))
+        }
+        None => None,
+    };
+    let extra_file_names = extra_file_names(&extra_files, config, &reserved_names)?;
+
+    if let Some(limit) = options.max_disk_bytes {
+        check_disk_sizes(config, &sources, limit)?;
+    }
+    if !options.grain_size.is_multiple_of(SECTOR_SIZE) {
+        return Err(Error::validation(format!(
+            "Grain size of {} bytes is not a whole number of sectors",
+            options.grain_size
+        )));
+    }
+    let grain_size_sectors = options.grain_size / SECTOR_SIZE;
+    check_grain_size(grain_size_sectors)?;
+    let threshold = options.adaptive_ratio_threshold;
+    if !threshold.is_finite() || threshold <= 0.0 {
+        return Err(Error::validation(format!(
+            "Adaptive compression ratio threshold {} is not a positive number",
+            threshold
+        )));
+    }
+    if let Some(levels) = &options.per_disk_compression {
+        if levels.len() != config.disks.len() {
+            return Err(Error::validation(format!(
+                "{} per-disk compression levels given for {} disks",
+                levels.len(),
+                config.disks.len()
+            )));
+        }
+    }
+
+    // Restrict disks with a changed-block map to their changed ranges
+    for disk_config in &config.disks {
+        if options.changed_blocks_only.contains_key(&disk_config.file_name) {
+            report.warnings.push(format!(
+                "disk '{}' holds only its changed blocks; unchanged ranges read as zeros, so \
+                 it must be written over the previous export to be usable",
+                disk_config.file_name
+            ));
+        }
+    }
+    let output_grain_size = match options.disk_format {
+        DiskFormat::StreamOptimized => options.grain_size,
+        DiskFormat::Sparse => DEFAULT_GRAIN_SIZE * SECTOR_SIZE,
+    };
+    let sources: Vec<Box<dyn DiskSource>> = config
+        .disks
+        .iter()
+        .zip(sources)
+        .map(|(disk_config, source)| -> Box<dyn DiskSource> {
+            match options.changed_blocks_only.get(&disk_config.file_name) {
+                Some(changed) => Box::new(ChangedBlocksSource::new(
+                    source,
+                    changed.aligned(output_grain_size),
+                )),
+                None => source,
             }
-            DiskType::SplitSparse(extents, base_dir, capacity) => {
-                let vmdk = process_split_sparse_disk(
-                    &extents,
-                    &base_dir,
-                    capacity,
-                    &pipeline,
-                    compression_level,
-                    options.chunk_size,
-                    &mut progress,
-                    &progress_callback,
-                )?;
-                (vmdk, capacity)
+        })
+        .map(|source| -> Box<dyn DiskSource> {
+            let rounded = options.capacity_rounding.round(source.size());
+            if rounded == source.size() {
+                return source;
             }
-        };
+            Box::new(PaddedDiskSource::new(source, rounded))
+        })
+        .collect();
+
+    if options.disk_format == DiskFormat::StreamOptimized {
+        for (disk_config, source) in config.disks.iter().zip(&sources) {
+            report.warnings.extend(grain_count_warning(
+                &disk_config.file_name,
+                source.size(),
+                options.grain_size,
+            ));
+        }
+    }
 
-        // Store for later writing
-        let output_filename = disk_config.file_name.clone();
-        vmdk_buffers.push((output_filename.clone(), compressed_vmdk, capacity_bytes));
+    let mut reporter = ProgressReporter::new(&progress_callback, options.progress_interval);
 
-        // Track disk info for OVF
-        disk_infos.push(DiskInfo {
-            id: format!("vmdisk{}", disk_index + 1),
-            file_ref: format!("file{}", disk_index + 1),
-            capacity_bytes,
-            file_size_bytes: 0, // Will be updated after writing
-        });
+    // Phase 1: Parsing
+    let total_disk_size: u64 = sources.iter().map(|source| source.size()).sum();
+    let total_disks = config.disks.len();
+
+    let mut progress = ExportProgress::new(ExportPhase::Parsing, total_disk_size, total_disks);
+    // Until some data is compressed, assume it won't shrink
+    progress.compressed_bytes_total = total_disk_size;
+    if progress.is_indeterminate() {
+        report.warnings.push(format!(
+            "Disk sizes could not be determined: all {} disks report 0 bytes, so export \
+             progress can't be shown as a percentage",
+            total_disks
+        ));
     }
+    reporter.report(&progress);
 
-    // Phase 3: Writing disks to OVA
-    progress.phase = ExportPhase::Writing;
-    report_progress(progress.clone());
+    // Create the pipeline for parallel compression
+    let mut pipeline_config =
+        PipelineConfig::new(options.chunk_size, options.compression, options.num_threads);
+    pipeline_config.adaptive_ratio_threshold = options.adaptive_ratio_threshold;
+    let pipeline = Pipeline::new(pipeline_config);
+    report.threads = pipeline.num_threads();
 
-    for (i, (filename, vmdk_data, _)) in vmdk_buffers.iter().enumerate() {
-        disk_infos[i].file_size_bytes = vmdk_data.len() as u64;
-        ova_writer.add_file(filename, vmdk_data)?;
+    // Open the output and create the OVA writer
+    let mut ova_writer =
+        OvaWriter::new(output.open()?)?.with_manifest_algorithm(options.manifest_algorithm);
+    if let Some(signer) = &options.manifest_signer {
+        ova_writer = ova_writer.with_signer(Arc::clone(signer));
     }
 
-    // Phase 4: Generate and add OVF descriptor
-    progress.phase = ExportPhase::Finalizing;
-    report_progress(progress.clone());
+    let build_ovf = |disk_infos: &[DiskInfo], nvram_file: Option<(String, u64)>| match custom_ovf {
+        Some(ovf_xml) => Ok(ovf_xml.to_string()),
+        None => {
+            let mut builder = OvfBuilder::new(config)
+                .with_storage_policy(options.storage_policy.clone())
+                .with_disk_format(options.disk_format)
+                .with_guestinfo(options.include_guestinfo)
+                .with_numa(options.include_numa)
+                .with_os_type_override(options.os_type_override.clone())
+                .with_nvram_file(nvram_file);
+            if let Some((name, mime_type)) = &icon {
+                builder = builder.with_icon(name.as_str(), *mime_type);
+            }
+            builder.build(disk_infos)
+        }
+    };
 
-    let ovf_builder = OvfBuilder::new(&config);
-    let ovf_xml = ovf_builder.build(&disk_infos)?;
+    // The OVF declares the file sizes, which are only known once written.
+    // Reserve room for it using the largest size a file can have, then fill
+    // it in after the disks.
+    let ovf_reserved_size = match options.ova_order {
+        OvaOrder::OvfFirst => {
+            let placeholder_disks: Vec<DiskInfo> = sources
+                .iter()
+                .enumerate()
+                .map(|(disk_index, source)| DiskInfo {
+                    id: format!("vmdisk{}", disk_index + 1),
+                    file_ref: format!("file{}", disk_index + 1),
+                    capacity_bytes: source.size(),
+                    file_size_bytes: MAX_TAR_ENTRY_SIZE,
+                })
+                .collect();
+            let placeholder_nvram = nvram_name.clone().map(|name| (name, MAX_TAR_ENTRY_SIZE));
+            let size = build_ovf(&placeholder_disks, placeholder_nvram)?.len() as u64;
+            ova_writer.reserve_file(&ovf_filename, size)?;
+            Some(size)
+        }
+        OvaOrder::OvfLast => None,
+    };
 
-    // OVF filename is based on VM name
-    let ovf_filename = format!("{}.ovf", sanitize_filename(&config.display_name));
+    // Process each disk
+    let mut disk_infos: Vec<DiskInfo> = Vec::new();
+    // The earlier disk each disk duplicates, whose file it reuses
+    let duplicate_of = if options.dedup_disks && custom_ovf.is_none() {
+        find_duplicate_disks(&sources, &options)?
+    } else {
+        vec![None; sources.len()]
+    };
+
+    // With parallel disks, each disk is compressed by its own producer and
+    // its VMDK bytes are drained into the OVA below, in disk order. The
+    // producers share the pipeline's threads, and each publishes how much of
+    // its disk it has processed so progress covers all of them.
+    let parallel = options.parallel_disks
+        && options.disk_format == DiskFormat::StreamOptimized
+        && sources.len() > 1;
+    let disks_processed: Vec<Arc<AtomicU64>> = sources.iter().map(|_| Arc::default()).collect();
+    // Duplicate disks count as processed from the start
+    for (disk_index, duplicate) in duplicate_of.iter().enumerate() {
+        if duplicate.is_some() {
+            disks_processed[disk_index].store(sources[disk_index].size(), Ordering::Relaxed);
+        }
+    }
+    let parallel_bytes_processed = || {
+        disks_processed
+            .iter()
+            .map(|processed| processed.load(Ordering::Relaxed))
+            .sum::<u64>()
+    };
+    let producers: Vec<_> = if parallel {
+        config
+            .disks
+            .iter()
+            .zip(&sources)
+            .enumerate()
+            .filter(|(disk_index, _)| duplicate_of[*disk_index].is_none())
+            .map(|(disk_index, (disk_config, source))| {
+                let pipeline = &pipeline;
+                let options = &options;
+                let processed = Arc::clone(&disks_processed[disk_index]);
+                move |sender: &mut OrderedSender| {
+                    write_disk_stream(
+                        source.as_ref(),
+                        &disk_config.file_name,
+                        pipeline,
+                        options.disk_compression(disk_index),
+                        options,
+                        processed,
+                        sender,
+                    )
+                }
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let drain = OrderedDrain::new(PARALLEL_DISK_BUFFER_SIZE, PARALLEL_DISK_BUFFERS);
+    let mut parallel_buffered_bytes = 0u64;
+
+    drain.run(producers, |streams| {
+        let mut streams = streams.into_iter();
+        for (disk_index, (disk_config, source)) in config.disks.iter().zip(&sources).enumerate() {
+            timer.enter(ExportPhase::Compressing);
+            progress.phase = ExportPhase::Compressing;
+            progress.current_disk = disk_index + 1;
+            reporter.report(&progress);
+
+            // Point a duplicate disk at the earlier disk's file instead of
+            // writing the same VMDK again
+            if let Some(original) = duplicate_of[disk_index] {
+                report.disks.push(DiskStats {
+                    file_name: disk_config.file_name.clone(),
+                    ..report.disks[original].clone()
+                });
+                disk_infos.push(DiskInfo {
+                    id: format!("vmdisk{}", disk_index + 1),
+                    ..disk_infos[original].clone()
+                });
+                if parallel {
+                    progress.bytes_processed = parallel_bytes_processed();
+                } else {
+                    progress.bytes_processed += source.size();
+                }
+                progress.estimate_compressed_total();
+                reporter.report(&progress);
+                continue;
+            }
+
+            // Read and compress the disk data, streaming a streamOptimized VMDK
+            // straight into the OVA; a sparse VMDK is assembled in memory first
+            let capacity_bytes = source.size();
+            let filename = &disk_config.file_name;
+            let compression = options.disk_compression(disk_index);
+            let written_before = progress.compressed_bytes_written;
+            let mut logical_sha256 = None;
+            let (bytes_read, compressed_bytes) = match (options.disk_format, streams.next()) {
+                (DiskFormat::StreamOptimized, Some(mut stream)) => {
+                    let mut entry = ova_writer.add_file_streaming_unsized(filename)?;
+                    for buffer in stream.by_ref() {
+                        entry
+                            .write_all(&buffer)
+                            .map_err(|e| output.error(e))?;
+                        progress.bytes_processed = parallel_bytes_processed();
+                        progress.compressed_bytes_written = written_before + entry.bytes_written();
+                        progress.estimate_compressed_total();
+                        reporter.report_throttled(&progress);
+                    }
+                    let (bytes_read, buffered_bytes, disk_sha256) = stream.finish()?;
+                    parallel_buffered_bytes += buffered_bytes;
+                    logical_sha256 = disk_sha256;
+                    progress.bytes_processed = parallel_bytes_processed();
+
+                    timer.enter(ExportPhase::Writing);
+                    progress.phase = ExportPhase::Writing;
+                    reporter.report(&progress);
+                    let compressed_bytes = entry.bytes_written();
+                    entry.finish()?;
+                    (bytes_read, compressed_bytes)
+                }
+                (DiskFormat::StreamOptimized, None) => {
+                    let mut entry = ova_writer.add_file_streaming_unsized(filename)?;
+                    let mut vmdk_writer = StreamVmdkWriter::with_grain_size(
+                        &mut entry,
+                        capacity_bytes,
+                        grain_size_sectors,
+                    )?;
+                    let mut verifier =
+                        EndToEndVerifier::for_options(&options, vmdk_writer.grain_size_bytes());
+                    let (bytes_read, buffered_bytes) = process_disk(
+                        source.as_ref(),
+                        &pipeline,
+                        compression,
+                        options.chunk_size,
+                        &mut vmdk_writer,
+                        verifier.as_mut(),
+                        &mut progress,
+                        &mut reporter,
+                    )?;
+                    report.peak_memory_bytes = report.peak_memory_bytes.max(buffered_bytes);
+                    if let Some(verifier) = verifier {
+                        logical_sha256 = Some(verifier.finish(filename, capacity_bytes)?);
+                    }
+
+                    // Write the grain tables, directory and footer
+                    timer.enter(ExportPhase::Writing);
+                    progress.phase = ExportPhase::Writing;
+                    reporter.report(&progress);
+                    vmdk_writer.finish()?;
+                    let compressed_bytes = entry.bytes_written();
+                    entry.finish()?;
+                    (bytes_read, compressed_bytes)
+                }
+                (DiskFormat::Sparse, _) => {
+                    let (vmdk_data, bytes_read) = process_disk_sparse(
+                        source.as_ref(),
+                        options.chunk_size,
+                        &mut progress,
+                        &mut reporter,
+                    )?;
+                    let compressed_bytes = vmdk_data.len() as u64;
+                    report.peak_memory_bytes =
+                        report.peak_memory_bytes.max(bytes_read + compressed_bytes);
+
+                    timer.enter(ExportPhase::Writing);
+                    progress.phase = ExportPhase::Writing;
+                    reporter.report(&progress);
+                    ova_writer.add_file(filename, &vmdk_data)?;
+                    (bytes_read, compressed_bytes)
+                }
+            };
 
-    // OVF should be first in the OVA, but we already wrote disks
-    // In a proper OVA, the order should be: OVF, disks, manifest
-    // For now, we add it after disks - this is still valid OVA
-    ova_writer.add_file(&ovf_filename, ovf_xml.as_bytes())?;
+            report.disks.push(DiskStats {
+                file_name: disk_config.file_name.clone(),
+                capacity_bytes,
+                bytes_read,
+                compressed_bytes,
+                logical_sha256,
+                compression: (options.disk_format == DiskFormat::StreamOptimized)
+                    .then_some(compression),
+            });
+
+            progress.compressed_bytes_written = written_before + compressed_bytes;
+            progress.estimate_compressed_total();
+            reporter.report(&progress);
+
+            // Track disk info for OVF
+            disk_infos.push(DiskInfo {
+                id: format!("vmdisk{}", disk_index + 1),
+                file_ref: format!("file{}", disk_index + 1),
+                capacity_bytes,
+                file_size_bytes: compressed_bytes,
+            });
+        }
+        Ok(())
+    })?;
+    report.peak_memory_bytes = report.peak_memory_bytes.max(
+        parallel_buffered_bytes + (drain.peak_in_flight() * PARALLEL_DISK_BUFFER_SIZE) as u64,
+    );
 
-    // Finish the OVA (writes manifest and end marker)
+    // The NVRAM file and sidecar files follow the disks
+    let nvram_file = match (nvram, nvram_name) {
+        (Some(path), Some(name)) => {
+            let size = add_extra_file(&mut ova_writer, path, &name)?;
+            Some((name, size))
+        }
+        _ => None,
+    };
+    for (path, name) in extra_files.iter().zip(&extra_file_names) {
+        add_extra_file(&mut ova_writer, path, name)?;
+    }
+    if let Some(name) = &logical_manifest_name {
+        let digests: String = report
+            .disks
+            .iter()
+            .filter_map(|disk| {
+                let digest = disk.logical_sha256.as_ref()?;
+                Some(format!("SHA256({})= {}\n", disk.file_name, digest))
+            })
+            .collect();
+        if options.disk_format != DiskFormat::StreamOptimized {
+            report.warnings.push(format!(
+                "Logical disk digests are only computed for streamOptimized disks; {} is empty",
+                name
+            ));
+        }
+        ova_writer.add_file(name, digests.as_bytes())?;
+    }
+
+    // Phase 4: Generate and add OVF descriptor
+    timer.enter(ExportPhase::Finalizing);
+    progress.phase = ExportPhase::Finalizing;
+    progress.compressed_bytes_total = progress.compressed_bytes_written;
+    reporter.report(&progress);
+
+    let ovf_xml = build_ovf(&disk_infos, nvram_file)?;
+    if options.validate_ovf {
+        let issues = validate_ovf(&ovf_xml)?;
+        if !issues.is_empty() {
+            return Err(Error::validation(format!(
+                "OVF fails validation: {}",
+                issues.join("; ")
+            )));
+        }
+    }
+    match ovf_reserved_size {
+        Some(size) => {
+            let ovf_xml = pad_ovf(ovf_xml, size)?;
+            ova_writer.fill_reserved_file(&ovf_filename, ovf_xml.as_bytes())?;
+        }
+        None => ova_writer.add_file(&ovf_filename, ovf_xml.as_bytes())?,
+    }
+
+    // Finish the OVA (writes manifest, certificate and end marker)
     ova_writer.finish()?;
 
     // Phase 5: Complete
+    report.phase_timings = timer.finish();
     progress.phase = ExportPhase::Complete;
     progress.bytes_processed = progress.bytes_total;
-    report_progress(progress);
+    reporter.report(&progress);
+    report.warnings.extend(reporter.panic_warning());
 
-    Ok(())
+    Ok(report)
 }
 
-/// Process a single disk: read, compress, and create streamOptimized VMDK.
-fn process_disk(
-    flat_path: &Path,
-    capacity_bytes: u64,
-    pipeline: &Pipeline,
-    compression_level: u32,
-    chunk_size: usize,
-    progress: &mut ExportProgress,
-    progress_callback: &Option<ProgressCallback>,
-) -> Result<Vec<u8>> {
-    // Open the flat extent file
-    let reader = VmdkReader::open(flat_path)?;
-    let file_size = reader.size();
-
-    // Collect all chunks for parallel processing
-    let chunks: Vec<Vec<u8>> = reader
-        .chunks(chunk_size)
-        .collect::<Result<Vec<_>>>()?;
+/// Pad an OVF descriptor to the size reserved for it, with whitespace after
+/// the root element.
+///
+/// A descriptor that already has the reserved size, such as a custom OVF,
+/// is returned unchanged.
+fn pad_ovf(ovf_xml: String, size: u64) -> Result<String> {
+    if ovf_xml.len() as u64 == size {
+        return Ok(ovf_xml);
+    }
+    let trimmed = ovf_xml.trim_end();
+    let padding = (size as usize)
+        .checked_sub(trimmed.len() + 1)
+        .ok_or_else(|| {
+            Error::ova(format!(
+                "OVF descriptor is {} bytes, more than the {} bytes reserved for it",
+                ovf_xml.len(),
+                size
+            ))
+        })?;
+    Ok(format!("{}{}\n", trimmed, " ".repeat(padding)))
+}
 
-    // Compress chunks in parallel
-    let compressed_chunks: Vec<Vec<u8>> = pipeline.process(chunks, |_idx, chunk| {
-        compress_grain(&chunk, compression_level)
-    })?;
+/// Warn about a disk whose grain size makes for an excessive number of grains.
+///
+/// Only grain sizes below the default are flagged: the default is what
+/// VMware itself writes, so a larger grain is the suggested fix.
+fn grain_count_warning(file_name: &str, capacity_bytes: u64, grain_size: u64) -> Option<String> {
+    let grains = capacity_bytes.div_ceil(grain_size);
+    if grains <= EXCESSIVE_GRAIN_COUNT || grain_size >= DEFAULT_GRAIN_SIZE * SECTOR_SIZE {
+        return None;
+    }
+    Some(format!(
+        "Disk '{}' spans {} grains of {} KB; the grain markers and tables will bloat the \
+         OVA and slow down finishing it. Consider a grain size of {} KB or more",
+        file_name,
+        grains,
+        grain_size / 1024,
+        DEFAULT_GRAIN_SIZE * SECTOR_SIZE / 1024
+    ))
+}
 
-    // Create streamOptimized VMDK in memory
-    let mut vmdk_buffer = Cursor::new(Vec::new());
-    let mut vmdk_writer = StreamVmdkWriter::new(&mut vmdk_buffer, capacity_bytes)?;
-    let _grain_size_bytes = vmdk_writer.grain_size_bytes() as usize;
+/// Work out the archive name of each extra file.
+///
+/// Fails if a file is missing or has no file name, or if a name is repeated
+/// or would collide with a disk, one of the `reserved` names, the manifest or
+/// the certificate.
+fn extra_file_names(
+    extra_files: &[PathBuf],
+    config: &VmxConfig,
+    reserved: &[&str],
+) -> Result<Vec<String>> {
+    let mut taken: Vec<&str> = config.disks.iter().map(|d| d.file_name.as_str()).collect();
+    taken.extend(reserved);
+    taken.extend([MANIFEST_FILE_NAME, CERT_FILE_NAME]);
+
+    let mut names: Vec<String> = Vec::with_capacity(extra_files.len());
+    for path in extra_files {
+        // Catch a missing file before the disks are exported
+        fs::metadata(path).map_err(|e| Error::io(e, path))?;
+        let name = archive_file_name(path)?;
+        if taken.contains(&name.as_str()) || names.contains(&name) {
+            return Err(Error::validation(format!(
+                "Extra file '{}' would be stored as '{}', which is already used in the OVA",
+                path.display(),
+                name
+            )));
+        }
+        names.push(name);
+    }
 
-    // Write compressed grains
-    let mut bytes_written = 0u64;
-    for (chunk_idx, compressed_chunk) in compressed_chunks.into_iter().enumerate() {
-        // Calculate LBA for this chunk (in sectors)
-        let chunk_offset_bytes = chunk_idx as u64 * chunk_size as u64;
-        let lba = chunk_offset_bytes / 512; // Convert to sectors
+    Ok(names)
+}
 
-        // Write the grain (the stream writer handles grain-level addressing)
-        vmdk_writer.write_grain(lba, &compressed_chunk)?;
+/// MIME type of an icon image, from its file extension.
+fn icon_mime_type(path: &Path) -> Result<&'static str> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("png") => Ok("image/png"),
+        Some("jpg" | "jpeg") => Ok("image/jpeg"),
+        Some("gif") => Ok("image/gif"),
+        Some("svg") => Ok("image/svg+xml"),
+        Some("ico") => Ok("image/x-icon"),
+        _ => Err(Error::validation(format!(
+            "Icon '{}' is not a PNG, JPEG, GIF, SVG or ICO image",
+            path.display()
+        ))),
+    }
+}
 
-        // Update progress
-        let original_chunk_size = if chunk_idx < (file_size as usize / chunk_size) {
-            chunk_size as u64
-        } else {
-            file_size - (chunk_idx as u64 * chunk_size as u64)
-        };
-        bytes_written += original_chunk_size;
-        progress.bytes_processed = bytes_written;
+/// Name a file is stored under in the OVA: its file name.
+fn archive_file_name(path: &Path) -> Result<String> {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(str::to_string)
+        .ok_or_else(|| {
+            Error::validation(format!(
+                "Extra file '{}' has no usable file name",
+                path.display()
+            ))
+        })
+}
+
+/// Stream an extra file from disk into the OVA, returning its size.
+fn add_extra_file<W: Write + Seek>(
+    ova_writer: &mut OvaWriter<W>,
+    path: &Path,
+    name: &str,
+) -> Result<u64> {
+    let mut file = File::open(path).map_err(|e| Error::io(e, path))?;
+    let size = file.metadata().map_err(|e| Error::io(e, path))?.len();
+
+    let mut entry = ova_writer.add_file_streaming(name, size)?;
+    std::io::copy(&mut file, &mut entry).map_err(|e| Error::io(e, path))?;
+    entry.finish()?;
+    Ok(size)
+}
 
-        if let Some(ref callback) = progress_callback {
-            callback(progress.clone());
+/// Reject the export if any disk's capacity exceeds `limit` bytes.
+fn check_disk_sizes(
+    config: &VmxConfig,
+    sources: &[Box<dyn DiskSource>],
+    limit: u64,
+) -> Result<()> {
+    for (disk_config, source) in config.disks.iter().zip(sources) {
+        if source.size() > limit {
+            return Err(Error::validation(format!(
+                "Disk '{}' has a capacity of {} bytes, exceeding the maximum disk size of {} bytes",
+                disk_config.file_name,
+                source.size(),
+                limit
+            )));
         }
     }
+    Ok(())
+}
 
-    // Finish the VMDK (writes grain tables, directory, footer, etc.)
-    vmdk_writer.finish()?;
+/// Warning for a VM whose blank `displayName` was replaced by `parse_vmx`.
+fn blank_display_name_warning(config: &VmxConfig) -> String {
+    format!(
+        "The VMX's displayName is empty; the VM is exported as '{}' instead",
+        config.display_name
+    )
+}
 
-    Ok(vmdk_buffer.into_inner())
+/// Check that a flat extent exists and holds whole sectors.
+///
+/// An empty extent for a disk with a non-zero size, or one whose length
+/// isn't a multiple of the sector size, means the file was truncated or
+/// isn't the disk's data, and would be exported as a broken disk.
+///
+/// # Errors
+///
+/// Returns a VMDK error naming the extent's path.
+fn check_flat_extent(file_name: &str, flat_path: &Path, declared_sectors: u64) -> Result<()> {
+    let metadata = match fs::metadata(flat_path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            // Keep this an I/O error so callers classify it as a missing file
+            let message = format!(
+                "Flat extent '{}' of disk '{}' not found",
+                flat_path.display(),
+                file_name
+            );
+            return Err(Error::io(
+                std::io::Error::new(std::io::ErrorKind::NotFound, message),
+                flat_path,
+            ));
+        }
+        Err(e) => return Err(Error::io(e, flat_path)),
+    };
+    let len = metadata.len();
+    if len == 0 && declared_sectors > 0 {
+        return Err(Error::vmdk(format!(
+            "Flat extent '{}' of disk '{}' is empty but the descriptor declares {} sectors",
+            flat_path.display(),
+            file_name,
+            declared_sectors
+        )));
+    }
+    if !len.is_multiple_of(SECTOR_SIZE) {
+        return Err(Error::vmdk(format!(
+            "Flat extent '{}' of disk '{}' is {} bytes, not a multiple of the {}-byte sector size",
+            flat_path.display(),
+            file_name,
+            len,
+            SECTOR_SIZE
+        )));
+    }
+    Ok(())
 }
 
-/// Process a sparse VMDK: read grains, compress, and create streamOptimized VMDK.
-fn process_sparse_disk(
-    sparse_path: &Path,
-    capacity_bytes: u64,
-    pipeline: &Pipeline,
-    compression_level: u32,
-    chunk_size: usize,
-    progress: &mut ExportProgress,
-    progress_callback: &Option<ProgressCallback>,
-) -> Result<Vec<u8>> {
-    // Open the sparse VMDK
-    let reader = SparseVmdkReader::open(sparse_path)?;
-
-    // Collect all chunks for parallel processing
-    let chunks: Vec<Vec<u8>> = reader
-        .chunks(chunk_size)
-        .collect::<Result<Vec<_>>>()?;
+/// Check that a caller-supplied OVF is well-formed and references exactly
+/// the disks that will be written to the OVA.
+fn validate_custom_ovf(ovf_xml: &str, config: &VmxConfig) -> Result<()> {
+    let mut referenced = parse_file_references(ovf_xml)?;
+    let mut exported: Vec<String> = config.disks.iter().map(|d| d.file_name.clone()).collect();
+    referenced.sort();
+    exported.sort();
+
+    if referenced != exported {
+        return Err(Error::validation(format!(
+            "OVF references files {:?} but the exported disks are {:?}",
+            referenced, exported
+        )));
+    }
 
-    let total_chunks = chunks.len();
+    Ok(())
+}
 
-    // Compress chunks in parallel
-    let compressed_chunks: Vec<Vec<u8>> = pipeline.process(chunks, |_idx, chunk| {
-        compress_grain(&chunk, compression_level)
-    })?;
+/// Open the data source for a disk referenced by the VMX.
+///
+/// Handles monolithic sparse VMDKs as well as descriptors pointing at flat,
+/// split sparse or streamOptimized extents. Snapshot delta disks are layered over their parent
+/// chain, so the source reads as the flattened disk. When
+/// `options.synthesize_missing_disks` is set, a missing extent file yields a
+/// [`ZeroDiskSource`] and a warning.
+fn open_disk_source(
+    vmx_dir: &Path,
+    file_name: &str,
+    options: &ExportOptions,
+    warnings: &mut Vec<String>,
+) -> Result<Box<dyn DiskSource>> {
+    let mut chain = Vec::new();
+    open_vmdk(vmx_dir, &vmx_dir.join(file_name), file_name, options, warnings, &mut chain)
+}
+
+/// Open one VMDK of a disk's snapshot chain.
+///
+/// # Arguments
+///
+/// * `extent_dir` - Directory extent file names are relative to.
+/// * `path` - Path to the descriptor or monolithic sparse VMDK.
+/// * `file_name` - The disk's name in the VMX, used in warnings.
+/// * `options` - Export options.
+/// * `warnings` - Collects non-fatal problems.
+/// * `chain` - Descriptors already opened for this disk, to detect loops.
+fn open_vmdk(
+    extent_dir: &Path,
+    path: &Path,
+    file_name: &str,
+    options: &ExportOptions,
+    warnings: &mut Vec<String>,
+    chain: &mut Vec<PathBuf>,
+) -> Result<Box<dyn DiskSource>> {
+    let vmdk_path = resolve_disk_path(path, options.follow_symlinks)?;
+    if chain.contains(&vmdk_path) {
+        return Err(Error::vmdk(format!(
+            "Snapshot chain of disk '{}' loops back to '{}'",
+            file_name,
+            vmdk_path.display()
+        )));
+    }
+    chain.push(vmdk_path.clone());
+
+    // Sparse VMDK - the file itself contains the data
+    if is_sparse_vmdk(&vmdk_path)? {
+        let reader = open_sparse(&vmdk_path, options)?;
+        let descriptor = reader
+            .embedded_descriptor()?
+            .map(|text| parse_descriptor(&text))
+            .transpose()?;
+        return match descriptor {
+            Some(descriptor) if descriptor.is_delta() => {
+                let allocated = reader.allocated_ranges().collect();
+                let delta = Snapshot {
+                    path,
+                    descriptor: &descriptor,
+                    source: Box::new(reader),
+                    allocated,
+                };
+                layer_on_parent(delta, file_name, options, warnings, chain)
+            }
+            _ => Ok(Box::new(reader)),
+        };
+    }
 
-    // Create streamOptimized VMDK in memory
-    let mut vmdk_buffer = Cursor::new(Vec::new());
-    let mut vmdk_writer = StreamVmdkWriter::new(&mut vmdk_buffer, capacity_bytes)?;
+    // Text descriptor - parse it to find the data file(s)
+    let descriptor_content = fs::read_to_string(&vmdk_path)
+        .map_err(|e| Error::io(e, &vmdk_path))?;
+    let descriptor = parse_descriptor(&descriptor_content)?;
+    let capacity = descriptor.disk_size_bytes();
+    warnings.extend(
+        descriptor
+            .warnings
+            .iter()
+            .map(|warning| format!("disk '{}': {}", file_name, warning)),
+    );
+    let access = descriptor.access_mode();
+    if access != ExtentAccess::ReadWrite {
+        warnings.push(format!(
+            "disk '{}': '{}' has {} extents; the exported copy is writable",
+            file_name,
+            vmdk_path.display(),
+            access
+        ));
+    }
+    if !descriptor.deletable {
+        warnings.push(format!(
+            "disk '{}': '{}' is marked not deletable, as template base disks are; the \
+             exported copy is deletable",
+            file_name,
+            vmdk_path.display()
+        ));
+    }
 
-    // Write compressed grains
-    let mut bytes_written = 0u64;
-    for (chunk_idx, compressed_chunk) in compressed_chunks.into_iter().enumerate() {
-        // Calculate LBA for this chunk (in sectors)
-        let chunk_offset_bytes = chunk_idx as u64 * chunk_size as u64;
-        let lba = chunk_offset_bytes / 512; // Convert to sectors
+    // Check for flat extents first (monolithicFlat, twoGbMaxExtentFlat)
+    let flat_extents: Vec<&Extent> = descriptor
+        .extents
+        .iter()
+        .filter(|e| e.extent_type == ExtentType::Flat)
+        .collect();
+    if !flat_extents.is_empty() {
+        let mut extents = Vec::with_capacity(flat_extents.len());
+        for flat_extent in flat_extents {
+            let flat_path = resolve_disk_path(
+                &extent_dir.join(&flat_extent.filename),
+                options.follow_symlinks,
+            )?;
+            if !flat_path.exists() && options.synthesize_missing_disks {
+                warnings.push(missing_disk_warning(file_name, &flat_path, capacity));
+                return Ok(Box::new(ZeroDiskSource::new(capacity)));
+            }
+            check_flat_extent(file_name, &flat_path, flat_extent.size_sectors)?;
+            let reader = open_flat(&flat_path, options)?;
 
-        // Write the grain (the stream writer handles grain-level addressing)
-        vmdk_writer.write_grain(lba, &compressed_chunk)?;
+            // Each extent covers exactly the sectors the descriptor gives it;
+            // flat files may have slack past that size
+            extents.push(fit_to_size(reader, flat_extent.size_sectors.saturating_mul(512)));
+        }
+        return Ok(Box::new(ConcatDiskSource::new(extents)));
+    }
 
-        // Update progress
-        let original_chunk_size = if chunk_idx < total_chunks - 1 {
-            chunk_size as u64
-        } else {
-            capacity_bytes - (chunk_idx as u64 * chunk_size as u64)
-        };
-        bytes_written += original_chunk_size;
-        progress.bytes_processed = bytes_written;
+    // Check for sparse extents (twoGbMaxExtentSparse, etc.)
+    let sparse_extents: Vec<(PathBuf, u64)> = descriptor
+        .extents
+        .iter()
+        .filter(|e| e.extent_type == ExtentType::Sparse)
+        .map(|e| {
+            let path = resolve_disk_path(&extent_dir.join(&e.filename), options.follow_symlinks)?;
+            Ok((path, e.size_sectors.saturating_mul(512)))
+        })
+        .collect::<Result<_>>()?;
 
-        if let Some(ref callback) = progress_callback {
-            callback(progress.clone());
+    if sparse_extents.is_empty() {
+        if let Some(guidance) = descriptor.vmfs_clone_guidance() {
+            return Err(Error::unsupported(guidance));
         }
+        return Err(Error::unsupported(format!(
+            "Unsupported VMDK type: {}",
+            descriptor.create_type
+        )));
     }
 
-    // Finish the VMDK (writes grain tables, directory, footer, etc.)
-    vmdk_writer.finish()?;
+    if let Some((missing_path, _)) = sparse_extents.iter().find(|(p, _)| !p.exists()) {
+        if options.synthesize_missing_disks {
+            warnings.push(missing_disk_warning(file_name, missing_path, capacity));
+            return Ok(Box::new(ZeroDiskSource::new(capacity)));
+        }
+    }
 
-    Ok(vmdk_buffer.into_inner())
+    // Each extent covers exactly the sectors the descriptor gives it,
+    // whatever capacity its own header declares
+    let mut extents = Vec::with_capacity(sparse_extents.len());
+    let mut allocated = Vec::new();
+    let mut extent_start = 0u64;
+    let stream_optimized = descriptor.create_type == CreateType::StreamOptimized;
+    for (extent_path, declared_bytes) in &sparse_extents {
+        let reader = open_sparse(extent_path, options)?;
+        if stream_optimized && !reader.is_stream_optimized() {
+            return Err(Error::vmdk(format!(
+                "Disk '{}' is declared streamOptimized but its extent '{}' is not a stream file",
+                file_name,
+                extent_path.display()
+            )));
+        }
+        if descriptor.is_delta() {
+            allocated.extend(reader.allocated_ranges().filter_map(|range| {
+                let end = std::cmp::min(range.end, *declared_bytes);
+                (range.start < end).then(|| extent_start + range.start..extent_start + end)
+            }));
+        }
+        extents.push(fit_to_size(Box::new(reader), *declared_bytes));
+        extent_start += declared_bytes;
+    }
+    let source = Box::new(ConcatDiskSource::new(extents));
+
+    if descriptor.is_delta() {
+        let delta = Snapshot {
+            path,
+            descriptor: &descriptor,
+            source,
+            allocated,
+        };
+        return layer_on_parent(delta, file_name, options, warnings, chain);
+    }
+    Ok(source)
 }
 
-/// Process a split sparse VMDK (twoGbMaxExtentSparse): read from multiple extent files,
-/// compress, and create a single streamOptimized VMDK.
-fn process_split_sparse_disk(
-    extents: &[Extent],
-    base_dir: &Path,
-    capacity_bytes: u64,
-    pipeline: &Pipeline,
-    compression_level: u32,
-    chunk_size: usize,
-    progress: &mut ExportProgress,
-    progress_callback: &Option<ProgressCallback>,
-) -> Result<Vec<u8>> {
-    // Create a virtual reader that chains multiple sparse extent files
-    // We'll collect chunks from all extents in order
-    let mut all_chunks: Vec<Vec<u8>> = Vec::new();
-    let mut partial_chunk: Vec<u8> = Vec::new();
+/// A snapshot delta disk, opened but not yet layered over its parent.
+struct Snapshot<'a> {
+    /// Path to the delta's descriptor, which its parent hint is relative to.
+    path: &'a Path,
+    descriptor: &'a VmdkDescriptor,
+    source: Box<dyn DiskSource>,
+    /// Byte ranges the delta has data for.
+    allocated: Vec<Range<u64>>,
+}
 
-    for extent in extents {
-        let extent_path = base_dir.join(&extent.filename);
-        let reader = SparseVmdkReader::open(&extent_path)?;
+/// Open the parent of a snapshot delta and layer the delta over it.
+fn layer_on_parent(
+    delta: Snapshot<'_>,
+    file_name: &str,
+    options: &ExportOptions,
+    warnings: &mut Vec<String>,
+    chain: &mut Vec<PathBuf>,
+) -> Result<Box<dyn DiskSource>> {
+    let parent_path = locate_parent(delta.path, delta.descriptor).map_err(|e| match e {
+        Error::Vmdk { message } => snapshot_guidance(delta.path, &message),
+        e => e,
+    })?;
+    let parent_dir = parent_path.parent().unwrap_or_else(|| Path::new(""));
+    let parent = open_vmdk(parent_dir, &parent_path, file_name, options, warnings, chain)?;
+    Ok(Box::new(OverlayDiskSource::new(delta.source, delta.allocated, parent)))
+}
 
-        // Read chunks from this extent
-        for chunk_result in reader.chunks(chunk_size) {
-            let chunk = chunk_result?;
+/// Build the error for a snapshot delta that can't be layered over its parent.
+///
+/// Exporting the delta alone would leave out everything written before the
+/// snapshot, so the user is pointed at the base disk or at consolidating.
+fn snapshot_guidance(delta_path: &Path, message: &str) -> Error {
+    let delta_name = delta_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let base = snapshot_base_name(&delta_name)
+        .map(|base| format!(" (probably '{}')", base))
+        .unwrap_or_default();
+    Error::vmdk(format!(
+        "{}. '{}' is a snapshot delta holding only the changes since its snapshot, and can't \
+         be exported without its parent. Consolidate the VM's snapshots (Delete All in the \
+         Snapshot Manager) or point the VMX at the base disk{}",
+        message, delta_name, base
+    ))
+}
 
-            // If we have a partial chunk from the previous extent, combine with current data
-            if !partial_chunk.is_empty() {
-                partial_chunk.extend_from_slice(&chunk);
+/// Truncate or zero-pad a source to exactly `size` bytes.
+fn fit_to_size(source: Box<dyn DiskSource>, size: u64) -> Box<dyn DiskSource> {
+    match source.size().cmp(&size) {
+        std::cmp::Ordering::Greater => Box::new(TruncatedDiskSource::new(source, size)),
+        std::cmp::Ordering::Less => Box::new(PaddedDiskSource::new(source, size)),
+        std::cmp::Ordering::Equal => source,
+    }
+}
 
-                // Extract full chunks from the combined data
-                while partial_chunk.len() >= chunk_size {
-                    let full_chunk: Vec<u8> = partial_chunk.drain(..chunk_size).collect();
-                    all_chunks.push(full_chunk);
-                }
-            } else if chunk.len() == chunk_size {
-                all_chunks.push(chunk);
-            } else {
-                // This is a partial chunk (last chunk of extent), save for combining
-                partial_chunk = chunk;
+/// Open a flat extent, mapped whole or a window at a time as `options` ask,
+/// applying the sequential read hint if requested.
+fn open_flat(path: &Path, options: &ExportOptions) -> Result<Box<dyn DiskSource>> {
+    match options.mmap_window {
+        Some(window_size) => Ok(Box::new(WindowedVmdkReader::open(path, window_size)?)),
+        None => {
+            let reader = VmdkReader::open(path)?;
+            if options.sequential_read_hint {
+                reader.advise_sequential()?;
             }
+            Ok(Box::new(reader))
         }
     }
+}
 
-    // Don't forget any remaining partial chunk
-    if !partial_chunk.is_empty() {
-        all_chunks.push(partial_chunk);
+/// Open a sparse extent, applying the sequential read hint if requested.
+///
+/// Uses [`SparseVmdkReader::open_full`] rather than the lazy `open`: every
+/// grain is about to be read anyway, and a broken grain directory should
+/// fail the export before any output is written.
+fn open_sparse(path: &Path, options: &ExportOptions) -> Result<SparseVmdkReader> {
+    let reader = SparseVmdkReader::open_full(path)?;
+    if options.sequential_read_hint {
+        reader.advise_sequential()?;
     }
+    Ok(reader)
+}
 
-    let total_chunks = all_chunks.len();
+/// Canonicalize a disk file path, optionally refusing symbolic links.
+///
+/// Paths that don't exist are returned unchanged so the caller can decide how
+/// to handle the missing file.
+fn resolve_disk_path(path: &Path, follow_symlinks: bool) -> Result<PathBuf> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_symlink() && !follow_symlinks => {
+            Err(Error::validation(format!(
+                "Disk file '{}' is a symbolic link and following symlinks is disabled",
+                path.display()
+            )))
+        }
+        Ok(_) => fs::canonicalize(path).map_err(|e| Error::io(e, path)),
+        Err(_) => Ok(path.to_path_buf()),
+    }
+}
 
-    // Compress chunks in parallel
-    let compressed_chunks: Vec<Vec<u8>> = pipeline.process(all_chunks, |_idx, chunk| {
-        compress_grain(&chunk, compression_level)
-    })?;
+/// Find the disks whose VMDK would duplicate an earlier disk's byte for byte.
+///
+/// Disks compressed the same way from the same contents come out identical,
+/// so only disks the same size and compression as an earlier one are read,
+/// to compare the SHA256 of their contents.
+///
+/// # Returns
+///
+/// For each disk, the index of the first earlier disk it duplicates.
+fn find_duplicate_disks(
+    sources: &[Box<dyn DiskSource>],
+    options: &ExportOptions,
+) -> Result<Vec<Option<usize>>> {
+    let mut hashes: Vec<Option<String>> = vec![None; sources.len()];
+    let mut duplicate_of = vec![None; sources.len()];
+    for disk_index in 0..sources.len() {
+        let candidates: Vec<usize> = (0..disk_index)
+            .filter(|&earlier| {
+                duplicate_of[earlier].is_none()
+                    && sources[earlier].size() == sources[disk_index].size()
+                    && options.disk_compression(earlier) == options.disk_compression(disk_index)
+            })
+            .collect();
+        for earlier in candidates {
+            for index in [earlier, disk_index] {
+                if hashes[index].is_none() {
+                    let hash = source_sha256(sources[index].as_ref(), options.chunk_size)?;
+                    hashes[index] = Some(hash);
+                }
+            }
+            if hashes[earlier] == hashes[disk_index] {
+                duplicate_of[disk_index] = Some(earlier);
+                break;
+            }
+        }
+    }
+    Ok(duplicate_of)
+}
 
-    // Create streamOptimized VMDK in memory
-    let mut vmdk_buffer = Cursor::new(Vec::new());
-    let mut vmdk_writer = StreamVmdkWriter::new(&mut vmdk_buffer, capacity_bytes)?;
+/// SHA256 of a disk's contents, read `chunk_size` bytes at a time.
+///
+/// Unallocated ranges are hashed as zeros without being read.
+fn source_sha256(source: &dyn DiskSource, chunk_size: usize) -> Result<String> {
+    let mut hasher = Sha256::new();
+    let chunk_size = std::cmp::max(chunk_size as u64, 1);
+    let mut offset = 0u64;
+    while offset < source.size() {
+        let len = std::cmp::min(chunk_size, source.size() - offset);
+        if source.is_unallocated(offset, len as usize) {
+            hash_zeros(&mut hasher, len);
+        } else {
+            hasher.update(source.read_at(offset, len as usize)?);
+        }
+        offset += len;
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
 
-    // Write compressed grains
-    let mut bytes_written = 0u64;
-    for (chunk_idx, compressed_chunk) in compressed_chunks.into_iter().enumerate() {
-        // Calculate LBA for this chunk (in sectors)
-        let chunk_offset_bytes = chunk_idx as u64 * chunk_size as u64;
-        let lba = chunk_offset_bytes / 512; // Convert to sectors
+/// Build the warning recorded when a missing disk is replaced by zeros.
+fn missing_disk_warning(disk_name: &str, missing_path: &Path, capacity_bytes: u64) -> String {
+    format!(
+        "disk '{}' is missing its backing file '{}'; exported a zero-filled placeholder of {} bytes",
+        disk_name,
+        missing_path.display(),
+        capacity_bytes
+    )
+}
 
-        // Write the grain
-        vmdk_writer.write_grain(lba, &compressed_chunk)?;
+/// A chunk of a disk read for compression.
+struct DiskChunk {
+    /// Byte offset of the chunk in the disk.
+    offset: u64,
+    /// Length of the chunk in bytes.
+    len: u64,
+    /// The chunk's data, or `None` if the source reports it unallocated.
+    data: Option<Vec<u8>>,
+}
 
-        // Update progress
-        let original_chunk_size = if chunk_idx < total_chunks - 1 {
-            chunk_size as u64
+/// Process a single disk: read, compress, and write its grains to a
+/// streamOptimized VMDK.
+///
+/// Chunks are read lazily and streamed through
+/// [`Pipeline::process_streaming`], which compresses a window of them at a
+/// time and hands them back in order to be written, so memory use is bounded
+/// by the window rather than the disk size. Ranges the source reports as
+/// unallocated are not read; they are left out of the grain tables and read
+/// back as zeros.
+///
+/// While `compression` is still sampling, only the sampled chunks are
+/// compressed, so it can settle on a level for the rest.
+///
+/// When a `verifier` is given, the data read and the compressed grains are
+/// fed to it as they go by. `progress` advances as each chunk is written,
+/// in both the bytes processed and the VMDK bytes written.
+///
+/// # Returns
+///
+/// The number of bytes read from the source, and the most raw and compressed
+/// data held in memory for a window at once.
+#[allow(clippy::too_many_arguments)]
+fn process_disk<W: Write>(
+    source: &dyn DiskSource,
+    pipeline: &Pipeline,
+    compression: CompressionLevel,
+    chunk_size: usize,
+    vmdk_writer: &mut StreamVmdkWriter<W>,
+    mut verifier: Option<&mut EndToEndVerifier>,
+    progress: &mut ExportProgress,
+    reporter: &mut ProgressReporter,
+) -> Result<(u64, u64)> {
+    let capacity_bytes = source.size();
+    let grain_size = vmdk_writer.grain_size_bytes();
+    let window = pipeline.streaming_window();
+
+    // Read whole grains so each chunk splits evenly into them
+    let read_len = std::cmp::max(chunk_size as u64 / grain_size, 1) * grain_size;
+    let mut offset = 0u64;
+    let mut chunks = std::iter::from_fn(|| {
+        if offset >= capacity_bytes {
+            return None;
+        }
+        let chunk_offset = offset;
+        let len = std::cmp::min(read_len, capacity_bytes - offset);
+        offset += len;
+        let data = if source.is_unallocated(chunk_offset, len as usize) {
+            Ok(None)
         } else {
-            capacity_bytes - (chunk_idx as u64 * chunk_size as u64)
+            source.read_at(chunk_offset, len as usize).map(Some)
+        };
+        Some(data.map(|data| DiskChunk {
+            offset: chunk_offset,
+            len,
+            data,
+        }))
+    })
+    .peekable();
+
+    let mut compressor =
+        AdaptiveCompressor::with_threshold(compression, pipeline.config().adaptive_ratio_threshold);
+    let mut bytes_read = 0u64;
+    let written_before = progress.compressed_bytes_written;
+    // Raw and compressed bytes of the last `window` chunks
+    let mut in_flight: VecDeque<u64> = VecDeque::with_capacity(window + 1);
+    let mut in_flight_bytes = 0u64;
+    let mut peak_buffered = 0u64;
+    while chunks.peek().is_some() {
+        let samples = compressor.remaining_samples();
+        let batch = if samples > 0 { samples } else { usize::MAX };
+        let level = compressor.effective_level();
+        let compress = |_idx, chunk: DiskChunk| {
+            let compressed_grains = match &chunk.data {
+                Some(data) => {
+                    compress_grains_nonzero(source, chunk.offset, data, grain_size, level)?
+                }
+                None => Vec::new(),
+            };
+            Ok((chunk, compressed_grains))
         };
-        bytes_written += original_chunk_size;
-        progress.bytes_processed = bytes_written;
 
-        if let Some(ref callback) = progress_callback {
-            callback(progress.clone());
-        }
+        let write = |_idx, (chunk, compressed_grains): (DiskChunk, Vec<Option<Vec<u8>>>)| {
+            let compressed_bytes: u64 =
+                compressed_grains.iter().flatten().map(|grain| grain.len() as u64).sum();
+            let raw_bytes = chunk.data.as_ref().map_or(0, |data| data.len() as u64);
+            in_flight.push_back(raw_bytes + compressed_bytes);
+            in_flight_bytes += raw_bytes + compressed_bytes;
+            if in_flight.len() > window {
+                in_flight_bytes -= in_flight.pop_front().unwrap_or(0);
+            }
+            peak_buffered = peak_buffered.max(in_flight_bytes);
+
+            // Skipped chunks count as processed right away
+            let Some(data) = chunk.data else {
+                progress.bytes_processed += chunk.len;
+                progress.estimate_compressed_total();
+                return Ok(());
+            };
+            bytes_read += raw_bytes;
+            let allocated_grains = compressed_grains.iter().flatten().count() as u64;
+            if allocated_grains > 0 {
+                compressor.record(allocated_grains * grain_size, compressed_bytes);
+            }
+            if let Some(verifier) = verifier.as_deref_mut() {
+                verifier.add_source(chunk.offset, &data);
+                verifier.add_output(chunk.offset, raw_bytes, &compressed_grains)?;
+            }
+
+            // Write compressed grains, one per grain-sized piece of the chunk
+            for (i, compressed_grain) in compressed_grains.into_iter().enumerate() {
+                if let Some(compressed_grain) = compressed_grain {
+                    let lba = (chunk.offset + i as u64 * grain_size) / SECTOR_SIZE;
+                    vmdk_writer.write_grain(lba, &compressed_grain)?;
+                }
+            }
+
+            // Update progress
+            progress.bytes_processed += raw_bytes;
+            progress.compressed_bytes_written = written_before + vmdk_writer.bytes_written();
+            progress.estimate_compressed_total();
+            reporter.report_throttled(progress);
+            Ok(())
+        };
+        pipeline.process_streaming(chunks.by_ref().take(batch), compress, write)?;
     }
 
-    // Finish the VMDK
+    Ok((bytes_read, peak_buffered))
+}
+
+/// Compress a disk into a streamOptimized VMDK written to `sender`, as one
+/// producer of the disks written in parallel, at the given `compression`.
+///
+/// The bytes of the disk processed so far are stored in `processed` as the
+/// disk is compressed, for the OVA writer to aggregate into its progress.
+///
+/// # Returns
+///
+/// The bytes read from the source, the most bytes buffered at once and, with
+/// `end_to_end_verify` or `logical_manifest`, the SHA256 of the disk's
+/// logical contents.
+fn write_disk_stream(
+    source: &dyn DiskSource,
+    filename: &str,
+    pipeline: &Pipeline,
+    compression: CompressionLevel,
+    options: &ExportOptions,
+    processed: Arc<AtomicU64>,
+    sender: &mut OrderedSender,
+) -> Result<(u64, u64, Option<String>)> {
+    let capacity_bytes = source.size();
+    let grain_size_sectors = options.grain_size / SECTOR_SIZE;
+    let mut vmdk_writer =
+        StreamVmdkWriter::with_grain_size(sender, capacity_bytes, grain_size_sectors)?;
+    let mut verifier = EndToEndVerifier::for_options(options, vmdk_writer.grain_size_bytes());
+
+    // Progress is reported by the OVA writer, from what each disk publishes
+    let publish: Option<ProgressCallback> = Some(Box::new(move |progress: ExportProgress| {
+        processed.store(progress.bytes_processed, Ordering::Relaxed)
+    }));
+    let mut reporter = ProgressReporter::new(&publish, Duration::ZERO);
+    let mut progress = ExportProgress::new(ExportPhase::Compressing, capacity_bytes, 1);
+    let (bytes_read, buffered_bytes) = process_disk(
+        source,
+        pipeline,
+        compression,
+        options.chunk_size,
+        &mut vmdk_writer,
+        verifier.as_mut(),
+        &mut progress,
+        &mut reporter,
+    )?;
+    let logical_sha256 = verifier
+        .map(|verifier| verifier.finish(filename, capacity_bytes))
+        .transpose()?;
+
     vmdk_writer.finish()?;
+    Ok((bytes_read, buffered_bytes, logical_sha256))
+}
 
-    Ok(vmdk_buffer.into_inner())
+/// Hashes a disk's source data and its decompressed grains as two logical
+/// byte streams, for [`ExportOptions::end_to_end_verify`].
+///
+/// Ranges not passed in (unallocated ranges and all-zero grains) are hashed
+/// as zeros on both sides. For [`ExportOptions::logical_manifest`] alone only
+/// the source is hashed.
+struct EndToEndVerifier {
+    grain_size: u64,
+    source: Sha256,
+    /// Bytes of the disk hashed into `source` so far.
+    source_pos: u64,
+    /// `None` when only the source is hashed.
+    output: Option<Sha256>,
+    /// Bytes of the disk hashed into `output` so far.
+    output_pos: u64,
 }
 
-/// Calculate total disk size from VMX config.
-fn calculate_total_disk_size(config: &VmxConfig, vmx_dir: &Path) -> Result<u64> {
-    let mut total = 0u64;
+impl EndToEndVerifier {
+    fn new(grain_size: u64) -> Self {
+        Self {
+            grain_size,
+            source: Sha256::new(),
+            source_pos: 0,
+            output: Some(Sha256::new()),
+            output_pos: 0,
+        }
+    }
 
-    for disk_config in &config.disks {
-        let vmdk_path = vmx_dir.join(&disk_config.file_name);
+    /// Create a verifier that only hashes the source, without decompressing
+    /// the grains.
+    fn source_only(grain_size: u64) -> Self {
+        Self {
+            output: None,
+            ..Self::new(grain_size)
+        }
+    }
 
-        if vmdk_path.exists() {
-            // Check if this is a sparse VMDK or a text descriptor
-            if is_sparse_vmdk(&vmdk_path)? {
-                // Sparse VMDK - use the virtual capacity
-                let sparse_reader = SparseVmdkReader::open(&vmdk_path)?;
-                total += sparse_reader.capacity();
+    /// Create the verifier an export with `options` needs, if any.
+    fn for_options(options: &ExportOptions, grain_size: u64) -> Option<Self> {
+        if options.end_to_end_verify {
+            Some(Self::new(grain_size))
+        } else if options.logical_manifest {
+            Some(Self::source_only(grain_size))
+        } else {
+            None
+        }
+    }
+
+    /// Hash a chunk read from the source at `offset`.
+    fn add_source(&mut self, offset: u64, chunk: &[u8]) {
+        hash_zeros(&mut self.source, offset - self.source_pos);
+        self.source.update(chunk);
+        self.source_pos = offset + chunk.len() as u64;
+    }
+
+    /// Decompress and hash the grains of a chunk of `chunk_len` bytes at `offset`.
+    fn add_output(
+        &mut self,
+        offset: u64,
+        chunk_len: u64,
+        compressed_grains: &[Option<Vec<u8>>],
+    ) -> Result<()> {
+        let Some(output) = self.output.as_mut() else {
+            return Ok(());
+        };
+        hash_zeros(output, offset - self.output_pos);
+        for (i, compressed_grain) in compressed_grains.iter().enumerate() {
+            let grain_offset = i as u64 * self.grain_size;
+            let grain_len = std::cmp::min(self.grain_size, chunk_len - grain_offset);
+            match compressed_grain {
+                Some(compressed_grain) => {
+                    let mut grain = Vec::with_capacity(grain_len as usize);
+                    DeflateDecoder::new(compressed_grain.as_slice())
+                        .read_to_end(&mut grain)
+                        .map_err(|e| {
+                            Error::validation(format!(
+                                "end-to-end verification failed: grain at byte {} \
+                                 does not decompress: {}",
+                                offset + grain_offset,
+                                e
+                            ))
+                        })?;
+                    output.update(&grain);
+                }
+                None => hash_zeros(output, grain_len),
+            }
+        }
+        self.output_pos = offset + chunk_len;
+        Ok(())
+    }
+
+    /// Compare the two streams over the whole disk.
+    ///
+    /// # Returns
+    ///
+    /// The SHA256 of the logical disk, or a validation error naming the
+    /// disk if the output does not match the source.
+    fn finish(mut self, disk_name: &str, capacity_bytes: u64) -> Result<String> {
+        hash_zeros(&mut self.source, capacity_bytes - self.source_pos);
+        let source = format!("{:x}", self.source.finalize());
+        let Some(mut output) = self.output else {
+            return Ok(source);
+        };
+        hash_zeros(&mut output, capacity_bytes - self.output_pos);
+        let output = format!("{:x}", output.finalize());
+        if source != output {
+            return Err(Error::validation(format!(
+                "end-to-end verification failed for disk '{}': source SHA256 {} \
+                 but the compressed output decompresses to SHA256 {}",
+                disk_name, source, output
+            )));
+        }
+        Ok(source)
+    }
+}
+
+/// Feed `len` zero bytes to a hasher.
+fn hash_zeros(hasher: &mut Sha256, mut len: u64) {
+    static ZEROS: [u8; 64 * 1024] = [0; 64 * 1024];
+    while len > 0 {
+        let n = std::cmp::min(len, ZEROS.len() as u64) as usize;
+        hasher.update(&ZEROS[..n]);
+        len -= n as u64;
+    }
+}
+
+/// Split a chunk read from `source` at `offset` into grains and compress
+/// each one.
+///
+/// All-zero grains come back as `None`, unless the source needs their zeros
+/// written (see [`DiskSource::must_write_zeros`]).
+fn compress_grains_nonzero(
+    source: &dyn DiskSource,
+    offset: u64,
+    chunk: &[u8],
+    grain_size: u64,
+    level: u32,
+) -> Result<Vec<Option<Vec<u8>>>> {
+    chunk
+        .chunks(grain_size as usize)
+        .enumerate()
+        .map(|(i, grain)| {
+            let grain_offset = offset + i as u64 * grain_size;
+            if is_zero_grain(grain) && !source.must_write_zeros(grain_offset, grain.len()) {
+                Ok(None)
             } else {
-                // Text descriptor
-                let content = fs::read_to_string(&vmdk_path)
-                    .map_err(|e| Error::io(e, &vmdk_path))?;
-                let descriptor = parse_descriptor(&content)?;
+                compress_grain(grain, level).map(Some)
+            }
+        })
+        .collect()
+}
 
-                // Try flat extent first
-                if let Some(flat_extent) = descriptor.extents.iter().find(|e| e.extent_type == ExtentType::Flat) {
-                    let flat_path = vmx_dir.join(&flat_extent.filename);
-                    if flat_path.exists() {
-                        let metadata = fs::metadata(&flat_path)
-                            .map_err(|e| Error::io(e, &flat_path))?;
-                        total += metadata.len();
-                    }
-                } else {
-                    // Try sparse extents (split sparse VMDK)
-                    for extent in descriptor.extents.iter().filter(|e| e.extent_type == ExtentType::Sparse) {
-                        let extent_path = vmx_dir.join(&extent.filename);
-                        if extent_path.exists() {
-                            let metadata = fs::metadata(&extent_path)
-                                .map_err(|e| Error::io(e, &extent_path))?;
-                            total += metadata.len();
-                        }
-                    }
+/// Process a single disk into an uncompressed hosted sparse VMDK.
+///
+/// All-zero grains are left unallocated, unless the source needs their zeros
+/// written (see [`DiskSource::must_write_zeros`]).
+fn process_disk_sparse(
+    source: &dyn DiskSource,
+    chunk_size: usize,
+    progress: &mut ExportProgress,
+    reporter: &mut ProgressReporter,
+) -> Result<(Vec<u8>, u64)> {
+    let capacity_bytes = source.size();
+    let mut vmdk_writer = SparseVmdkWriter::new(Cursor::new(Vec::new()), capacity_bytes)?;
+    let grain_size = vmdk_writer.grain_size_bytes();
+
+    // Read whole grains so each one can be written on its own
+    let read_len = std::cmp::max(chunk_size as u64 / grain_size, 1) * grain_size;
+    let mut bytes_read = 0u64;
+    let mut offset = 0u64;
+    while offset < capacity_bytes {
+        let len = std::cmp::min(read_len, capacity_bytes - offset) as usize;
+        if !source.is_unallocated(offset, len) {
+            let chunk = source.read_at(offset, len)?;
+            bytes_read += chunk.len() as u64;
+            for (i, grain) in chunk.chunks(grain_size as usize).enumerate() {
+                let grain_offset = offset + i as u64 * grain_size;
+                if !is_zero_grain(grain) || source.must_write_zeros(grain_offset, grain.len()) {
+                    let lba = grain_offset / SECTOR_SIZE;
+                    vmdk_writer.write_grain(lba, grain)?;
                 }
             }
         }
+        offset += len as u64;
+
+        progress.bytes_processed += len as u64;
+        reporter.report_throttled(progress);
     }
 
-    Ok(total)
+    Ok((vmdk_writer.finish()?.into_inner(), bytes_read))
 }
 
 /// Sanitize a filename by removing or replacing invalid characters.
@@ -746,6 +3042,7 @@ fn sanitize_filename(name: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ova::compute_sha256;
 
     #[test]
     fn test_export_options_default() {
@@ -775,6 +3072,52 @@ mod tests {
         assert_eq!(options.num_threads, 4);
     }
 
+    #[test]
+    fn test_export_options_disk_compression() {
+        let mut options = ExportOptions::max_compression();
+        assert_eq!(options.disk_compression(1), CompressionLevel::Max);
+
+        options.per_disk_compression =
+            Some(vec![CompressionLevel::Fast, CompressionLevel::Balanced]);
+        assert_eq!(options.disk_compression(0), CompressionLevel::Fast);
+        assert_eq!(options.disk_compression(1), CompressionLevel::Balanced);
+    }
+
+    #[test]
+    fn test_export_options_builder_defaults() {
+        let options = ExportOptions::builder().build();
+        let default = ExportOptions::default();
+        assert_eq!(options.compression, default.compression);
+        assert_eq!(options.chunk_size, default.chunk_size);
+        assert_eq!(options.num_threads, default.num_threads);
+        assert_eq!(options.disk_format, default.disk_format);
+        assert_eq!(options.grain_size, default.grain_size);
+        assert!(!options.parallel_disks);
+        assert!(options.follow_symlinks);
+    }
+
+    #[test]
+    fn test_export_options_builder_overrides() {
+        let options = ExportOptions::builder()
+            .compression(CompressionLevel::Max)
+            .chunk_size(1024 * 1024)
+            .threads(4)
+            .disk_format(DiskFormat::Sparse)
+            .grain_size(4096)
+            .parallel_disks(true)
+            .progress_interval(Duration::from_millis(250))
+            .build();
+        assert_eq!(options.compression, CompressionLevel::Max);
+        assert_eq!(options.chunk_size, 1024 * 1024);
+        assert_eq!(options.num_threads, 4);
+        assert_eq!(options.disk_format, DiskFormat::Sparse);
+        assert_eq!(options.grain_size, 4096);
+        assert!(options.parallel_disks);
+        assert_eq!(options.progress_interval, Duration::from_millis(250));
+        // Settings without a builder method keep their defaults
+        assert_eq!(options.max_disk_bytes, None);
+    }
+
     #[test]
     fn test_export_phase_display() {
         assert_eq!(format!("{}", ExportPhase::Parsing), "Parsing");
@@ -815,10 +3158,183 @@ mod tests {
             phase: ExportPhase::Complete,
             bytes_processed: 0,
             bytes_total: 0,
+            compressed_bytes_written: 0,
+            compressed_bytes_total: 0,
             current_disk: 0,
             total_disks: 0,
+            elapsed: Duration::ZERO,
         };
         assert_eq!(complete.percent_complete(), 100.0);
+        assert_eq!(complete.percent_written(), 100.0);
+    }
+
+    #[test]
+    fn test_export_progress_indeterminate() {
+        assert!(ExportProgress::new(ExportPhase::Compressing, 0, 2).is_indeterminate());
+        assert!(!ExportProgress::new(ExportPhase::Compressing, 0, 0).is_indeterminate());
+        assert!(!ExportProgress::new(ExportPhase::Compressing, 512, 1).is_indeterminate());
+    }
+
+    #[test]
+    fn test_export_progress_bytes_per_sec() {
+        let mut progress = ExportProgress::new(ExportPhase::Compressing, 1000, 1);
+        progress.bytes_processed = 250;
+        assert_eq!(progress.bytes_per_sec(), 0.0);
+
+        progress.elapsed = Duration::from_secs(2);
+        assert_eq!(progress.bytes_per_sec(), 125.0);
+    }
+
+    #[test]
+    fn test_export_progress_eta() {
+        let mut progress = ExportProgress::new(ExportPhase::Compressing, 1000, 1);
+        assert_eq!(progress.eta(), None);
+
+        // No rate yet: data processed but no time elapsed
+        progress.bytes_processed = 250;
+        assert_eq!(progress.eta(), None);
+
+        progress.elapsed = Duration::from_secs(2);
+        assert_eq!(progress.eta(), Some(Duration::from_secs(6)));
+
+        progress.bytes_processed = 1000;
+        assert_eq!(progress.eta(), Some(Duration::ZERO));
+
+        let empty = ExportProgress::new(ExportPhase::Complete, 0, 0);
+        assert_eq!(empty.eta(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_export_progress_percent_written() {
+        let mut progress = ExportProgress::new(ExportPhase::Writing, 1000, 1);
+        assert_eq!(progress.percent_written(), 0.0);
+
+        progress.compressed_bytes_total = 200;
+        progress.compressed_bytes_written = 50;
+        assert_eq!(progress.percent_written(), 25.0);
+    }
+
+    #[test]
+    fn test_phase_timer_records_each_phase() {
+        let mut timer = PhaseTimer::start(ExportPhase::Parsing);
+        std::thread::sleep(Duration::from_millis(2));
+        timer.enter(ExportPhase::Compressing);
+        std::thread::sleep(Duration::from_millis(2));
+        timer.enter(ExportPhase::Parsing);
+        timer.enter(ExportPhase::Writing);
+
+        let timings = timer.finish();
+        assert_eq!(timings.len(), 3);
+        assert!(timings[&ExportPhase::Parsing] >= Duration::from_millis(2));
+        assert!(timings[&ExportPhase::Compressing] >= Duration::from_millis(2));
+        assert!(timings.contains_key(&ExportPhase::Writing));
+        assert!(!timings.contains_key(&ExportPhase::Complete));
+    }
+
+    #[test]
+    fn test_report_to_json() {
+        let report = ExportReport {
+            warnings: vec!["disk \"b\" is missing".to_string()],
+            phase_timings: HashMap::from([(ExportPhase::Compressing, Duration::from_millis(1500))]),
+            disks: vec![DiskStats {
+                file_name: "disk.vmdk".to_string(),
+                capacity_bytes: 1000,
+                bytes_read: 800,
+                compressed_bytes: 250,
+                logical_sha256: None,
+                compression: Some(CompressionLevel::Balanced),
+            }],
+            threads: 4,
+            peak_memory_bytes: 1050,
+        };
+
+        let json = report.to_json();
+        assert!(json.contains("\"threads\": 4,"));
+        assert!(json.contains("\"compressing\": 1.500000"));
+        assert!(!json.contains("\"parsing\""));
+        assert!(json.contains("\"file_name\": \"disk.vmdk\""));
+        assert!(json.contains("\"compression_ratio\": 0.250000"));
+        assert!(json.contains("\"logical_sha256\": null"));
+        assert!(json.contains(r#""warnings": ["disk \"b\" is missing"]"#));
+    }
+
+    /// Feed one chunk at `offset` through a verifier, compressing it first.
+    fn verify_chunk(
+        verifier: &mut EndToEndVerifier,
+        offset: u64,
+        chunk: &[u8],
+        corrupt: impl Fn(&mut Vec<Option<Vec<u8>>>),
+    ) -> Result<()> {
+        let source = ZeroDiskSource::new(offset + chunk.len() as u64);
+        let mut compressed_grains =
+            compress_grains_nonzero(&source, offset, chunk, 4096, 6).unwrap();
+        corrupt(&mut compressed_grains);
+        verifier.add_source(offset, chunk);
+        verifier.add_output(offset, chunk.len() as u64, &compressed_grains)
+    }
+
+    #[test]
+    fn test_end_to_end_verifier_accepts_round_trip() {
+        let mut chunk: Vec<u8> = (0..10_000).map(|i| (i % 13) as u8).collect();
+        chunk[4096..8192].fill(0);
+        let mut verifier = EndToEndVerifier::new(4096);
+        // The first 8192 bytes are an unallocated gap
+        verify_chunk(&mut verifier, 8192, &chunk, |_| {}).unwrap();
+
+        let mut disk = vec![0u8; 8192];
+        disk.extend_from_slice(&chunk);
+        disk.resize(20_000, 0);
+        assert_eq!(verifier.finish("disk.vmdk", 20_000).unwrap(), compute_sha256(&disk));
+    }
+
+    #[test]
+    fn test_end_to_end_verifier_detects_corrupt_grain() {
+        let chunk: Vec<u8> = (0..8192).map(|i| (i % 13) as u8).collect();
+
+        // A grain that decompresses cleanly, but to the wrong data
+        let mut verifier = EndToEndVerifier::new(4096);
+        verify_chunk(&mut verifier, 0, &chunk, |grains| {
+            grains[1] = Some(compress_grain(&[0x42; 4096], 6).unwrap());
+        })
+        .unwrap();
+        let err = verifier.finish("disk.vmdk", 8192).unwrap_err();
+        assert!(matches!(err, Error::Validation { .. }));
+        assert!(err.to_string().contains("'disk.vmdk'"), "{}", err);
+
+        // A grain that doesn't decompress at all
+        let mut verifier = EndToEndVerifier::new(4096);
+        let err = verify_chunk(&mut verifier, 0, &chunk, |grains| {
+            grains[1] = Some(vec![0xFF; 16]);
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("grain at byte 4096"), "{}", err);
+    }
+
+    #[test]
+    fn test_grain_count_warning() {
+        const GB: u64 = 1024 * 1024 * 1024;
+
+        // 4 KB grains over 128 GB make 32M grains
+        let warning = grain_count_warning("big.vmdk", 128 * GB, 4096).unwrap();
+        assert!(warning.contains("'big.vmdk' spans 33554432 grains of 4 KB"), "{}", warning);
+        assert!(warning.contains("64 KB or more"), "{}", warning);
+
+        assert_eq!(grain_count_warning("small.vmdk", GB, 4096), None);
+        assert_eq!(grain_count_warning("big.vmdk", 128 * GB, 64 * 1024), None);
+        assert_eq!(grain_count_warning("huge.vmdk", 4096 * GB, 64 * 1024), None);
+    }
+
+    #[test]
+    fn test_disk_stats_ratio_empty_disk() {
+        let stats = DiskStats {
+            file_name: "empty.vmdk".to_string(),
+            capacity_bytes: 0,
+            bytes_read: 0,
+            compressed_bytes: 1536,
+            logical_sha256: None,
+            compression: Some(CompressionLevel::Balanced),
+        };
+        assert_eq!(stats.compression_ratio(), 0.0);
     }
 
     #[test]
@@ -826,11 +3342,13 @@ mod tests {
         let detail = DiskDetail {
             filename: "disk.vmdk".to_string(),
             size_bytes: 10 * 1024 * 1024 * 1024,
-            create_type: "monolithicFlat".to_string(),
+            create_type: CreateType::MonolithicFlat,
+            access: ExtentAccess::ReadWrite,
+            deletable: true,
         };
         assert_eq!(detail.filename, "disk.vmdk");
         assert_eq!(detail.size_bytes, 10 * 1024 * 1024 * 1024);
-        assert_eq!(detail.create_type, "monolithicFlat");
+        assert_eq!(detail.create_type, CreateType::MonolithicFlat);
     }
 
     #[test]
@@ -843,9 +3361,12 @@ mod tests {
             disks: vec![DiskDetail {
                 filename: "disk.vmdk".to_string(),
                 size_bytes: 10 * 1024 * 1024 * 1024,
-                create_type: "monolithicFlat".to_string(),
+                create_type: CreateType::MonolithicFlat,
+                access: ExtentAccess::ReadWrite,
+                deletable: true,
             }],
             total_disk_size: 10 * 1024 * 1024 * 1024,
+            hardware_version: Some("vmx-21".to_string()),
         };
         assert_eq!(info.name, "TestVM");
         assert_eq!(info.guest_os, "ubuntu-64");
@@ -868,4 +3389,52 @@ mod tests {
     fn test_default_chunk_size() {
         assert_eq!(DEFAULT_CHUNK_SIZE, 64 * 1024 * 1024);
     }
+
+    #[test]
+    fn test_process_disk_streams_bounded_window() {
+        use crate::vmdk::StreamOptimizedReader;
+        use std::io::Write;
+
+        const CHUNK: usize = 64 * 1024;
+        let data: Vec<u8> = (0..64 * CHUNK).map(|i| (i / 512 % 251) as u8).collect();
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(&data).unwrap();
+        file.flush().unwrap();
+        let source = VmdkReader::open(file.path()).unwrap();
+
+        let pipeline = Pipeline::new(PipelineConfig::new(CHUNK, CompressionLevel::Fast, 2));
+        let mut vmdk_writer =
+            StreamVmdkWriter::with_grain_size(Vec::new(), data.len() as u64, 128).unwrap();
+        let mut progress = ExportProgress::new(ExportPhase::Compressing, data.len() as u64, 1);
+        let mut reporter = ProgressReporter::new(&None, Duration::ZERO);
+        let (bytes_read, peak_buffered) = process_disk(
+            &source,
+            &pipeline,
+            CompressionLevel::Fast,
+            CHUNK,
+            &mut vmdk_writer,
+            None,
+            &mut progress,
+            &mut reporter,
+        )
+        .unwrap();
+
+        // Only one window of chunks is held, raw and compressed
+        let window = pipeline.streaming_window();
+        assert_eq!(pipeline.peak_buffered_results(), window);
+        assert!(peak_buffered > 0);
+        assert!(peak_buffered <= (2 * window * CHUNK) as u64, "{}", peak_buffered);
+        assert_eq!(bytes_read, data.len() as u64);
+        assert_eq!(progress.bytes_processed, data.len() as u64);
+
+        let vmdk = vmdk_writer.finish().unwrap();
+        let reader = StreamOptimizedReader::from_bytes(&vmdk).unwrap();
+        let mut exported = vec![0u8; data.len()];
+        for grain in reader.grains() {
+            let grain = grain.unwrap();
+            let start = (grain.lba * 512) as usize;
+            exported[start..start + grain.data.len()].copy_from_slice(&grain.data);
+        }
+        assert!(exported == data, "grains were not written in place");
+    }
 }
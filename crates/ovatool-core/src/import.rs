@@ -0,0 +1,285 @@
+//! Import of OVA archives back into VMware VMs.
+//!
+//! This module reverses an export: it reads the OVF descriptor of an OVA,
+//! writes a VMX file describing the same virtual hardware, and expands each
+//! streamOptimized disk into a flat extent with a text descriptor. Only the
+//! OVFs OVATool itself produces are supported for now.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+use crate::ova::OvaReader;
+use crate::ovf::{parse_ovf_summary, OvfDiskSummary, OvfNetworkSummary, GUEST_OS_MAPPINGS};
+use crate::vmdk::{CreateType, StreamOptimizedReader, SECTOR_SIZE};
+use crate::vmx::{write_vmx, ControllerConfig, DiskConfig, NetworkConfig, VmxConfig};
+
+/// SCSI controller the imported disks are attached to.
+const IMPORT_CONTROLLER: &str = "scsi0";
+
+/// Adapter model of [`IMPORT_CONTROLLER`] and of the disk descriptors.
+const IMPORT_ADAPTER: &str = "lsilogic";
+
+/// SCSI unit reserved for the controller itself.
+const SCSI_CONTROLLER_UNIT: u32 = 7;
+
+/// Guest OS used when the OVF's `vmw:osType` isn't recognized.
+const FALLBACK_GUEST_OS: &str = "other";
+
+/// Summary of a completed import.
+#[derive(Debug, Clone, Default)]
+pub struct ImportReport {
+    /// Path of the VMX file written.
+    pub vmx_path: PathBuf,
+    /// Paths of the disk descriptors written, in OVF disk order.
+    pub disk_paths: Vec<PathBuf>,
+    /// Problems that were tolerated, such as an unrecognized guest OS.
+    pub warnings: Vec<String>,
+}
+
+/// Import an OVA into a VMX file and flat VMDKs.
+///
+/// The VMX takes the name, guest OS, memory, CPU count, hardware version,
+/// firmware and network adapters from the OVF, and attaches the disks to an
+/// LSI Logic SCSI controller in OVF disk order. Each disk keeps its file
+/// name from the OVA as the name of its descriptor, with the data in a
+/// `-flat.vmdk` extent next to it.
+///
+/// # Arguments
+///
+/// * `ova_path` - Path to the OVA file.
+/// * `output_dir` - Directory to write the VM into; created if missing.
+///
+/// # Returns
+///
+/// An `ImportReport` listing the files written.
+///
+/// # Errors
+///
+/// Returns an unsupported error for disks that aren't streamOptimized, and
+/// an OVA error for disk names that would leave `output_dir`.
+pub fn import_ova(ova_path: &Path, output_dir: &Path) -> Result<ImportReport> {
+    let file = File::open(ova_path).map_err(|e| Error::io(e, ova_path))?;
+    let mut reader = OvaReader::new(file)?;
+    let ovf = reader.read_ovf()?;
+    let summary = parse_ovf_summary(&ovf)?;
+    let mut report = ImportReport::default();
+
+    fs::create_dir_all(output_dir).map_err(|e| Error::io(e, output_dir))?;
+
+    let mut disks = Vec::with_capacity(summary.disks.len());
+    let mut units = (0..).filter(|&unit| unit != SCSI_CONTROLLER_UNIT);
+    for disk in &summary.disks {
+        let descriptor_path = import_disk(&mut reader, disk, output_dir)?;
+        disks.push(DiskConfig {
+            file_name: disk.file_name.clone(),
+            controller: IMPORT_CONTROLLER.to_string(),
+            unit: units.next().unwrap_or_default(),
+        });
+        report.disk_paths.push(descriptor_path);
+    }
+
+    let guest_os = GUEST_OS_MAPPINGS
+        .iter()
+        .find(|mapping| mapping.os_type.eq_ignore_ascii_case(&summary.os_type))
+        .map(|mapping| mapping.guest_os)
+        .unwrap_or_else(|| {
+            report.warnings.push(format!(
+                "OVF osType '{}' is not recognized; the VM's guest OS is set to '{}'",
+                summary.os_type, FALLBACK_GUEST_OS
+            ));
+            FALLBACK_GUEST_OS
+        });
+
+    let mut raw = HashMap::new();
+    raw.insert("config.version".to_string(), "8".to_string());
+    if let Some(version) = summary
+        .hardware_version
+        .as_deref()
+        .and_then(|version| version.strip_prefix("vmx-"))
+    {
+        raw.insert("virtualHW.version".to_string(), version.to_string());
+    }
+
+    let controllers = if disks.is_empty() {
+        Vec::new()
+    } else {
+        vec![ControllerConfig {
+            name: IMPORT_CONTROLLER.to_string(),
+            shared_bus: None,
+            virtual_dev: Some(IMPORT_ADAPTER.to_string()),
+        }]
+    };
+    let config = VmxConfig {
+        display_name: summary.name.clone(),
+        guest_os: guest_os.to_string(),
+        memory_mb: summary.memory_mb,
+        num_cpus: summary.cpus,
+        disks,
+        networks: summary
+            .networks
+            .iter()
+            .enumerate()
+            .map(|(index, network)| network_config(index, network))
+            .collect(),
+        cdroms: Vec::new(),
+        controllers,
+        firmware: summary.firmware,
+        nvram: None,
+        annotation: String::new(),
+        raw,
+    };
+
+    let vmx_name = Path::new(&ovf_entry_name(&reader)?)
+        .with_extension("vmx")
+        .to_string_lossy()
+        .into_owned();
+    report.vmx_path = output_dir.join(vmx_name);
+    write_vmx(&config, &report.vmx_path)?;
+
+    Ok(report)
+}
+
+/// VMX settings of the OVF's `index`th network adapter, as `ethernet<index>`.
+fn network_config(index: usize, network: &OvfNetworkSummary) -> NetworkConfig {
+    let setting = |key: &str| {
+        network
+            .config
+            .iter()
+            .find(|(config_key, _)| config_key == key)
+            .map(|(_, value)| value.as_str())
+    };
+    NetworkConfig {
+        name: format!("ethernet{}", index),
+        virtual_dev: network.adapter_type.as_ref().map(|dev| dev.to_ascii_lowercase()),
+        network_name: Some(network.connection.clone()).filter(|name| !name.is_empty()),
+        start_connected: network.connected,
+        connection_type: setting("connectionType").map(str::to_string),
+        rss: setting("rss").and_then(|rss| rss.parse().ok()),
+        num_queue_pairs: setting("numqps").and_then(|numqps| numqps.parse().ok()),
+    }
+}
+
+/// Name of the OVF descriptor within the archive.
+fn ovf_entry_name<R: std::io::Read + Seek>(reader: &OvaReader<R>) -> Result<String> {
+    reader
+        .entries()
+        .iter()
+        .find(|entry| entry.name.ends_with(".ovf"))
+        .map(|entry| entry.name.clone())
+        .ok_or_else(|| Error::ova("archive contains no OVF descriptor"))
+}
+
+/// Expand one streamOptimized disk of the archive into a flat extent and
+/// its descriptor.
+///
+/// # Returns
+///
+/// The path of the descriptor written.
+fn import_disk<R: std::io::Read + Seek>(
+    reader: &mut OvaReader<R>,
+    disk: &OvfDiskSummary,
+    output_dir: &Path,
+) -> Result<PathBuf> {
+    if CreateType::parse(&disk.format) != CreateType::StreamOptimized {
+        return Err(Error::unsupported(format!(
+            "Disk '{}' has format '{}'; only streamOptimized disks can be imported",
+            disk.file_name, disk.format
+        )));
+    }
+    let is_plain_name = Path::new(&disk.file_name)
+        .file_name()
+        .is_some_and(|name| name == disk.file_name.as_str());
+    if !is_plain_name {
+        return Err(Error::ova(format!(
+            "Disk file name '{}' is not a plain file name",
+            disk.file_name
+        )));
+    }
+
+    // The stream reader needs the whole file, so stage it next to the output
+    let stream_path = output_dir.join(format!("{}.stream", disk.file_name));
+    let mut stream_file = File::create(&stream_path).map_err(|e| Error::io(e, &stream_path))?;
+    let extracted = reader
+        .extract_file(&disk.file_name, &mut stream_file)
+        .and_then(|_| {
+            drop(stream_file);
+            write_flat_disk(&stream_path, &disk.file_name, output_dir)
+        });
+    // A failed extraction is the error worth reporting, not its cleanup
+    let removed = fs::remove_file(&stream_path).map_err(|e| Error::io(e, &stream_path));
+    let descriptor_path = extracted?;
+    removed?;
+    Ok(descriptor_path)
+}
+
+/// Decompress a streamOptimized VMDK into a flat extent with a descriptor.
+///
+/// Unallocated grains are left as holes in the flat file, which read as
+/// zeros.
+fn write_flat_disk(stream_path: &Path, file_name: &str, output_dir: &Path) -> Result<PathBuf> {
+    let stream = StreamOptimizedReader::open(stream_path)?;
+    let stem = file_name.strip_suffix(".vmdk").unwrap_or(file_name);
+    let flat_name = format!("{}-flat.vmdk", stem);
+    let flat_path = output_dir.join(&flat_name);
+
+    let mut flat = File::create(&flat_path).map_err(|e| Error::io(e, &flat_path))?;
+    flat.set_len(stream.capacity())
+        .map_err(|e| Error::io(e, &flat_path))?;
+    for grain in stream.grains() {
+        let grain = grain?;
+        flat.seek(SeekFrom::Start(grain.lba * SECTOR_SIZE))
+            .and_then(|_| flat.write_all(&grain.data))
+            .map_err(|e| Error::io(e, &flat_path))?;
+    }
+
+    let descriptor_path = output_dir.join(file_name);
+    let descriptor = flat_descriptor(stream.capacity() / SECTOR_SIZE, &flat_name);
+    fs::write(&descriptor_path, descriptor).map_err(|e| Error::io(e, &descriptor_path))?;
+    Ok(descriptor_path)
+}
+
+/// Text descriptor of a monolithicFlat disk with a single extent.
+fn flat_descriptor(capacity_sectors: u64, flat_name: &str) -> String {
+    // SCSI disks report 255 heads and 63 sectors per track
+    let cylinders = (capacity_sectors / (255 * 63)).clamp(1, 65535);
+    format!(
+        "# Disk DescriptorFile\n\
+         version=1\n\
+         CID=fffffffe\n\
+         parentCID=ffffffff\n\
+         createType=\"{}\"\n\
+         \n\
+         # Extent description\n\
+         RW {} FLAT \"{}\" 0\n\
+         \n\
+         # The Disk Data Base\n\
+         ddb.adapterType = \"{}\"\n\
+         ddb.geometry.cylinders = \"{}\"\n\
+         ddb.geometry.heads = \"255\"\n\
+         ddb.geometry.sectors = \"63\"\n",
+        CreateType::MonolithicFlat,
+        capacity_sectors,
+        flat_name,
+        IMPORT_ADAPTER,
+        cylinders
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vmdk::parse_descriptor;
+
+    #[test]
+    fn test_flat_descriptor_parses() {
+        let descriptor = parse_descriptor(&flat_descriptor(2048, "disk-flat.vmdk")).unwrap();
+        assert_eq!(descriptor.create_type, CreateType::MonolithicFlat);
+        assert_eq!(descriptor.disk_size_bytes(), 1024 * 1024);
+        assert_eq!(descriptor.extents[0].filename, "disk-flat.vmdk");
+        assert_eq!(descriptor.adapter_type, "lsilogic");
+        assert!(!descriptor.is_delta());
+    }
+}
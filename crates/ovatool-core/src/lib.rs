@@ -10,12 +10,17 @@
 //! # Modules
 //!
 //! - [`error`] - Error types and Result alias
+//! - [`cbt`] - Changed-block maps for incremental exports
+//! - [`compare`] - Structural comparison of OVA archives
+//! - [`diagnose`] - Pre-flight checks listing why a VM can't be exported
 //! - [`vmx`] - VMX file parsing
-//! - [`vmdk`] - VMDK disk handling (reading, compression, stream-optimized writing)
-//! - [`ovf`] - OVF descriptor generation
-//! - [`ova`] - OVA archive creation
+//! - [`vmdk`] - VMDK disk handling (reading, compression, stream-optimized writing and verification)
+//! - [`ovf`] - OVF descriptor generation and parsing
+//! - [`ova`] - OVA archive creation and reading
 //! - [`pipeline`] - Parallel processing pipeline
+//! - [`source`] - Disk data sources the exporter reads from
 //! - [`export`] - Export orchestrator coordinating the full pipeline
+//! - [`import`] - Import of OVAs back into VMX and flat VMDK files
 //!
 //! # Quick Start
 //!
@@ -29,11 +34,16 @@
 //! export_vm(vmx_path, output_path, ExportOptions::default(), None).unwrap();
 //! ```
 
+pub mod cbt;
+pub mod compare;
+pub mod diagnose;
 pub mod error;
 pub mod export;
+pub mod import;
 pub mod ova;
 pub mod ovf;
 pub mod pipeline;
+pub mod source;
 pub mod vmdk;
 pub mod vmx;
 
@@ -41,9 +51,26 @@ pub use error::{Error, Result};
 
 // Re-export main export functionality for convenience
 pub use export::{
-    export_vm, get_vm_info, DiskDetail, ExportOptions, ExportPhase, ExportProgress,
-    ProgressCallback, VmInfo, DEFAULT_CHUNK_SIZE,
+    export_sources, export_vm, export_vm_to_writer, export_vm_with_ovf, get_ova_info, get_vm_info,
+    plan_export, CapacityRounding, DiskDetail, DiskLayout, DiskPlan, DiskStats, ExportOptions,
+    ExportOptionsBuilder, ExportPhase, ExportPlan, ExportProgress, ExportReport, ProgressCallback,
+    VmInfo, DEFAULT_CHUNK_SIZE,
 };
 
+// Re-export the OVA import
+pub use import::{import_ova, ImportReport};
+
 // Re-export compression level from pipeline
 pub use pipeline::CompressionLevel;
+
+// Re-export the output disk format
+pub use ovf::DiskFormat;
+
+// Re-export the OVA file order
+pub use ova::{ManifestAlgorithm, OvaOrder};
+
+// Re-export the disk source trait for custom backends
+pub use source::DiskSource;
+
+// Re-export changed-block tracking input for incremental exports
+pub use cbt::ChangedBlockMap;
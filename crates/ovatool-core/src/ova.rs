@@ -1,14 +1,16 @@
-//! OVA archive creation.
+//! OVA archive creation and reading.
 //!
 //! This module handles creating OVA (Open Virtual Appliance) archives,
-//! which are TAR files containing OVF descriptors and disk images.
+//! which are TAR files containing OVF descriptors and disk images, and
+//! reading them back for inspection.
 //!
 //! # OVA Format
 //!
 //! An OVA file is a TAR archive containing:
 //! 1. An OVF descriptor file (XML)
 //! 2. One or more VMDK disk images
-//! 3. Optionally, a manifest file (.mf) with SHA256 checksums
+//! 3. Optionally, a manifest file (.mf) with SHA256 (or SHA1/SHA512) checksums
+//! 4. Optionally, a certificate file (.cert) signing the manifest
 //!
 //! # Example
 //!
@@ -23,12 +25,36 @@
 //! writer.finish().unwrap();
 //! ```
 
-use sha2::{Digest, Sha256};
-use std::io::{self, Seek, Write};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::error::{Error, Result};
 
+/// Name of the manifest written by [`OvaWriter::finish`].
+pub const MANIFEST_FILE_NAME: &str = "manifest.mf";
+
+/// Name of the certificate written by [`OvaWriter::finish`] when signing.
+pub const CERT_FILE_NAME: &str = "manifest.cert";
+
+/// Largest file size that fits the 11-digit octal size field of a USTAR header.
+pub const MAX_TAR_ENTRY_SIZE: u64 = 0o77777777777;
+
+/// Reject files too large to describe in a USTAR header.
+fn check_entry_size(name: &str, size: u64) -> Result<()> {
+    if size > MAX_TAR_ENTRY_SIZE {
+        return Err(Error::ova(format!(
+            "file '{}' is {} bytes, larger than the {} bytes a TAR entry can hold",
+            name, size, MAX_TAR_ENTRY_SIZE
+        )));
+    }
+    Ok(())
+}
+
 /// Compute SHA256 hash of data and return as hex string.
 pub fn compute_sha256(data: &[u8]) -> String {
     let mut hasher = Sha256::new();
@@ -37,6 +63,98 @@ pub fn compute_sha256(data: &[u8]) -> String {
     hex_encode(&result)
 }
 
+/// Digest algorithm of the manifest's `ALGORITHM(file)= digest` lines.
+///
+/// SHA256 is what current VMware tools write; older importers only accept
+/// SHA1 manifests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ManifestAlgorithm {
+    /// SHA-1, for importers predating OVF 2.0.
+    Sha1,
+    /// SHA-256, the default.
+    #[default]
+    Sha256,
+    /// SHA-512.
+    Sha512,
+}
+
+impl ManifestAlgorithm {
+    /// Returns the name used as the manifest line prefix, e.g. `SHA256`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ManifestAlgorithm::Sha1 => "SHA1",
+            ManifestAlgorithm::Sha256 => "SHA256",
+            ManifestAlgorithm::Sha512 => "SHA512",
+        }
+    }
+
+    /// Parse a manifest line prefix such as `SHA1`, case-insensitively.
+    pub fn parse(name: &str) -> Option<Self> {
+        [Self::Sha1, Self::Sha256, Self::Sha512]
+            .into_iter()
+            .find(|algorithm| algorithm.as_str().eq_ignore_ascii_case(name))
+    }
+
+    /// Compute the digest of `data` as a hex string.
+    pub fn digest(&self, data: &[u8]) -> String {
+        let mut hasher = self.hasher();
+        hasher.update(data);
+        hasher.finish()
+    }
+
+    /// Create a hasher for data written incrementally.
+    fn hasher(&self) -> ManifestHasher {
+        match self {
+            ManifestAlgorithm::Sha1 => ManifestHasher::Sha1(Sha1::new()),
+            ManifestAlgorithm::Sha256 => ManifestHasher::Sha256(Sha256::new()),
+            ManifestAlgorithm::Sha512 => ManifestHasher::Sha512(Sha512::new()),
+        }
+    }
+}
+
+impl fmt::Display for ManifestAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Incremental hasher for one of the [`ManifestAlgorithm`]s.
+enum ManifestHasher {
+    Sha1(Sha1),
+    Sha256(Sha256),
+    Sha512(Sha512),
+}
+
+impl ManifestHasher {
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            ManifestHasher::Sha1(hasher) => hasher.update(data),
+            ManifestHasher::Sha256(hasher) => hasher.update(data),
+            ManifestHasher::Sha512(hasher) => hasher.update(data),
+        }
+    }
+
+    /// Returns the digest as a hex string.
+    fn finish(self) -> String {
+        match self {
+            ManifestHasher::Sha1(hasher) => hex_encode(&hasher.finalize()),
+            ManifestHasher::Sha256(hasher) => hex_encode(&hasher.finalize()),
+            ManifestHasher::Sha512(hasher) => hex_encode(&hasher.finalize()),
+        }
+    }
+}
+
+impl Write for ManifestHasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
 /// Encode bytes as lowercase hex string.
 fn hex_encode(bytes: &[u8]) -> String {
     bytes.iter().map(|b| format!("{:02x}", b)).collect()
@@ -82,85 +200,130 @@ impl<W: Write> Write for Sha256Writer<W> {
     }
 }
 
+/// Longest name the USTAR name field holds.
+const USTAR_NAME_LEN: usize = 100;
+
+/// Longest directory prefix the USTAR prefix field holds.
+const USTAR_PREFIX_LEN: usize = 155;
+
+/// TAR type flag of a regular file.
+const TYPE_REGULAR: u8 = b'0';
+
+/// TAR type flag of a PAX extended header, which applies to the next entry.
+const TYPE_PAX_HEADER: u8 = b'x';
+
+/// TAR type flag of a PAX global header, which applies to every entry.
+const TYPE_PAX_GLOBAL: u8 = b'g';
+
 /// Create a USTAR TAR header for a regular file.
 ///
+/// Names longer than 100 bytes are split at a `/` into the 155-byte prefix
+/// field and the name field. Names that can't be split that way are
+/// truncated; [`create_tar_headers`] precedes such headers with a PAX
+/// header holding the full name.
+///
 /// # Arguments
 ///
-/// * `name` - The filename (max 100 bytes including null terminator)
+/// * `name` - The filename
 /// * `size` - The file size in bytes
 ///
 /// # Returns
 ///
 /// A 512-byte TAR header block.
 pub fn create_tar_header(name: &str, size: u64) -> [u8; 512] {
-    let mut header = [0u8; 512];
-
-    // Name at offset 0 (100 bytes, null-terminated)
-    let name_bytes = name.as_bytes();
-    let name_len = name_bytes.len().min(99);
-    header[..name_len].copy_from_slice(&name_bytes[..name_len]);
-
-    // Mode at offset 100 (8 bytes, octal "0000644\0")
-    header[100..107].copy_from_slice(b"0000644");
-    header[107] = 0;
-
-    // UID at offset 108 (8 bytes, octal "0000000\0")
-    header[108..115].copy_from_slice(b"0000000");
-    header[115] = 0;
-
-    // GID at offset 116 (8 bytes, octal "0000000\0")
-    header[116..123].copy_from_slice(b"0000000");
-    header[123] = 0;
-
-    // Size at offset 124 (12 bytes, octal with null/space terminator)
-    let size_str = format!("{:011o}", size);
-    header[124..135].copy_from_slice(size_str.as_bytes());
-    header[135] = 0;
-
-    // Mtime at offset 136 (12 bytes, octal unix timestamp)
     let mtime = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or_default()
         .as_secs();
-    let mtime_str = format!("{:011o}", mtime);
-    header[136..147].copy_from_slice(mtime_str.as_bytes());
-    header[147] = 0;
-
-    // Checksum placeholder at offset 148 (8 bytes of spaces for initial calculation)
-    header[148..156].copy_from_slice(b"        ");
-
-    // Type flag at offset 156 (1 byte, '0' for regular file)
-    header[156] = b'0';
-
-    // Link name at offset 157 (100 bytes, empty for regular files)
-    // Already zeros
-
-    // USTAR indicator at offset 257 ("ustar\0" + version "00")
-    header[257..263].copy_from_slice(b"ustar\0");
-    header[263..265].copy_from_slice(b"00");
+    create_tar_header_with_mtime(name, size, mtime)
+}
 
-    // User name at offset 265 (32 bytes)
-    header[265..269].copy_from_slice(b"root");
+/// Create a USTAR TAR header with a specific timestamp (for testing).
+pub fn create_tar_header_with_mtime(name: &str, size: u64, mtime: u64) -> [u8; 512] {
+    tar_header_block(name, size, mtime, TYPE_REGULAR)
+}
 
-    // Group name at offset 297 (32 bytes)
-    header[297..301].copy_from_slice(b"root");
+/// Create the TAR header blocks for a regular file, whatever its name's
+/// length.
+///
+/// Names that fit a USTAR header, split over its prefix and name fields if
+/// need be, get a single header. Longer names get a PAX extended header
+/// recording the full path first, which readers apply to the header after
+/// it.
+///
+/// # Arguments
+///
+/// * `name` - The filename
+/// * `size` - The file size in bytes
+///
+/// # Returns
+///
+/// The header blocks, a multiple of 512 bytes long.
+pub fn create_tar_headers(name: &str, size: u64) -> Vec<u8> {
+    let mtime = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let mut headers = Vec::with_capacity(512);
+    if split_ustar_name(name).is_none() {
+        let record = pax_record("path", name);
+        let pax_name = format!("PaxHeader/{}", name);
+        headers.extend_from_slice(&tar_header_block(
+            &pax_name,
+            record.len() as u64,
+            mtime,
+            TYPE_PAX_HEADER,
+        ));
+        headers.extend_from_slice(record.as_bytes());
+        headers.resize(headers.len().next_multiple_of(512), 0);
+    }
+    headers.extend_from_slice(&tar_header_block(name, size, mtime, TYPE_REGULAR));
+    headers
+}
 
-    // Calculate checksum (sum of all bytes, treating checksum field as spaces)
-    let checksum: u32 = header.iter().map(|&b| b as u32).sum();
-    let checksum_str = format!("{:06o}\0 ", checksum);
-    header[148..156].copy_from_slice(checksum_str.as_bytes());
+/// Split a name over the USTAR prefix and name fields.
+///
+/// # Returns
+///
+/// The prefix (empty if the name fits on its own) and the name, or `None`
+/// if the name has no `/` at which both parts fit.
+fn split_ustar_name(name: &str) -> Option<(&str, &str)> {
+    if name.len() <= USTAR_NAME_LEN {
+        return Some(("", name));
+    }
+    name.match_indices('/')
+        .map(|(i, _)| (&name[..i], &name[i + 1..]))
+        .find(|(prefix, rest)| {
+            prefix.len() <= USTAR_PREFIX_LEN && !rest.is_empty() && rest.len() <= USTAR_NAME_LEN
+        })
+}
 
-    header
+/// Format a PAX extended header record, `<length> <key>=<value>\n`, where
+/// the length counts the whole record including its own digits.
+fn pax_record(key: &str, value: &str) -> String {
+    let body_len = key.len() + value.len() + 3;
+    let mut len = body_len + 1;
+    while len != body_len + len.to_string().len() {
+        len = body_len + len.to_string().len();
+    }
+    format!("{} {}={}\n", len, key, value)
 }
 
-/// Create a USTAR TAR header with a specific timestamp (for testing).
-pub fn create_tar_header_with_mtime(name: &str, size: u64, mtime: u64) -> [u8; 512] {
+/// Fill in a USTAR header block.
+fn tar_header_block(name: &str, size: u64, mtime: u64, type_flag: u8) -> [u8; 512] {
     let mut header = [0u8; 512];
 
-    // Name at offset 0 (100 bytes, null-terminated)
-    let name_bytes = name.as_bytes();
-    let name_len = name_bytes.len().min(99);
-    header[..name_len].copy_from_slice(&name_bytes[..name_len]);
+    // Name at offset 0 (100 bytes) and prefix at offset 345 (155 bytes),
+    // truncated at a character boundary when a PAX header holds the name
+    let (prefix, name) = split_ustar_name(name).unwrap_or_else(|| {
+        let mut end = USTAR_NAME_LEN;
+        while !name.is_char_boundary(end) {
+            end -= 1;
+        }
+        ("", &name[..end])
+    });
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    header[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
 
     // Mode at offset 100 (8 bytes, octal "0000644\0")
     header[100..107].copy_from_slice(b"0000644");
@@ -187,8 +350,8 @@ pub fn create_tar_header_with_mtime(name: &str, size: u64, mtime: u64) -> [u8; 5
     // Checksum placeholder at offset 148 (8 bytes of spaces for initial calculation)
     header[148..156].copy_from_slice(b"        ");
 
-    // Type flag at offset 156 (1 byte, '0' for regular file)
-    header[156] = b'0';
+    // Type flag at offset 156 (1 byte)
+    header[156] = type_flag;
 
     // Link name at offset 157 (100 bytes, empty for regular files)
     // Already zeros
@@ -215,17 +378,76 @@ pub fn create_tar_header_with_mtime(name: &str, size: u64, mtime: u64) -> [u8; 5
 struct ManifestEntry {
     filename: String,
     hash: String,
+    /// Offset of the file's TAR headers in the archive.
+    offset: u64,
+    /// Length of the file's TAR headers, PAX header included.
+    header_len: u64,
+}
+
+/// Signs an OVA's manifest to produce its certificate file.
+///
+/// No RSA implementation is bundled; callers sign with their own key store
+/// or crypto library. The signature must be a digest of the manifest, taken
+/// with [`digest_algorithm`](Self::digest_algorithm), signed with the private
+/// key matching the certificate. The signing digest is independent of the
+/// manifest's own [`ManifestAlgorithm`].
+pub trait ManifestSigner: fmt::Debug + Send + Sync {
+    /// Signs the manifest bytes, returning the raw signature.
+    fn sign(&self, manifest: &[u8]) -> Result<Vec<u8>>;
+
+    /// Returns the digest algorithm [`sign`](Self::sign) uses, which names
+    /// the certificate's signature line (default SHA256).
+    fn digest_algorithm(&self) -> ManifestAlgorithm {
+        ManifestAlgorithm::Sha256
+    }
+
+    /// Returns the PEM-encoded X.509 certificate holding the public key.
+    fn certificate_pem(&self) -> String;
+}
+
+/// Order of the files in an exported OVA.
+///
+/// The manifest and certificate always come last, after every file they
+/// cover, as vCenter requires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OvaOrder {
+    /// OVF descriptor, then the disks and other files, as the OVF
+    /// specification requires.
+    ///
+    /// The descriptor declares the compressed disk sizes, so room for it is
+    /// reserved up front and filled in once the disks are written.
+    #[default]
+    OvfFirst,
+    /// Disks and other files, then the OVF descriptor. Accepted by most
+    /// importers, but not by those that stream the archive in one pass.
+    OvfLast,
 }
 
-/// OVA archive writer that creates TAR files with SHA256 manifest.
+/// OVA archive writer that creates TAR files with a SHA256 manifest, or
+/// one using another [`ManifestAlgorithm`].
 ///
-/// Files are written to the TAR archive as they are added. When `finish()`
-/// is called, the manifest file is generated and appended, followed by
-/// the TAR end-of-archive marker (two 512-byte zero blocks).
+/// Files are written to the TAR archive in the order they are added, which
+/// should be the OVF descriptor followed by the disks. A file whose contents
+/// are only known later can be reserved in its place with
+/// [`reserve_file`](Self::reserve_file). When `finish()` is
+/// called, the manifest file is generated and appended, then the certificate
+/// if a signer was set, then the TAR end-of-archive marker (two 512-byte zero
+/// blocks).
 pub struct OvaWriter<W: Write + Seek> {
     writer: W,
     entries: Vec<ManifestEntry>,
+    /// Stream position of the next byte written. Offsets recorded for
+    /// seeking back are stream positions too, so an archive started past the
+    /// beginning of the writer is patched in place.
     current_position: u64,
+    /// Name of a streaming file that was started but not finished.
+    unfinished_entry: Option<String>,
+    /// Signs the manifest into a certificate file.
+    signer: Option<Arc<dyn ManifestSigner>>,
+    /// Reserved files not yet filled in, with their sizes.
+    reserved: BTreeMap<String, u64>,
+    /// Digest algorithm of the manifest.
+    algorithm: ManifestAlgorithm,
 }
 
 impl<W: Write + Seek> OvaWriter<W> {
@@ -233,19 +455,61 @@ impl<W: Write + Seek> OvaWriter<W> {
     ///
     /// # Arguments
     ///
-    /// * `writer` - The underlying writer (typically a file)
+    /// * `writer` - The underlying writer (typically a file). The archive
+    ///   starts at its current position.
     ///
     /// # Returns
     ///
     /// A new OvaWriter ready to accept files.
-    pub fn new(writer: W) -> Result<Self> {
+    pub fn new(mut writer: W) -> Result<Self> {
+        let current_position = writer
+            .stream_position()
+            .map_err(|e| Error::ova(format!("failed to get the archive's start position: {}", e)))?;
         Ok(Self {
             writer,
             entries: Vec::new(),
-            current_position: 0,
+            current_position,
+            unfinished_entry: None,
+            signer: None,
+            reserved: BTreeMap::new(),
+            algorithm: ManifestAlgorithm::default(),
         })
     }
 
+    /// Use `algorithm` for the manifest's digests instead of SHA256.
+    ///
+    /// Must be set before any file is added.
+    ///
+    /// # Arguments
+    ///
+    /// * `algorithm` - Digest algorithm of the manifest lines.
+    pub fn with_manifest_algorithm(mut self, algorithm: ManifestAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Sign the manifest, adding a certificate file after it.
+    ///
+    /// # Arguments
+    ///
+    /// * `signer` - Signs the manifest bytes when the archive is finished.
+    pub fn with_signer(mut self, signer: Arc<dyn ManifestSigner>) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    /// Fail if a streaming file is still open, since anything written now
+    /// would land inside its data and corrupt the archive.
+    fn check_no_unfinished_entry(&self) -> Result<()> {
+        match &self.unfinished_entry {
+            Some(name) => Err(Error::ova(format!(
+                "streaming file '{}' was not finished",
+                name
+            ))),
+            None => Ok(()),
+        }
+    }
+
     /// Add a file to the OVA archive.
     ///
     /// The file is immediately written to the archive and its hash
@@ -256,21 +520,28 @@ impl<W: Write + Seek> OvaWriter<W> {
     /// * `name` - The filename within the archive
     /// * `data` - The file contents
     pub fn add_file(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        self.check_no_unfinished_entry()?;
+        let size = u64::try_from(data.len())
+            .map_err(|_| Error::ova(format!("file '{}' is too large", name)))?;
+        check_entry_size(name, size)?;
+
         // Compute hash
-        let hash = compute_sha256(data);
+        let hash = self.algorithm.digest(data);
+        let offset = self.current_position;
 
-        // Write TAR header
-        let header = create_tar_header(name, data.len() as u64);
+        // Write TAR headers
+        let headers = create_tar_headers(name, size);
+        let header_len = headers.len() as u64;
         self.writer
-            .write_all(&header)
+            .write_all(&headers)
             .map_err(|e| Error::ova(format!("failed to write TAR header: {}", e)))?;
-        self.current_position += 512;
+        self.current_position += header_len;
 
         // Write file data
         self.writer
             .write_all(data)
             .map_err(|e| Error::ova(format!("failed to write file data: {}", e)))?;
-        self.current_position += data.len() as u64;
+        self.current_position += size;
 
         // Pad to 512-byte boundary
         let padding_needed = (512 - (data.len() % 512)) % 512;
@@ -286,6 +557,8 @@ impl<W: Write + Seek> OvaWriter<W> {
         self.entries.push(ManifestEntry {
             filename: name.to_string(),
             hash,
+            offset,
+            header_len,
         });
 
         Ok(())
@@ -304,55 +577,183 @@ impl<W: Write + Seek> OvaWriter<W> {
     ///
     /// # Returns
     ///
-    /// A `StreamingFileWriter` that the caller writes to.
+    /// A `StreamingFileWriter` that the caller writes to. Until it is
+    /// finished, no other file can be added and the archive cannot be
+    /// finished.
     pub fn add_file_streaming(&mut self, name: &str, size: u64) -> Result<StreamingFileWriter<'_, W>> {
-        // Write TAR header
-        let header = create_tar_header(name, size);
+        check_entry_size(name, size)?;
+        self.start_streaming_entry(name, Some(size))
+    }
+
+    /// Begin adding a file whose size is not known in advance.
+    ///
+    /// Works like [`add_file_streaming`](Self::add_file_streaming), except
+    /// that the TAR header is written with a placeholder size and patched
+    /// with the real size when the returned writer is finished, which seeks
+    /// back over the file's data.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The filename within the archive
+    ///
+    /// # Returns
+    ///
+    /// A `StreamingFileWriter` that the caller writes to.
+    pub fn add_file_streaming_unsized(&mut self, name: &str) -> Result<StreamingFileWriter<'_, W>> {
+        self.start_streaming_entry(name, None)
+    }
+
+    /// Write the TAR header of a streaming file and hand out its writer.
+    fn start_streaming_entry(
+        &mut self,
+        name: &str,
+        size: Option<u64>,
+    ) -> Result<StreamingFileWriter<'_, W>> {
+        self.check_no_unfinished_entry()?;
+        let offset = self.current_position;
+
+        // Write TAR headers
+        let headers = create_tar_headers(name, size.unwrap_or(0));
+        let header_len = headers.len() as u64;
         self.writer
-            .write_all(&header)
+            .write_all(&headers)
             .map_err(|e| Error::ova(format!("failed to write TAR header: {}", e)))?;
-        self.current_position += 512;
+        self.current_position += header_len;
+        self.unfinished_entry = Some(name.to_string());
+        let hasher = self.algorithm.hasher();
 
         Ok(StreamingFileWriter {
             ova_writer: self,
             filename: name.to_string(),
+            offset,
+            header_len,
             expected_size: size,
-            hasher: Sha256::new(),
+            hasher,
             bytes_written: 0,
         })
     }
 
+    /// Reserve room for a file whose contents are added later.
+    ///
+    /// The file's TAR header is written with the given size and its data is
+    /// zero-filled until [`fill_reserved_file`](Self::fill_reserved_file)
+    /// writes the contents. The file keeps its place in the archive and in
+    /// the manifest.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The filename within the archive
+    /// * `size` - The exact size the file's contents will have
+    pub fn reserve_file(&mut self, name: &str, size: u64) -> Result<()> {
+        let mut entry = self.add_file_streaming(name, size)?;
+        io::copy(&mut io::repeat(0).take(size), &mut entry)
+            .map_err(|e| Error::ova(format!("failed to reserve '{}': {}", name, e)))?;
+        entry.finish()?;
+        self.reserved.insert(name.to_string(), size);
+        Ok(())
+    }
+
+    /// Write the contents of a file reserved with
+    /// [`reserve_file`](Self::reserve_file).
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The filename the room was reserved under
+    /// * `data` - The file contents, exactly as large as reserved
+    pub fn fill_reserved_file(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        self.check_no_unfinished_entry()?;
+        let size = *self
+            .reserved
+            .get(name)
+            .ok_or_else(|| Error::ova(format!("no room was reserved for '{}'", name)))?;
+        if data.len() as u64 != size {
+            return Err(Error::ova(format!(
+                "'{}' is {} bytes but {} bytes were reserved for it",
+                name,
+                data.len(),
+                size
+            )));
+        }
+
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.filename == name)
+            .ok_or_else(|| Error::ova(format!("no room was reserved for '{}'", name)))?;
+        entry.hash = self.algorithm.digest(data);
+        let data_offset = entry.offset + entry.header_len;
+        self.reserved.remove(name);
+
+        let writer = &mut self.writer;
+        let end = self.current_position;
+        let mut fill = || -> io::Result<()> {
+            writer.seek(SeekFrom::Start(data_offset))?;
+            writer.write_all(data)?;
+            writer.seek(SeekFrom::Start(end))?;
+            Ok(())
+        };
+        fill().map_err(|e| Error::ova(format!("failed to write '{}': {}", name, e)))
+    }
+
+    /// Remove the most recently added file from the archive.
+    ///
+    /// The writer seeks back to the file's TAR header, so the next file
+    /// overwrites it, and the file is dropped from the manifest. If less is
+    /// written afterwards than was discarded, stale bytes remain past the
+    /// end-of-archive marker; callers writing to a file should truncate it
+    /// to the position returned by [`finish`](Self::finish)'s writer.
+    ///
+    /// # Returns
+    ///
+    /// Error if no file was added or a streaming file is unfinished.
+    pub fn discard_last_file(&mut self) -> Result<()> {
+        self.check_no_unfinished_entry()?;
+        let entry = self
+            .entries
+            .pop()
+            .ok_or_else(|| Error::ova("no file to discard"))?;
+        self.reserved.remove(&entry.filename);
+
+        self.writer
+            .seek(SeekFrom::Start(entry.offset))
+            .map_err(|e| Error::ova(format!("failed to discard '{}': {}", entry.filename, e)))?;
+        self.current_position = entry.offset;
+
+        Ok(())
+    }
+
     /// Finish writing the OVA archive.
     ///
-    /// This writes the manifest file (if any files were added) and the
-    /// TAR end-of-archive marker (two 512-byte zero blocks).
+    /// This writes the manifest file (if any files were added), the signed
+    /// certificate (if a signer was set) and the TAR end-of-archive marker
+    /// (two 512-byte zero blocks). The manifest lists every file added
+    /// before it, and the certificate covers the manifest bytes, so neither
+    /// is listed in the manifest.
     ///
     /// # Returns
     ///
     /// The underlying writer.
     pub fn finish(mut self) -> Result<W> {
+        self.check_no_unfinished_entry()?;
+        if let Some(name) = self.reserved.keys().next() {
+            return Err(Error::ova(format!("reserved file '{}' was not filled in", name)));
+        }
+
         // Generate and write manifest if we have entries
         if !self.entries.is_empty() {
             let manifest = self.generate_manifest();
-            let manifest_bytes = manifest.as_bytes();
-
-            // Write manifest file
-            let header = create_tar_header("manifest.mf", manifest_bytes.len() as u64);
-            self.writer
-                .write_all(&header)
-                .map_err(|e| Error::ova(format!("failed to write manifest header: {}", e)))?;
-
-            self.writer
-                .write_all(manifest_bytes)
-                .map_err(|e| Error::ova(format!("failed to write manifest: {}", e)))?;
-
-            // Pad manifest to 512-byte boundary
-            let padding_needed = (512 - (manifest_bytes.len() % 512)) % 512;
-            if padding_needed > 0 {
-                let padding = vec![0u8; padding_needed];
-                self.writer
-                    .write_all(&padding)
-                    .map_err(|e| Error::ova(format!("failed to write manifest padding: {}", e)))?;
+            self.write_trailing_file(MANIFEST_FILE_NAME, manifest.as_bytes())?;
+
+            if let Some(signer) = self.signer.take() {
+                let signature = signer.sign(manifest.as_bytes())?;
+                let cert = format!(
+                    "{}({})= {}\n{}",
+                    signer.digest_algorithm().as_str(),
+                    MANIFEST_FILE_NAME,
+                    hex_encode(&signature),
+                    signer.certificate_pem()
+                );
+                self.write_trailing_file(CERT_FILE_NAME, cert.as_bytes())?;
             }
         }
 
@@ -365,53 +766,93 @@ impl<W: Write + Seek> OvaWriter<W> {
         Ok(self.writer)
     }
 
+    /// Write a file that is not listed in the manifest.
+    fn write_trailing_file(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        let headers = create_tar_headers(name, data.len() as u64);
+        self.writer
+            .write_all(&headers)
+            .map_err(|e| Error::ova(format!("failed to write {} header: {}", name, e)))?;
+
+        self.writer
+            .write_all(data)
+            .map_err(|e| Error::ova(format!("failed to write {}: {}", name, e)))?;
+
+        // Pad to 512-byte boundary
+        let padding_needed = (512 - (data.len() % 512)) % 512;
+        if padding_needed > 0 {
+            let padding = vec![0u8; padding_needed];
+            self.writer
+                .write_all(&padding)
+                .map_err(|e| Error::ova(format!("failed to write {} padding: {}", name, e)))?;
+        }
+
+        self.current_position += (headers.len() + data.len() + padding_needed) as u64;
+        Ok(())
+    }
+
     /// Generate manifest content.
     fn generate_manifest(&self) -> String {
         self.entries
             .iter()
-            .map(|entry| format!("SHA256({})= {}\n", entry.filename, entry.hash))
+            .map(|entry| format!("{}({})= {}\n", self.algorithm, entry.filename, entry.hash))
             .collect()
     }
 }
 
 /// A writer for streaming large files into an OVA archive.
 ///
-/// This struct wraps the OVA writer and computes the SHA256 hash
+/// This struct wraps the OVA writer and computes the manifest digest
 /// incrementally as data is written. When finished, it pads the
 /// file to a 512-byte boundary and records the hash for the manifest.
 pub struct StreamingFileWriter<'a, W: Write + Seek> {
     ova_writer: &'a mut OvaWriter<W>,
     filename: String,
-    expected_size: u64,
-    hasher: Sha256,
+    /// Offset of the file's TAR headers in the archive.
+    offset: u64,
+    /// Length of the file's TAR headers, PAX header included.
+    header_len: u64,
+    /// Declared size, or `None` if the header is patched on finish.
+    expected_size: Option<u64>,
+    hasher: ManifestHasher,
     bytes_written: u64,
 }
 
 impl<'a, W: Write + Seek> StreamingFileWriter<'a, W> {
+    /// Returns the number of bytes written to the file so far.
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
     /// Finish writing the file.
     ///
     /// This pads the file to a 512-byte boundary and records
-    /// the hash for the manifest.
+    /// the hash for the manifest. A file started without a size has its
+    /// TAR header rewritten with the number of bytes written.
     ///
     /// # Returns
     ///
-    /// Error if the wrong number of bytes were written.
-    pub fn finish(self) -> Result<()> {
-        if self.bytes_written != self.expected_size {
-            return Err(Error::ova(format!(
-                "expected {} bytes but wrote {} bytes for file '{}'",
-                self.expected_size, self.bytes_written, self.filename
-            )));
+    /// The file's manifest digest as a hex string, or an error if the wrong
+    /// number of bytes were written.
+    pub fn finish(mut self) -> Result<String> {
+        match self.expected_size {
+            Some(expected_size) if self.bytes_written != expected_size => {
+                return Err(Error::ova(format!(
+                    "expected {} bytes but wrote {} bytes for file '{}'",
+                    expected_size, self.bytes_written, self.filename
+                )));
+            }
+            Some(_) => {}
+            None => self.patch_header()?,
         }
 
         // Compute final hash
-        let hash = hex_encode(&self.hasher.finalize());
+        let hash = self.hasher.finish();
 
         // Update position
         self.ova_writer.current_position += self.bytes_written;
 
         // Pad to 512-byte boundary
-        let padding_needed = (512 - (self.bytes_written as usize % 512)) % 512;
+        let padding_needed = ((512 - self.bytes_written % 512) % 512) as usize;
         if padding_needed > 0 {
             let padding = vec![0u8; padding_needed];
             self.ova_writer
@@ -422,25 +863,45 @@ impl<'a, W: Write + Seek> StreamingFileWriter<'a, W> {
         }
 
         // Record for manifest
+        self.ova_writer.unfinished_entry = None;
         self.ova_writer.entries.push(ManifestEntry {
             filename: self.filename,
-            hash,
+            hash: hash.clone(),
+            offset: self.offset,
+            header_len: self.header_len,
         });
 
-        Ok(())
+        Ok(hash)
+    }
+
+    /// Rewrite the TAR header with the size written, then return to the end
+    /// of the data.
+    fn patch_header(&mut self) -> Result<()> {
+        check_entry_size(&self.filename, self.bytes_written)?;
+        // The headers' length depends only on the name, so they fit in place
+        let headers = create_tar_headers(&self.filename, self.bytes_written);
+        let offset = self.offset;
+        let data_end = offset + self.header_len + self.bytes_written;
+
+        let writer = &mut self.ova_writer.writer;
+        let mut patch = || -> io::Result<()> {
+            writer.seek(SeekFrom::Start(offset))?;
+            writer.write_all(&headers)?;
+            writer.seek(SeekFrom::Start(data_end))?;
+            Ok(())
+        };
+        patch().map_err(|e| Error::ova(format!("failed to patch TAR header: {}", e)))
     }
 }
 
 impl<'a, W: Write + Seek> Write for StreamingFileWriter<'a, W> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         // Check if this would exceed expected size
-        if self.bytes_written + buf.len() as u64 > self.expected_size {
+        let limit = self.expected_size.unwrap_or(MAX_TAR_ENTRY_SIZE);
+        if self.bytes_written + buf.len() as u64 > limit {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
-                format!(
-                    "write would exceed expected size of {} bytes",
-                    self.expected_size
-                ),
+                format!("write would exceed expected size of {} bytes", limit),
             ));
         }
 
@@ -455,6 +916,396 @@ impl<'a, W: Write + Seek> Write for StreamingFileWriter<'a, W> {
     }
 }
 
+/// Returns the bytes of a TAR header field up to its first NUL.
+fn nul_terminated(field: &[u8]) -> &[u8] {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    &field[..end]
+}
+
+/// Largest PAX extended header read; real ones hold a few short records.
+const MAX_PAX_HEADER_SIZE: u64 = 1024 * 1024;
+
+/// Read the records of a PAX extended header whose data follows the
+/// reader's position.
+fn read_pax_records<R: Read>(reader: &mut R, name: &str, size: u64) -> Result<String> {
+    if size > MAX_PAX_HEADER_SIZE {
+        return Err(Error::ova(format!(
+            "PAX header '{}' is {} bytes, more than the {} allowed",
+            name, size, MAX_PAX_HEADER_SIZE
+        )));
+    }
+    let mut records = vec![0u8; size as usize];
+    reader
+        .read_exact(&mut records)
+        .map_err(|e| Error::ova(format!("failed to read PAX header '{}': {}", name, e)))?;
+    String::from_utf8(records)
+        .map_err(|_| Error::ova(format!("PAX header '{}' is not valid UTF-8", name)))
+}
+
+/// Find the `path` record among PAX extended header records.
+///
+/// Each record is `<length> <key>=<value>\n`; records that don't parse are
+/// skipped.
+fn pax_path_record(records: &str) -> Option<String> {
+    let mut rest = records;
+    let mut path = None;
+    while !rest.is_empty() {
+        let (len, _) = rest.split_once(' ')?;
+        let len: usize = len.parse().ok()?;
+        let record = rest.get(..len)?;
+        rest = &rest[len..];
+        let (_, key_value) = record.split_once(' ')?;
+        if let Some(value) = key_value.strip_suffix('\n').and_then(|kv| kv.strip_prefix("path=")) {
+            path = Some(value.to_string());
+        }
+    }
+    path
+}
+
+/// A file stored in an OVA archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OvaEntry {
+    /// Filename within the archive.
+    pub name: String,
+    /// Byte offset of the file contents within the archive.
+    pub offset: u64,
+    /// Size of the file in bytes.
+    pub size: u64,
+}
+
+/// A manifest entry whose file doesn't match its recorded digest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DigestMismatch {
+    /// Filename listed in the manifest.
+    pub name: String,
+    /// Digest algorithm named by the manifest line.
+    pub algorithm: ManifestAlgorithm,
+    /// Digest recorded in the manifest.
+    pub expected: String,
+    /// Digest of the file in `algorithm`, or `None` if the archive doesn't
+    /// contain it.
+    pub actual: Option<String>,
+}
+
+/// OVA archive reader.
+///
+/// Scans the TAR headers once when opened, after which individual files
+/// can be read by name without reading the rest of the archive.
+pub struct OvaReader<R: Read + Seek> {
+    reader: R,
+    entries: Vec<OvaEntry>,
+}
+
+impl<R: Read + Seek> OvaReader<R> {
+    /// Open an OVA archive and index its contents.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The underlying reader (typically a file)
+    ///
+    /// # Returns
+    ///
+    /// A reader listing every file in the archive.
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut entries = Vec::new();
+        let mut position = 0u64;
+        let mut header = [0u8; 512];
+        // Full name from a PAX header, applying to the next entry
+        let mut pax_path: Option<String> = None;
+
+        loop {
+            reader
+                .seek(SeekFrom::Start(position))
+                .map_err(|e| Error::ova(format!("failed to seek in archive: {}", e)))?;
+            match reader.read_exact(&mut header) {
+                Ok(()) => {}
+                // Tolerate archives missing the end-of-archive marker
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(Error::ova(format!("failed to read TAR header: {}", e))),
+            }
+
+            // End of archive
+            if header.iter().all(|&b| b == 0) {
+                break;
+            }
+
+            let non_utf8 =
+                || Error::ova(format!("TAR entry at byte {} has a non-UTF-8 name", position));
+            let name = std::str::from_utf8(nul_terminated(&header[..100])).map_err(|_| non_utf8())?;
+            let prefix = if &header[257..262] == b"ustar" {
+                std::str::from_utf8(nul_terminated(&header[345..500])).map_err(|_| non_utf8())?
+            } else {
+                ""
+            };
+            let name = if prefix.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}/{}", prefix, name)
+            };
+
+            let size_field = std::str::from_utf8(&header[124..136]).unwrap_or("");
+            let size = u64::from_str_radix(size_field.trim_matches(|c| c == '\0' || c == ' '), 8)
+                .map_err(|_| Error::ova(format!("TAR entry '{}' has an invalid size field", name)))?;
+
+            // Header plus content padded to a whole block
+            let next = size
+                .div_ceil(512)
+                .checked_mul(512)
+                .and_then(|content| content.checked_add(512))
+                .and_then(|entry_len| position.checked_add(entry_len))
+                .ok_or_else(|| {
+                    Error::ova(format!(
+                        "TAR entry '{}' size {} overflows the archive offset",
+                        name, size
+                    ))
+                })?;
+
+            match header[156] {
+                TYPE_PAX_HEADER => {
+                    let records = read_pax_records(&mut reader, &name, size)?;
+                    pax_path = pax_path_record(&records).or(pax_path);
+                }
+                TYPE_PAX_GLOBAL => {}
+                _ => entries.push(OvaEntry {
+                    name: pax_path.take().unwrap_or(name),
+                    offset: position + 512,
+                    size,
+                }),
+            }
+            position = next;
+        }
+
+        Ok(Self { reader, entries })
+    }
+
+    /// Returns the files in the archive, in archive order.
+    pub fn entries(&self) -> &[OvaEntry] {
+        &self.entries
+    }
+
+    /// Look up a file by name, checking that the archive holds all of it.
+    fn find_entry(&mut self, name: &str) -> Result<OvaEntry> {
+        let entry = self
+            .entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .cloned()
+            .ok_or_else(|| Error::ova(format!("file '{}' not found in archive", name)))?;
+
+        // Don't trust the header's size until the archive is known to hold it
+        let archive_len = self
+            .reader
+            .seek(SeekFrom::End(0))
+            .map_err(|e| Error::ova(format!("failed to seek in archive: {}", e)))?;
+        if entry.offset.checked_add(entry.size).is_none_or(|end| end > archive_len) {
+            return Err(Error::ova(format!(
+                "'{}' ({} bytes) extends beyond the end of the archive",
+                name, entry.size
+            )));
+        }
+
+        Ok(entry)
+    }
+
+    /// Read a file from the archive by name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The filename within the archive
+    pub fn read_file(&mut self, name: &str) -> Result<Vec<u8>> {
+        let entry = self.find_entry(name)?;
+        let len = usize::try_from(entry.size).map_err(|_| {
+            Error::ova(format!(
+                "'{}' is too large to read into memory ({} bytes)",
+                name, entry.size
+            ))
+        })?;
+        let mut data = vec![0u8; len];
+        self.reader
+            .seek(SeekFrom::Start(entry.offset))
+            .and_then(|_| self.reader.read_exact(&mut data))
+            .map_err(|e| Error::ova(format!("failed to read '{}': {}", name, e)))?;
+        Ok(data)
+    }
+
+    /// Copy a file from the archive to a writer, without holding it in memory.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The filename within the archive
+    /// * `writer` - Destination for the file contents
+    ///
+    /// # Returns
+    ///
+    /// The number of bytes copied.
+    pub fn extract_file<W: Write>(&mut self, name: &str, writer: &mut W) -> Result<u64> {
+        let entry = self.find_entry(name)?;
+        self.reader
+            .seek(SeekFrom::Start(entry.offset))
+            .map_err(|e| Error::ova(format!("failed to seek in archive: {}", e)))?;
+
+        let copied = io::copy(&mut (&mut self.reader).take(entry.size), writer)
+            .map_err(|e| Error::ova(format!("failed to extract '{}': {}", name, e)))?;
+        if copied != entry.size {
+            return Err(Error::ova(format!(
+                "'{}' ended after {} of {} bytes",
+                name, copied, entry.size
+            )));
+        }
+        Ok(copied)
+    }
+
+    /// Check every file listed in the manifest against its digest, using
+    /// the algorithm each line names.
+    ///
+    /// Files are hashed as they are read, so disk images are never held in
+    /// memory. Files in the archive that the manifest doesn't list are not
+    /// checked.
+    ///
+    /// # Returns
+    ///
+    /// The manifest entries that don't match, in manifest order; empty if
+    /// every file matches. Fails if the archive has no manifest.
+    pub fn verify(&mut self) -> Result<Vec<DigestMismatch>> {
+        let manifest_name = self
+            .entries
+            .iter()
+            .find(|entry| entry.name.ends_with(".mf"))
+            .map(|entry| entry.name.clone())
+            .ok_or_else(|| Error::ova("archive contains no manifest"))?;
+
+        let manifest = String::from_utf8(self.read_file(&manifest_name)?)
+            .map_err(|_| Error::ova(format!("manifest '{}' is not valid UTF-8", manifest_name)))?;
+
+        let mut mismatches = Vec::new();
+        for (algorithm, name, expected) in manifest.lines().filter_map(parse_manifest_line) {
+            let actual = if self.entries.iter().any(|entry| entry.name == name) {
+                let mut hasher = algorithm.hasher();
+                self.extract_file(name, &mut hasher)?;
+                Some(hasher.finish())
+            } else {
+                None
+            };
+
+            if !actual.as_deref().is_some_and(|actual| actual.eq_ignore_ascii_case(expected)) {
+                mismatches.push(DigestMismatch {
+                    name: name.to_string(),
+                    algorithm,
+                    expected: expected.to_string(),
+                    actual,
+                });
+            }
+        }
+
+        Ok(mismatches)
+    }
+
+    /// Read the archive's OVF descriptor.
+    ///
+    /// # Returns
+    ///
+    /// The contents of the first `.ovf` file in the archive.
+    pub fn read_ovf(&mut self) -> Result<String> {
+        let name = self
+            .entries
+            .iter()
+            .find(|entry| entry.name.ends_with(".ovf"))
+            .map(|entry| entry.name.clone())
+            .ok_or_else(|| Error::ova("archive contains no OVF descriptor"))?;
+
+        String::from_utf8(self.read_file(&name)?)
+            .map_err(|_| Error::ova(format!("OVF descriptor '{}' is not valid UTF-8", name)))
+    }
+
+    /// Check the OVF descriptor against its digest in the manifest.
+    ///
+    /// This detects a modified descriptor without hashing the (much larger)
+    /// disk images listed alongside it.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the manifest lists the OVF and the digests match, or a
+    /// validation error if the descriptor does not match its recorded digest.
+    pub fn verify_ovf_digest(&mut self) -> Result<()> {
+        let ovf_name = self
+            .entries
+            .iter()
+            .find(|entry| entry.name.ends_with(".ovf"))
+            .map(|entry| entry.name.clone())
+            .ok_or_else(|| Error::ova("archive contains no OVF descriptor"))?;
+        let manifest_name = self
+            .entries
+            .iter()
+            .find(|entry| entry.name.ends_with(".mf"))
+            .map(|entry| entry.name.clone())
+            .ok_or_else(|| Error::ova("archive contains no manifest"))?;
+
+        let manifest = String::from_utf8(self.read_file(&manifest_name)?)
+            .map_err(|_| Error::ova(format!("manifest '{}' is not valid UTF-8", manifest_name)))?;
+        let (algorithm, expected) =
+            parse_manifest_digest(&manifest, &ovf_name).ok_or_else(|| {
+                Error::validation(format!(
+                    "manifest '{}' has no digest for '{}'",
+                    manifest_name, ovf_name
+                ))
+            })?;
+
+        let actual = algorithm.digest(&self.read_file(&ovf_name)?);
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(Error::validation(format!(
+                "OVF descriptor '{}' does not match its manifest digest: expected {}, got {}",
+                ovf_name, expected, actual
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Read the digests recorded in the archive's manifest.
+    ///
+    /// # Returns
+    ///
+    /// The digests keyed by file name; empty if the archive has no manifest.
+    pub fn manifest_digests(&mut self) -> Result<BTreeMap<String, String>> {
+        let Some(manifest_name) = self
+            .entries
+            .iter()
+            .find(|entry| entry.name.ends_with(".mf"))
+            .map(|entry| entry.name.clone())
+        else {
+            return Ok(BTreeMap::new());
+        };
+
+        let manifest = String::from_utf8(self.read_file(&manifest_name)?)
+            .map_err(|_| Error::ova(format!("manifest '{}' is not valid UTF-8", manifest_name)))?;
+        Ok(manifest
+            .lines()
+            .filter_map(parse_manifest_line)
+            .map(|(_, file, digest)| (file.to_string(), digest.to_string()))
+            .collect())
+    }
+}
+
+/// Split a manifest line of the form `SHA256(<name>)= <hex digest>`, or
+/// with another [`ManifestAlgorithm`]'s name.
+fn parse_manifest_line(line: &str) -> Option<(ManifestAlgorithm, &str, &str)> {
+    let (algorithm, rest) = line.trim().split_once('(')?;
+    let algorithm = ManifestAlgorithm::parse(algorithm)?;
+    let (file, digest) = rest.split_once(")=")?;
+    Some((algorithm, file, digest.trim()))
+}
+
+/// Find the digest recorded for `name` in a manifest, with its algorithm.
+fn parse_manifest_digest<'m>(
+    manifest: &'m str,
+    name: &str,
+) -> Option<(ManifestAlgorithm, &'m str)> {
+    manifest
+        .lines()
+        .filter_map(parse_manifest_line)
+        .find_map(|(algorithm, file, digest)| (file == name).then_some((algorithm, digest)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -608,6 +1459,82 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_ova_unsized_streaming_patches_header() {
+        let mut ova_writer = OvaWriter::new(Cursor::new(Vec::new())).unwrap();
+
+        let data = vec![0x5Au8; 1500];
+        let mut stream_writer = ova_writer.add_file_streaming_unsized("disk.vmdk").unwrap();
+        stream_writer.write_all(&data).unwrap();
+        assert_eq!(stream_writer.bytes_written(), 1500);
+        assert_eq!(stream_writer.finish().unwrap(), compute_sha256(&data));
+        ova_writer.add_file("vm.ovf", b"<Envelope/>").unwrap();
+        let archive = ova_writer.finish().unwrap().into_inner();
+
+        let mut reader = OvaReader::new(Cursor::new(archive)).unwrap();
+        assert_eq!(reader.entries()[0].size, 1500);
+        assert_eq!(reader.read_file("disk.vmdk").unwrap(), data);
+        assert_eq!(reader.read_ovf().unwrap(), "<Envelope/>");
+    }
+
+    #[test]
+    fn test_ova_discard_last_file() {
+        let mut ova_writer = OvaWriter::new(Cursor::new(Vec::new())).unwrap();
+        assert!(ova_writer.discard_last_file().is_err());
+
+        ova_writer.add_file("disk1.vmdk", &[1u8; 600]).unwrap();
+        ova_writer.add_file("disk2.vmdk", &[2u8; 600]).unwrap();
+        ova_writer.discard_last_file().unwrap();
+        ova_writer.add_file("vm.ovf", b"<Envelope/>").unwrap();
+        let archive = ova_writer.finish().unwrap().into_inner();
+
+        let mut reader = OvaReader::new(Cursor::new(archive)).unwrap();
+        let names: Vec<&str> = reader.entries().iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["disk1.vmdk", "vm.ovf", "manifest.mf"]);
+        let manifest = String::from_utf8(reader.read_file("manifest.mf").unwrap()).unwrap();
+        assert!(!manifest.contains("disk2.vmdk"), "{}", manifest);
+    }
+
+    #[test]
+    fn test_ova_reserved_file() {
+        let mut ova_writer = OvaWriter::new(Cursor::new(Vec::new())).unwrap();
+        ova_writer.reserve_file("vm.ovf", 11).unwrap();
+        ova_writer.add_file("disk.vmdk", &[7u8; 600]).unwrap();
+        assert!(ova_writer.fill_reserved_file("vm.ovf", b"too long for it").is_err());
+        assert!(ova_writer.fill_reserved_file("disk.vmdk", &[7u8; 600]).is_err());
+        ova_writer.fill_reserved_file("vm.ovf", b"<Envelope/>").unwrap();
+        let archive = ova_writer.finish().unwrap().into_inner();
+
+        let mut reader = OvaReader::new(Cursor::new(archive)).unwrap();
+        let names: Vec<&str> = reader.entries().iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["vm.ovf", "disk.vmdk", "manifest.mf"]);
+        assert_eq!(reader.read_ovf().unwrap(), "<Envelope/>");
+        assert_eq!(reader.read_file("disk.vmdk").unwrap(), vec![7u8; 600]);
+        assert!(reader.verify().unwrap().is_empty());
+
+        let mut ova_writer = OvaWriter::new(Cursor::new(Vec::new())).unwrap();
+        ova_writer.reserve_file("vm.ovf", 11).unwrap();
+        let err = ova_writer.finish().unwrap_err();
+        assert!(err.to_string().contains("'vm.ovf' was not filled in"), "{}", err);
+    }
+
+    #[test]
+    fn test_ova_unfinished_streaming_file_blocks_writer() {
+        let buffer = Cursor::new(Vec::new());
+        let mut ova_writer = OvaWriter::new(buffer).unwrap();
+
+        {
+            let mut stream_writer = ova_writer.add_file_streaming("disk.vmdk", 1024).unwrap();
+            stream_writer.write_all(&[0u8; 100]).unwrap();
+        }
+
+        let err = ova_writer.add_file("test.ovf", b"<Envelope/>").unwrap_err();
+        assert!(matches!(err, Error::Ova { .. }));
+        assert!(err.to_string().contains("'disk.vmdk' was not finished"), "{}", err);
+        assert!(ova_writer.add_file_streaming("other.vmdk", 0).is_err());
+        assert!(ova_writer.finish().is_err());
+    }
+
     #[test]
     fn test_ova_padding() {
         let buffer = Cursor::new(Vec::new());
@@ -629,6 +1556,101 @@ mod tests {
         assert_eq!(data.len() % 512, 0);
     }
 
+    #[test]
+    fn test_reader_round_trip() {
+        let mut writer = OvaWriter::new(Cursor::new(Vec::new())).unwrap();
+        writer.add_file("vm.ovf", b"<Envelope/>").unwrap();
+        writer.add_file("disk.vmdk", &vec![7u8; 1000]).unwrap();
+        let data = writer.finish().unwrap().into_inner();
+
+        let mut reader = OvaReader::new(Cursor::new(data)).unwrap();
+        let names: Vec<&str> = reader.entries().iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["vm.ovf", "disk.vmdk", "manifest.mf"]);
+        assert_eq!(reader.entries()[1].size, 1000);
+
+        assert_eq!(reader.read_ovf().unwrap(), "<Envelope/>");
+        assert_eq!(reader.read_file("disk.vmdk").unwrap(), vec![7u8; 1000]);
+        assert!(reader.read_file("missing.vmdk").is_err());
+    }
+
+    #[test]
+    fn test_streaming_rejects_oversized_entry() {
+        let mut ova_writer = OvaWriter::new(Cursor::new(Vec::new())).unwrap();
+
+        assert!(ova_writer
+            .add_file_streaming("disk.vmdk", MAX_TAR_ENTRY_SIZE + 1)
+            .is_err());
+        assert!(ova_writer.add_file_streaming("disk.vmdk", u64::MAX).is_err());
+        // Nothing but the end-of-archive marker was written
+        assert_eq!(ova_writer.finish().unwrap().into_inner().len(), 1024);
+    }
+
+    #[test]
+    fn test_tar_header_max_size() {
+        let header = create_tar_header("disk.vmdk", MAX_TAR_ENTRY_SIZE);
+        assert_eq!(&header[124..135], b"77777777777");
+    }
+
+    #[test]
+    fn test_reader_rejects_entry_past_end() {
+        // Header claims the largest size a TAR entry can have, but no data follows
+        let data = create_tar_header("disk.vmdk", MAX_TAR_ENTRY_SIZE).to_vec();
+
+        let mut reader = OvaReader::new(Cursor::new(data)).unwrap();
+        assert_eq!(reader.entries()[0].size, MAX_TAR_ENTRY_SIZE);
+        let err = reader.read_file("disk.vmdk").unwrap_err();
+        assert!(err.to_string().contains("beyond the end of the archive"));
+    }
+
+    #[test]
+    fn test_verify_ovf_digest() {
+        let mut writer = OvaWriter::new(Cursor::new(Vec::new())).unwrap();
+        writer.add_file("vm.ovf", b"<Envelope/>").unwrap();
+        writer.add_file("disk.vmdk", b"data").unwrap();
+        let data = writer.finish().unwrap().into_inner();
+
+        let mut reader = OvaReader::new(Cursor::new(data)).unwrap();
+        reader.verify_ovf_digest().unwrap();
+    }
+
+    #[test]
+    fn test_verify_ovf_digest_detects_tampering() {
+        let mut writer = OvaWriter::new(Cursor::new(Vec::new())).unwrap();
+        writer.add_file("vm.ovf", b"<Envelope/>").unwrap();
+        let mut data = writer.finish().unwrap().into_inner();
+
+        // Change the descriptor in place, leaving the manifest untouched
+        let ovf_pos = find_file_in_tar(&data, "vm.ovf").unwrap() + 512;
+        data[ovf_pos + 1] = b'X';
+
+        let mut reader = OvaReader::new(Cursor::new(data)).unwrap();
+        let err = reader.verify_ovf_digest().unwrap_err();
+        assert!(matches!(err, Error::Validation { .. }));
+        assert!(err.to_string().contains("vm.ovf"));
+    }
+
+    #[test]
+    fn test_verify_ovf_digest_requires_manifest_entry() {
+        let mut writer = OvaWriter::new(Cursor::new(Vec::new())).unwrap();
+        writer.add_file("vm.ovf", b"<Envelope/>").unwrap();
+        writer.add_file("extra.mf", b"SHA256(disk.vmdk)= 00\n").unwrap();
+        let data = writer.finish().unwrap().into_inner();
+
+        // The first manifest in the archive doesn't list the OVF
+        let mut reader = OvaReader::new(Cursor::new(data)).unwrap();
+        assert!(reader.verify_ovf_digest().is_err());
+    }
+
+    #[test]
+    fn test_reader_without_ovf() {
+        let mut writer = OvaWriter::new(Cursor::new(Vec::new())).unwrap();
+        writer.add_file("disk.vmdk", b"data").unwrap();
+        let data = writer.finish().unwrap().into_inner();
+
+        let mut reader = OvaReader::new(Cursor::new(data)).unwrap();
+        assert!(reader.read_ovf().is_err());
+    }
+
     /// Helper function to find a file in a TAR archive.
     fn find_file_in_tar(data: &[u8], filename: &str) -> Option<usize> {
         let mut pos = 0;
@@ -654,8 +1676,8 @@ mod tests {
             let size = u64::from_str_radix(size_str.trim_matches('\0').trim(), 8).ok()?;
 
             // Move to next header (header + content + padding)
-            let content_blocks = (size + 511) / 512;
-            pos += 512 + (content_blocks * 512) as usize;
+            let content_len = usize::try_from(size.div_ceil(512).checked_mul(512)?).ok()?;
+            pos = pos.checked_add(512)?.checked_add(content_len)?;
         }
         None
     }
@@ -4,8 +4,11 @@
 //! from VMX metadata. The generated OVF is compatible with VMware and other
 //! virtualization platforms that support the OVF 1.0 specification.
 
-use crate::error::Result;
-use crate::vmx::VmxConfig;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+use crate::error::{Error, Result};
+use crate::vmx::{FirmwareType, NetworkConfig, ProductInfo, VmxConfig};
 
 /// Information about a disk to include in the OVF.
 #[derive(Debug, Clone)]
@@ -23,12 +26,221 @@ pub struct DiskInfo {
 /// Builder for generating OVF XML descriptors.
 pub struct OvfBuilder<'a> {
     config: &'a VmxConfig,
+    storage_policy: Option<String>,
+    info: OvfInfoTexts,
+    layout: OvfLayout,
+    eula: Option<String>,
+    disk_format: DiskFormat,
+    include_guestinfo: bool,
+    include_numa: bool,
+    os_type_override: Option<(u32, String)>,
+    nvram_file: Option<(String, u64)>,
+    product: Option<ProductInfo>,
+    icon: Option<(String, String)>,
+}
+
+/// On-disk format of the VMDKs described by the OVF.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiskFormat {
+    /// Compressed streamOptimized VMDK, the usual format inside an OVA.
+    #[default]
+    StreamOptimized,
+    /// Uncompressed hosted sparse (monolithicSparse) VMDK.
+    Sparse,
+}
+
+impl DiskFormat {
+    /// Returns the `ovf:format` URI identifying the format.
+    pub fn format_uri(&self) -> &'static str {
+        match self {
+            DiskFormat::StreamOptimized => {
+                "http://www.vmware.com/interfaces/specifications/vmdk.html#streamOptimized"
+            }
+            DiskFormat::Sparse => {
+                "http://www.vmware.com/interfaces/specifications/vmdk.html#sparse"
+            }
+        }
+    }
+}
+
+/// Whitespace layout of the generated descriptor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OvfLayout {
+    /// One element per line, indented by two spaces per level.
+    #[default]
+    Pretty,
+    /// No whitespace between elements.
+    Compact,
+}
+
+/// `<ovf:Info>` texts emitted for each descriptor section.
+///
+/// The defaults are the English descriptions VMware tools write. Override
+/// them to localize the descriptor or to describe the appliance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OvfInfoTexts {
+    /// Info for the DiskSection.
+    pub disk_section: String,
+    /// Info for the NetworkSection.
+    pub network_section: String,
+    /// Info for the VirtualSystem, i.e. the VM description.
+    pub virtual_system: String,
+    /// Info for the OperatingSystemSection.
+    pub operating_system: String,
+    /// Info for the VirtualHardwareSection.
+    pub virtual_hardware: String,
+    /// Info for each BootOrderSection.
+    pub boot_order: String,
+    /// Info for the EulaSection.
+    pub eula: String,
+    /// Info for the ProductSection.
+    pub product: String,
+    /// Info for the AnnotationSection.
+    pub annotation: String,
+    /// Info for a VirtualSystemCollection, i.e. the appliance description.
+    pub virtual_system_collection: String,
 }
 
+impl Default for OvfInfoTexts {
+    fn default() -> Self {
+        Self {
+            disk_section: "Virtual disk information".to_string(),
+            network_section: "Network configuration".to_string(),
+            virtual_system: "A virtual machine".to_string(),
+            operating_system: "The guest operating system".to_string(),
+            virtual_hardware: "Virtual hardware requirements".to_string(),
+            boot_order: "Virtual hardware device boot order".to_string(),
+            eula: "End User License Agreement".to_string(),
+            product: "Information about the installed software".to_string(),
+            annotation: "A human-readable annotation".to_string(),
+            virtual_system_collection: "A collection of virtual machines".to_string(),
+        }
+    }
+}
+
+/// Identifier of the storage group referenced by the VM and its disks.
+const STORAGE_GROUP_ID: &str = "group1";
+
+/// Hardware version used when the VMX does not specify `virtualHW.version`.
+const DEFAULT_HARDWARE_VERSION: &str = "vmx-21";
+
 impl<'a> OvfBuilder<'a> {
     /// Create a new OVF builder from a VMX configuration.
     pub fn new(config: &'a VmxConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            storage_policy: None,
+            info: OvfInfoTexts::default(),
+            layout: OvfLayout::default(),
+            eula: None,
+            disk_format: DiskFormat::default(),
+            include_guestinfo: false,
+            include_numa: false,
+            os_type_override: None,
+            nvram_file: None,
+            product: config.product_info(),
+            icon: None,
+        }
+    }
+
+    /// Set the storage policy the VM and its disks should be placed with.
+    ///
+    /// When set, a `vmw:StorageGroupSection` naming the policy is emitted and
+    /// referenced from the VirtualSystem and each disk item.
+    pub fn with_storage_policy(mut self, policy: Option<String>) -> Self {
+        self.storage_policy = policy;
+        self
+    }
+
+    /// Override the `<ovf:Info>` texts of every section.
+    pub fn with_info_texts(mut self, info: OvfInfoTexts) -> Self {
+        self.info = info;
+        self
+    }
+
+    /// Choose between indented and compact output.
+    pub fn with_layout(mut self, layout: OvfLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Set the VM description shown as the VirtualSystem's `<ovf:Info>`.
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.info.virtual_system = description.into();
+        self
+    }
+
+    /// Include a license agreement the deployment wizard asks the user to accept.
+    ///
+    /// Emitted as an `ovf:EulaSection` in the VirtualSystem.
+    pub fn with_eula(mut self, text: impl Into<String>) -> Self {
+        self.eula = Some(text.into());
+        self
+    }
+
+    /// Set the format the disks are written in (default streamOptimized).
+    ///
+    /// The format only changes the declared `ovf:format`; the caller must
+    /// write the disks in the same format.
+    pub fn with_disk_format(mut self, format: DiskFormat) -> Self {
+        self.disk_format = format;
+        self
+    }
+
+    /// Also carry the VMX's `guestinfo.*` variables over as ExtraConfig.
+    ///
+    /// Off by default, since guest variables often hold secrets; the VMware
+    /// Tools settings in [`GUEST_CUSTOMIZATION_KEYS`](crate::vmx::GUEST_CUSTOMIZATION_KEYS)
+    /// are always carried over.
+    pub fn with_guestinfo(mut self, include: bool) -> Self {
+        self.include_guestinfo = include;
+        self
+    }
+
+    /// Also carry the VMX's NUMA topology settings over as ExtraConfig.
+    ///
+    /// See [`VmxConfig::numa_settings`] for the keys included. Off by
+    /// default, since a node affinity only suits hosts with that many nodes.
+    pub fn with_numa(mut self, include: bool) -> Self {
+        self.include_numa = include;
+        self
+    }
+
+    /// Set the OVF OS id and `vmw:osType` directly, bypassing the guest OS table.
+    ///
+    /// Useful for guest OSes the table maps to `otherGuest`.
+    pub fn with_os_type_override(mut self, os_type: Option<(u32, String)>) -> Self {
+        self.os_type_override = os_type;
+        self
+    }
+
+    /// Reference an NVRAM file packaged in the OVA, given its name and size.
+    ///
+    /// The file is listed in the References section and pointed at by an
+    /// `nvram` ExtraConfig entry, so importers restore the firmware settings.
+    pub fn with_nvram_file(mut self, nvram: Option<(String, u64)>) -> Self {
+        self.nvram_file = nvram;
+        self
+    }
+
+    /// Set the product details shown by the ProductSection.
+    ///
+    /// Defaults to the VMX's [`product_info`](VmxConfig::product_info); the
+    /// section is omitted when `None`.
+    pub fn with_product(mut self, product: Option<ProductInfo>) -> Self {
+        self.product = product;
+        self
+    }
+
+    /// Reference an image packaged in the OVA as the appliance's icon, given
+    /// its name and MIME type (e.g. `image/png`).
+    ///
+    /// The file is listed in the References section and pointed at by an
+    /// `ovf:Icon` in the ProductSection, which deployment wizards show as
+    /// the appliance's logo.
+    pub fn with_icon(mut self, file_name: impl Into<String>, mime_type: impl Into<String>) -> Self {
+        self.icon = Some((file_name.into(), mime_type.into()));
+        self
     }
 
     /// Build the OVF XML descriptor.
@@ -59,13 +271,33 @@ impl<'a> OvfBuilder<'a> {
         // NetworkSection
         xml.push_str(&self.build_network_section());
 
+        // StorageGroupSection (only with a storage policy)
+        xml.push_str(&self.build_storage_group_section());
+
         // VirtualSystem
         xml.push_str(&self.build_virtual_system(disks));
 
         // Envelope closing
         xml.push_str("</ovf:Envelope>\n");
 
-        Ok(xml)
+        match self.layout {
+            OvfLayout::Pretty => Ok(xml),
+            OvfLayout::Compact => Ok(compact_xml(&xml)),
+        }
+    }
+
+    /// Build the OVF and check it with [`validate_ovf`].
+    ///
+    /// # Arguments
+    ///
+    /// * `disks` - Information about the disks to include in the OVF.
+    ///
+    /// # Returns
+    ///
+    /// A description of each structural rule the OVF breaks, empty if it is
+    /// valid.
+    pub fn validate(&self, disks: &[DiskInfo]) -> Result<Vec<String>> {
+        validate_ovf(&self.build(disks)?)
     }
 
     /// Build the opening Envelope tag with all required namespaces.
@@ -83,36 +315,62 @@ impl<'a> OvfBuilder<'a> {
     fn build_references(&self, disks: &[DiskInfo]) -> String {
         let mut xml = String::new();
         xml.push_str("  <ovf:References>\n");
+        xml.push_str(&self.build_file_references(disks));
+        xml.push_str("  </ovf:References>\n");
+        xml
+    }
 
+    /// Build one `ovf:File` line per distinct file.
+    ///
+    /// Disks sharing a `file_ref` reference the file of the first of them.
+    fn build_file_references(&self, disks: &[DiskInfo]) -> String {
+        let mut xml = String::new();
         for (i, disk) in disks.iter().enumerate() {
-            let filename = if i < self.config.disks.len() {
-                &self.config.disks[i].file_name
-            } else {
-                "disk.vmdk"
-            };
+            if disks[..i].iter().any(|earlier| earlier.file_ref == disk.file_ref) {
+                continue;
+            }
             xml.push_str(&format!(
                 "    <ovf:File ovf:href=\"{}\" ovf:id=\"{}\" ovf:size=\"{}\"/>\n",
-                filename, disk.file_ref, disk.file_size_bytes
+                self.disk_file_name(i),
+                disk.file_ref,
+                disk.file_size_bytes
+            ));
+        }
+        if let Some((name, size)) = &self.nvram_file {
+            xml.push_str(&format!(
+                "    <ovf:File ovf:href=\"{}\" ovf:id=\"{}\" ovf:size=\"{}\"/>\n",
+                escape_xml(name),
+                nvram_file_id(disks),
+                size
+            ));
+        }
+        if let Some((name, _)) = &self.icon {
+            xml.push_str(&format!(
+                "    <ovf:File ovf:href=\"{}\" ovf:id=\"{}\"/>\n",
+                escape_xml(name),
+                ICON_FILE_ID
             ));
         }
-
-        xml.push_str("  </ovf:References>\n");
         xml
     }
 
+    /// File name of the disk at `index`, as written to the archive.
+    fn disk_file_name(&self, index: usize) -> &str {
+        self.config
+            .disks
+            .get(index)
+            .map_or("disk.vmdk", |disk| disk.file_name.as_str())
+    }
+
     /// Build the DiskSection describing disk capacities and formats.
     fn build_disk_section(&self, disks: &[DiskInfo]) -> String {
         let mut xml = String::new();
         xml.push_str("  <ovf:DiskSection>\n");
-        xml.push_str("    <ovf:Info>Virtual disk information</ovf:Info>\n");
-
-        for disk in disks {
-            xml.push_str(&format!(
-                "    <ovf:Disk ovf:capacity=\"{}\" ovf:capacityAllocationUnits=\"byte\" ovf:diskId=\"{}\" ovf:fileRef=\"{}\" ovf:format=\"http://www.vmware.com/interfaces/specifications/vmdk.html#streamOptimized\"/>\n",
-                disk.capacity_bytes, escape_xml(&disk.id), disk.file_ref
-            ));
-        }
-
+        xml.push_str(&format!(
+            "    <ovf:Info>{}</ovf:Info>\n",
+            escape_xml(&self.info.disk_section)
+        ));
+        xml.push_str(&build_disk_entries(disks, self.disk_format));
         xml.push_str("  </ovf:DiskSection>\n");
         xml
     }
@@ -121,70 +379,197 @@ impl<'a> OvfBuilder<'a> {
     fn build_network_section(&self) -> String {
         let mut xml = String::new();
         xml.push_str("  <ovf:NetworkSection>\n");
-        xml.push_str("    <ovf:Info>Network configuration</ovf:Info>\n");
+        xml.push_str(&format!(
+            "    <ovf:Info>{}</ovf:Info>\n",
+            escape_xml(&self.info.network_section)
+        ));
+        xml.push_str(&build_network_entries(&self.config.networks, &mut Vec::new()));
+        xml.push_str("  </ovf:NetworkSection>\n");
+        xml
+    }
 
-        if self.config.networks.is_empty() {
-            // Default network if none specified
-            xml.push_str("    <ovf:Network ovf:name=\"VM Network\">\n");
-            xml.push_str("      <ovf:Description>The VM Network</ovf:Description>\n");
-            xml.push_str("    </ovf:Network>\n");
-        } else {
-            for network in &self.config.networks {
-                let network_name = network
-                    .network_name
-                    .as_deref()
-                    .unwrap_or("VM Network");
-                xml.push_str(&format!(
-                    "    <ovf:Network ovf:name=\"{}\">\n",
-                    escape_xml(network_name)
-                ));
-                xml.push_str(&format!(
-                    "      <ovf:Description>The {} network</ovf:Description>\n",
-                    escape_xml(network_name)
-                ));
-                xml.push_str("    </ovf:Network>\n");
-            }
+    /// Build the StorageGroupSection declaring the storage policy, if any.
+    fn build_storage_group_section(&self) -> String {
+        let Some(policy) = &self.storage_policy else {
+            return String::new();
+        };
+
+        let mut xml = String::new();
+        xml.push_str(&format!(
+            "  <vmw:StorageGroupSection ovf:required=\"false\" vmw:id=\"{}\" vmw:name=\"{}\">\n",
+            STORAGE_GROUP_ID,
+            escape_xml(policy)
+        ));
+        xml.push_str("    <ovf:Info>Storage policy group reference</ovf:Info>\n");
+        xml.push_str(&format!(
+            "    <vmw:Description>{}</vmw:Description>\n",
+            escape_xml(policy)
+        ));
+        xml.push_str("  </vmw:StorageGroupSection>\n");
+        xml
+    }
+
+    /// Build a StorageSection referencing the storage group, if any.
+    fn build_storage_section(&self, indent: &str, info: &str) -> String {
+        if self.storage_policy.is_none() {
+            return String::new();
         }
 
-        xml.push_str("  </ovf:NetworkSection>\n");
+        let mut xml = String::new();
+        xml.push_str(&format!(
+            "{}<vmw:StorageSection ovf:required=\"false\" vmw:group=\"{}\">\n",
+            indent, STORAGE_GROUP_ID
+        ));
+        xml.push_str(&format!("{}  <ovf:Info>{}</ovf:Info>\n", indent, info));
+        xml.push_str(&format!("{}</vmw:StorageSection>\n", indent));
         xml
     }
 
+    /// Identifier of the VirtualSystem.
+    fn virtual_system_id(&self) -> String {
+        // Names can sanitize to the same id; the UUID keeps ids distinct
+        self.config
+            .uuid()
+            .unwrap_or_else(|| sanitize_id(&self.config.display_name))
+    }
+
     /// Build the VirtualSystem section with hardware configuration.
     fn build_virtual_system(&self, disks: &[DiskInfo]) -> String {
         let mut xml = String::new();
-        let vm_id = sanitize_id(&self.config.display_name);
-
         xml.push_str(&format!(
             "  <ovf:VirtualSystem ovf:id=\"{}\">\n",
-            escape_xml(&vm_id)
+            escape_xml(&self.virtual_system_id())
+        ));
+        xml.push_str(&format!(
+            "    <ovf:Info>{}</ovf:Info>\n",
+            escape_xml(&self.info.virtual_system)
         ));
-        xml.push_str("    <ovf:Info>A virtual machine</ovf:Info>\n");
         xml.push_str(&format!(
             "    <ovf:Name>{}</ovf:Name>\n",
             escape_xml(&self.config.display_name)
         ));
 
+        // The VM's description
+        xml.push_str(&self.build_annotation_section());
+
+        // Product details, shown in the deployment wizard
+        xml.push_str(&self.build_product_section());
+
+        // License agreement, shown before the VM is deployed
+        xml.push_str(&self.build_eula_section());
+
         // Operating System Section
         xml.push_str(&self.build_os_section());
 
         // Virtual Hardware Section
         xml.push_str(&self.build_hardware_section(disks));
 
+        // Boot order, referencing hardware items
+        xml.push_str(&self.build_boot_order_sections());
+
+        // Storage policy for the VM home
+        xml.push_str(&self.build_storage_section("    ", "Storage policy for this virtual machine"));
+
         xml.push_str("  </ovf:VirtualSystem>\n");
         xml
     }
 
+    /// Build the AnnotationSection, if the VM has a description.
+    fn build_annotation_section(&self) -> String {
+        if self.config.annotation.is_empty() {
+            return String::new();
+        }
+
+        let mut xml = String::new();
+        xml.push_str("    <ovf:AnnotationSection>\n");
+        xml.push_str(&format!(
+            "      <ovf:Info>{}</ovf:Info>\n",
+            escape_xml(&self.info.annotation)
+        ));
+        xml.push_str(&format!(
+            "      <ovf:Annotation>{}</ovf:Annotation>\n",
+            escape_xml(&self.config.annotation)
+        ));
+        xml.push_str("    </ovf:AnnotationSection>\n");
+        xml
+    }
+
+    /// Build the ProductSection, if product details or an icon are known.
+    fn build_product_section(&self) -> String {
+        if self.product.is_none() && self.icon.is_none() {
+            return String::new();
+        }
+        let product = self.product.clone().unwrap_or_default();
+
+        let mut xml = String::new();
+        xml.push_str("    <ovf:ProductSection>\n");
+        xml.push_str(&format!(
+            "      <ovf:Info>{}</ovf:Info>\n",
+            escape_xml(&self.info.product)
+        ));
+        for (element, value) in [
+            ("Product", &product.product),
+            ("Vendor", &product.vendor),
+            ("Version", &product.version),
+            ("FullVersion", &product.full_version),
+        ] {
+            if let Some(value) = value {
+                xml.push_str(&format!(
+                    "      <ovf:{}>{}</ovf:{}>\n",
+                    element,
+                    escape_xml(value),
+                    element
+                ));
+            }
+        }
+        if let Some((_, mime_type)) = &self.icon {
+            xml.push_str(&format!(
+                "      <ovf:Icon ovf:fileRef=\"{}\" ovf:mimeType=\"{}\"/>\n",
+                ICON_FILE_ID,
+                escape_xml(mime_type)
+            ));
+        }
+        xml.push_str("    </ovf:ProductSection>\n");
+        xml
+    }
+
+    /// Build the EulaSection, if a license was set.
+    fn build_eula_section(&self) -> String {
+        let Some(eula) = &self.eula else {
+            return String::new();
+        };
+
+        let mut xml = String::new();
+        xml.push_str("    <ovf:EulaSection>\n");
+        xml.push_str(&format!(
+            "      <ovf:Info>{}</ovf:Info>\n",
+            escape_xml(&self.info.eula)
+        ));
+        xml.push_str(&format!(
+            "      <ovf:License>{}</ovf:License>\n",
+            escape_xml(eula)
+        ));
+        xml.push_str("    </ovf:EulaSection>\n");
+        xml
+    }
+
     /// Build the OperatingSystemSection.
     fn build_os_section(&self) -> String {
-        let (os_id, os_type) = map_guest_os(&self.config.guest_os);
+        let (os_id, os_type) = match &self.os_type_override {
+            Some((os_id, os_type)) => (*os_id, os_type.as_str()),
+            None => map_guest_os(&self.config.guest_os),
+        };
 
         let mut xml = String::new();
         xml.push_str(&format!(
             "    <ovf:OperatingSystemSection ovf:id=\"{}\" vmw:osType=\"{}\">\n",
-            os_id, os_type
+            os_id,
+            escape_xml(os_type)
+        ));
+        xml.push_str(&format!(
+            "      <ovf:Info>{}</ovf:Info>\n",
+            escape_xml(&self.info.operating_system)
         ));
-        xml.push_str("      <ovf:Info>The guest operating system</ovf:Info>\n");
         xml.push_str(&format!(
             "      <ovf:Description>{}</ovf:Description>\n",
             escape_xml(&self.config.guest_os)
@@ -197,7 +582,10 @@ impl<'a> OvfBuilder<'a> {
     fn build_hardware_section(&self, disks: &[DiskInfo]) -> String {
         let mut xml = String::new();
         xml.push_str("    <ovf:VirtualHardwareSection>\n");
-        xml.push_str("      <ovf:Info>Virtual hardware requirements</ovf:Info>\n");
+        xml.push_str(&format!(
+            "      <ovf:Info>{}</ovf:Info>\n",
+            escape_xml(&self.info.virtual_hardware)
+        ));
 
         // System info
         xml.push_str(&self.build_system_item());
@@ -208,8 +596,8 @@ impl<'a> OvfBuilder<'a> {
         // Memory item (ResourceType=4)
         xml.push_str(&self.build_memory_item());
 
-        // SCSI Controller (ResourceType=6)
-        xml.push_str(&self.build_scsi_controller());
+        // SCSI Controllers (ResourceType=6)
+        xml.push_str(&self.build_scsi_controllers());
 
         // Disk items (ResourceType=17)
         for (i, disk) in disks.iter().enumerate() {
@@ -226,10 +614,59 @@ impl<'a> OvfBuilder<'a> {
             xml.push_str(&self.build_default_network_item());
         }
 
+        // CD/DVD drives (ResourceType=15) and the IDE/SATA/NVMe controllers
+        xml.push_str(&self.build_cdrom_items());
+
+        // Firmware, which importers assume is BIOS when absent
+        xml.push_str(&self.build_firmware_config());
+
+        // Guest customization settings and the NVRAM file
+        xml.push_str(&self.build_extra_config(disks));
+
         xml.push_str("    </ovf:VirtualHardwareSection>\n");
         xml
     }
 
+    /// Build the `vmw:Config` element selecting EFI firmware; empty for BIOS.
+    fn build_firmware_config(&self) -> String {
+        match self.config.firmware {
+            FirmwareType::Bios => String::new(),
+            FirmwareType::Efi => format!(
+                "      <vmw:Config ovf:required=\"false\" vmw:key=\"firmware\" vmw:value=\"{}\"/>\n",
+                FirmwareType::Efi.as_str()
+            ),
+        }
+    }
+
+    /// Build a `vmw:ExtraConfig` element per preserved guest customization
+    /// and NUMA setting.
+    fn build_extra_config(&self, disks: &[DiskInfo]) -> String {
+        let nvram = self
+            .nvram_file
+            .as_ref()
+            .map(|_| ("nvram", format!("ovf:/file/{}", nvram_file_id(disks))));
+        let numa = if self.include_numa {
+            self.config.numa_settings()
+        } else {
+            Vec::new()
+        };
+        let mut xml = String::new();
+        for (key, value) in self
+            .config
+            .guest_customization(self.include_guestinfo)
+            .into_iter()
+            .chain(numa)
+            .chain(nvram.as_ref().map(|(key, value)| (*key, value.as_str())))
+        {
+            xml.push_str(&format!(
+                "      <vmw:ExtraConfig ovf:required=\"false\" vmw:key=\"{}\" vmw:value=\"{}\"/>\n",
+                escape_xml(key),
+                escape_xml(value)
+            ));
+        }
+        xml
+    }
+
     /// Build the System item describing the virtual system type.
     fn build_system_item(&self) -> String {
         let mut xml = String::new();
@@ -240,7 +677,12 @@ impl<'a> OvfBuilder<'a> {
             "        <vssd:VirtualSystemIdentifier>{}</vssd:VirtualSystemIdentifier>\n",
             escape_xml(&self.config.display_name)
         ));
-        xml.push_str("        <vssd:VirtualSystemType>vmx-21</vssd:VirtualSystemType>\n");
+        xml.push_str(&format!(
+            "        <vssd:VirtualSystemType>{}</vssd:VirtualSystemType>\n",
+            self.config
+                .hardware_version()
+                .unwrap_or_else(|| DEFAULT_HARDWARE_VERSION.to_string())
+        ));
         xml.push_str("      </ovf:System>\n");
         xml
     }
@@ -279,29 +721,130 @@ impl<'a> OvfBuilder<'a> {
         xml
     }
 
-    /// Build the SCSI Controller hardware item.
-    fn build_scsi_controller(&self) -> String {
+    /// SCSI controllers to emit, in order: those present in the VMX or used
+    /// by a disk, or a default `scsi0`.
+    fn scsi_controllers(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self
+            .config
+            .controllers
+            .iter()
+            .map(|c| c.name.as_str())
+            .chain(
+                self.config
+                    .disks
+                    .iter()
+                    .map(|d| d.controller.as_str())
+                    .filter(|c| c.starts_with("scsi")),
+            )
+            .collect();
+        names.sort();
+        names.dedup();
+        if names.is_empty() && self.config.disks.is_empty() {
+            names.push("scsi0");
+        }
+        names
+    }
+
+    /// `ResourceSubType` of a SCSI controller, from its VMX `virtualDev`.
+    fn scsi_sub_type(&self, name: &str) -> &'static str {
+        let virtual_dev = self
+            .config
+            .controllers
+            .iter()
+            .find(|c| c.name == name)
+            .and_then(|c| c.virtual_dev.as_deref());
+        match virtual_dev {
+            Some("pvscsi") => "VirtualSCSI",
+            Some("lsisas1068") => "lsilogicsas",
+            Some("buslogic") => "buslogic",
+            _ => "lsilogic",
+        }
+    }
+
+    /// Instance ID of the SCSI controller at `index`.
+    ///
+    /// The first controller keeps ID 3; additional ones follow the CD/DVD items.
+    fn scsi_controller_instance_id(&self, index: usize) -> usize {
+        match index {
+            0 => 3,
+            _ => self.cdrom_instance_id(self.config.cdroms.len()) + index - 1,
+        }
+    }
+
+    /// Instance ID of the controller a disk's item is parented to.
+    fn disk_parent_instance_id(&self, index: usize) -> usize {
+        let Some(disk) = self.config.disks.get(index) else {
+            return 3;
+        };
+        if let Some(position) = self
+            .other_controllers()
+            .iter()
+            .position(|c| *c == disk.controller)
+        {
+            return self.cdrom_base_instance_id() + position;
+        }
+        self.scsi_controllers()
+            .iter()
+            .position(|c| *c == disk.controller)
+            .map(|position| self.scsi_controller_instance_id(position))
+            .unwrap_or(3)
+    }
+
+    /// Build the SCSI Controller hardware items.
+    fn build_scsi_controllers(&self) -> String {
         let mut xml = String::new();
-        xml.push_str("      <ovf:Item>\n");
-        xml.push_str("        <rasd:Address>0</rasd:Address>\n");
-        xml.push_str("        <rasd:Description>SCSI Controller</rasd:Description>\n");
-        xml.push_str("        <rasd:ElementName>SCSI Controller 0</rasd:ElementName>\n");
-        xml.push_str("        <rasd:InstanceID>3</rasd:InstanceID>\n");
-        xml.push_str("        <rasd:ResourceSubType>lsilogic</rasd:ResourceSubType>\n");
-        xml.push_str("        <rasd:ResourceType>6</rasd:ResourceType>\n");
-        xml.push_str("      </ovf:Item>\n");
+        for (index, name) in self.scsi_controllers().into_iter().enumerate() {
+            let (_, bus_number) = split_controller(name);
+            let shared_bus = self
+                .config
+                .controllers
+                .iter()
+                .find(|c| c.name == name)
+                .and_then(|c| c.shared_bus.as_deref());
+
+            xml.push_str("      <ovf:Item>\n");
+            xml.push_str(&format!("        <rasd:Address>{}</rasd:Address>\n", bus_number));
+            xml.push_str("        <rasd:Description>SCSI Controller</rasd:Description>\n");
+            xml.push_str(&format!(
+                "        <rasd:ElementName>SCSI Controller {}</rasd:ElementName>\n",
+                bus_number
+            ));
+            xml.push_str(&format!(
+                "        <rasd:InstanceID>{}</rasd:InstanceID>\n",
+                self.scsi_controller_instance_id(index)
+            ));
+            xml.push_str(&format!(
+                "        <rasd:ResourceSubType>{}</rasd:ResourceSubType>\n",
+                self.scsi_sub_type(name)
+            ));
+            xml.push_str("        <rasd:ResourceType>6</rasd:ResourceType>\n");
+            if let Some(mode) = shared_bus {
+                // vSphere names the sharing modes virtualSharing/physicalSharing
+                xml.push_str(&format!(
+                    "        <vmw:Config ovf:required=\"false\" vmw:key=\"sharedBus\" vmw:value=\"{}Sharing\"/>\n",
+                    escape_xml(mode)
+                ));
+            }
+            xml.push_str("      </ovf:Item>\n");
+        }
         xml
     }
 
     /// Build a disk hardware item.
     fn build_disk_item(&self, index: usize, disk: &DiskInfo) -> String {
         let instance_id = 4 + index; // Start after System(0), CPU(1), Memory(2), SCSI(3)
+        // The unit number is the disk's address on its own controller
+        let address = self
+            .config
+            .disks
+            .get(index)
+            .map_or(index as u32, |disk| disk.unit);
 
         let mut xml = String::new();
         xml.push_str("      <ovf:Item>\n");
         xml.push_str(&format!(
             "        <rasd:AddressOnParent>{}</rasd:AddressOnParent>\n",
-            index
+            address
         ));
         xml.push_str("        <rasd:Description>Hard Disk</rasd:Description>\n");
         xml.push_str(&format!(
@@ -316,8 +859,12 @@ impl<'a> OvfBuilder<'a> {
             "        <rasd:InstanceID>{}</rasd:InstanceID>\n",
             instance_id
         ));
-        xml.push_str("        <rasd:Parent>3</rasd:Parent>\n"); // Parent is SCSI controller
+        xml.push_str(&format!(
+            "        <rasd:Parent>{}</rasd:Parent>\n",
+            self.disk_parent_instance_id(index)
+        ));
         xml.push_str("        <rasd:ResourceType>17</rasd:ResourceType>\n");
+        xml.push_str(&self.build_storage_section("        ", "Storage policy for this disk"));
         xml.push_str("      </ovf:Item>\n");
         xml
     }
@@ -340,7 +887,10 @@ impl<'a> OvfBuilder<'a> {
         let mut xml = String::new();
         xml.push_str("      <ovf:Item>\n");
         xml.push_str("        <rasd:AddressOnParent>0</rasd:AddressOnParent>\n");
-        xml.push_str("        <rasd:AutomaticAllocation>true</rasd:AutomaticAllocation>\n");
+        xml.push_str(&format!(
+            "        <rasd:AutomaticAllocation>{}</rasd:AutomaticAllocation>\n",
+            network.start_connected
+        ));
         xml.push_str(&format!(
             "        <rasd:Connection>{}</rasd:Connection>\n",
             escape_xml(network_name)
@@ -359,10 +909,161 @@ impl<'a> OvfBuilder<'a> {
             escape_xml(adapter_type)
         ));
         xml.push_str("        <rasd:ResourceType>10</rasd:ResourceType>\n");
+        if let Some(connection_type) = &network.connection_type {
+            xml.push_str(&format!(
+                "        <vmw:Config ovf:required=\"false\" vmw:key=\"connectionType\" vmw:value=\"{}\"/>\n",
+                escape_xml(connection_type)
+            ));
+        }
+        // Multiqueue tuning is optional; targets without it still deploy
+        if let Some(rss) = network.rss {
+            xml.push_str(&format!(
+                "        <vmw:Config ovf:required=\"false\" vmw:key=\"rss\" vmw:value=\"{}\"/>\n",
+                rss
+            ));
+        }
+        if let Some(num_queue_pairs) = network.num_queue_pairs {
+            xml.push_str(&format!(
+                "        <vmw:Config ovf:required=\"false\" vmw:key=\"numqps\" vmw:value=\"{}\"/>\n",
+                num_queue_pairs
+            ));
+        }
         xml.push_str("      </ovf:Item>\n");
         xml
     }
 
+    /// First instance ID after the disk and network items.
+    fn cdrom_base_instance_id(&self) -> usize {
+        4 + self.config.disks.len() + std::cmp::max(self.config.networks.len(), 1)
+    }
+
+    /// IDE, SATA and NVMe controllers the CD/DVD drives and disks attach to,
+    /// in first-use order.
+    fn other_controllers(&self) -> Vec<&str> {
+        let mut controllers: Vec<&str> = Vec::new();
+        let disk_controllers = self
+            .config
+            .disks
+            .iter()
+            .map(|d| d.controller.as_str())
+            .filter(|c| !c.starts_with("scsi"));
+        for controller in self
+            .config
+            .cdroms
+            .iter()
+            .map(|c| c.controller.as_str())
+            .chain(disk_controllers)
+        {
+            if !controllers.contains(&controller) {
+                controllers.push(controller);
+            }
+        }
+        controllers
+    }
+
+    /// Instance ID of the CD/DVD drive at `index`.
+    fn cdrom_instance_id(&self, index: usize) -> usize {
+        self.cdrom_base_instance_id() + self.other_controllers().len() + index
+    }
+
+    /// Build the IDE/SATA/NVMe controller items and the CD/DVD drive items.
+    fn build_cdrom_items(&self) -> String {
+        let controllers = self.other_controllers();
+        let base_id = self.cdrom_base_instance_id();
+
+        let mut xml = String::new();
+        for (i, controller) in controllers.iter().enumerate() {
+            let (kind, bus_number) = split_controller(controller);
+            let (description, resource_type, sub_type) = match kind {
+                "sata" => ("SATA Controller", 20, Some("vmware.sata.ahci")),
+                "nvme" => ("NVMe Controller", 20, Some("vmware.nvme.controller")),
+                _ => ("IDE Controller", 5, None),
+            };
+
+            xml.push_str("      <ovf:Item>\n");
+            xml.push_str(&format!("        <rasd:Address>{}</rasd:Address>\n", bus_number));
+            xml.push_str(&format!("        <rasd:Description>{}</rasd:Description>\n", description));
+            xml.push_str(&format!(
+                "        <rasd:ElementName>{} {}</rasd:ElementName>\n",
+                description, bus_number
+            ));
+            xml.push_str(&format!("        <rasd:InstanceID>{}</rasd:InstanceID>\n", base_id + i));
+            if let Some(sub_type) = sub_type {
+                xml.push_str(&format!(
+                    "        <rasd:ResourceSubType>{}</rasd:ResourceSubType>\n",
+                    sub_type
+                ));
+            }
+            xml.push_str(&format!(
+                "        <rasd:ResourceType>{}</rasd:ResourceType>\n",
+                resource_type
+            ));
+            xml.push_str("      </ovf:Item>\n");
+        }
+
+        for (i, cdrom) in self.config.cdroms.iter().enumerate() {
+            let parent_id = base_id
+                + controllers
+                    .iter()
+                    .position(|c| *c == cdrom.controller)
+                    .unwrap_or(0);
+
+            xml.push_str("      <ovf:Item ovf:required=\"false\">\n");
+            xml.push_str(&format!(
+                "        <rasd:AddressOnParent>{}</rasd:AddressOnParent>\n",
+                cdrom.unit
+            ));
+            // The ISO is not packaged, so the drive starts empty
+            xml.push_str("        <rasd:AutomaticAllocation>false</rasd:AutomaticAllocation>\n");
+            xml.push_str("        <rasd:Description>CD/DVD Drive</rasd:Description>\n");
+            xml.push_str(&format!(
+                "        <rasd:ElementName>CD/DVD Drive {}</rasd:ElementName>\n",
+                i + 1
+            ));
+            xml.push_str(&format!(
+                "        <rasd:InstanceID>{}</rasd:InstanceID>\n",
+                self.cdrom_instance_id(i)
+            ));
+            xml.push_str(&format!("        <rasd:Parent>{}</rasd:Parent>\n", parent_id));
+            xml.push_str("        <rasd:ResourceSubType>vmware.cdrom.remotepassthrough</rasd:ResourceSubType>\n");
+            xml.push_str("        <rasd:ResourceType>15</rasd:ResourceType>\n");
+            xml.push_str("      </ovf:Item>\n");
+        }
+        xml
+    }
+
+    /// Build one BootOrderSection per bootable device in `bios.bootOrder`.
+    ///
+    /// Each section references the first hardware item of that kind; devices
+    /// the VM doesn't have are skipped.
+    fn build_boot_order_sections(&self) -> String {
+        let mut xml = String::new();
+        for device in self.config.boot_order() {
+            let target = match device.as_str() {
+                "cdrom" if !self.config.cdroms.is_empty() => {
+                    Some(("cdrom", self.cdrom_instance_id(0)))
+                }
+                "hdd" if !self.config.disks.is_empty() => Some(("disk", 4)),
+                "ethernet" => Some(("net", 4 + self.config.disks.len())),
+                _ => None,
+            };
+            let Some((boot_type, instance_id)) = target else {
+                continue;
+            };
+
+            xml.push_str(&format!(
+                "    <vmw:BootOrderSection vmw:instanceId=\"{}\" vmw:type=\"{}\">\n",
+                instance_id, boot_type
+            ));
+            xml.push_str(&format!(
+                "      <ovf:Info>{}</ovf:Info>\n",
+                escape_xml(&self.info.boot_order)
+            ));
+            xml.push_str("    </vmw:BootOrderSection>\n");
+        }
+        xml
+    }
+
     /// Build a default network adapter if none are configured.
     fn build_default_network_item(&self) -> String {
         let instance_id = 4 + self.config.disks.len();
@@ -385,58 +1086,877 @@ impl<'a> OvfBuilder<'a> {
     }
 }
 
+/// Builder for an OVF describing a multi-VM appliance.
+///
+/// Each VM becomes an `ovf:VirtualSystem` inside one
+/// `ovf:VirtualSystemCollection`. Disk ids, file ids and file names are
+/// prefixed with the VM's sanitized display name, so VMs whose disks share a
+/// name don't collide; [`file_names`](Self::file_names) lists the names the
+/// disks must be stored under in the OVA.
+pub struct OvfCollectionBuilder<'a> {
+    name: String,
+    systems: Vec<(&'a VmxConfig, Vec<DiskInfo>)>,
+    info: OvfInfoTexts,
+    layout: OvfLayout,
+    disk_format: DiskFormat,
+}
+
+impl<'a> OvfCollectionBuilder<'a> {
+    /// Create a new collection builder.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the appliance, also used for the collection id.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            systems: Vec::new(),
+            info: OvfInfoTexts::default(),
+            layout: OvfLayout::default(),
+            disk_format: DiskFormat::default(),
+        }
+    }
+
+    /// Add a VM and the disks exported for it.
+    pub fn add_system(mut self, config: &'a VmxConfig, disks: Vec<DiskInfo>) -> Self {
+        self.systems.push((config, disks));
+        self
+    }
+
+    /// Override the `<ovf:Info>` texts of every section.
+    pub fn with_info_texts(mut self, info: OvfInfoTexts) -> Self {
+        self.info = info;
+        self
+    }
+
+    /// Choose between indented and compact output.
+    pub fn with_layout(mut self, layout: OvfLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Set the format the disks are written in (default streamOptimized).
+    pub fn with_disk_format(mut self, format: DiskFormat) -> Self {
+        self.disk_format = format;
+        self
+    }
+
+    /// Returns the archive file names of every disk, in References order.
+    pub fn file_names(&self) -> Result<Vec<String>> {
+        let systems = self.namespaced_systems()?;
+        Ok(systems
+            .iter()
+            .flat_map(|(config, disks)| {
+                let builder = OvfBuilder::new(config);
+                (0..disks.len())
+                    .map(|i| builder.disk_file_name(i).to_string())
+                    .collect::<Vec<_>>()
+            })
+            .collect())
+    }
+
+    /// Build the OVF XML descriptor.
+    ///
+    /// # Returns
+    ///
+    /// A string containing the complete OVF XML document, or an error if no
+    /// VMs were added or two VMs would get the same id.
+    pub fn build(&self) -> Result<String> {
+        let systems = self.namespaced_systems()?;
+        let builders: Vec<OvfBuilder> = systems
+            .iter()
+            .map(|(config, _)| OvfBuilder::new(config).with_info_texts(self.info.clone()))
+            .collect();
+
+        let mut ids: Vec<String> = builders.iter().map(|b| b.virtual_system_id()).collect();
+        ids.sort();
+        if let Some(pair) = ids.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(Error::ovf(format!(
+                "Two virtual machines share the VirtualSystem id '{}'",
+                pair[0]
+            )));
+        }
+
+        let mut xml = String::new();
+        xml.push_str(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        xml.push('\n');
+        xml.push_str(&builders[0].build_envelope_open());
+
+        xml.push_str("  <ovf:References>\n");
+        for (builder, (_, disks)) in builders.iter().zip(&systems) {
+            xml.push_str(&builder.build_file_references(disks));
+        }
+        xml.push_str("  </ovf:References>\n");
+
+        xml.push_str("  <ovf:DiskSection>\n");
+        xml.push_str(&format!(
+            "    <ovf:Info>{}</ovf:Info>\n",
+            escape_xml(&self.info.disk_section)
+        ));
+        for (_, disks) in &systems {
+            xml.push_str(&build_disk_entries(disks, self.disk_format));
+        }
+        xml.push_str("  </ovf:DiskSection>\n");
+
+        // VMs may share networks; declare each one once across all of them
+        xml.push_str("  <ovf:NetworkSection>\n");
+        xml.push_str(&format!(
+            "    <ovf:Info>{}</ovf:Info>\n",
+            escape_xml(&self.info.network_section)
+        ));
+        let mut declared = Vec::new();
+        for (config, _) in &systems {
+            xml.push_str(&build_network_entries(&config.networks, &mut declared));
+        }
+        xml.push_str("  </ovf:NetworkSection>\n");
+
+        xml.push_str(&format!(
+            "  <ovf:VirtualSystemCollection ovf:id=\"{}\">\n",
+            escape_xml(&sanitize_id(&self.name))
+        ));
+        xml.push_str(&format!(
+            "    <ovf:Info>{}</ovf:Info>\n",
+            escape_xml(&self.info.virtual_system_collection)
+        ));
+        xml.push_str(&format!("    <ovf:Name>{}</ovf:Name>\n", escape_xml(&self.name)));
+        for (builder, (_, disks)) in builders.iter().zip(&systems) {
+            for line in builder.build_virtual_system(disks).lines() {
+                xml.push_str("  ");
+                xml.push_str(line);
+                xml.push('\n');
+            }
+        }
+        xml.push_str("  </ovf:VirtualSystemCollection>\n");
+
+        xml.push_str("</ovf:Envelope>\n");
+
+        match self.layout {
+            OvfLayout::Pretty => Ok(xml),
+            OvfLayout::Compact => Ok(compact_xml(&xml)),
+        }
+    }
+
+    /// Copy each VM's configuration and disks with names prefixed by the VM.
+    fn namespaced_systems(&self) -> Result<Vec<(VmxConfig, Vec<DiskInfo>)>> {
+        if self.systems.is_empty() {
+            return Err(Error::ovf("A VirtualSystemCollection needs at least one VM"));
+        }
+
+        let mut prefixes: Vec<String> = Vec::new();
+        let mut systems = Vec::with_capacity(self.systems.len());
+        for (config, disks) in &self.systems {
+            let prefix = sanitize_id(&config.display_name);
+            if prefixes.contains(&prefix) {
+                return Err(Error::ovf(format!(
+                    "Two virtual machines are named '{}' in the collection",
+                    config.display_name
+                )));
+            }
+
+            let mut config = (*config).clone();
+            for disk in &mut config.disks {
+                disk.file_name = format!("{}-{}", prefix, disk.file_name);
+            }
+            let disks = disks
+                .iter()
+                .map(|disk| DiskInfo {
+                    id: format!("{}-{}", prefix, disk.id),
+                    file_ref: format!("{}-{}", prefix, disk.file_ref),
+                    ..disk.clone()
+                })
+                .collect();
+
+            prefixes.push(prefix);
+            systems.push((config, disks));
+        }
+        Ok(systems)
+    }
+}
+
+/// `ovf:id` of the appliance icon file.
+const ICON_FILE_ID: &str = "icon";
+
+/// `ovf:id` of the NVRAM file, numbered after the disk files.
+fn nvram_file_id(disks: &[DiskInfo]) -> String {
+    format!("file{}", disks.len() + 1)
+}
+
+/// Build one `ovf:Disk` line per disk.
+fn build_disk_entries(disks: &[DiskInfo], format: DiskFormat) -> String {
+    let mut xml = String::new();
+    for disk in disks {
+        xml.push_str(&format!(
+            "    <ovf:Disk ovf:capacity=\"{}\" ovf:capacityAllocationUnits=\"byte\" ovf:diskId=\"{}\" ovf:fileRef=\"{}\" ovf:format=\"{}\"/>\n",
+            disk.capacity_bytes,
+            escape_xml(&disk.id),
+            disk.file_ref,
+            format.format_uri()
+        ));
+    }
+    xml
+}
+
+/// Build `ovf:Network` entries for the networks not in `declared` yet.
+///
+/// Several adapters may share a network, so each one is declared once and
+/// added to `declared`. With no adapters, the default network is declared.
+fn build_network_entries<'n>(
+    networks: &'n [NetworkConfig],
+    declared: &mut Vec<&'n str>,
+) -> String {
+    let mut xml = String::new();
+
+    if networks.is_empty() {
+        // Default network if none specified
+        if !declared.contains(&"VM Network") {
+            declared.push("VM Network");
+            xml.push_str("    <ovf:Network ovf:name=\"VM Network\">\n");
+            xml.push_str("      <ovf:Description>The VM Network</ovf:Description>\n");
+            xml.push_str("    </ovf:Network>\n");
+        }
+        return xml;
+    }
+
+    for network in networks {
+        let network_name = network.network_name.as_deref().unwrap_or("VM Network");
+        if declared.contains(&network_name) {
+            continue;
+        }
+        declared.push(network_name);
+        let label = network.connection_type.as_deref().and_then(connection_type_label);
+        let description = match label {
+            Some(label) => format!("The {} network ({})", network_name, label),
+            None => format!("The {} network", network_name),
+        };
+        xml.push_str(&format!(
+            "    <ovf:Network ovf:name=\"{}\">\n",
+            escape_xml(network_name)
+        ));
+        xml.push_str(&format!(
+            "      <ovf:Description>{}</ovf:Description>\n",
+            escape_xml(&description)
+        ));
+        xml.push_str("    </ovf:Network>\n");
+    }
+
+    xml
+}
+
+/// Human-readable name for a VMX `connectionType`, if it is a known one.
+fn connection_type_label(connection_type: &str) -> Option<&'static str> {
+    match connection_type {
+        "bridged" => Some("bridged"),
+        "nat" => Some("NAT"),
+        "hostonly" => Some("host-only"),
+        _ => None,
+    }
+}
+
+/// A recognized VMware guest OS identifier and its OVF equivalents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GuestOsMapping {
+    /// VMX `guestOS` value (matched case-insensitively).
+    pub guest_os: &'static str,
+    /// Numeric OVF operating system identifier.
+    pub os_id: u32,
+    /// VMware-specific OS type string (`vmw:osType`).
+    pub os_type: &'static str,
+}
+
+/// Shorthand for a [`GUEST_OS_MAPPINGS`] entry.
+const fn guest_os_entry(
+    guest_os: &'static str,
+    os_id: u32,
+    os_type: &'static str,
+) -> GuestOsMapping {
+    GuestOsMapping {
+        guest_os,
+        os_id,
+        os_type,
+    }
+}
+
+/// Every recognized guest OS identifier, grouped by family.
+pub const GUEST_OS_MAPPINGS: &[GuestOsMapping] = &[
+    // Ubuntu variants
+    guest_os_entry("ubuntu-64", 96, "ubuntu64Guest"),
+    guest_os_entry("ubuntu64", 96, "ubuntu64Guest"),
+    guest_os_entry("ubuntu", 93, "ubuntuGuest"),
+    guest_os_entry("ubuntu-32", 93, "ubuntuGuest"),
+
+    // Debian variants
+    guest_os_entry("debian-64", 96, "debian10_64Guest"),
+    guest_os_entry("debian64", 96, "debian10_64Guest"),
+    guest_os_entry("debian10-64", 96, "debian10_64Guest"),
+    guest_os_entry("debian11-64", 96, "debian10_64Guest"),
+    guest_os_entry("debian12-64", 96, "debian10_64Guest"),
+    guest_os_entry("debian", 95, "debian10Guest"),
+    guest_os_entry("debian-32", 95, "debian10Guest"),
+    guest_os_entry("debian10", 95, "debian10Guest"),
+    guest_os_entry("debian11", 95, "debian10Guest"),
+    guest_os_entry("debian12", 95, "debian10Guest"),
+
+    // CentOS/RHEL variants
+    guest_os_entry("centos-64", 107, "centos64Guest"),
+    guest_os_entry("centos64", 107, "centos64Guest"),
+    guest_os_entry("centos7-64", 107, "centos64Guest"),
+    guest_os_entry("centos8-64", 107, "centos64Guest"),
+    guest_os_entry("centos9-64", 107, "centos64Guest"),
+    guest_os_entry("centos", 107, "centosGuest"),
+    guest_os_entry("centos-32", 107, "centosGuest"),
+    guest_os_entry("centos7", 107, "centosGuest"),
+    guest_os_entry("centos8", 107, "centosGuest"),
+    guest_os_entry("centos9", 107, "centosGuest"),
+    guest_os_entry("rhel-64", 80, "rhel7_64Guest"),
+    guest_os_entry("rhel64", 80, "rhel7_64Guest"),
+    guest_os_entry("rhel7-64", 80, "rhel7_64Guest"),
+    guest_os_entry("rhel8-64", 80, "rhel7_64Guest"),
+    guest_os_entry("rhel9-64", 80, "rhel7_64Guest"),
+    guest_os_entry("rhel", 79, "rhel7Guest"),
+    guest_os_entry("rhel-32", 79, "rhel7Guest"),
+    guest_os_entry("rhel7", 79, "rhel7Guest"),
+    guest_os_entry("rhel8", 79, "rhel7Guest"),
+    guest_os_entry("rhel9", 79, "rhel7Guest"),
+
+    // Windows variants
+    guest_os_entry("windows10-64", 109, "windows9_64Guest"),
+    guest_os_entry("windows10_64", 109, "windows9_64Guest"),
+    guest_os_entry("win10-64", 109, "windows9_64Guest"),
+    guest_os_entry("windows10", 108, "windows9Guest"),
+    guest_os_entry("windows10-32", 108, "windows9Guest"),
+    guest_os_entry("win10", 108, "windows9Guest"),
+    guest_os_entry("windows11-64", 109, "windows9_64Guest"),
+    guest_os_entry("windows11_64", 109, "windows9_64Guest"),
+    guest_os_entry("win11-64", 109, "windows9_64Guest"),
+    guest_os_entry("win11", 109, "windows9_64Guest"),
+    guest_os_entry("windows7-64", 105, "windows7_64Guest"),
+    guest_os_entry("windows7_64", 105, "windows7_64Guest"),
+    guest_os_entry("win7-64", 105, "windows7_64Guest"),
+    guest_os_entry("windows7", 104, "windows7Guest"),
+    guest_os_entry("windows7-32", 104, "windows7Guest"),
+    guest_os_entry("win7", 104, "windows7Guest"),
+    guest_os_entry("windows8-64", 107, "windows8_64Guest"),
+    guest_os_entry("windows8_64", 107, "windows8_64Guest"),
+    guest_os_entry("win8-64", 107, "windows8_64Guest"),
+    guest_os_entry("windows8", 106, "windows8Guest"),
+    guest_os_entry("windows8-32", 106, "windows8Guest"),
+    guest_os_entry("win8", 106, "windows8Guest"),
+    guest_os_entry("windowsserver2016-64", 112, "windows9Server64Guest"),
+    guest_os_entry("windows2016-64", 112, "windows9Server64Guest"),
+    guest_os_entry("win2016-64", 112, "windows9Server64Guest"),
+    guest_os_entry("windowsserver2019-64", 112, "windows9Server64Guest"),
+    guest_os_entry("windows2019-64", 112, "windows9Server64Guest"),
+    guest_os_entry("win2019-64", 112, "windows9Server64Guest"),
+    guest_os_entry("windowsserver2022-64", 112, "windows9Server64Guest"),
+    guest_os_entry("windows2022-64", 112, "windows9Server64Guest"),
+    guest_os_entry("win2022-64", 112, "windows9Server64Guest"),
+
+    // FreeBSD variants
+    guest_os_entry("freebsd-64", 114, "freebsd64Guest"),
+    guest_os_entry("freebsd64", 114, "freebsd64Guest"),
+    guest_os_entry("freebsd", 42, "freebsdGuest"),
+    guest_os_entry("freebsd-32", 42, "freebsdGuest"),
+
+    // macOS variants
+    guest_os_entry("darwin-64", 101, "darwin64Guest"),
+    guest_os_entry("darwin64", 101, "darwin64Guest"),
+    guest_os_entry("macos", 101, "darwin64Guest"),
+    guest_os_entry("darwin", 101, "darwin64Guest"),
+
+    // Other Linux
+    guest_os_entry("linux-64", 101, "otherLinux64Guest"),
+    guest_os_entry("other-linux-64", 101, "otherLinux64Guest"),
+    guest_os_entry("otherlinux-64", 101, "otherLinux64Guest"),
+    guest_os_entry("linux", 36, "otherLinuxGuest"),
+    guest_os_entry("other-linux", 36, "otherLinuxGuest"),
+    guest_os_entry("otherlinux", 36, "otherLinuxGuest"),
+
+    // Generic/Other
+    guest_os_entry("other-64", 102, "other64Guest"),
+    guest_os_entry("other64", 102, "other64Guest"),
+];
+
+/// OVF mapping used for guest OS identifiers not in [`GUEST_OS_MAPPINGS`].
+const UNKNOWN_GUEST_OS: (u32, &str) = (1, "otherGuest");
+
 /// Map VMware guest OS identifiers to OVF OS IDs and types.
 ///
 /// Returns a tuple of (os_id, os_type) where:
 /// - os_id is the numeric OVF OS identifier
 /// - os_type is the VMware-specific OS type string
 fn map_guest_os(guest_os: &str) -> (u32, &'static str) {
-    match guest_os.to_lowercase().as_str() {
-        // Ubuntu variants
-        "ubuntu-64" | "ubuntu64" => (96, "ubuntu64Guest"),
-        "ubuntu" | "ubuntu-32" => (93, "ubuntuGuest"),
-
-        // Debian variants
-        "debian-64" | "debian64" | "debian10-64" | "debian11-64" | "debian12-64" => {
-            (96, "debian10_64Guest")
-        }
-        "debian" | "debian-32" | "debian10" | "debian11" | "debian12" => (95, "debian10Guest"),
-
-        // CentOS/RHEL variants
-        "centos-64" | "centos64" | "centos7-64" | "centos8-64" | "centos9-64" => {
-            (107, "centos64Guest")
-        }
-        "centos" | "centos-32" | "centos7" | "centos8" | "centos9" => (107, "centosGuest"),
-        "rhel-64" | "rhel64" | "rhel7-64" | "rhel8-64" | "rhel9-64" => (80, "rhel7_64Guest"),
-        "rhel" | "rhel-32" | "rhel7" | "rhel8" | "rhel9" => (79, "rhel7Guest"),
-
-        // Windows variants
-        "windows10-64" | "windows10_64" | "win10-64" => (109, "windows9_64Guest"),
-        "windows10" | "windows10-32" | "win10" => (108, "windows9Guest"),
-        "windows11-64" | "windows11_64" | "win11-64" | "win11" => (109, "windows9_64Guest"),
-        "windows7-64" | "windows7_64" | "win7-64" => (105, "windows7_64Guest"),
-        "windows7" | "windows7-32" | "win7" => (104, "windows7Guest"),
-        "windows8-64" | "windows8_64" | "win8-64" => (107, "windows8_64Guest"),
-        "windows8" | "windows8-32" | "win8" => (106, "windows8Guest"),
-        "windowsserver2016-64" | "windows2016-64" | "win2016-64" => (112, "windows9Server64Guest"),
-        "windowsserver2019-64" | "windows2019-64" | "win2019-64" => (112, "windows9Server64Guest"),
-        "windowsserver2022-64" | "windows2022-64" | "win2022-64" => (112, "windows9Server64Guest"),
-
-        // FreeBSD variants
-        "freebsd-64" | "freebsd64" => (114, "freebsd64Guest"),
-        "freebsd" | "freebsd-32" => (42, "freebsdGuest"),
-
-        // macOS variants
-        "darwin-64" | "darwin64" | "macos" | "darwin" => (101, "darwin64Guest"),
-
-        // Other Linux
-        "linux-64" | "other-linux-64" | "otherlinux-64" => (101, "otherLinux64Guest"),
-        "linux" | "other-linux" | "otherlinux" => (36, "otherLinuxGuest"),
-
-        // Generic/Other
-        "other-64" | "other64" => (102, "other64Guest"),
-        _ => (1, "otherGuest"),
+    GUEST_OS_MAPPINGS
+        .iter()
+        .find(|mapping| mapping.guest_os.eq_ignore_ascii_case(guest_os))
+        .map(|mapping| (mapping.os_id, mapping.os_type))
+        .unwrap_or(UNKNOWN_GUEST_OS)
+}
+
+/// Check that an OVF document is well-formed and list the files it references.
+///
+/// The document must be balanced XML with an `Envelope` root element.
+///
+/// # Arguments
+///
+/// * `xml` - The OVF descriptor to check.
+///
+/// # Returns
+///
+/// The `ovf:href` values of every `File` element, in document order.
+pub fn parse_file_references(xml: &str) -> Result<Vec<String>> {
+    let mut reader = Reader::from_str(xml);
+    let mut hrefs = Vec::new();
+    let mut depth = 0usize;
+    let mut root_seen = false;
+
+    loop {
+        let event = reader.read_event().map_err(|e| {
+            Error::ovf(format!(
+                "Malformed OVF at byte {}: {}",
+                reader.error_position(),
+                e
+            ))
+        })?;
+
+        let element = match &event {
+            Event::Start(e) | Event::Empty(e) => e,
+            Event::End(_) => {
+                depth -= 1;
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+
+        if depth == 0 {
+            if root_seen {
+                return Err(Error::ovf("OVF has more than one root element"));
+            }
+            if element.local_name().as_ref() != b"Envelope" {
+                return Err(Error::ovf("OVF root element must be Envelope"));
+            }
+            root_seen = true;
+        }
+
+        if element.local_name().as_ref() == b"File" {
+            if let Some(href) = attribute(element, b"href")? {
+                hrefs.push(href);
+            }
+        }
+
+        if matches!(event, Event::Start(_)) {
+            depth += 1;
+        }
+    }
+
+    if !root_seen {
+        return Err(Error::ovf("OVF has no root element"));
+    }
+    if depth != 0 {
+        return Err(Error::ovf("OVF ends with unclosed elements"));
+    }
+
+    Ok(hrefs)
+}
+
+/// Check an OVF document against the structural rules deployment tools
+/// enforce beyond well-formedness.
+///
+/// Within each VirtualHardwareSection, item InstanceIDs must be unique and
+/// every `rasd:Parent` must name one of them. Every DiskSection disk must be
+/// attached by an item's `ovf:/disk/<id>` HostResource, and every References
+/// file must be used by a disk, an icon or an `ovf:/file/<id>` reference.
+///
+/// # Arguments
+///
+/// * `xml` - The OVF descriptor to check.
+///
+/// # Returns
+///
+/// A description of each rule broken, empty if the OVF is valid.
+pub fn validate_ovf(xml: &str) -> Result<Vec<String>> {
+    let mut reader = Reader::from_str(xml);
+    let mut issues = Vec::new();
+
+    let mut file_ids: Vec<String> = Vec::new();
+    let mut disk_ids: Vec<String> = Vec::new();
+    // Files and disks referenced anywhere in the document
+    let mut used_files: Vec<String> = Vec::new();
+    let mut used_disks: Vec<String> = Vec::new();
+    // InstanceIDs and Parents of the current VirtualHardwareSection
+    let mut instance_ids: Vec<String> = Vec::new();
+    let mut parents: Vec<String> = Vec::new();
+
+    let mut path: Vec<Vec<u8>> = Vec::new();
+
+    loop {
+        let event = reader.read_event().map_err(|e| {
+            Error::ovf(format!(
+                "Malformed OVF at byte {}: {}",
+                reader.error_position(),
+                e
+            ))
+        })?;
+
+        match &event {
+            Event::Start(e) | Event::Empty(e) => {
+                let name = e.local_name().as_ref().to_vec();
+                match name.as_slice() {
+                    b"File" => file_ids.extend(attribute(e, b"id")?),
+                    b"Disk" => {
+                        disk_ids.extend(attribute(e, b"diskId")?);
+                        used_files.extend(attribute(e, b"fileRef")?);
+                    }
+                    b"Icon" => used_files.extend(attribute(e, b"fileRef")?),
+                    b"VirtualHardwareSection" => {
+                        instance_ids.clear();
+                        parents.clear();
+                    }
+                    _ => {}
+                }
+                for attr in e.attributes() {
+                    let attr =
+                        attr.map_err(|e| Error::ovf(format!("Malformed OVF attribute: {}", e)))?;
+                    let value = attr
+                        .unescape_value()
+                        .map_err(|e| Error::ovf(format!("Malformed OVF attribute: {}", e)))?;
+                    if let Some(id) = value.strip_prefix("ovf:/file/") {
+                        used_files.push(id.to_string());
+                    }
+                }
+                if matches!(event, Event::Start(_)) {
+                    path.push(name);
+                }
+            }
+            Event::Text(t) => {
+                let text = t
+                    .unescape()
+                    .map_err(|e| Error::ovf(format!("Malformed OVF text: {}", e)))?;
+                let text = text.trim();
+                let in_system = path.len() >= 2 && path[path.len() - 2] == b"System";
+                match path.last().map(Vec::as_slice) {
+                    Some(b"InstanceID") if !in_system => {
+                        if instance_ids.iter().any(|id| id == text) {
+                            issues.push(format!(
+                                "InstanceID {} is used by more than one item",
+                                text
+                            ));
+                        }
+                        instance_ids.push(text.to_string());
+                    }
+                    Some(b"Parent") => parents.push(text.to_string()),
+                    Some(b"HostResource") => {
+                        if let Some(id) = text.strip_prefix("ovf:/disk/") {
+                            used_disks.push(id.to_string());
+                        } else if let Some(id) = text.strip_prefix("ovf:/file/") {
+                            used_files.push(id.to_string());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::End(e) => {
+                if e.local_name().as_ref() == b"VirtualHardwareSection" {
+                    for parent in &parents {
+                        if !instance_ids.contains(parent) {
+                            issues.push(format!(
+                                "Parent {} does not match the InstanceID of any item",
+                                parent
+                            ));
+                        }
+                    }
+                }
+                path.pop();
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    for id in &disk_ids {
+        if !used_disks.contains(id) {
+            issues.push(format!("Disk '{}' is not attached by any hardware item", id));
+        }
+    }
+    for id in &used_disks {
+        if !disk_ids.contains(id) {
+            issues.push(format!("Hardware item attaches undeclared disk '{}'", id));
+        }
+    }
+    for id in &file_ids {
+        if !used_files.contains(id) {
+            issues.push(format!("File '{}' is not used by any disk or section", id));
+        }
+    }
+    for id in &used_files {
+        if !file_ids.contains(id) {
+            issues.push(format!("File '{}' is referenced but not declared in References", id));
+        }
+    }
+
+    Ok(issues)
+}
+
+/// Summary of a virtual machine described by an OVF descriptor.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OvfSummary {
+    /// Name of the virtual system.
+    pub name: String,
+    /// VMware guest OS identifier (`vmw:osType`), if present.
+    pub os_type: String,
+    /// Memory size in megabytes.
+    pub memory_mb: u32,
+    /// Number of virtual CPUs.
+    pub cpus: u32,
+    /// Virtual hardware version (e.g. `vmx-21`), if present.
+    pub hardware_version: Option<String>,
+    /// Firmware, from the `firmware` `vmw:Config`; BIOS when absent.
+    pub firmware: FirmwareType,
+    /// Disks in DiskSection order.
+    pub disks: Vec<OvfDiskSummary>,
+    /// Network adapters in VirtualHardwareSection order.
+    pub networks: Vec<OvfNetworkSummary>,
+}
+
+/// A disk listed in an OVF DiskSection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OvfDiskSummary {
+    /// Href of the file backing the disk.
+    pub file_name: String,
+    /// Virtual capacity in bytes.
+    pub capacity: u64,
+    /// Disk format (e.g. `streamOptimized`), taken from the format URI fragment.
+    pub format: String,
+}
+
+/// A network adapter item (ResourceType 10) of an OVF.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OvfNetworkSummary {
+    /// Network the adapter connects to (`rasd:Connection`).
+    pub connection: String,
+    /// Adapter model (`rasd:ResourceSubType`, e.g. `vmxnet3`), if present.
+    pub adapter_type: Option<String>,
+    /// Whether the adapter is connected at power on (`rasd:AutomaticAllocation`).
+    pub connected: bool,
+    /// The item's `vmw:Config` settings, as key and value.
+    pub config: Vec<(String, String)>,
+}
+
+/// Parse the virtual machine summary from an OVF descriptor.
+///
+/// Only the first VirtualSystem is considered.
+///
+/// # Arguments
+///
+/// * `xml` - The OVF descriptor.
+pub fn parse_ovf_summary(xml: &str) -> Result<OvfSummary> {
+    let mut reader = Reader::from_str(xml);
+    let mut summary = OvfSummary::default();
+
+    // File id -> href, used to resolve Disk fileRef attributes
+    let mut files: Vec<(String, String)> = Vec::new();
+    // (fileRef, capacity, format) for each Disk
+    let mut disks: Vec<(String, u64, String)> = Vec::new();
+
+    let mut path: Vec<Vec<u8>> = Vec::new();
+    let mut item_resource_type: Option<u32> = None;
+    let mut item_quantity: Option<u64> = None;
+    let mut item_units = String::new();
+    let mut item_network = OvfNetworkSummary::default();
+
+    loop {
+        let event = reader.read_event().map_err(|e| {
+            Error::ovf(format!(
+                "Malformed OVF at byte {}: {}",
+                reader.error_position(),
+                e
+            ))
+        })?;
+
+        match &event {
+            Event::Start(e) | Event::Empty(e) => {
+                let name = e.local_name().as_ref().to_vec();
+                match name.as_slice() {
+                    b"File" => {
+                        let id = attribute(e, b"id")?.unwrap_or_default();
+                        let href = attribute(e, b"href")?.unwrap_or_default();
+                        files.push((id, href));
+                    }
+                    b"Disk" => {
+                        let file_ref = attribute(e, b"fileRef")?.unwrap_or_default();
+                        let capacity = attribute(e, b"capacity")?
+                            .and_then(|v| v.parse::<u64>().ok())
+                            .unwrap_or(0);
+                        let units = attribute(e, b"capacityAllocationUnits")?;
+                        let capacity =
+                            capacity.saturating_mul(allocation_multiplier(units.as_deref()));
+                        let format = attribute(e, b"format")?
+                            .and_then(|f| f.rsplit_once('#').map(|(_, frag)| frag.to_string()))
+                            .unwrap_or_else(|| "unknown".to_string());
+                        disks.push((file_ref, capacity, format));
+                    }
+                    b"OperatingSystemSection" => {
+                        if let Some(os_type) = attribute(e, b"osType")? {
+                            summary.os_type = os_type;
+                        }
+                    }
+                    b"Item" => {
+                        item_resource_type = None;
+                        item_quantity = None;
+                        item_units.clear();
+                        item_network = OvfNetworkSummary::default();
+                    }
+                    b"Config" => {
+                        let key = attribute(e, b"key")?.unwrap_or_default();
+                        let value = attribute(e, b"value")?.unwrap_or_default();
+                        if path.last().is_some_and(|parent| parent == b"Item") {
+                            item_network.config.push((key, value));
+                        } else if key == "firmware" && value.eq_ignore_ascii_case("efi") {
+                            summary.firmware = FirmwareType::Efi;
+                        }
+                    }
+                    _ => {}
+                }
+                if matches!(event, Event::Start(_)) {
+                    path.push(name);
+                }
+            }
+            Event::Text(t) => {
+                let text = t
+                    .unescape()
+                    .map_err(|e| Error::ovf(format!("Malformed OVF text: {}", e)))?;
+                let text = text.trim();
+                match path.last().map(Vec::as_slice) {
+                    Some(b"Name") if summary.name.is_empty() => summary.name = text.to_string(),
+                    Some(b"VirtualSystemType") if summary.hardware_version.is_none() => {
+                        summary.hardware_version = Some(text.to_string())
+                    }
+                    Some(b"ResourceType") => item_resource_type = text.parse().ok(),
+                    Some(b"VirtualQuantity") => item_quantity = text.parse().ok(),
+                    Some(b"AllocationUnits") => item_units = text.to_string(),
+                    Some(b"Connection") => item_network.connection = text.to_string(),
+                    Some(b"ResourceSubType") => item_network.adapter_type = Some(text.to_string()),
+                    Some(b"AutomaticAllocation") => item_network.connected = text == "true",
+                    _ => {}
+                }
+            }
+            Event::End(e) => {
+                if e.local_name().as_ref() == b"Item" {
+                    let quantity = item_quantity.unwrap_or(0);
+                    match item_resource_type {
+                        Some(3) => summary.cpus = quantity as u32,
+                        Some(4) => {
+                            // Memory defaults to megabytes when no units are given
+                            let bytes = if item_units.is_empty() {
+                                quantity << 20
+                            } else {
+                                quantity.saturating_mul(allocation_multiplier(Some(&item_units)))
+                            };
+                            summary.memory_mb = (bytes >> 20) as u32;
+                        }
+                        Some(10) => summary.networks.push(std::mem::take(&mut item_network)),
+                        _ => {}
+                    }
+                }
+                path.pop();
+            }
+            Event::Eof => break,
+            _ => {}
+        }
     }
+
+    summary.disks = disks
+        .into_iter()
+        .map(|(file_ref, capacity, format)| {
+            let file_name = files
+                .iter()
+                .find(|(id, _)| *id == file_ref)
+                .map(|(_, href)| href.clone())
+                .unwrap_or(file_ref);
+            OvfDiskSummary {
+                file_name,
+                capacity,
+                format,
+            }
+        })
+        .collect();
+
+    Ok(summary)
+}
+
+/// Read an attribute by local name, ignoring its namespace prefix.
+fn attribute(element: &quick_xml::events::BytesStart, name: &[u8]) -> Result<Option<String>> {
+    for attr in element.attributes() {
+        let attr = attr.map_err(|e| Error::ovf(format!("Malformed OVF attribute: {}", e)))?;
+        if attr.key.local_name().as_ref() == name {
+            let value = attr
+                .unescape_value()
+                .map_err(|e| Error::ovf(format!("Malformed OVF attribute: {}", e)))?;
+            return Ok(Some(value.into_owned()));
+        }
+    }
+    Ok(None)
+}
+
+/// Convert OVF allocation units (`byte`, `byte * 2^20`, ...) to a byte multiplier.
+fn allocation_multiplier(units: Option<&str>) -> u64 {
+    let Some(units) = units else {
+        return 1;
+    };
+    let exponent = units
+        .split_once("2^")
+        .and_then(|(_, exp)| exp.trim().parse::<u32>().ok())
+        .unwrap_or(0);
+    1u64.checked_shl(exponent).unwrap_or(1)
+}
+
+/// Remove whitespace that sits between two tags.
+///
+/// Line breaks between attributes inside a tag collapse to a single space.
+/// Whitespace inside text content and attribute values is untouched, so the
+/// document's elements and values are unchanged.
+fn compact_xml(xml: &str) -> String {
+    let mut out = String::with_capacity(xml.len());
+    let mut pending = String::new();
+    let mut in_tag = false;
+    let mut quote: Option<char> = None;
+
+    for c in xml.chars() {
+        if quote.is_none() && c.is_whitespace() {
+            pending.push(c);
+            continue;
+        }
+
+        if in_tag && quote.is_none() {
+            if !pending.is_empty() {
+                out.push(' ');
+            }
+        } else if !(c == '<' && (out.is_empty() || out.ends_with('>'))) {
+            // Keep the run unless it separates one tag from the next
+            out.push_str(&pending);
+        }
+        pending.clear();
+        out.push(c);
+
+        match (c, quote) {
+            ('"' | '\'', None) if in_tag => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            ('<', None) => in_tag = true,
+            ('>', None) => in_tag = false,
+            _ => {}
+        }
+    }
+
+    out
+}
+
+/// Split a controller name such as `ide1` into its kind and bus number.
+fn split_controller(controller: &str) -> (&str, &str) {
+    let digits = controller
+        .find(|c: char| c.is_ascii_digit())
+        .unwrap_or(controller.len());
+    let (kind, number) = controller.split_at(digits);
+    (kind, if number.is_empty() { "0" } else { number })
 }
 
 /// Escape special XML characters in a string.
@@ -483,11 +2003,33 @@ mod tests {
                 name: "ethernet0".to_string(),
                 virtual_dev: Some("vmxnet3".to_string()),
                 network_name: Some("NAT".to_string()),
+                start_connected: true,
+                connection_type: None,
+                rss: None,
+                num_queue_pairs: None,
             }],
+            cdroms: vec![],
+            controllers: vec![],
+            firmware: FirmwareType::Bios,
+            nvram: None,
+            annotation: String::new(),
             raw: HashMap::new(),
         }
     }
 
+    #[test]
+    fn test_compact_xml() {
+        assert_eq!(
+            compact_xml("<a>\n  <b x=\"1 2\">some text</b>\n  <c/>\n</a>\n"),
+            "<a><b x=\"1 2\">some text</b><c/></a>"
+        );
+        assert_eq!(compact_xml("<a> x </a>"), "<a> x </a>");
+        assert_eq!(
+            compact_xml("<a\n    x=\"1\"\n    y='a  b'>\n</a>"),
+            "<a x=\"1\" y='a  b'></a>"
+        );
+    }
+
     #[test]
     fn test_escape_xml() {
         assert_eq!(escape_xml("hello"), "hello");
@@ -497,6 +2039,116 @@ mod tests {
         assert_eq!(escape_xml("it's"), "it&apos;s");
     }
 
+    #[test]
+    fn test_parse_file_references_generated() {
+        let config = create_test_config();
+        let disks = vec![DiskInfo {
+            id: "vmdisk1".to_string(),
+            file_ref: "file1".to_string(),
+            capacity_bytes: 1024,
+            file_size_bytes: 512,
+        }];
+        let ovf = OvfBuilder::new(&config).build(&disks).unwrap();
+
+        assert_eq!(parse_file_references(&ovf).unwrap(), vec!["disk.vmdk"]);
+    }
+
+    #[test]
+    fn test_parse_file_references_malformed() {
+        assert!(parse_file_references("").is_err());
+        assert!(parse_file_references("<Envelope><References>").is_err());
+        assert!(parse_file_references("<Envelope></References>").is_err());
+        assert!(parse_file_references("<Other/>").is_err());
+        assert!(parse_file_references("<Envelope/><Envelope/>").is_err());
+    }
+
+    #[test]
+    fn test_parse_ovf_summary_generated() {
+        let mut config = create_test_config();
+        config
+            .raw
+            .insert("virtualHW.version".to_string(), "19".to_string());
+        let disks = vec![DiskInfo {
+            id: "vmdisk1".to_string(),
+            file_ref: "file1".to_string(),
+            capacity_bytes: 1024 * 1024,
+            file_size_bytes: 512,
+        }];
+        let ovf = OvfBuilder::new(&config).build(&disks).unwrap();
+
+        let summary = parse_ovf_summary(&ovf).unwrap();
+        assert_eq!(summary.name, "TestVM");
+        assert_eq!(summary.os_type, "ubuntu64Guest");
+        assert_eq!(summary.cpus, 2);
+        assert_eq!(summary.memory_mb, 4096);
+        assert_eq!(summary.hardware_version.as_deref(), Some("vmx-19"));
+        assert_eq!(summary.firmware, FirmwareType::Bios);
+        assert_eq!(
+            summary.disks,
+            vec![OvfDiskSummary {
+                file_name: "disk.vmdk".to_string(),
+                capacity: 1024 * 1024,
+                format: "streamOptimized".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_ovf_summary_firmware_and_networks() {
+        let mut config = create_test_config();
+        config.firmware = FirmwareType::Efi;
+        config.networks = vec![NetworkConfig {
+            name: "ethernet0".to_string(),
+            virtual_dev: Some("vmxnet3".to_string()),
+            network_name: Some("Lab".to_string()),
+            start_connected: false,
+            connection_type: Some("bridged".to_string()),
+            rss: Some(true),
+            num_queue_pairs: None,
+        }];
+        let ovf = OvfBuilder::new(&config).build(&[]).unwrap();
+
+        let summary = parse_ovf_summary(&ovf).unwrap();
+        assert_eq!(summary.firmware, FirmwareType::Efi);
+        assert_eq!(
+            summary.networks,
+            vec![OvfNetworkSummary {
+                connection: "Lab".to_string(),
+                adapter_type: Some("vmxnet3".to_string()),
+                connected: false,
+                config: vec![
+                    ("connectionType".to_string(), "bridged".to_string()),
+                    ("rss".to_string(), "true".to_string()),
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_ovf_summary_allocation_units() {
+        let ovf = r#"<Envelope>
+  <DiskSection>
+    <Disk ovf:capacity="2" ovf:capacityAllocationUnits="byte * 2^30" ovf:diskId="d1" ovf:fileRef="f1"/>
+  </DiskSection>
+  <VirtualSystem>
+    <VirtualHardwareSection>
+      <Item>
+        <rasd:AllocationUnits>byte * 2^30</rasd:AllocationUnits>
+        <rasd:ResourceType>4</rasd:ResourceType>
+        <rasd:VirtualQuantity>8</rasd:VirtualQuantity>
+      </Item>
+    </VirtualHardwareSection>
+  </VirtualSystem>
+</Envelope>"#;
+
+        let summary = parse_ovf_summary(ovf).unwrap();
+        assert_eq!(summary.memory_mb, 8 * 1024);
+        assert_eq!(summary.disks[0].capacity, 2 << 30);
+        assert_eq!(summary.disks[0].file_name, "f1");
+        assert_eq!(summary.disks[0].format, "unknown");
+        assert_eq!(summary.hardware_version, None);
+    }
+
     #[test]
     fn test_sanitize_id() {
         assert_eq!(sanitize_id("TestVM"), "TestVM");
@@ -519,6 +2171,25 @@ mod tests {
         assert_eq!(os_type, "windows9_64Guest");
     }
 
+    #[test]
+    fn test_guest_os_mappings_unique() {
+        for (i, mapping) in GUEST_OS_MAPPINGS.iter().enumerate() {
+            assert_eq!(mapping.guest_os, mapping.guest_os.to_lowercase());
+            assert!(
+                GUEST_OS_MAPPINGS[i + 1..]
+                    .iter()
+                    .all(|other| other.guest_os != mapping.guest_os),
+                "duplicate guest OS identifier {}",
+                mapping.guest_os
+            );
+        }
+    }
+
+    #[test]
+    fn test_map_guest_os_case_insensitive() {
+        assert_eq!(map_guest_os("Windows10-64"), (109, "windows9_64Guest"));
+    }
+
     #[test]
     fn test_map_guest_os_unknown() {
         let (id, os_type) = map_guest_os("unknownOS");
@@ -703,7 +2374,7 @@ mod tests {
         let config = create_test_config();
         let builder = OvfBuilder::new(&config);
 
-        let scsi = builder.build_scsi_controller();
+        let scsi = builder.build_scsi_controllers();
         assert!(scsi.contains("<rasd:ResourceType>6</rasd:ResourceType>"));
         assert!(scsi.contains("lsilogic"));
         assert!(scsi.contains("SCSI Controller 0"));
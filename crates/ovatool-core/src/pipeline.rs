@@ -4,7 +4,11 @@
 //! managing the flow from VMX parsing through OVA creation.
 
 use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
 use std::sync::{Arc, Mutex};
+use std::thread::{self, ScopedJoinHandle};
 
 use rayon::prelude::*;
 
@@ -20,15 +24,99 @@ pub enum CompressionLevel {
     Balanced,
     /// Maximum compression (zlib level 9).
     Max,
+    /// Maximum compression, dropping to fast for disks that barely compress.
+    ///
+    /// The first [`ADAPTIVE_SAMPLE_CHUNKS`] chunks of each disk are compressed
+    /// at level 9; if they shrink to no less than the ratio threshold
+    /// ([`ADAPTIVE_RATIO_THRESHOLD`] unless configured otherwise) of their
+    /// size, the rest of the disk is compressed at level 1.
+    Adaptive,
 }
 
 impl CompressionLevel {
     /// Convert to zlib compression level.
+    ///
+    /// For [`Adaptive`](Self::Adaptive) this is the level sampling starts at.
     pub fn to_zlib_level(&self) -> u32 {
         match self {
             CompressionLevel::Fast => 1,
             CompressionLevel::Balanced => 6,
-            CompressionLevel::Max => 9,
+            CompressionLevel::Max | CompressionLevel::Adaptive => 9,
+        }
+    }
+}
+
+/// Chunks sampled per disk before [`CompressionLevel::Adaptive`] settles on a level.
+pub const ADAPTIVE_SAMPLE_CHUNKS: usize = 4;
+
+/// Default compressed-to-original ratio above which adaptive compression
+/// drops to fast.
+pub const ADAPTIVE_RATIO_THRESHOLD: f64 = 0.95;
+
+/// Tracks the zlib level to compress one disk's chunks at.
+///
+/// Fixed levels never change. [`CompressionLevel::Adaptive`] samples the
+/// compression ratio of the first chunks and then settles on a level for the
+/// rest of the disk.
+#[derive(Debug, Clone)]
+pub struct AdaptiveCompressor {
+    level: CompressionLevel,
+    ratio_threshold: f64,
+    effective_level: u32,
+    sampled_chunks: usize,
+    sampled_bytes: u64,
+    sampled_compressed_bytes: u64,
+}
+
+impl AdaptiveCompressor {
+    /// Create a tracker for one disk compressed at `level`.
+    pub fn new(level: CompressionLevel) -> Self {
+        Self::with_threshold(level, ADAPTIVE_RATIO_THRESHOLD)
+    }
+
+    /// Create a tracker that drops to fast compression when the sampled
+    /// chunks compress to more than `ratio_threshold` of their size.
+    pub fn with_threshold(level: CompressionLevel, ratio_threshold: f64) -> Self {
+        Self {
+            level,
+            ratio_threshold,
+            effective_level: level.to_zlib_level(),
+            sampled_chunks: 0,
+            sampled_bytes: 0,
+            sampled_compressed_bytes: 0,
+        }
+    }
+
+    /// Returns the zlib level to compress the next chunks at.
+    pub fn effective_level(&self) -> u32 {
+        self.effective_level
+    }
+
+    /// Returns how many more chunks to sample before the level is settled.
+    pub fn remaining_samples(&self) -> usize {
+        if self.level != CompressionLevel::Adaptive {
+            return 0;
+        }
+        ADAPTIVE_SAMPLE_CHUNKS.saturating_sub(self.sampled_chunks)
+    }
+
+    /// Record the size of a sampled chunk before and after compression.
+    ///
+    /// Once enough chunks are sampled, drops to fast compression if they
+    /// compressed poorly. Chunks recorded after that are ignored.
+    pub fn record(&mut self, input_bytes: u64, compressed_bytes: u64) {
+        if self.remaining_samples() == 0 {
+            return;
+        }
+        self.sampled_chunks += 1;
+        self.sampled_bytes += input_bytes;
+        self.sampled_compressed_bytes += compressed_bytes;
+
+        if self.remaining_samples() == 0 && self.sampled_bytes > 0 {
+            let ratio = self.sampled_compressed_bytes as f64 / self.sampled_bytes as f64;
+            if ratio > self.ratio_threshold {
+                self.effective_level = CompressionLevel::Fast.to_zlib_level();
+            }
         }
     }
 }
@@ -42,6 +130,9 @@ pub struct PipelineConfig {
     pub compression_level: CompressionLevel,
     /// Number of threads to use. 0 means use rayon's default (usually number of CPUs).
     pub num_threads: usize,
+    /// Ratio threshold of [`CompressionLevel::Adaptive`] (default
+    /// [`ADAPTIVE_RATIO_THRESHOLD`]).
+    pub adaptive_ratio_threshold: f64,
 }
 
 impl Default for PipelineConfig {
@@ -50,6 +141,7 @@ impl Default for PipelineConfig {
             chunk_size: 1024 * 1024, // 1 MB default
             compression_level: CompressionLevel::default(),
             num_threads: 0, // Use rayon's default
+            adaptive_ratio_threshold: ADAPTIVE_RATIO_THRESHOLD,
         }
     }
 }
@@ -61,6 +153,7 @@ impl PipelineConfig {
             chunk_size,
             compression_level,
             num_threads,
+            adaptive_ratio_threshold: ADAPTIVE_RATIO_THRESHOLD,
         }
     }
 }
@@ -145,23 +238,41 @@ impl ProgressTracker {
     }
 }
 
+/// Chunk count above which [`Pipeline::process`] switches to the streaming path.
+///
+/// The collect-all path keeps every result in a `BTreeMap` until the last
+/// chunk finishes; past this size the per-entry overhead dominates.
+pub const STREAMING_CHUNK_THRESHOLD: usize = 16 * 1024;
+
+/// Chunks in flight per worker thread on the streaming path.
+///
+/// Export streams each disk through this window, so it bounds the raw and
+/// compressed data held per disk.
+const STREAMING_WINDOW_PER_THREAD: usize = 2;
+
 /// The parallel processing pipeline.
-#[derive(Debug)]
+///
+/// Cloning is cheap: clones share the same thread pool, so one pipeline can
+/// be handed to several disk tasks without building a pool for each.
+#[derive(Debug, Clone)]
 pub struct Pipeline {
     config: PipelineConfig,
-    thread_pool: Option<rayon::ThreadPool>,
+    thread_pool: Option<Arc<rayon::ThreadPool>>,
+    /// Most results held awaiting reordering by any single call, for tests.
+    #[cfg(test)]
+    peak_buffered: Arc<AtomicUsize>,
 }
 
 impl Pipeline {
     /// Create a new pipeline with the given configuration.
     pub fn new(config: PipelineConfig) -> Self {
         let thread_pool = if config.num_threads > 0 {
-            Some(
+            Some(Arc::new(
                 rayon::ThreadPoolBuilder::new()
                     .num_threads(config.num_threads)
                     .build()
                     .expect("Failed to build thread pool"),
-            )
+            ))
         } else {
             None
         };
@@ -169,9 +280,21 @@ impl Pipeline {
         Self {
             config,
             thread_pool,
+            #[cfg(test)]
+            peak_buffered: Arc::new(AtomicUsize::new(0)),
         }
     }
 
+    /// Get the pipeline configuration.
+    pub fn config(&self) -> &PipelineConfig {
+        &self.config
+    }
+
+    /// Get the configured compression level.
+    pub fn compression(&self) -> CompressionLevel {
+        self.config.compression_level
+    }
+
     /// Get the zlib compression level.
     pub fn compression_level(&self) -> u32 {
         self.config.compression_level.to_zlib_level()
@@ -182,11 +305,35 @@ impl Pipeline {
         self.config.chunk_size
     }
 
+    /// Returns the number of worker threads chunks are processed on.
+    pub fn num_threads(&self) -> usize {
+        match &self.thread_pool {
+            Some(pool) => pool.current_num_threads(),
+            None => rayon::current_num_threads(),
+        }
+    }
+
+    /// Returns the number of chunks [`process_streaming`](Self::process_streaming)
+    /// holds at once.
+    pub fn streaming_window(&self) -> usize {
+        self.num_threads() * STREAMING_WINDOW_PER_THREAD
+    }
+
+    /// Returns the most results any call has buffered while reordering.
+    #[cfg(test)]
+    pub(crate) fn peak_buffered_results(&self) -> usize {
+        self.peak_buffered.load(Ordering::Relaxed)
+    }
+
     /// Process chunks in parallel using the provided processor function.
     ///
     /// The processor function receives the chunk index and data, and returns
     /// a result. Results are reordered to match the input order.
     ///
+    /// Jobs with more than [`STREAMING_CHUNK_THRESHOLD`] chunks are routed
+    /// through [`process_streaming`](Self::process_streaming) to bound the
+    /// number of results held for reordering.
+    ///
     /// # Arguments
     ///
     /// * `chunks` - Vector of byte chunks to process
@@ -204,6 +351,18 @@ impl Pipeline {
             return Ok(Vec::new());
         }
 
+        if chunks.len() > STREAMING_CHUNK_THRESHOLD {
+            let mut results = Vec::with_capacity(chunks.len());
+            self.process_streaming(chunks.into_iter().map(Ok), processor, |_idx, result| {
+                results.push(result);
+                Ok(())
+            })?;
+            return Ok(results);
+        }
+
+        #[cfg(test)]
+        self.peak_buffered.fetch_max(chunks.len(), Ordering::Relaxed);
+
         // Process chunks in parallel and collect results with their indices
         let process_indexed = |chunks: Vec<Vec<u8>>| -> Result<Vec<T>> {
             // Create indexed chunks
@@ -230,6 +389,71 @@ impl Pipeline {
         }
     }
 
+    /// Process chunks in parallel, handing results to `sink` in input order.
+    ///
+    /// Chunks are pulled from `chunks` a window of a few chunks per worker
+    /// thread at a time, and the window's results are handed to `sink` before
+    /// the next window is pulled. Only one window of chunks and results is
+    /// ever held, so a lazy iterator (e.g. one reading a disk) can be
+    /// processed in bounded memory whatever its length. The iterator and
+    /// `sink` run on the calling thread.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunks` - Chunks to process, typically byte buffers; an error stops
+    ///   processing
+    /// * `processor` - Function to process each chunk, receives (index, chunk)
+    /// * `sink` - Function receiving each (index, result) in input order
+    ///
+    /// # Errors
+    ///
+    /// The first error from `chunks`, `processor` or `sink`. Results of
+    /// earlier windows have already been handed to `sink`.
+    pub fn process_streaming<I, C, F, T, S>(
+        &self,
+        chunks: I,
+        processor: F,
+        mut sink: S,
+    ) -> Result<()>
+    where
+        I: IntoIterator<Item = Result<C>>,
+        C: Send,
+        F: Fn(usize, C) -> Result<T> + Send + Sync,
+        T: Send,
+        S: FnMut(usize, T) -> Result<()>,
+    {
+        let window = self.streaming_window();
+
+        let mut chunks = chunks.into_iter().enumerate().peekable();
+        while chunks.peek().is_some() {
+            let batch: Vec<(usize, C)> = chunks
+                .by_ref()
+                .take(window)
+                .map(|(idx, chunk)| chunk.map(|chunk| (idx, chunk)))
+                .collect::<Result<_>>()?;
+            #[cfg(test)]
+            self.peak_buffered.fetch_max(batch.len(), Ordering::Relaxed);
+
+            // Indexed collect into a Vec keeps input order
+            let process_batch = || -> Result<Vec<(usize, T)>> {
+                batch
+                    .into_par_iter()
+                    .map(|(idx, chunk)| processor(idx, chunk).map(|result| (idx, result)))
+                    .collect()
+            };
+            let results = match &self.thread_pool {
+                Some(pool) => pool.install(process_batch)?,
+                None => process_batch()?,
+            };
+
+            for (idx, result) in results {
+                sink(idx, result)?;
+            }
+        }
+
+        Ok(())
+    }
+
     /// Process chunks with progress tracking.
     ///
     /// Same as `process` but updates a progress tracker.
@@ -278,6 +502,181 @@ impl Default for Pipeline {
     }
 }
 
+/// Runs several producers concurrently while their output is consumed
+/// strictly in producer order.
+///
+/// Each producer runs on its own thread and writes its output through an
+/// [`OrderedSender`], which hands it over in buffers of `buffer_size` bytes.
+/// The consumer runs on the calling thread and drains the producers one
+/// after another, so a single writer sees each producer's bytes
+/// contiguously and in the original order. At most `buffers_per_producer`
+/// buffers of each producer are in flight, plus the one the consumer has
+/// just received; a producer that gets that far ahead of the consumer blocks
+/// until it catches up.
+#[derive(Debug)]
+pub struct OrderedDrain {
+    buffer_size: usize,
+    buffers_per_producer: usize,
+    /// Most buffers in flight at once, across all producers.
+    peak_in_flight: Arc<AtomicUsize>,
+}
+
+impl OrderedDrain {
+    /// Create a drain handing over `buffer_size`-byte buffers, at most
+    /// `buffers_per_producer` (at least 1) in flight per producer.
+    pub fn new(buffer_size: usize, buffers_per_producer: usize) -> Self {
+        Self {
+            buffer_size: buffer_size.max(1),
+            buffers_per_producer: buffers_per_producer.max(1),
+            peak_in_flight: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Returns the most buffers that were in flight at once in any run.
+    pub fn peak_in_flight(&self) -> usize {
+        self.peak_in_flight.load(Ordering::Relaxed)
+    }
+
+    /// Run `producers` concurrently and hand their streams to `consume`.
+    ///
+    /// `consume` receives one [`OrderedStream`] per producer, in producer
+    /// order. Producers still running when `consume` returns see their
+    /// sender fail and stop.
+    ///
+    /// # Arguments
+    ///
+    /// * `producers` - Functions writing their output to an `OrderedSender`
+    /// * `consume` - Function draining the streams on the calling thread
+    ///
+    /// # Returns
+    ///
+    /// The result of `consume`.
+    pub fn run<P, T, C, R>(&self, producers: Vec<P>, consume: C) -> Result<R>
+    where
+        P: FnOnce(&mut OrderedSender) -> Result<T> + Send,
+        T: Send,
+        C: for<'scope> FnOnce(Vec<OrderedStream<'scope, T>>) -> Result<R>,
+    {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        thread::scope(|scope| {
+            let streams = producers
+                .into_iter()
+                .map(|producer| {
+                    // The buffer blocked in `send` counts as in flight too
+                    let (sender, receiver) = mpsc::sync_channel(self.buffers_per_producer - 1);
+                    let mut sender = OrderedSender {
+                        sender,
+                        buffer: Vec::with_capacity(self.buffer_size),
+                        buffer_size: self.buffer_size,
+                        in_flight: Arc::clone(&in_flight),
+                        peak_in_flight: Arc::clone(&self.peak_in_flight),
+                    };
+                    let handle = scope.spawn(move || {
+                        let value = producer(&mut sender)?;
+                        sender.send_buffer().map_err(Error::io_simple)?;
+                        Ok(value)
+                    });
+                    OrderedStream {
+                        receiver,
+                        handle,
+                        in_flight: Arc::clone(&in_flight),
+                    }
+                })
+                .collect();
+            consume(streams)
+        })
+    }
+}
+
+/// Writes one producer's output to an [`OrderedDrain`].
+///
+/// Writes are gathered into buffers and handed to the consumer as each one
+/// fills; the last partial buffer is handed over when the producer returns.
+/// Writing fails once the consumer has stopped.
+#[derive(Debug)]
+pub struct OrderedSender {
+    sender: SyncSender<Vec<u8>>,
+    buffer: Vec<u8>,
+    buffer_size: usize,
+    in_flight: Arc<AtomicUsize>,
+    peak_in_flight: Arc<AtomicUsize>,
+}
+
+impl OrderedSender {
+    /// Hand the current buffer to the consumer, blocking while too many are
+    /// in flight.
+    fn send_buffer(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let buffer = std::mem::replace(&mut self.buffer, Vec::with_capacity(self.buffer_size));
+        let in_flight = self.in_flight.fetch_add(1, Ordering::Relaxed) + 1;
+        self.peak_in_flight.fetch_max(in_flight, Ordering::Relaxed);
+        self.sender.send(buffer).map_err(|_| {
+            self.in_flight.fetch_sub(1, Ordering::Relaxed);
+            io::Error::new(io::ErrorKind::BrokenPipe, "Ordered drain consumer stopped")
+        })
+    }
+}
+
+impl Write for OrderedSender {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = std::cmp::min(buf.len(), self.buffer_size - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..len]);
+        if self.buffer.len() == self.buffer_size {
+            self.send_buffer()?;
+        }
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send_buffer()
+    }
+}
+
+/// The output of one producer of an [`OrderedDrain`], in the order written.
+///
+/// Iterating yields the producer's buffers until it finishes;
+/// [`finish`](Self::finish) then returns what the producer returned.
+#[derive(Debug)]
+pub struct OrderedStream<'scope, T> {
+    receiver: Receiver<Vec<u8>>,
+    handle: ScopedJoinHandle<'scope, Result<T>>,
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl<T> OrderedStream<'_, T> {
+    /// Wait for the producer to finish, discarding any buffers not yet
+    /// received.
+    ///
+    /// # Returns
+    ///
+    /// The producer's result, or a pipeline error if it panicked.
+    pub fn finish(self) -> Result<T> {
+        let Self {
+            receiver,
+            handle,
+            in_flight,
+        } = self;
+        for _ in receiver.iter() {
+            in_flight.fetch_sub(1, Ordering::Relaxed);
+        }
+        handle
+            .join()
+            .map_err(|_| Error::pipeline("Producer thread panicked"))?
+    }
+}
+
+impl<T> Iterator for OrderedStream<'_, T> {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Vec<u8>> {
+        let buffer = self.receiver.recv().ok()?;
+        self.in_flight.fetch_sub(1, Ordering::Relaxed);
+        Some(buffer)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -381,6 +780,171 @@ mod tests {
         assert_eq!(pipeline.chunk_size(), 4096);
     }
 
+    #[test]
+    fn test_large_job_uses_streaming_path() {
+        let pipeline = Pipeline::new(PipelineConfig::new(1, CompressionLevel::Fast, 2));
+        let count = STREAMING_CHUNK_THRESHOLD + 1000;
+        let chunks: Vec<Vec<u8>> = (0..count).map(|i| vec![(i % 256) as u8]).collect();
+
+        let results: Vec<usize> = pipeline.process(chunks, |idx, _data| Ok(idx)).unwrap();
+
+        assert_eq!(results, (0..count).collect::<Vec<_>>());
+        assert_eq!(
+            pipeline.peak_buffered_results(),
+            2 * STREAMING_WINDOW_PER_THREAD,
+            "Only one window of results should be buffered"
+        );
+    }
+
+    #[test]
+    fn test_small_job_uses_collect_path() {
+        let pipeline = Pipeline::default();
+        let chunks: Vec<Vec<u8>> = (0..100).map(|i| vec![i as u8]).collect();
+
+        pipeline.process(chunks, |idx, _data| Ok(idx)).unwrap();
+        assert_eq!(pipeline.peak_buffered_results(), 100);
+    }
+
+    #[test]
+    fn test_process_streaming_order_and_errors() {
+        let pipeline = Pipeline::default();
+        let chunks: Vec<Vec<u8>> = (0..50).map(|i| vec![i as u8]).collect();
+
+        let mut seen = Vec::new();
+        let chunk_results = chunks.clone().into_iter().map(Ok);
+        pipeline
+            .process_streaming(chunk_results, |_idx, data| Ok(data[0]), |idx, value| {
+                seen.push((idx, value));
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(seen, (0..50).map(|i| (i, i as u8)).collect::<Vec<_>>());
+
+        let result = pipeline.process_streaming(
+            chunks.into_iter().map(Ok),
+            |idx, _data| {
+                if idx == 30 {
+                    Err(Error::pipeline("boom"))
+                } else {
+                    Ok(idx)
+                }
+            },
+            |_idx, _value| Ok(()),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_process_streaming_bounds_chunks_in_flight() {
+        use std::cell::Cell;
+
+        let pipeline = Pipeline::new(PipelineConfig::new(1, CompressionLevel::Fast, 2));
+        let window = 2 * STREAMING_WINDOW_PER_THREAD;
+        let pulled = Cell::new(0usize);
+        let sunk = Cell::new(0usize);
+        let peak_in_flight = Cell::new(0usize);
+
+        // Chunks pulled but not yet handed to the sink are in flight
+        let chunks = (0..1000usize).map(|i| {
+            pulled.set(pulled.get() + 1);
+            peak_in_flight.set(peak_in_flight.get().max(pulled.get() - sunk.get()));
+            Ok(vec![(i % 256) as u8])
+        });
+        let mut seen = Vec::new();
+        pipeline
+            .process_streaming(chunks, |idx, _data| Ok(idx), |idx, value| {
+                sunk.set(sunk.get() + 1);
+                seen.push((idx, value));
+                Ok(())
+            })
+            .unwrap();
+
+        assert_eq!(seen, (0..1000).map(|i| (i, i)).collect::<Vec<_>>());
+        assert_eq!(peak_in_flight.get(), window);
+
+        // A read error stops processing after the windows before it
+        let chunks = (0..100usize).map(|i| {
+            if i == 50 {
+                Err(Error::pipeline("read failed"))
+            } else {
+                Ok(vec![0u8])
+            }
+        });
+        let mut sunk = 0;
+        let result = pipeline.process_streaming(chunks, |idx, _data| Ok(idx), |_idx, _value| {
+            sunk += 1;
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert_eq!(sunk, 50 / window * window);
+    }
+
+    #[test]
+    fn test_ordered_drain_three_producers() {
+        use std::time::{Duration, Instant};
+
+        let drain = OrderedDrain::new(1024, 2);
+        // Each producer writes 64 KiB of its own byte, far more than fits in flight
+        let producers: Vec<_> = (0..3u8)
+            .map(|id| {
+                move |sender: &mut OrderedSender| -> Result<u8> {
+                    for _ in 0..64 {
+                        sender.write_all(&[id; 1000]).map_err(Error::io_simple)?;
+                    }
+                    Ok(id)
+                }
+            })
+            .collect();
+
+        let mut output = Vec::new();
+        let results = drain
+            .run(producers, |streams| {
+                // Until the consumer starts, every producer blocks with exactly
+                // its share of buffers in flight
+                let deadline = Instant::now() + Duration::from_secs(10);
+                while drain.peak_in_flight() < 3 * 2 && Instant::now() < deadline {
+                    thread::yield_now();
+                }
+                thread::sleep(Duration::from_millis(20));
+                assert_eq!(drain.peak_in_flight(), 3 * 2);
+
+                let mut results = Vec::new();
+                for mut stream in streams {
+                    output.extend(stream.by_ref().flatten());
+                    results.push(stream.finish()?);
+                }
+                Ok(results)
+            })
+            .unwrap();
+
+        assert_eq!(results, vec![0, 1, 2]);
+        let expected: Vec<u8> = (0..3u8).flat_map(|id| vec![id; 64 * 1000]).collect();
+        assert!(output == expected, "Producer output was interleaved or reordered");
+    }
+
+    #[test]
+    fn test_ordered_drain_producer_error() {
+        let drain = OrderedDrain::new(16, 1);
+        let producers: Vec<_> = (0..2)
+            .map(|id| {
+                move |sender: &mut OrderedSender| -> Result<()> {
+                    match id {
+                        0 => sender.write_all(&[1; 100]).map_err(Error::io_simple),
+                        _ => Err(Error::pipeline("boom")),
+                    }
+                }
+            })
+            .collect();
+
+        let result = drain.run(producers, |streams| {
+            for stream in streams {
+                stream.finish()?;
+            }
+            Ok(())
+        });
+        assert!(result.unwrap_err().to_string().contains("boom"));
+    }
+
     #[test]
     fn test_pipeline_process_empty() {
         let pipeline = Pipeline::default();
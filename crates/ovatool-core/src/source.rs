@@ -0,0 +1,532 @@
+//! Disk data sources for the export pipeline.
+//!
+//! The exporter reads disk contents through the [`DiskSource`] trait rather
+//! than directly from files. This allows disks to be exported from backends
+//! other than the local filesystem (object storage, network block devices,
+//! in-memory images) without staging them to disk first.
+//!
+//! File-backed implementations are provided for the VMDK layouts handled by
+//! [`export_vm`](crate::export::export_vm):
+//!
+//! - [`VmdkReader`] - flat extent files (memory-mapped)
+//! - [`WindowedVmdkReader`] - flat extent files (mapped a window at a time)
+//! - [`SparseVmdkReader`] - hosted sparse and streamOptimized extent files
+//! - [`ConcatDiskSource`] - multiple extents forming one virtual disk
+//! - [`TruncatedDiskSource`] - the leading part of another source
+//! - [`PaddedDiskSource`] - another source extended with zeros
+//! - [`OverlayDiskSource`] - a snapshot delta layered over its parent
+//! - [`ZeroDiskSource`] - an all-zero placeholder disk
+
+use crate::error::Result;
+use crate::vmdk::{SparseVmdkReader, VmdkReader, WindowedVmdkReader};
+use std::ops::Range;
+
+/// Random-access source of virtual disk data.
+///
+/// Implementations must be safe to share across threads, since the export
+/// pipeline may read from several sources concurrently.
+pub trait DiskSource: Send + Sync {
+    /// Returns the virtual size of the disk in bytes.
+    fn size(&self) -> u64;
+
+    /// Reads a range of the disk.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - Byte offset into the disk.
+    /// * `len` - Number of bytes to read.
+    ///
+    /// # Returns
+    ///
+    /// The data at the requested range. The buffer is shorter than `len`
+    /// only when the range extends past the end of the disk.
+    fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>>;
+
+    /// Returns `true` if the range is known to contain only zeros.
+    ///
+    /// The exporter skips reading ranges reported as unallocated. The default
+    /// implementation returns `false`, which is always correct.
+    fn is_unallocated(&self, _offset: u64, _len: usize) -> bool {
+        false
+    }
+
+    /// Returns `true` if zeros read from the range must still be written.
+    ///
+    /// The exporter normally leaves all-zero grains out of the output. A
+    /// source whose output is written over an older copy of the disk, such
+    /// as a [`ChangedBlocksSource`](crate::cbt::ChangedBlocksSource), returns
+    /// `true` where its zeros replace older data, so those grains are stored.
+    /// The default implementation returns `false`.
+    fn must_write_zeros(&self, _offset: u64, _len: usize) -> bool {
+        false
+    }
+}
+
+impl DiskSource for VmdkReader {
+    fn size(&self) -> u64 {
+        VmdkReader::size(self)
+    }
+
+    fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        let data = self.data();
+        let start = std::cmp::min(offset, data.len() as u64) as usize;
+        let end = std::cmp::min(start as u64 + len as u64, data.len() as u64) as usize;
+        Ok(data[start..end].to_vec())
+    }
+}
+
+impl DiskSource for WindowedVmdkReader {
+    fn size(&self) -> u64 {
+        WindowedVmdkReader::size(self)
+    }
+
+    fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        WindowedVmdkReader::read_at(self, offset, len)
+    }
+}
+
+impl DiskSource for SparseVmdkReader {
+    fn size(&self) -> u64 {
+        self.capacity()
+    }
+
+    fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        SparseVmdkReader::read_at(self, offset, len)
+    }
+
+    fn is_unallocated(&self, offset: u64, len: usize) -> bool {
+        SparseVmdkReader::is_unallocated(self, offset, len as u64)
+    }
+}
+
+/// A disk made of several sources laid out back to back.
+///
+/// Used for split VMDKs (e.g. `twoGbMaxExtentSparse`), where each extent file
+/// holds a consecutive slice of the virtual disk. Each split sparse extent has
+/// its own header and grain directory, so it is opened as its own
+/// [`SparseVmdkReader`], sized to the sectors its descriptor line declares.
+pub struct ConcatDiskSource {
+    /// Parts paired with their starting offset in the virtual disk.
+    parts: Vec<(u64, Box<dyn DiskSource>)>,
+    size: u64,
+}
+
+impl ConcatDiskSource {
+    /// Create a disk source from parts in disk order.
+    pub fn new(parts: Vec<Box<dyn DiskSource>>) -> Self {
+        let mut size = 0u64;
+        let parts = parts
+            .into_iter()
+            .map(|part| {
+                let start = size;
+                size += part.size();
+                (start, part)
+            })
+            .collect();
+
+        Self { parts, size }
+    }
+
+    /// Iterate over the parts overlapping `offset..offset + len`.
+    fn overlapping(
+        &self,
+        offset: u64,
+        len: usize,
+    ) -> impl Iterator<Item = (u64, &dyn DiskSource)> + '_ {
+        let end = offset + len as u64;
+        self.parts
+            .iter()
+            .filter(move |(start, part)| *start < end && start + part.size() > offset)
+            .map(|(start, part)| (*start, part.as_ref()))
+    }
+}
+
+impl DiskSource for ConcatDiskSource {
+    fn size(&self) -> u64 {
+        self.size
+    }
+
+    fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        let end = std::cmp::min(offset + len as u64, self.size);
+        let mut data = Vec::with_capacity(end.saturating_sub(offset) as usize);
+
+        for (start, part) in self.overlapping(offset, len) {
+            let part_offset = (offset + data.len() as u64) - start;
+            let part_len = std::cmp::min(end - start, part.size()) - part_offset;
+            data.extend_from_slice(&part.read_at(part_offset, part_len as usize)?);
+        }
+
+        Ok(data)
+    }
+
+    fn is_unallocated(&self, offset: u64, len: usize) -> bool {
+        self.overlapping(offset, len).all(|(start, part)| {
+            let part_offset = offset.saturating_sub(start);
+            let part_end = std::cmp::min(offset + len as u64 - start, part.size());
+            part.is_unallocated(part_offset, (part_end - part_offset) as usize)
+        })
+    }
+
+    fn must_write_zeros(&self, offset: u64, len: usize) -> bool {
+        self.overlapping(offset, len).any(|(start, part)| {
+            let part_offset = offset.saturating_sub(start);
+            let part_end = std::cmp::min(offset + len as u64 - start, part.size());
+            part.must_write_zeros(part_offset, (part_end - part_offset) as usize)
+        })
+    }
+}
+
+/// The first bytes of another source.
+///
+/// Used for flat extent files with trailing slack beyond the size their
+/// descriptor declares, so only the declared region is exported.
+pub struct TruncatedDiskSource {
+    inner: Box<dyn DiskSource>,
+    size: u64,
+}
+
+impl TruncatedDiskSource {
+    /// Create a source exposing at most `size` bytes of `inner`.
+    pub fn new(inner: Box<dyn DiskSource>, size: u64) -> Self {
+        let size = std::cmp::min(size, inner.size());
+        Self { inner, size }
+    }
+}
+
+impl DiskSource for TruncatedDiskSource {
+    fn size(&self) -> u64 {
+        self.size
+    }
+
+    fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        let available = self.size.saturating_sub(offset);
+        self.inner
+            .read_at(offset, std::cmp::min(len as u64, available) as usize)
+    }
+
+    fn is_unallocated(&self, offset: u64, len: usize) -> bool {
+        let available = self.size.saturating_sub(offset);
+        self.inner
+            .is_unallocated(offset, std::cmp::min(len as u64, available) as usize)
+    }
+
+    fn must_write_zeros(&self, offset: u64, len: usize) -> bool {
+        let available = self.size.saturating_sub(offset);
+        self.inner
+            .must_write_zeros(offset, std::cmp::min(len as u64, available) as usize)
+    }
+}
+
+/// Another source extended with zeros to a larger size.
+///
+/// Used to round disk capacities up to a boundary some importers prefer.
+pub struct PaddedDiskSource {
+    inner: Box<dyn DiskSource>,
+    size: u64,
+}
+
+impl PaddedDiskSource {
+    /// Create a source of at least `size` bytes, zero beyond the end of `inner`.
+    pub fn new(inner: Box<dyn DiskSource>, size: u64) -> Self {
+        let size = std::cmp::max(size, inner.size());
+        Self { inner, size }
+    }
+}
+
+impl DiskSource for PaddedDiskSource {
+    fn size(&self) -> u64 {
+        self.size
+    }
+
+    fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        let end = std::cmp::min(offset.saturating_add(len as u64), self.size);
+        let mut data = if offset < self.inner.size() {
+            self.inner.read_at(offset, len)?
+        } else {
+            Vec::new()
+        };
+        data.resize(end.saturating_sub(offset) as usize, 0);
+        Ok(data)
+    }
+
+    fn is_unallocated(&self, offset: u64, len: usize) -> bool {
+        let inner_size = self.inner.size();
+        if offset >= inner_size {
+            return true;
+        }
+        let inner_len = std::cmp::min(len as u64, inner_size - offset) as usize;
+        self.inner.is_unallocated(offset, inner_len)
+    }
+
+    fn must_write_zeros(&self, offset: u64, len: usize) -> bool {
+        let inner_size = self.inner.size();
+        if offset >= inner_size {
+            return false;
+        }
+        let inner_len = std::cmp::min(len as u64, inner_size - offset) as usize;
+        self.inner.must_write_zeros(offset, inner_len)
+    }
+}
+
+/// A source layered over another, as a snapshot delta is over its parent.
+///
+/// Reads inside the upper source's allocated ranges come from the upper
+/// source and everything else comes from the lower one.
+pub struct OverlayDiskSource {
+    upper: Box<dyn DiskSource>,
+    /// Sorted, non-overlapping ranges backed by `upper`.
+    allocated: Vec<Range<u64>>,
+    lower: Box<dyn DiskSource>,
+}
+
+impl OverlayDiskSource {
+    /// Create a source the size of `upper`, falling back to `lower` outside
+    /// the `allocated` ranges.
+    ///
+    /// A `lower` source smaller than `upper` reads as zeros past its end.
+    pub fn new(
+        upper: Box<dyn DiskSource>,
+        mut allocated: Vec<Range<u64>>,
+        lower: Box<dyn DiskSource>,
+    ) -> Self {
+        allocated.retain(|range| !range.is_empty());
+        allocated.sort_by_key(|range| range.start);
+        let lower: Box<dyn DiskSource> = if lower.size() < upper.size() {
+            Box::new(PaddedDiskSource::new(lower, upper.size()))
+        } else {
+            lower
+        };
+        Self {
+            upper,
+            allocated,
+            lower,
+        }
+    }
+
+    /// Split `offset..end` into runs served by a single layer.
+    fn segments(&self, offset: u64, end: u64) -> impl Iterator<Item = (Range<u64>, bool)> + '_ {
+        let mut pos = offset;
+        std::iter::from_fn(move || {
+            if pos >= end {
+                return None;
+            }
+            let index = self.allocated.partition_point(|range| range.end <= pos);
+            let (segment_end, in_upper) = match self.allocated.get(index) {
+                Some(range) if range.start <= pos => (range.end, true),
+                Some(range) => (range.start, false),
+                None => (end, false),
+            };
+            let segment = pos..std::cmp::min(segment_end, end);
+            pos = segment.end;
+            Some((segment, in_upper))
+        })
+    }
+
+    fn layer(&self, in_upper: bool) -> &dyn DiskSource {
+        if in_upper {
+            self.upper.as_ref()
+        } else {
+            self.lower.as_ref()
+        }
+    }
+}
+
+impl DiskSource for OverlayDiskSource {
+    fn size(&self) -> u64 {
+        self.upper.size()
+    }
+
+    fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        let end = std::cmp::min(offset.saturating_add(len as u64), self.size());
+        let mut data = Vec::with_capacity(end.saturating_sub(offset) as usize);
+        for (segment, in_upper) in self.segments(offset, end) {
+            let segment_len = (segment.end - segment.start) as usize;
+            data.extend_from_slice(&self.layer(in_upper).read_at(segment.start, segment_len)?);
+        }
+        Ok(data)
+    }
+
+    fn is_unallocated(&self, offset: u64, len: usize) -> bool {
+        let end = std::cmp::min(offset.saturating_add(len as u64), self.size());
+        self.segments(offset, end).all(|(segment, in_upper)| {
+            let segment_len = (segment.end - segment.start) as usize;
+            self.layer(in_upper).is_unallocated(segment.start, segment_len)
+        })
+    }
+
+    fn must_write_zeros(&self, offset: u64, len: usize) -> bool {
+        let end = std::cmp::min(offset.saturating_add(len as u64), self.size());
+        self.segments(offset, end).any(|(segment, in_upper)| {
+            let segment_len = (segment.end - segment.start) as usize;
+            self.layer(in_upper).must_write_zeros(segment.start, segment_len)
+        })
+    }
+}
+
+/// An all-zero disk of a fixed size.
+///
+/// Used as a placeholder for disks whose backing file is missing.
+pub struct ZeroDiskSource {
+    size: u64,
+}
+
+impl ZeroDiskSource {
+    /// Create a zero-filled disk source of `size` bytes.
+    pub fn new(size: u64) -> Self {
+        Self { size }
+    }
+}
+
+impl DiskSource for ZeroDiskSource {
+    fn size(&self) -> u64 {
+        self.size
+    }
+
+    fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        let available = self.size.saturating_sub(offset);
+        Ok(vec![0u8; std::cmp::min(len as u64, available) as usize])
+    }
+
+    fn is_unallocated(&self, _offset: u64, _len: usize) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vmdk::SparseVmdkWriter;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    struct MemorySource(Vec<u8>);
+
+    impl DiskSource for MemorySource {
+        fn size(&self) -> u64 {
+            self.0.len() as u64
+        }
+
+        fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>> {
+            let start = std::cmp::min(offset as usize, self.0.len());
+            let end = std::cmp::min(start + len, self.0.len());
+            Ok(self.0[start..end].to_vec())
+        }
+    }
+
+    #[test]
+    fn test_vmdk_reader_read_at() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&(0..100u8).collect::<Vec<_>>()).unwrap();
+        file.flush().unwrap();
+
+        let reader = VmdkReader::open(file.path()).unwrap();
+        assert_eq!(DiskSource::size(&reader), 100);
+        assert_eq!(DiskSource::read_at(&reader, 10, 3).unwrap(), vec![10, 11, 12]);
+        assert_eq!(DiskSource::read_at(&reader, 98, 10).unwrap(), vec![98, 99]);
+        assert!(DiskSource::read_at(&reader, 200, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_concat_read_across_parts() {
+        let source = ConcatDiskSource::new(vec![
+            Box::new(MemorySource(vec![1, 2, 3])),
+            Box::new(MemorySource(vec![4, 5])),
+            Box::new(MemorySource(vec![6, 7, 8, 9])),
+        ]);
+
+        assert_eq!(source.size(), 9);
+        assert_eq!(source.read_at(0, 9).unwrap(), vec![1, 2, 3, 4, 5, 6, 7, 8, 9]);
+        assert_eq!(source.read_at(2, 4).unwrap(), vec![3, 4, 5, 6]);
+        assert_eq!(source.read_at(7, 10).unwrap(), vec![8, 9]);
+        assert!(source.read_at(9, 1).unwrap().is_empty());
+    }
+
+    /// Write a sparse extent whose every grain is filled with `fill`.
+    fn sparse_extent(capacity: u64, fill: u8) -> NamedTempFile {
+        let file = NamedTempFile::new().unwrap();
+        let mut writer = SparseVmdkWriter::new(file.reopen().unwrap(), capacity).unwrap();
+        let grain_size = writer.grain_size_bytes();
+        for offset in (0..capacity).step_by(grain_size as usize) {
+            let len = grain_size.min(capacity - offset) as usize;
+            writer.write_grain(offset / 512, &vec![fill; len]).unwrap();
+        }
+        writer.finish().unwrap();
+        file
+    }
+
+    #[test]
+    fn test_concat_split_sparse_extents() {
+        // The first extent's header declares 64 KiB more than its 1 MiB
+        // extent line; the disk must switch extents at the declared size
+        let first = sparse_extent(1024 * 1024 + 65536, 0x11);
+        let second = sparse_extent(1024 * 1024, 0x22);
+        let first = SparseVmdkReader::open(first.path()).unwrap();
+        let second = SparseVmdkReader::open(second.path()).unwrap();
+        let source = ConcatDiskSource::new(vec![
+            Box::new(TruncatedDiskSource::new(Box::new(first), 1024 * 1024)),
+            Box::new(second),
+        ]);
+
+        assert_eq!(source.size(), 2 * 1024 * 1024);
+        let mut expected = vec![0x11u8; 1000];
+        expected.extend_from_slice(&[0x22u8; 3000]);
+        assert_eq!(source.read_at(1024 * 1024 - 1000, 4000).unwrap(), expected);
+        assert!(!source.is_unallocated(1024 * 1024 - 1, 2));
+    }
+
+    #[test]
+    fn test_concat_unallocated() {
+        let source = ConcatDiskSource::new(vec![
+            Box::new(ZeroDiskSource::new(4)),
+            Box::new(MemorySource(vec![1, 2])),
+        ]);
+
+        assert!(source.is_unallocated(0, 4));
+        assert!(!source.is_unallocated(2, 4));
+        assert!(!source.is_unallocated(4, 2));
+    }
+
+    #[test]
+    fn test_truncated_source() {
+        let source = TruncatedDiskSource::new(Box::new(MemorySource(vec![1, 2, 3, 4, 5])), 3);
+        assert_eq!(source.size(), 3);
+        assert_eq!(source.read_at(1, 10).unwrap(), vec![2, 3]);
+        assert!(source.read_at(3, 2).unwrap().is_empty());
+
+        let source = TruncatedDiskSource::new(Box::new(MemorySource(vec![1, 2])), 8);
+        assert_eq!(source.size(), 2, "never larger than the inner source");
+    }
+
+    #[test]
+    fn test_padded_source() {
+        let source = PaddedDiskSource::new(Box::new(MemorySource(vec![1, 2, 3])), 6);
+        assert_eq!(source.size(), 6);
+        assert_eq!(source.read_at(1, 4).unwrap(), vec![2, 3, 0, 0]);
+        assert_eq!(source.read_at(4, 10).unwrap(), vec![0, 0]);
+        assert!(!source.is_unallocated(0, 6));
+        assert!(source.is_unallocated(3, 3));
+    }
+
+    #[test]
+    fn test_overlay_source() {
+        let source = OverlayDiskSource::new(
+            Box::new(MemorySource(vec![9; 8])),
+            vec![2..4, 6..7],
+            Box::new(MemorySource(vec![1, 2, 3, 4, 5])),
+        );
+
+        assert_eq!(source.size(), 8);
+        assert_eq!(source.read_at(0, 8).unwrap(), vec![1, 2, 9, 9, 5, 0, 9, 0]);
+        assert_eq!(source.read_at(3, 2).unwrap(), vec![9, 5]);
+        assert!(source.is_unallocated(7, 1), "padding past the lower source");
+        assert!(!source.is_unallocated(4, 4));
+    }
+
+    #[test]
+    fn test_zero_source() {
+        let source = ZeroDiskSource::new(10);
+        assert_eq!(source.size(), 10);
+        assert_eq!(source.read_at(8, 4).unwrap(), vec![0, 0]);
+        assert!(source.is_unallocated(0, 10));
+    }
+}
@@ -0,0 +1,144 @@
+//! Snapshot delta disks.
+//!
+//! Taking a snapshot redirects a VM's writes to a delta disk, which stores
+//! only the grains written since. The rest of its contents come from the
+//! parent named by the descriptor's `parentFileNameHint`, which may itself
+//! be a delta. This module locates and validates the parent of a delta so the
+//! two can be layered with an [`OverlayDiskSource`](crate::source::OverlayDiskSource).
+
+use crate::error::{Error, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::descriptor::{parse_descriptor, VmdkDescriptor};
+use super::sparse::{is_sparse_vmdk, SparseVmdkReader};
+
+/// Reads the descriptor of a VMDK.
+///
+/// Text descriptors are parsed directly; monolithic sparse files are parsed
+/// from their embedded descriptor.
+///
+/// # Arguments
+///
+/// * `path` - Path to the descriptor or monolithic sparse VMDK.
+///
+/// # Returns
+///
+/// A `Result` containing the descriptor, or `None` for a sparse file
+/// without an embedded descriptor.
+pub fn read_descriptor(path: &Path) -> Result<Option<VmdkDescriptor>> {
+    if is_sparse_vmdk(path)? {
+        return SparseVmdkReader::open(path)?
+            .embedded_descriptor()?
+            .map(|text| parse_descriptor(&text))
+            .transpose();
+    }
+
+    let content = fs::read_to_string(path).map_err(|e| Error::io(e, path))?;
+    parse_descriptor(&content).map(Some)
+}
+
+/// Locates the parent of a delta disk.
+///
+/// The `parentFileNameHint` is resolved relative to the delta's directory.
+/// Hints are often absolute paths from the host the VM was created on, so
+/// when the hinted file does not exist the parent is looked for by file name
+/// next to the delta. The parent's CID must match the delta's `parentCID`;
+/// a mismatch means the parent was modified after the snapshot was taken.
+///
+/// # Arguments
+///
+/// * `delta_path` - Path to the delta disk's descriptor.
+/// * `descriptor` - The delta disk's parsed descriptor.
+///
+/// # Returns
+///
+/// A `Result` containing the path to the parent descriptor.
+pub fn locate_parent(delta_path: &Path, descriptor: &VmdkDescriptor) -> Result<PathBuf> {
+    let hint = descriptor.parent_file_name_hint.as_deref().ok_or_else(|| {
+        Error::vmdk(format!(
+            "Delta disk '{}' has parentCID {:08x} but no parentFileNameHint",
+            delta_path.display(),
+            descriptor.parent_cid
+        ))
+    })?;
+
+    let delta_dir = delta_path.parent().unwrap_or_else(|| Path::new(""));
+    let hinted = delta_dir.join(hint);
+    let parent_path = if hinted.exists() {
+        hinted
+    } else {
+        // Windows hosts record backslash-separated paths
+        let file_name = hint.rsplit(['/', '\\']).next().unwrap_or(hint);
+        let nearby = delta_dir.join(file_name);
+        if !nearby.exists() {
+            return Err(Error::vmdk(format!(
+                "Parent disk '{}' of delta disk '{}' not found",
+                hint,
+                delta_path.display()
+            )));
+        }
+        nearby
+    };
+
+    if let Some(parent) = read_descriptor(&parent_path)? {
+        if parent.cid != descriptor.parent_cid {
+            return Err(Error::vmdk(format!(
+                "Parent disk '{}' has CID {:08x} but delta disk '{}' expects {:08x}; \
+                 the parent was modified after the snapshot was taken",
+                parent_path.display(),
+                parent.cid,
+                delta_path.display(),
+                descriptor.parent_cid
+            )));
+        }
+    }
+
+    Ok(parent_path)
+}
+
+/// Returns the probable base disk name of a snapshot delta or redo log,
+/// judging by its file name alone.
+///
+/// VMware names snapshot deltas after their base disk with a six-digit
+/// suffix (`disk-000001.vmdk`, also `-delta.vmdk` and `-sesparse.vmdk`
+/// extents), and redo logs of non-persistent disks `disk.vmdk.REDO_xxxxxx`.
+///
+/// # Arguments
+///
+/// * `file_name` - File name of the disk.
+///
+/// # Returns
+///
+/// The base disk's file name, or `None` if the name isn't a delta's.
+pub fn snapshot_base_name(file_name: &str) -> Option<String> {
+    if let Some(redo) = file_name.to_ascii_uppercase().find(".REDO") {
+        return Some(file_name[..redo].to_string());
+    }
+
+    let stem = file_name.strip_suffix(".vmdk")?;
+    let stem = stem
+        .strip_suffix("-delta")
+        .or_else(|| stem.strip_suffix("-sesparse"))
+        .unwrap_or(stem);
+    let (base, counter) = stem.rsplit_once('-')?;
+    (counter.len() == 6 && counter.bytes().all(|b| b.is_ascii_digit()) && !base.is_empty())
+        .then(|| format!("{}.vmdk", base))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_base_name() {
+        assert_eq!(snapshot_base_name("web-000001.vmdk").as_deref(), Some("web.vmdk"));
+        assert_eq!(snapshot_base_name("web-000012-delta.vmdk").as_deref(), Some("web.vmdk"));
+        assert_eq!(snapshot_base_name("web-000002-sesparse.vmdk").as_deref(), Some("web.vmdk"));
+        assert_eq!(snapshot_base_name("web.vmdk.REDO_a1b2c3").as_deref(), Some("web.vmdk"));
+        assert_eq!(snapshot_base_name("web.vmdk"), None);
+        assert_eq!(snapshot_base_name("web-flat.vmdk"), None);
+        assert_eq!(snapshot_base_name("web-s001.vmdk"), None);
+        assert_eq!(snapshot_base_name("-000001.vmdk"), None);
+    }
+}
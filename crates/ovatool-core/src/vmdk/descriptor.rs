@@ -40,6 +40,156 @@ impl ExtentType {
     }
 }
 
+/// Access mode of a VMDK extent, the first field of its extent line.
+///
+/// Ordered from least to most restrictive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ExtentAccess {
+    /// `RW`: the extent can be read and written.
+    ReadWrite,
+    /// `RDONLY`: the extent can only be read.
+    ReadOnly,
+    /// `NOACCESS`: the extent can't be accessed.
+    NoAccess,
+}
+
+impl ExtentAccess {
+    /// Parse an access mode such as `RDONLY`, case-insensitively.
+    pub fn parse(s: &str) -> Option<Self> {
+        [Self::ReadWrite, Self::ReadOnly, Self::NoAccess]
+            .into_iter()
+            .find(|access| access.as_str().eq_ignore_ascii_case(s))
+    }
+
+    /// Returns the access mode as written in extent lines, e.g. `RDONLY`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExtentAccess::ReadWrite => "RW",
+            ExtentAccess::ReadOnly => "RDONLY",
+            ExtentAccess::NoAccess => "NOACCESS",
+        }
+    }
+}
+
+impl std::fmt::Display for ExtentAccess {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The layout of a VMDK, from its descriptor's `createType`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CreateType {
+    /// A descriptor and a single flat extent (`monolithicFlat`).
+    MonolithicFlat,
+    /// A single hosted sparse file with an embedded descriptor
+    /// (`monolithicSparse`).
+    MonolithicSparse,
+    /// A descriptor and sparse extents of up to 2 GB each
+    /// (`twoGbMaxExtentSparse`).
+    TwoGbMaxExtentSparse,
+    /// A descriptor and flat extents of up to 2 GB each
+    /// (`twoGbMaxExtentFlat`).
+    TwoGbMaxExtentFlat,
+    /// A compressed, sequentially written sparse file (`streamOptimized`).
+    StreamOptimized,
+    /// A VMFS flat disk (`vmfs`).
+    Vmfs,
+    /// A thin-provisioned VMFS disk (`vmfsThin`).
+    VmfsThin,
+    /// A lazy-zeroed thick VMFS disk (`vmfsPreallocated`).
+    VmfsPreallocated,
+    /// An eager-zeroed thick VMFS disk (`vmfsEagerZeroedThick`).
+    VmfsEagerZeroedThick,
+    /// A VMFS snapshot delta disk (`vmfsSparse`).
+    VmfsSparse,
+    /// A raw VMFS disk (`vmfsRaw`).
+    VmfsRaw,
+    /// A virtual-mode raw device mapping (`vmfsRawDeviceMap`).
+    VmfsRawDeviceMap,
+    /// A physical-mode raw device mapping (`vmfsPassthroughRawDeviceMap`).
+    VmfsPassthroughRawDeviceMap,
+    /// A space-efficient sparse snapshot delta on ESXi (`seSparse`).
+    SeSparse,
+    /// Any other create type, as written in the descriptor.
+    Unknown(String),
+}
+
+impl CreateType {
+    /// Parse a create type, ignoring case as VMware does.
+    ///
+    /// # Arguments
+    ///
+    /// * `s` - The `createType` value.
+    ///
+    /// # Returns
+    ///
+    /// The matching variant, or [`CreateType::Unknown`] holding `s`.
+    pub fn parse(s: &str) -> Self {
+        match s.to_ascii_lowercase().as_str() {
+            "monolithicflat" => CreateType::MonolithicFlat,
+            "monolithicsparse" => CreateType::MonolithicSparse,
+            "twogbmaxextentsparse" => CreateType::TwoGbMaxExtentSparse,
+            "twogbmaxextentflat" => CreateType::TwoGbMaxExtentFlat,
+            "streamoptimized" => CreateType::StreamOptimized,
+            "vmfs" => CreateType::Vmfs,
+            "vmfsthin" => CreateType::VmfsThin,
+            "vmfspreallocated" => CreateType::VmfsPreallocated,
+            "vmfseagerzeroedthick" => CreateType::VmfsEagerZeroedThick,
+            "vmfssparse" => CreateType::VmfsSparse,
+            "vmfsraw" => CreateType::VmfsRaw,
+            "vmfsrawdevicemap" => CreateType::VmfsRawDeviceMap,
+            "vmfspassthroughrawdevicemap" => CreateType::VmfsPassthroughRawDeviceMap,
+            "sesparse" => CreateType::SeSparse,
+            _ => CreateType::Unknown(s.to_string()),
+        }
+    }
+
+    /// Returns the value VMware writes for this create type.
+    pub fn as_str(&self) -> &str {
+        match self {
+            CreateType::MonolithicFlat => "monolithicFlat",
+            CreateType::MonolithicSparse => "monolithicSparse",
+            CreateType::TwoGbMaxExtentSparse => "twoGbMaxExtentSparse",
+            CreateType::TwoGbMaxExtentFlat => "twoGbMaxExtentFlat",
+            CreateType::StreamOptimized => "streamOptimized",
+            CreateType::Vmfs => "vmfs",
+            CreateType::VmfsThin => "vmfsThin",
+            CreateType::VmfsPreallocated => "vmfsPreallocated",
+            CreateType::VmfsEagerZeroedThick => "vmfsEagerZeroedThick",
+            CreateType::VmfsSparse => "vmfsSparse",
+            CreateType::VmfsRaw => "vmfsRaw",
+            CreateType::VmfsRawDeviceMap => "vmfsRawDeviceMap",
+            CreateType::VmfsPassthroughRawDeviceMap => "vmfsPassthroughRawDeviceMap",
+            CreateType::SeSparse => "seSparse",
+            CreateType::Unknown(s) => s,
+        }
+    }
+
+    /// Returns true for the `vmfs*` create types, which can only be read on
+    /// an ESXi datastore.
+    pub fn is_vmfs(&self) -> bool {
+        match self {
+            CreateType::Vmfs
+            | CreateType::VmfsThin
+            | CreateType::VmfsPreallocated
+            | CreateType::VmfsEagerZeroedThick
+            | CreateType::VmfsSparse
+            | CreateType::VmfsRaw
+            | CreateType::VmfsRawDeviceMap
+            | CreateType::VmfsPassthroughRawDeviceMap => true,
+            CreateType::Unknown(s) => s.to_ascii_lowercase().starts_with("vmfs"),
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for CreateType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 /// A VMDK extent entry describing a portion of the disk.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Extent {
@@ -55,6 +205,19 @@ pub struct Extent {
     pub offset: u64,
 }
 
+impl Extent {
+    /// Returns the extent's access mode; an unrecognized one is treated as
+    /// [`ExtentAccess::NoAccess`].
+    pub fn access_mode(&self) -> ExtentAccess {
+        ExtentAccess::parse(&self.access).unwrap_or(ExtentAccess::NoAccess)
+    }
+
+    /// Returns true if the extent is read-only (`RDONLY` access).
+    pub fn is_read_only(&self) -> bool {
+        self.access_mode() == ExtentAccess::ReadOnly
+    }
+}
+
 /// Parsed VMDK descriptor containing disk metadata.
 #[derive(Debug, Clone)]
 pub struct VmdkDescriptor {
@@ -64,8 +227,10 @@ pub struct VmdkDescriptor {
     pub cid: u32,
     /// Parent content ID for delta disks.
     pub parent_cid: u32,
-    /// The type of VMDK (e.g., "monolithicFlat", "twoGbMaxExtentSparse").
-    pub create_type: String,
+    /// Path to the parent disk's descriptor, set only on delta disks.
+    pub parent_file_name_hint: Option<String>,
+    /// The type of VMDK (e.g., `monolithicFlat`, `twoGbMaxExtentSparse`).
+    pub create_type: CreateType,
     /// List of extent entries.
     pub extents: Vec<Extent>,
     /// Disk geometry: cylinders.
@@ -78,9 +243,31 @@ pub struct VmdkDescriptor {
     pub hw_version: String,
     /// Disk adapter type (e.g., "lsilogic", "ide", "buslogic").
     pub adapter_type: String,
+    /// Whether VMware may delete the disk along with the VM, from
+    /// `ddb.deletable` (default true). Template base disks shared by linked
+    /// clones are marked `false`.
+    pub deletable: bool,
+    /// Problems that were tolerated while parsing, such as a malformed CID
+    /// on a base disk.
+    pub warnings: Vec<String>,
 }
 
 impl VmdkDescriptor {
+    /// Returns true if this is a delta disk that depends on a parent disk.
+    pub fn is_delta(&self) -> bool {
+        self.parent_file_name_hint.is_some() || self.parent_cid != NO_PARENT_CID
+    }
+
+    /// Returns the most restrictive access mode of the disk's extents, or
+    /// [`ExtentAccess::ReadWrite`] if it has none.
+    pub fn access_mode(&self) -> ExtentAccess {
+        self.extents
+            .iter()
+            .map(Extent::access_mode)
+            .max()
+            .unwrap_or(ExtentAccess::ReadWrite)
+    }
+
     /// Calculate the total disk size in bytes.
     pub fn disk_size_bytes(&self) -> u64 {
         self.disk_size_sectors() * 512
@@ -90,10 +277,49 @@ impl VmdkDescriptor {
     pub fn disk_size_sectors(&self) -> u64 {
         self.extents.iter().map(|e| e.size_sectors).sum()
     }
+
+    /// Explain how to make a VMFS-only disk exportable.
+    ///
+    /// Disks with a `vmfs*` create type live on an ESXi datastore and must be
+    /// cloned to a hosted format before they can be exported.
+    ///
+    /// # Returns
+    ///
+    /// A message naming the create type, or `None` for non-VMFS disks.
+    pub fn vmfs_clone_guidance(&self) -> Option<String> {
+        if !self.create_type.is_vmfs() {
+            return None;
+        }
+
+        let kind = match self.create_type {
+            CreateType::Vmfs => "a VMFS flat disk",
+            CreateType::VmfsThin => "a thin-provisioned VMFS disk",
+            CreateType::VmfsPreallocated => "a thick-provisioned (lazy-zeroed) VMFS disk",
+            CreateType::VmfsEagerZeroedThick => "a thick-provisioned (eager-zeroed) VMFS disk",
+            CreateType::VmfsSparse => "a VMFS snapshot delta disk",
+            CreateType::VmfsRaw => "a raw VMFS disk",
+            CreateType::VmfsRawDeviceMap => "a virtual-mode raw device mapping",
+            CreateType::VmfsPassthroughRawDeviceMap => "a physical-mode raw device mapping",
+            _ => "a VMFS disk",
+        };
+
+        Some(format!(
+            "createType '{}' is {} that can only be read on an ESXi datastore; \
+             clone it to a hosted format first (e.g. `vmkfstools -i <disk>.vmdk -d 2gbsparse <copy>.vmdk`)",
+            self.create_type, kind
+        ))
+    }
 }
 
+/// `parentCID` value of a base disk, which has no parent.
+const NO_PARENT_CID: u32 = 0xffffffff;
+
 /// Parse a VMDK descriptor from its text content.
 ///
+/// A malformed `CID` or `parentCID` only matters for delta disks, whose
+/// parent linkage depends on them. On a base disk it is recorded in
+/// [`VmdkDescriptor::warnings`] and the default is used instead.
+///
 /// # Arguments
 ///
 /// * `content` - The text content of the VMDK descriptor file.
@@ -107,15 +333,20 @@ impl VmdkDescriptor {
 /// Returns an error if the descriptor format is invalid or required fields are missing.
 pub fn parse_descriptor(content: &str) -> Result<VmdkDescriptor> {
     let mut version = 1;
-    let mut cid = 0u32;
-    let mut parent_cid = 0xffffffffu32;
-    let mut create_type = String::new();
+    let mut cid_value = None;
+    let mut parent_cid_value = None;
+    let mut parent_file_name_hint = None;
+    let mut create_type = CreateType::Unknown(String::new());
     let mut extents = Vec::new();
     let mut cylinders = 0u64;
     let mut heads = 0u32;
     let mut sectors = 0u32;
     let mut hw_version = String::new();
     let mut adapter_type = String::new();
+    let mut deletable = true;
+
+    // Some tools prepend a UTF-8 byte order mark, which `trim` doesn't remove
+    let content = content.strip_prefix('\u{FEFF}').unwrap_or(content);
 
     for line in content.lines() {
         let line = line.trim();
@@ -141,15 +372,16 @@ pub fn parse_descriptor(content: &str) -> Result<VmdkDescriptor> {
                         .map_err(|_| Error::vmdk(format!("invalid version: {}", value)))?;
                 }
                 "CID" => {
-                    cid = u32::from_str_radix(&value, 16)
-                        .map_err(|_| Error::vmdk(format!("invalid CID: {}", value)))?;
+                    cid_value = Some(value);
                 }
                 "parentCID" => {
-                    parent_cid = u32::from_str_radix(&value, 16)
-                        .map_err(|_| Error::vmdk(format!("invalid parentCID: {}", value)))?;
+                    parent_cid_value = Some(value);
+                }
+                "parentFileNameHint" => {
+                    parent_file_name_hint = Some(value);
                 }
                 "createType" => {
-                    create_type = value;
+                    create_type = CreateType::parse(&value);
                 }
                 "ddb.virtualHWVersion" => {
                     hw_version = value;
@@ -172,6 +404,9 @@ pub fn parse_descriptor(content: &str) -> Result<VmdkDescriptor> {
                 "ddb.adapterType" => {
                     adapter_type = value;
                 }
+                "ddb.deletable" => {
+                    deletable = !value.eq_ignore_ascii_case("false");
+                }
                 _ => {
                     // Ignore unknown keys
                 }
@@ -179,10 +414,28 @@ pub fn parse_descriptor(content: &str) -> Result<VmdkDescriptor> {
         }
     }
 
+    // CIDs are checked once the whole descriptor is read, since whether a
+    // malformed one is fatal depends on the parent linkage
+    let is_delta = parent_file_name_hint.is_some()
+        || parent_cid_value
+            .as_deref()
+            .and_then(parse_cid)
+            .is_some_and(|parent_cid| parent_cid != NO_PARENT_CID);
+    let mut warnings = Vec::new();
+    let cid = resolve_cid("CID", cid_value.as_deref(), 0, is_delta, &mut warnings)?;
+    let parent_cid = resolve_cid(
+        "parentCID",
+        parent_cid_value.as_deref(),
+        NO_PARENT_CID,
+        is_delta,
+        &mut warnings,
+    )?;
+
     Ok(VmdkDescriptor {
         version,
         cid,
         parent_cid,
+        parent_file_name_hint,
         create_type,
         extents,
         cylinders,
@@ -190,9 +443,48 @@ pub fn parse_descriptor(content: &str) -> Result<VmdkDescriptor> {
         sectors,
         hw_version,
         adapter_type,
+        deletable,
+        warnings,
     })
 }
 
+/// Parse a hexadecimal content ID.
+fn parse_cid(value: &str) -> Option<u32> {
+    u32::from_str_radix(value, 16).ok()
+}
+
+/// Resolve a CID field, tolerating a malformed value on base disks.
+///
+/// # Arguments
+///
+/// * `key` - The descriptor key, for messages.
+/// * `value` - The raw value, or `None` if the key is absent.
+/// * `default` - The value used when the key is absent or tolerated.
+/// * `is_delta` - Whether the disk depends on a parent.
+/// * `warnings` - Receives a warning when a malformed value is tolerated.
+fn resolve_cid(
+    key: &str,
+    value: Option<&str>,
+    default: u32,
+    is_delta: bool,
+    warnings: &mut Vec<String>,
+) -> Result<u32> {
+    let Some(value) = value else {
+        return Ok(default);
+    };
+    match parse_cid(value) {
+        Some(cid) => Ok(cid),
+        None if is_delta => Err(Error::vmdk(format!("invalid {}: {}", key, value))),
+        None => {
+            warnings.push(format!(
+                "ignoring invalid {} '{}' on a base disk; using {:08x}",
+                key, value, default
+            ));
+            Ok(default)
+        }
+    }
+}
+
 /// Parse a key=value or key = value line.
 ///
 /// Returns None if the line doesn't contain an equals sign.
@@ -388,7 +680,8 @@ mod tests {
             version: 1,
             cid: 0,
             parent_cid: 0xffffffff,
-            create_type: "test".to_string(),
+            parent_file_name_hint: None,
+            create_type: CreateType::Unknown("test".to_string()),
             extents: vec![
                 Extent {
                     access: "RW".to_string(),
@@ -410,6 +703,8 @@ mod tests {
             sectors: 0,
             hw_version: String::new(),
             adapter_type: String::new(),
+            deletable: true,
+            warnings: Vec::new(),
         };
 
         assert_eq!(descriptor.disk_size_sectors(), 3000);
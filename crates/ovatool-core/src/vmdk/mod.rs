@@ -1,17 +1,33 @@
 //! VMDK disk handling.
 //!
 //! This module provides functionality for reading and processing VMDK files,
-//! including sparse disk formats and stream-optimized conversion.
+//! including sparse disk formats, snapshot deltas, stream-optimized and hosted
+//! sparse output, reading and verification.
 
+pub mod delta;
 pub mod descriptor;
 pub mod reader;
 pub mod sparse;
+pub mod sparse_writer;
 pub mod stream;
+pub mod stream_reader;
+pub mod verify;
 
-pub use descriptor::{parse_descriptor, Extent, ExtentType, VmdkDescriptor};
-pub use reader::{ChunkIterator, IndexedChunk, IndexedChunkIterator, VmdkReader};
+pub use delta::{locate_parent, read_descriptor, snapshot_base_name};
+pub use descriptor::{
+    parse_descriptor, CreateType, Extent, ExtentAccess, ExtentType, VmdkDescriptor,
+};
+pub use reader::{
+    ChunkIterator, IndexedChunk, IndexedChunkIterator, VmdkReader, WindowedChunkIterator,
+    WindowedVmdkReader,
+};
 pub use sparse::{is_sparse_vmdk, SparseChunkIterator, SparseVmdkReader};
+pub use sparse_writer::SparseVmdkWriter;
 pub use stream::{
-    compress_grain, GrainMarker, Marker, MarkerType, SparseExtentHeader, StreamVmdkWriter,
+    check_grain_size, compress_grain, compress_grains, is_zero_grain, is_zero_scalar,
+    CompressionAlgorithm, GrainMarker, Marker, MarkerType,
+    SparseExtentHeader, StreamVmdkWriter,
     DEFAULT_GRAIN_SIZE, GT_ENTRIES_PER_GT, SECTOR_SIZE, VMDK_MAGIC,
 };
+pub use stream_reader::{DecompressedGrain, StreamGrainIterator, StreamOptimizedReader};
+pub use verify::{verify_stream_vmdk, StreamVerifyReport};
@@ -4,10 +4,10 @@
 //! with support for chunked iteration suitable for parallel processing.
 
 use crate::error::{Error, Result};
-use memmap2::Mmap;
+use memmap2::{Mmap, MmapOptions};
 use std::fs::File;
-use std::path::Path;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
 /// A memory-mapped VMDK file reader.
 ///
@@ -86,6 +86,26 @@ impl VmdkReader {
         self.size
     }
 
+    /// Advises the kernel that the file will be read sequentially.
+    ///
+    /// This lets the kernel read ahead more aggressively and drop pages
+    /// behind the reader, which helps on spinning disks. It is only a hint:
+    /// on platforms other than Unix it does nothing.
+    ///
+    /// # Returns
+    ///
+    /// An error if the kernel rejects the advice.
+    pub fn advise_sequential(&self) -> Result<()> {
+        // Nothing is mapped for an empty file
+        #[cfg(unix)]
+        if self.size > 0 {
+            self.mmap
+                .advise(memmap2::Advice::Sequential)
+                .map_err(Error::io_simple)?;
+        }
+        Ok(())
+    }
+
     /// Returns a reference to the raw memory-mapped data.
     ///
     /// This provides direct access to the file contents for cases where
@@ -272,6 +292,170 @@ impl Iterator for IndexedChunkIterator {
     }
 }
 
+/// A VMDK file reader that maps only a sliding window of the file.
+///
+/// Where [`VmdkReader`] maps the whole file up front, this reader keeps a
+/// single mapping of at most `window_size` bytes and moves it forward as
+/// reads progress. Resident memory stays bounded by the window size, which
+/// matters for very large flat extents on memory-constrained hosts.
+///
+/// Reads are cheapest when they are sequential, as in chunked iteration; a
+/// read outside the current window remaps it.
+pub struct WindowedVmdkReader {
+    file: File,
+    path: PathBuf,
+    size: u64,
+    window_size: usize,
+    /// The currently mapped region, if any.
+    window: Mutex<Option<MappedWindow>>,
+}
+
+/// A mapped region of a file, starting at byte `start`.
+struct MappedWindow {
+    start: u64,
+    mmap: Mmap,
+}
+
+impl MappedWindow {
+    /// Returns `true` if the window holds `start..end`.
+    fn covers(&self, start: u64, end: u64) -> bool {
+        start >= self.start && end <= self.start + self.mmap.len() as u64
+    }
+}
+
+impl WindowedVmdkReader {
+    /// Opens a VMDK file for windowed reading.
+    ///
+    /// Nothing is mapped until the first read.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the VMDK file to open.
+    /// * `window_size` - Maximum number of bytes mapped at once. A single read
+    ///   larger than this maps just enough to satisfy it.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `WindowedVmdkReader` on success, or an error
+    /// if the window size is zero or the file cannot be opened.
+    pub fn open(path: &Path, window_size: usize) -> Result<Self> {
+        if window_size == 0 {
+            return Err(Error::validation("Memory-map window size must be greater than 0"));
+        }
+
+        let file = File::open(path).map_err(|e| Error::io(e, path))?;
+        let size = file.metadata().map_err(|e| Error::io(e, path))?.len();
+
+        Ok(Self {
+            file,
+            path: path.to_path_buf(),
+            size,
+            window_size,
+            window: Mutex::new(None),
+        })
+    }
+
+    /// Returns the size of the VMDK file in bytes.
+    #[inline]
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns the maximum number of bytes mapped at once.
+    #[inline]
+    pub fn window_size(&self) -> usize {
+        self.window_size
+    }
+
+    /// Reads a range of the file, remapping the window if needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - Byte offset into the file.
+    /// * `len` - Number of bytes to read.
+    ///
+    /// # Returns
+    ///
+    /// The data at the requested range, truncated at the end of the file.
+    pub fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        let start = std::cmp::min(offset, self.size);
+        let end = std::cmp::min(start.saturating_add(len as u64), self.size);
+        if start == end {
+            return Ok(Vec::new());
+        }
+
+        let mut window = self.window.lock().unwrap();
+        if !window.as_ref().is_some_and(|w| w.covers(start, end)) {
+            // Drop the old mapping before creating the new one
+            *window = None;
+            let map_len = std::cmp::max(self.window_size as u64, end - start);
+            let map_len = std::cmp::min(map_len, self.size - start) as usize;
+
+            // Safety: We're mapping a read-only file that we hold open.
+            let mmap = unsafe {
+                MmapOptions::new()
+                    .offset(start)
+                    .len(map_len)
+                    .map(&self.file)
+                    .map_err(|e| Error::io(e, &self.path))?
+            };
+            *window = Some(MappedWindow { start, mmap });
+        }
+
+        let window = window.as_ref().unwrap();
+        let from = (start - window.start) as usize;
+        let to = (end - window.start) as usize;
+        Ok(window.mmap[from..to].to_vec())
+    }
+
+    /// Creates an iterator that yields chunks of the file data.
+    ///
+    /// The window advances through the file as the iterator progresses.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk_size` - The size of each chunk in bytes. The last chunk
+    ///   may be smaller if the file size is not evenly divisible.
+    ///
+    /// # Returns
+    ///
+    /// A `WindowedChunkIterator` that yields `Result<Vec<u8>>` for each chunk.
+    pub fn chunks(&self, chunk_size: usize) -> WindowedChunkIterator<'_> {
+        WindowedChunkIterator {
+            reader: self,
+            chunk_size,
+            current_offset: 0,
+        }
+    }
+}
+
+/// An iterator over chunks of a [`WindowedVmdkReader`].
+pub struct WindowedChunkIterator<'a> {
+    reader: &'a WindowedVmdkReader,
+    chunk_size: usize,
+    current_offset: u64,
+}
+
+impl Iterator for WindowedChunkIterator<'_> {
+    type Item = Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current_offset >= self.reader.size {
+            return None;
+        }
+
+        let chunk = self.reader.read_at(self.current_offset, self.chunk_size);
+        if let Ok(data) = &chunk {
+            self.current_offset += data.len() as u64;
+        } else {
+            // Stop after reporting the error
+            self.current_offset = self.reader.size;
+        }
+
+        Some(chunk)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -326,4 +510,52 @@ mod tests {
         assert!(!chunks[0].is_last);
         assert!(chunks[1].is_last);
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_advise_sequential() {
+        let file = create_test_file(10_000);
+        let reader = VmdkReader::open(file.path()).unwrap();
+        reader.advise_sequential().unwrap();
+
+        let data: Vec<u8> = reader.chunks(4096).flat_map(|c| c.unwrap()).collect();
+        let expected: Vec<u8> = (0u8..=255).cycle().take(10_000).collect();
+        assert_eq!(data, expected);
+
+        // Empty files have nothing to advise
+        let empty = create_test_file(0);
+        VmdkReader::open(empty.path()).unwrap().advise_sequential().unwrap();
+    }
+
+    #[test]
+    fn test_windowed_chunks_match_full_mmap() {
+        // 10000 bytes with a 4096-byte window spans three windows
+        let file = create_test_file(10_000);
+        let full = VmdkReader::open(file.path()).unwrap();
+        let windowed = WindowedVmdkReader::open(file.path(), 4096).unwrap();
+        assert_eq!(windowed.size(), full.size());
+
+        for chunk_size in [1000, 4096, 5000] {
+            let expected: Vec<Vec<u8>> = full.chunks(chunk_size).map(|c| c.unwrap()).collect();
+            let actual: Vec<Vec<u8>> = windowed.chunks(chunk_size).map(|c| c.unwrap()).collect();
+            assert_eq!(actual, expected, "chunk size {}", chunk_size);
+        }
+    }
+
+    #[test]
+    fn test_windowed_read_outside_window() {
+        let file = create_test_file(10_000);
+        let reader = WindowedVmdkReader::open(file.path(), 1024).unwrap();
+
+        assert_eq!(reader.read_at(9000, 3).unwrap(), vec![40, 41, 42]);
+        assert_eq!(reader.read_at(0, 2).unwrap(), vec![0, 1]);
+        assert_eq!(reader.read_at(9998, 10).unwrap(), vec![14, 15]);
+        assert!(reader.read_at(20_000, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_windowed_rejects_zero_window() {
+        let file = create_test_file(16);
+        assert!(WindowedVmdkReader::open(file.path(), 0).is_err());
+    }
 }
@@ -6,28 +6,29 @@
 use crate::error::{Error, Result};
 use memmap2::Mmap;
 use std::fs::File;
+use std::ops::Range;
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 
-use super::stream::{SECTOR_SIZE, VMDK_MAGIC};
+use super::stream::{GD_AT_END, SECTOR_SIZE, VMDK_MAGIC};
 
 /// Flags in sparse VMDK header.
-const FLAG_VALID_NEWLINE: u32 = 1 << 0;
-const FLAG_REDUNDANT_GRAIN_TABLE: u32 = 1 << 1;
 const FLAG_COMPRESSED: u32 = 1 << 16;
 const FLAG_MARKERS: u32 = 1 << 17;
 
 /// A reader for sparse VMDK files.
 ///
 /// This reader handles hosted sparse VMDKs (monolithicSparse, twoGbMaxExtentSparse)
-/// which store data in grain tables with optional compression.
+/// which store data in grain tables with optional compression, as well as
+/// streamOptimized VMDKs whose grain directory is found through the footer.
 pub struct SparseVmdkReader {
     /// Memory-mapped file data.
     mmap: Arc<Mmap>,
     /// Parsed header.
     header: SparseHeader,
-    /// Grain directory entries (offsets to grain tables in sectors).
-    grain_directory: Vec<u32>,
+    /// Grain directory entries (offsets to grain tables in sectors), loaded
+    /// on first use; holds the error message if the directory is invalid.
+    grain_directory: OnceLock<std::result::Result<Vec<u32>, String>>,
     /// Total virtual disk size in bytes.
     capacity_bytes: u64,
 }
@@ -104,14 +105,100 @@ impl SparseHeader {
 
     /// Calculate the number of grain directory entries.
     fn num_gd_entries(&self) -> u64 {
-        let grains_total = (self.capacity + self.grain_size - 1) / self.grain_size;
-        (grains_total + self.num_gtes_per_gt as u64 - 1) / self.num_gtes_per_gt as u64
+        let grains_total = self.capacity.div_ceil(self.grain_size);
+        grains_total.div_ceil(self.num_gtes_per_gt as u64)
     }
 }
 
+/// Read the footer of a streamOptimized VMDK.
+///
+/// The file ends with a footer marker, the footer (a copy of the header with
+/// the real grain directory offset) and an end-of-stream marker, one sector each.
+fn read_footer(data: &[u8]) -> Result<SparseHeader> {
+    let footer_start = data
+        .len()
+        .checked_sub(2 * SECTOR_SIZE as usize)
+        .filter(|&start| start >= SECTOR_SIZE as usize)
+        .ok_or_else(|| Error::vmdk("StreamOptimized VMDK is too short to hold a footer"))?;
+    let footer = SparseHeader::from_bytes(&data[footer_start..])
+        .map_err(|e| Error::vmdk(format!("Invalid streamOptimized footer: {}", e)))?;
+    if footer.gd_offset == GD_AT_END {
+        return Err(Error::vmdk("StreamOptimized footer has no grain directory offset"));
+    }
+    Ok(footer)
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Grain directories this thread has read, to check when they are loaded.
+    static GRAIN_DIRECTORY_READS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// Read the grain directory, checking that it and every grain table it
+/// references lie within the file.
+///
+/// Errors are returned as messages so they can be cached with the reader.
+fn load_grain_directory(
+    data: &[u8],
+    header: &SparseHeader,
+) -> std::result::Result<Vec<u32>, String> {
+    #[cfg(test)]
+    GRAIN_DIRECTORY_READS.with(|reads| reads.set(reads.get() + 1));
+
+    let num_gd_entries = header.num_gd_entries();
+    let gd_end = header
+        .gd_offset
+        .checked_mul(SECTOR_SIZE)
+        .and_then(|start| num_gd_entries.checked_mul(4).and_then(|len| start.checked_add(len)));
+    if gd_end.is_none_or(|end| end > data.len() as u64) {
+        return Err("Grain directory extends beyond file".to_string());
+    }
+
+    // Both values are bounded by the file length checked above
+    let gd_offset_bytes = (header.gd_offset * SECTOR_SIZE) as usize;
+    let num_gd_entries = num_gd_entries as usize;
+
+    let mut grain_directory = Vec::with_capacity(num_gd_entries);
+    for i in 0..num_gd_entries {
+        let offset = gd_offset_bytes + i * 4;
+        let entry = u32::from_le_bytes([
+            data[offset],
+            data[offset + 1],
+            data[offset + 2],
+            data[offset + 3],
+        ]);
+        grain_directory.push(entry);
+    }
+
+    // Every allocated grain table must lie fully within the file
+    let gt_size_bytes = header.num_gtes_per_gt as u64 * 4;
+    for (gd_index, &gt_offset_sectors) in grain_directory.iter().enumerate() {
+        if gt_offset_sectors == 0 {
+            continue;
+        }
+        let gt_end = gt_offset_sectors as u64 * SECTOR_SIZE + gt_size_bytes;
+        if gt_end > data.len() as u64 {
+            return Err(format!(
+                "Grain directory entry {} points to a grain table at sector {} \
+                 that extends beyond the end of the file ({} bytes)",
+                gd_index,
+                gt_offset_sectors,
+                data.len()
+            ));
+        }
+    }
+
+    Ok(grain_directory)
+}
+
 impl SparseVmdkReader {
     /// Opens a sparse VMDK file and creates a reader.
     ///
+    /// Only the header is read. The grain directory is loaded and checked
+    /// when grain data is first needed, so querying the capacity or the
+    /// embedded descriptor stays fast; use [`open_full`](Self::open_full) to
+    /// validate the whole layout up front.
+    ///
     /// # Arguments
     ///
     /// * `path` - Path to the sparse VMDK file.
@@ -128,55 +215,134 @@ impl SparseVmdkReader {
 
         // Validate version
         if header.version > 3 {
-            return Err(Error::vmdk(format!(
+            return Err(Error::unsupported(format!(
                 "Unsupported sparse VMDK version: {}",
                 header.version
             )));
         }
 
-        // StreamOptimized VMDKs with markers need special handling
-        if header.has_markers() {
-            return Err(Error::vmdk(
-                "StreamOptimized VMDKs with markers are not supported for reading. \
-                 Please use a flat or hosted sparse VMDK.",
-            ));
-        }
-
-        // Read grain directory
-        let gd_offset_bytes = header.gd_offset * SECTOR_SIZE;
-        let num_gd_entries = header.num_gd_entries() as usize;
-
-        if gd_offset_bytes as usize + num_gd_entries * 4 > mmap.len() {
-            return Err(Error::vmdk("Grain directory extends beyond file"));
-        }
-
-        let mut grain_directory = Vec::with_capacity(num_gd_entries);
-        for i in 0..num_gd_entries {
-            let offset = gd_offset_bytes as usize + i * 4;
-            let entry = u32::from_le_bytes([
-                mmap[offset],
-                mmap[offset + 1],
-                mmap[offset + 2],
-                mmap[offset + 3],
-            ]);
-            grain_directory.push(entry);
+        // Reject geometry that would make grain addressing meaningless
+        if header.grain_size == 0 || header.num_gtes_per_gt == 0 {
+            return Err(Error::vmdk(format!(
+                "Invalid sparse VMDK geometry: grain size {} sectors, {} entries per grain table",
+                header.grain_size, header.num_gtes_per_gt
+            )));
         }
 
-        let capacity_bytes = header.capacity * SECTOR_SIZE;
+        // StreamOptimized VMDKs record the grain directory in the footer
+        let header = if header.has_markers() && header.gd_offset == GD_AT_END {
+            read_footer(&mmap)?
+        } else {
+            header
+        };
+
+        // Header fields are untrusted; reject values whose byte sizes overflow
+        let capacity_bytes = header
+            .capacity
+            .checked_mul(SECTOR_SIZE)
+            .ok_or_else(|| {
+                Error::vmdk(format!(
+                    "Sparse VMDK capacity of {} sectors overflows",
+                    header.capacity
+                ))
+            })?;
+        header
+            .grain_size
+            .checked_mul(SECTOR_SIZE)
+            .and_then(|bytes| usize::try_from(bytes).ok())
+            .ok_or_else(|| {
+                Error::vmdk(format!(
+                    "Sparse VMDK grain size of {} sectors overflows",
+                    header.grain_size
+                ))
+            })?;
 
         Ok(Self {
             mmap: Arc::new(mmap),
             header,
-            grain_directory,
+            grain_directory: OnceLock::new(),
             capacity_bytes,
         })
     }
 
+    /// Opens a sparse VMDK file, loading and checking its grain directory.
+    ///
+    /// Unlike [`open`](Self::open), a grain directory or grain table lying
+    /// outside the file is reported here rather than on the first read.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the sparse VMDK file.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `SparseVmdkReader` on success.
+    pub fn open_full(path: &Path) -> Result<Self> {
+        let reader = Self::open(path)?;
+        reader.grain_directory()?;
+        Ok(reader)
+    }
+
+    /// Returns the grain directory, loading it on first use.
+    fn grain_directory(&self) -> Result<&[u32]> {
+        self.grain_directory
+            .get_or_init(|| load_grain_directory(&self.mmap, &self.header))
+            .as_deref()
+            .map_err(|message| Error::vmdk(message.clone()))
+    }
+
     /// Returns the virtual disk capacity in bytes.
     pub fn capacity(&self) -> u64 {
         self.capacity_bytes
     }
 
+    /// Returns the descriptor embedded in the file, if it has one.
+    ///
+    /// Monolithic sparse VMDKs carry their descriptor text inside the file,
+    /// padded with NUL bytes to a whole number of sectors.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the descriptor text, or `None` if the header
+    /// does not reference one.
+    pub fn embedded_descriptor(&self) -> Result<Option<String>> {
+        if self.header.descriptor_offset == 0 || self.header.descriptor_size == 0 {
+            return Ok(None);
+        }
+
+        let range = self
+            .header
+            .descriptor_offset
+            .checked_mul(SECTOR_SIZE)
+            .and_then(|start| {
+                let len = self.header.descriptor_size.checked_mul(SECTOR_SIZE)?;
+                Some(start..start.checked_add(len)?)
+            })
+            .filter(|range| range.end <= self.mmap.len() as u64)
+            .ok_or_else(|| Error::vmdk("Embedded descriptor extends beyond file"))?;
+
+        let text = &self.mmap[range.start as usize..range.end as usize];
+        let text = text.split(|&b| b == 0).next().unwrap_or_default();
+        Ok(Some(String::from_utf8_lossy(text).into_owned()))
+    }
+
+    /// Advises the kernel that the file will be read sequentially.
+    ///
+    /// This lets the kernel read ahead more aggressively and drop pages
+    /// behind the reader, which helps on spinning disks. It is only a hint:
+    /// on platforms other than Unix it does nothing.
+    ///
+    /// # Returns
+    ///
+    /// An error if the kernel rejects the advice.
+    pub fn advise_sequential(&self) -> Result<()> {
+        #[cfg(unix)]
+        self.mmap
+            .advise(memmap2::Advice::Sequential)
+            .map_err(Error::io_simple)?;
+        Ok(())
+    }
+
     /// Returns the grain size in bytes.
     pub fn grain_size_bytes(&self) -> u64 {
         self.header.grain_size * SECTOR_SIZE
@@ -194,57 +360,132 @@ impl SparseVmdkReader {
         let gte_index = grain_index % gtes_per_gt;
 
         // Get grain table offset from grain directory
-        if gt_index >= self.grain_directory.len() as u64 {
+        let grain_directory = self.grain_directory()?;
+        if gt_index >= grain_directory.len() as u64 {
             // Beyond grain directory - return zeros
             return Ok(vec![0u8; grain_size_bytes]);
         }
 
-        let gt_offset_sectors = self.grain_directory[gt_index as usize];
+        let gt_offset_sectors = grain_directory[gt_index as usize];
         if gt_offset_sectors == 0 {
             // Grain table not allocated - return zeros
             return Ok(vec![0u8; grain_size_bytes]);
         }
 
-        // Read grain table entry
-        let gt_offset_bytes = gt_offset_sectors as u64 * SECTOR_SIZE;
-        let gte_offset = gt_offset_bytes as usize + gte_index as usize * 4;
-
-        if gte_offset + 4 > self.mmap.len() {
-            return Err(Error::vmdk("Grain table entry extends beyond file"));
-        }
-
-        let grain_offset_sectors = u32::from_le_bytes([
-            self.mmap[gte_offset],
-            self.mmap[gte_offset + 1],
-            self.mmap[gte_offset + 2],
-            self.mmap[gte_offset + 3],
-        ]);
-
+        let grain_offset_sectors = self.grain_table_entry(gt_offset_sectors, gte_index)?;
         if grain_offset_sectors == 0 {
             // Grain not allocated - return zeros
             return Ok(vec![0u8; grain_size_bytes]);
         }
 
         // Read grain data
-        let grain_offset_bytes = grain_offset_sectors as u64 * SECTOR_SIZE;
+        let grain_offset_bytes = u64::from(grain_offset_sectors) * SECTOR_SIZE;
+        if grain_offset_bytes >= self.mmap.len() as u64 {
+            return Err(Error::vmdk("Grain extends beyond file"));
+        }
+        let grain_offset_bytes = grain_offset_bytes as usize;
 
         if self.header.is_compressed() {
             // Compressed grain - need to decompress
-            self.read_compressed_grain(grain_offset_bytes as usize, grain_size_bytes)
+            self.read_compressed_grain(grain_offset_bytes, grain_size_bytes)
         } else {
             // Uncompressed grain - direct read
-            let end = grain_offset_bytes as usize + grain_size_bytes;
-            if end > self.mmap.len() {
-                return Err(Error::vmdk("Grain extends beyond file"));
-            }
-            Ok(self.mmap[grain_offset_bytes as usize..end].to_vec())
+            let end = grain_offset_bytes
+                .checked_add(grain_size_bytes)
+                .filter(|&end| end <= self.mmap.len())
+                .ok_or_else(|| Error::vmdk("Grain extends beyond file"))?;
+            Ok(self.mmap[grain_offset_bytes..end].to_vec())
+        }
+    }
+
+    /// Reads one entry of the grain table at `gt_offset_sectors`.
+    ///
+    /// Returns the sector of the grain, or 0 if the grain is not allocated.
+    fn grain_table_entry(&self, gt_offset_sectors: u32, gte_index: u64) -> Result<u32> {
+        // A u32 sector offset can't overflow in bytes
+        let gte_offset = u64::from(gt_offset_sectors) * SECTOR_SIZE + gte_index * 4;
+        if gte_offset + 4 > self.mmap.len() as u64 {
+            return Err(Error::vmdk("Grain table entry extends beyond file"));
+        }
+        let gte_offset = gte_offset as usize;
+
+        Ok(u32::from_le_bytes([
+            self.mmap[gte_offset],
+            self.mmap[gte_offset + 1],
+            self.mmap[gte_offset + 2],
+            self.mmap[gte_offset + 3],
+        ]))
+    }
+
+    /// Returns whether a grain has backing data in the file.
+    ///
+    /// Grains are reported as allocated when the grain directory is invalid,
+    /// so that reading them reports the error instead of skipping them.
+    fn is_grain_allocated(&self, grain_index: u64) -> bool {
+        let Ok(grain_directory) = self.grain_directory() else {
+            return true;
+        };
+        let gtes_per_gt = u64::from(self.header.num_gtes_per_gt);
+        let gt_index = (grain_index / gtes_per_gt) as usize;
+        let gt_offset_sectors = match grain_directory.get(gt_index) {
+            Some(&sectors) if sectors != 0 => sectors,
+            _ => return false,
+        };
+        // Grain tables were checked to lie within the file when loading
+        self.grain_table_entry(gt_offset_sectors, grain_index % gtes_per_gt)
+            .is_ok_and(|sector| sector != 0)
+    }
+
+    /// Returns `true` if the file is a streamOptimized VMDK, whose grains
+    /// are compressed and preceded by markers.
+    pub fn is_stream_optimized(&self) -> bool {
+        self.header.is_compressed() && self.header.has_markers()
+    }
+
+    /// Returns `true` if no grain overlapping the range is allocated, so the
+    /// range reads as zeros.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - Byte offset into the virtual disk.
+    /// * `len` - Length of the range in bytes.
+    pub fn is_unallocated(&self, offset: u64, len: u64) -> bool {
+        let grain_size_bytes = self.grain_size_bytes();
+        let end = std::cmp::min(offset.saturating_add(len), self.capacity_bytes);
+        if offset >= end {
+            return true;
         }
+        (offset / grain_size_bytes..end.div_ceil(grain_size_bytes))
+            .all(|grain_index| !self.is_grain_allocated(grain_index))
+    }
+
+    /// Returns the byte ranges of the virtual disk backed by allocated grains.
+    ///
+    /// Adjacent allocated grains are merged into a single range, and the last
+    /// range is clamped to the disk capacity. Everything outside the ranges
+    /// reads as zeros.
+    pub fn allocated_ranges(&self) -> impl Iterator<Item = Range<u64>> + '_ {
+        let grain_size_bytes = self.grain_size_bytes();
+        let total_grains = self.capacity_bytes.div_ceil(grain_size_bytes);
+        let mut allocated = (0..total_grains)
+            .filter(move |&grain_index| self.is_grain_allocated(grain_index))
+            .peekable();
+
+        std::iter::from_fn(move || {
+            let first = allocated.next()?;
+            let mut last = first;
+            while allocated.next_if_eq(&(last + 1)).is_some() {
+                last += 1;
+            }
+            let end = std::cmp::min((last + 1) * grain_size_bytes, self.capacity_bytes);
+            Some(first * grain_size_bytes..end)
+        })
     }
 
     /// Reads and decompresses a compressed grain.
     fn read_compressed_grain(&self, offset: usize, uncompressed_size: usize) -> Result<Vec<u8>> {
         // Compressed grains have a 12-byte header: LBA (8 bytes) + size (4 bytes)
-        if offset + 12 > self.mmap.len() {
+        if offset.checked_add(12).is_none_or(|end| end > self.mmap.len()) {
             return Err(Error::vmdk("Compressed grain header extends beyond file"));
         }
 
@@ -256,7 +497,10 @@ impl SparseVmdkReader {
         ]) as usize;
 
         let data_offset = offset + 12;
-        if data_offset + compressed_size > self.mmap.len() {
+        if data_offset
+            .checked_add(compressed_size)
+            .is_none_or(|end| end > self.mmap.len())
+        {
             return Err(Error::vmdk("Compressed grain data extends beyond file"));
         }
 
@@ -266,21 +510,61 @@ impl SparseVmdkReader {
         use flate2::read::DeflateDecoder;
         use std::io::Read;
 
-        let mut decoder = DeflateDecoder::new(compressed_data);
-        let mut decompressed = vec![0u8; uncompressed_size];
-        decoder
-            .read_exact(&mut decompressed)
+        // The last grain of a disk may hold less than a full grain
+        let mut decompressed = Vec::with_capacity(uncompressed_size);
+        DeflateDecoder::new(compressed_data)
+            .take(uncompressed_size as u64)
+            .read_to_end(&mut decompressed)
             .map_err(|e| Error::vmdk(format!("Failed to decompress grain: {}", e)))?;
+        decompressed.resize(uncompressed_size, 0);
 
         Ok(decompressed)
     }
 
+    /// Reads a range of the virtual disk.
+    ///
+    /// Unallocated grains read as zeros. The range is clamped to the disk
+    /// capacity, so the returned buffer may be shorter than `len`.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - Byte offset into the virtual disk.
+    /// * `len` - Number of bytes to read.
+    pub fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        if offset >= self.capacity_bytes {
+            return Ok(Vec::new());
+        }
+
+        let len = std::cmp::min(len as u64, self.capacity_bytes - offset) as usize;
+        let mut data = Vec::with_capacity(len);
+        let grain_size_bytes = self.grain_size_bytes();
+
+        let mut bytes_read = 0u64;
+        while bytes_read < len as u64 {
+            let current_pos = offset + bytes_read;
+            let grain_index = current_pos / grain_size_bytes;
+            let offset_in_grain = (current_pos % grain_size_bytes) as usize;
+
+            let grain_data = self.read_grain(grain_index)?;
+
+            // Calculate how much to take from this grain
+            let bytes_needed = len as u64 - bytes_read;
+            let bytes_available = grain_size_bytes - offset_in_grain as u64;
+            let bytes_to_take = std::cmp::min(bytes_needed, bytes_available) as usize;
+
+            data.extend_from_slice(&grain_data[offset_in_grain..offset_in_grain + bytes_to_take]);
+            bytes_read += bytes_to_take as u64;
+        }
+
+        Ok(data)
+    }
+
     /// Creates an iterator that yields chunks of the virtual disk.
     ///
     /// # Arguments
     ///
     /// * `chunk_size` - The size of each chunk in bytes.
-    pub fn chunks(&self, chunk_size: usize) -> SparseChunkIterator {
+    pub fn chunks(&self, chunk_size: usize) -> SparseChunkIterator<'_> {
         SparseChunkIterator::new(self, chunk_size)
     }
 }
@@ -323,30 +607,10 @@ impl<'a> Iterator for SparseChunkIterator<'a> {
         let remaining = self.reader.capacity_bytes - self.current_offset;
         let chunk_len = std::cmp::min(remaining, self.chunk_size as u64) as usize;
 
-        // Build chunk by reading grains
-        let mut chunk_data = Vec::with_capacity(chunk_len);
-        let grain_size_bytes = self.reader.grain_size_bytes();
-
-        let mut bytes_read = 0u64;
-        while bytes_read < chunk_len as u64 {
-            let current_pos = self.current_offset + bytes_read;
-            let grain_index = current_pos / grain_size_bytes;
-            let offset_in_grain = (current_pos % grain_size_bytes) as usize;
-
-            // Read grain
-            let grain_data = match self.reader.read_grain(grain_index) {
-                Ok(data) => data,
-                Err(e) => return Some(Err(e)),
-            };
-
-            // Calculate how much to take from this grain
-            let bytes_needed = chunk_len as u64 - bytes_read;
-            let bytes_available = grain_size_bytes - offset_in_grain as u64;
-            let bytes_to_take = std::cmp::min(bytes_needed, bytes_available) as usize;
-
-            chunk_data.extend_from_slice(&grain_data[offset_in_grain..offset_in_grain + bytes_to_take]);
-            bytes_read += bytes_to_take as u64;
-        }
+        let chunk_data = match self.reader.read_at(self.current_offset, chunk_len) {
+            Ok(data) => data,
+            Err(e) => return Some(Err(e)),
+        };
 
         self.current_offset += chunk_len as u64;
         Some(Ok(chunk_data))
@@ -372,6 +636,194 @@ pub fn is_sparse_vmdk(path: &Path) -> Result<bool> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::vmdk::SparseVmdkWriter;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    /// Grain size used by test images (8 sectors = 4 KB).
+    const TEST_GRAIN_SECTORS: u64 = 8;
+
+    /// Build a minimal uncompressed sparse image with two grain tables.
+    ///
+    /// Layout: header (sector 0), grain directory (sector 1), grain table 0
+    /// (sectors 2-5), one grain of 0xAB bytes (sectors 6-13) mapped at grain 0.
+    fn build_sparse_image(gd_entries: [u32; 2]) -> NamedTempFile {
+        write_image(&sparse_image_bytes(gd_entries))
+    }
+
+    /// Raw bytes of the image built by [`build_sparse_image`].
+    fn sparse_image_bytes(gd_entries: [u32; 2]) -> Vec<u8> {
+        let mut image = vec![0u8; 14 * 512];
+        image[0..4].copy_from_slice(&VMDK_MAGIC.to_le_bytes());
+        image[4..8].copy_from_slice(&1u32.to_le_bytes());
+        // Capacity: two full grain tables
+        image[12..20].copy_from_slice(&(TEST_GRAIN_SECTORS * 512 * 2).to_le_bytes());
+        image[20..28].copy_from_slice(&TEST_GRAIN_SECTORS.to_le_bytes());
+        image[44..48].copy_from_slice(&512u32.to_le_bytes());
+        image[56..64].copy_from_slice(&1u64.to_le_bytes());
+
+        // Grain directory
+        image[512..516].copy_from_slice(&gd_entries[0].to_le_bytes());
+        image[516..520].copy_from_slice(&gd_entries[1].to_le_bytes());
+
+        // Grain table 0, entry 0 -> grain at sector 6
+        image[1024..1028].copy_from_slice(&6u32.to_le_bytes());
+        image[6 * 512..14 * 512].fill(0xAB);
+        image
+    }
+
+    /// Write an image to a temporary file.
+    fn write_image(image: &[u8]) -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(image).unwrap();
+        file.flush().unwrap();
+        file
+    }
+
+    #[test]
+    fn test_open_valid_grain_directory() {
+        let file = build_sparse_image([2, 0]);
+        let reader = SparseVmdkReader::open(file.path()).unwrap();
+
+        let grain_bytes = (TEST_GRAIN_SECTORS * 512) as usize;
+        let data = reader.read_at(0, grain_bytes * 2).unwrap();
+        assert!(data[..grain_bytes].iter().all(|&b| b == 0xAB));
+        assert!(data[grain_bytes..].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_is_unallocated() {
+        let file = build_sparse_image([2, 0]);
+        let reader = SparseVmdkReader::open(file.path()).unwrap();
+        let grain_bytes = TEST_GRAIN_SECTORS * 512;
+
+        assert!(!reader.is_stream_optimized());
+        assert!(!reader.is_unallocated(0, grain_bytes));
+        assert!(!reader.is_unallocated(grain_bytes - 1, 2));
+        assert!(reader.is_unallocated(grain_bytes, grain_bytes));
+        assert!(reader.is_unallocated(reader.capacity(), 10));
+    }
+
+    #[test]
+    fn test_embedded_descriptor() {
+        let file = build_sparse_image([2, 0]);
+        let reader = SparseVmdkReader::open(file.path()).unwrap();
+        assert_eq!(reader.embedded_descriptor().unwrap(), None);
+
+        // Append a one-sector descriptor after the grain
+        let mut image = sparse_image_bytes([2, 0]);
+        image[28..36].copy_from_slice(&14u64.to_le_bytes());
+        image[36..44].copy_from_slice(&1u64.to_le_bytes());
+        let mut sector = b"# Disk DescriptorFile\nCID=1234abcd\n".to_vec();
+        sector.resize(512, 0);
+        image.extend_from_slice(&sector);
+        let file = write_image(&image);
+
+        let reader = SparseVmdkReader::open(file.path()).unwrap();
+        assert_eq!(
+            reader.embedded_descriptor().unwrap().as_deref(),
+            Some("# Disk DescriptorFile\nCID=1234abcd\n")
+        );
+    }
+
+    #[test]
+    fn test_allocated_ranges_half_allocated() {
+        let file = NamedTempFile::new().unwrap();
+        let mut writer = SparseVmdkWriter::new(file.reopen().unwrap(), 1024 * 1024).unwrap();
+        let grain_bytes = writer.grain_size_bytes();
+        // Allocate grains 0-3 and 8-11 of 16
+        for grain_index in (0..4).chain(8..12) {
+            writer
+                .write_grain(grain_index * grain_bytes / 512, &[0x5A; 512])
+                .unwrap();
+        }
+        writer.finish().unwrap();
+
+        let reader = SparseVmdkReader::open(file.path()).unwrap();
+        let ranges: Vec<_> = reader.allocated_ranges().collect();
+        assert_eq!(ranges, vec![0..4 * grain_bytes, 8 * grain_bytes..12 * grain_bytes]);
+        let allocated: u64 = ranges.iter().map(|range| range.end - range.start).sum();
+        assert_eq!(allocated, reader.capacity() / 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_advise_sequential() {
+        let file = build_sparse_image([2, 0]);
+        let reader = SparseVmdkReader::open(file.path()).unwrap();
+        reader.advise_sequential().unwrap();
+
+        let data = reader.read_at(0, 512).unwrap();
+        assert!(data.iter().all(|&b| b == 0xAB));
+    }
+
+    #[test]
+    fn test_open_zero_capacity() {
+        let mut image = sparse_image_bytes([0, 0]);
+        image[12..20].copy_from_slice(&0u64.to_le_bytes());
+        let file = write_image(&image);
+
+        let reader = SparseVmdkReader::open(file.path()).unwrap();
+        assert_eq!(reader.capacity(), 0);
+        assert!(reader.read_at(0, 4096).unwrap().is_empty());
+
+        let chunks = reader.chunks(4096);
+        assert_eq!(chunks.count_chunks(), 0);
+        assert_eq!(chunks.count(), 0);
+    }
+
+    #[test]
+    fn test_open_rejects_grain_table_past_eof() {
+        let file = build_sparse_image([2, 1000]);
+        let err = SparseVmdkReader::open_full(file.path())
+            .err()
+            .expect("Open should fail for an out-of-bounds grain table");
+        let message = err.to_string();
+        assert!(message.contains("entry 1"), "unexpected error: {}", message);
+        assert!(message.contains("beyond the end of the file"));
+    }
+
+    #[test]
+    fn test_open_rejects_truncated_grain_table() {
+        // Grain table starting in the last sector can't hold 512 entries
+        let file = build_sparse_image([13, 0]);
+        assert!(SparseVmdkReader::open_full(file.path()).is_err());
+    }
+
+    #[test]
+    fn test_open_defers_grain_directory() {
+        let grain_directory_reads = || GRAIN_DIRECTORY_READS.with(|reads| reads.get());
+        let file = build_sparse_image([2, 0]);
+        let before = grain_directory_reads();
+
+        // Opening and querying metadata never reads the grain directory
+        let reader = SparseVmdkReader::open(file.path()).unwrap();
+        assert_eq!(reader.capacity(), TEST_GRAIN_SECTORS * 512 * 2 * SECTOR_SIZE);
+        assert_eq!(reader.embedded_descriptor().unwrap(), None);
+        assert_eq!(grain_directory_reads(), before);
+
+        // The first read loads it, and later reads reuse it
+        assert_eq!(reader.read_at(0, 512).unwrap(), vec![0xAB; 512]);
+        assert_eq!(grain_directory_reads(), before + 1);
+        reader.read_at(512, 512).unwrap();
+        assert_eq!(grain_directory_reads(), before + 1);
+
+        SparseVmdkReader::open_full(file.path()).unwrap();
+        assert_eq!(grain_directory_reads(), before + 2);
+
+        // A grain directory past the end of the file is only noticed on a read
+        let mut image = sparse_image_bytes([2, 0]);
+        image[56..64].copy_from_slice(&1000u64.to_le_bytes());
+        let file = write_image(&image);
+
+        let reader = SparseVmdkReader::open(file.path()).expect("Open should only read the header");
+
+        // Reads report the problem, and the range isn't skipped as unallocated
+        assert!(!reader.is_unallocated(0, 512));
+        let err = reader.read_at(0, 512).unwrap_err();
+        assert!(err.to_string().contains("Grain directory extends beyond file"), "{}", err);
+        assert!(SparseVmdkReader::open_full(file.path()).is_err());
+    }
 
     #[test]
     fn test_sparse_header_from_bytes() {
@@ -404,6 +856,41 @@ mod tests {
         assert_eq!(header.gd_offset, 100);
     }
 
+    #[test]
+    fn test_open_rejects_overflowing_capacity() {
+        let mut image = sparse_image_bytes([2, 0]);
+        image[12..20].copy_from_slice(&u64::MAX.to_le_bytes());
+
+        let err = SparseVmdkReader::open(write_image(&image).path())
+            .err()
+            .expect("Open should fail for a capacity that overflows");
+        assert!(err.to_string().contains("capacity"), "unexpected error: {}", err);
+    }
+
+    #[test]
+    fn test_open_rejects_overflowing_grain_size() {
+        let mut image = sparse_image_bytes([2, 0]);
+        image[20..28].copy_from_slice(&(u64::MAX / SECTOR_SIZE + 1).to_le_bytes());
+
+        let err = SparseVmdkReader::open(write_image(&image).path())
+            .err()
+            .expect("Open should fail for a grain size that overflows");
+        assert!(err.to_string().contains("grain size"), "unexpected error: {}", err);
+    }
+
+    #[test]
+    fn test_open_rejects_overflowing_gd_offset() {
+        for gd_offset in [u64::MAX / SECTOR_SIZE, u64::MAX / SECTOR_SIZE + 1, u64::MAX] {
+            let mut image = sparse_image_bytes([2, 0]);
+            image[56..64].copy_from_slice(&gd_offset.to_le_bytes());
+
+            let err = SparseVmdkReader::open_full(write_image(&image).path())
+                .err()
+                .expect("Open should fail for an out-of-range grain directory");
+            assert!(err.to_string().contains("Grain directory extends beyond file"));
+        }
+    }
+
     #[test]
     fn test_invalid_magic() {
         let header_bytes = vec![0u8; 512];
@@ -0,0 +1,233 @@
+//! Hosted sparse (monolithicSparse) VMDK writer.
+//!
+//! This module writes the uncompressed sparse extent format read by
+//! [`SparseVmdkReader`](super::sparse::SparseVmdkReader). Unlike the
+//! streamOptimized format, all metadata comes first: the header, the grain
+//! directory and every grain table are laid out before the first grain, and
+//! the grain tables are filled in when the writer finishes.
+
+use crate::error::{Error, Result};
+use std::collections::BTreeMap;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use super::stream::{grain_table_entry, SparseExtentHeader, GT_ENTRIES_PER_GT, SECTOR_SIZE};
+
+/// Header flag for a valid newline detection test.
+const FLAG_VALID_NEWLINE: u32 = 1;
+
+/// Writer for hosted sparse VMDK files.
+///
+/// Grains are written uncompressed, each padded to the full grain size.
+/// Grains that are never written read as zeros.
+///
+/// # Example
+///
+/// ```
+/// use ovatool_core::vmdk::SparseVmdkWriter;
+/// use std::io::Cursor;
+///
+/// let mut writer = SparseVmdkWriter::new(Cursor::new(Vec::new()), 1024 * 1024).unwrap();
+/// writer.write_grain(0, &[0xAB; 65536]).unwrap();
+/// let _file = writer.finish().unwrap();
+/// ```
+pub struct SparseVmdkWriter<W: Write + Seek> {
+    writer: W,
+    header: SparseExtentHeader,
+    /// Sector where the first grain table starts.
+    gt_start_sector: u64,
+    /// Number of grain tables.
+    num_gts: u64,
+    /// Current position in the file (in bytes).
+    current_pos: u64,
+    /// Map of grain index to the sector where its data was written.
+    grain_offsets: BTreeMap<u64, u32>,
+}
+
+impl<W: Write + Seek> SparseVmdkWriter<W> {
+    /// Creates a new SparseVmdkWriter and writes the disk metadata.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - The underlying writer (file, buffer, etc.).
+    /// * `capacity_bytes` - Total disk capacity in bytes.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the writer on success.
+    pub fn new(mut writer: W, capacity_bytes: u64) -> Result<Self> {
+        let mut header = SparseExtentHeader::new(capacity_bytes);
+        header.version = 1;
+        header.flags = FLAG_VALID_NEWLINE;
+        header.compress_algorithm = 0;
+
+        let total_grains = header.capacity.div_ceil(header.grain_size);
+        let num_gts = total_grains.div_ceil(u64::from(GT_ENTRIES_PER_GT));
+        let gd_sectors = (num_gts * 4).div_ceil(SECTOR_SIZE);
+        let gt_sectors = u64::from(GT_ENTRIES_PER_GT) * 4 / SECTOR_SIZE;
+
+        // Header, grain directory, then the grain tables, rounded up to a grain
+        header.gd_offset = 1;
+        let gt_start_sector = 1 + gd_sectors;
+        let metadata_sectors = gt_start_sector + num_gts * gt_sectors;
+        header.overhead = metadata_sectors.div_ceil(header.grain_size) * header.grain_size;
+
+        writer
+            .write_all(&header.to_bytes())
+            .map_err(|e| Error::vmdk(format!("Failed to write VMDK header: {}", e)))?;
+
+        // The grain directory points at every grain table
+        let mut gd_bytes = Vec::with_capacity((gd_sectors * SECTOR_SIZE) as usize);
+        for gt_index in 0..num_gts {
+            let entry = grain_table_entry(gt_start_sector + gt_index * gt_sectors)?;
+            gd_bytes.extend_from_slice(&entry.to_le_bytes());
+        }
+        gd_bytes.resize((gd_sectors * SECTOR_SIZE) as usize, 0);
+        writer
+            .write_all(&gd_bytes)
+            .map_err(|e| Error::vmdk(format!("Failed to write grain directory: {}", e)))?;
+
+        // Grain tables stay empty until finish(); pad up to the first grain
+        let padding = (header.overhead - gt_start_sector) * SECTOR_SIZE;
+        write_zeros(&mut writer, padding)
+            .map_err(|e| Error::vmdk(format!("Failed to write grain tables: {}", e)))?;
+
+        let current_pos = header.overhead * SECTOR_SIZE;
+        Ok(Self {
+            writer,
+            header,
+            gt_start_sector,
+            num_gts,
+            current_pos,
+            grain_offsets: BTreeMap::new(),
+        })
+    }
+
+    /// Returns the grain size in bytes.
+    pub fn grain_size_bytes(&self) -> u64 {
+        self.header.grain_size * SECTOR_SIZE
+    }
+
+    /// Writes an uncompressed grain at the specified LBA.
+    ///
+    /// # Arguments
+    ///
+    /// * `lba` - Logical block address (in sectors) of the grain.
+    /// * `data` - The grain data, at most one grain long. Shorter data is
+    ///   padded with zeros.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating success or failure.
+    pub fn write_grain(&mut self, lba: u64, data: &[u8]) -> Result<()> {
+        let grain_size_bytes = self.grain_size_bytes();
+        if !lba.is_multiple_of(self.header.grain_size) || lba >= self.header.capacity {
+            return Err(Error::vmdk(format!(
+                "LBA {} is not the start of a grain within the disk",
+                lba
+            )));
+        }
+        if data.len() as u64 > grain_size_bytes {
+            return Err(Error::vmdk(format!(
+                "Grain data of {} bytes exceeds the grain size of {} bytes",
+                data.len(),
+                grain_size_bytes
+            )));
+        }
+
+        let grain_index = lba / self.header.grain_size;
+        let grain_sector = grain_table_entry(self.current_pos / SECTOR_SIZE)?;
+
+        self.writer
+            .write_all(data)
+            .and_then(|_| write_zeros(&mut self.writer, grain_size_bytes - data.len() as u64))
+            .map_err(|e| Error::vmdk(format!("Failed to write grain data: {}", e)))?;
+
+        self.grain_offsets.insert(grain_index, grain_sector);
+        self.current_pos += grain_size_bytes;
+        Ok(())
+    }
+
+    /// Finishes writing the VMDK file.
+    ///
+    /// This fills in the grain tables for every grain written.
+    ///
+    /// # Returns
+    ///
+    /// The underlying writer, positioned at the end of the file.
+    pub fn finish(mut self) -> Result<W> {
+        let entries_per_gt = u64::from(GT_ENTRIES_PER_GT);
+        let gt_bytes_len = entries_per_gt * 4;
+
+        for gt_index in 0..self.num_gts {
+            let first_grain = gt_index * entries_per_gt;
+            let mut entries = self
+                .grain_offsets
+                .range(first_grain..first_grain + entries_per_gt)
+                .peekable();
+            if entries.peek().is_none() {
+                continue;
+            }
+
+            let mut gt_bytes = vec![0u8; gt_bytes_len as usize];
+            for (&grain_index, &sector) in entries {
+                let offset = ((grain_index - first_grain) * 4) as usize;
+                gt_bytes[offset..offset + 4].copy_from_slice(&sector.to_le_bytes());
+            }
+
+            let gt_offset = (self.gt_start_sector * SECTOR_SIZE) + gt_index * gt_bytes_len;
+            self.writer
+                .seek(SeekFrom::Start(gt_offset))
+                .and_then(|_| self.writer.write_all(&gt_bytes))
+                .map_err(|e| Error::vmdk(format!("Failed to write grain table: {}", e)))?;
+        }
+
+        self.writer
+            .seek(SeekFrom::Start(self.current_pos))
+            .and_then(|_| self.writer.flush())
+            .map_err(|e| Error::vmdk(format!("Failed to finish VMDK: {}", e)))?;
+
+        Ok(self.writer)
+    }
+}
+
+/// Write `len` zero bytes.
+fn write_zeros<W: Write>(writer: &mut W, len: u64) -> std::io::Result<()> {
+    std::io::copy(&mut std::io::repeat(0).take(len), writer).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vmdk::SparseVmdkReader;
+    use std::io::{Cursor, Write};
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_round_trip_through_sparse_reader() {
+        let mut writer = SparseVmdkWriter::new(Cursor::new(Vec::new()), 1024 * 1024).unwrap();
+        writer.write_grain(0, &[0x11; 65536]).unwrap();
+        // A short final write is padded to a whole grain
+        writer.write_grain(640, &[0x22; 1000]).unwrap();
+        let data = writer.finish().unwrap().into_inner();
+
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&data).unwrap();
+        file.flush().unwrap();
+
+        let reader = SparseVmdkReader::open(file.path()).unwrap();
+        assert_eq!(reader.capacity(), 1024 * 1024);
+        let disk = reader.read_at(0, 1024 * 1024).unwrap();
+        assert!(disk[..65536].iter().all(|&b| b == 0x11));
+        assert!(disk[65536..640 * 512].iter().all(|&b| b == 0));
+        assert!(disk[640 * 512..640 * 512 + 1000].iter().all(|&b| b == 0x22));
+        assert!(disk[640 * 512 + 1000..].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_rejects_unaligned_or_oversized_grain() {
+        let mut writer = SparseVmdkWriter::new(Cursor::new(Vec::new()), 1024 * 1024).unwrap();
+        assert!(writer.write_grain(1, &[0u8; 512]).is_err());
+        assert!(writer.write_grain(2048, &[0u8; 512]).is_err());
+        assert!(writer.write_grain(0, &[0u8; 65537]).is_err());
+    }
+}
@@ -13,8 +13,9 @@
 use crate::error::{Error, Result};
 use flate2::write::DeflateEncoder;
 use flate2::Compression;
+use rayon::prelude::*;
 use std::collections::BTreeMap;
-use std::io::{Seek, Write};
+use std::io::Write;
 
 /// VMDK magic number ("VMDK" as little-endian u32).
 pub const VMDK_MAGIC: u32 = 0x564D444B;
@@ -35,11 +36,29 @@ pub const GT_ENTRIES_PER_GT: u32 = 512;
 const STREAM_OPTIMIZED_FLAGS: u32 = 0x30001 | (1 << 16) | (1 << 17);
 
 /// Grain directory offset value indicating GD is at end of file.
-const GD_AT_END: u64 = 0xFFFFFFFFFFFFFFFF;
+pub(super) const GD_AT_END: u64 = 0xFFFFFFFFFFFFFFFF;
 
 /// Compression algorithm: DEFLATE.
 const COMPRESS_ALGORITHM_DEFLATE: u16 = 1;
 
+/// Grain compression algorithm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CompressionAlgorithm {
+    /// Raw DEFLATE (RFC 1951), the only algorithm VMware defines.
+    #[default]
+    Deflate,
+}
+
+impl CompressionAlgorithm {
+    /// Returns the value stored in the sparse extent header's
+    /// `compressAlgorithm` field.
+    pub fn header_value(&self) -> u16 {
+        match self {
+            CompressionAlgorithm::Deflate => COMPRESS_ALGORITHM_DEFLATE,
+        }
+    }
+}
+
 /// Marker types used in streamOptimized VMDK.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u32)]
@@ -178,7 +197,7 @@ impl SparseExtentHeader {
             overhead: 0,
             unclean_shutdown: 0,
             newline_chars: [b'\n', b' ', b'\r', b'\n'],
-            compress_algorithm: COMPRESS_ALGORITHM_DEFLATE,
+            compress_algorithm: CompressionAlgorithm::Deflate.header_value(),
         }
     }
 
@@ -250,6 +269,28 @@ impl SparseExtentHeader {
     }
 }
 
+/// Converts a compressed grain length to the marker's 32-bit size field.
+fn grain_marker_size(len: usize) -> Result<u32> {
+    u32::try_from(len).map_err(|_| {
+        Error::vmdk(format!(
+            "Compressed grain of {} bytes does not fit in a grain marker",
+            len
+        ))
+    })
+}
+
+/// Converts a sector offset to a 32-bit grain table or directory entry.
+///
+/// Entries can only address the first 2 TB of the VMDK file.
+pub(super) fn grain_table_entry(sector: u64) -> Result<u32> {
+    u32::try_from(sector).map_err(|_| {
+        Error::vmdk(format!(
+            "Sector offset {} is beyond what a grain table entry can address",
+            sector
+        ))
+    })
+}
+
 /// Compresses grain data using DEFLATE.
 ///
 /// # Arguments
@@ -270,6 +311,72 @@ pub fn compress_grain(data: &[u8], level: u32) -> Result<Vec<u8>> {
         .map_err(|e| Error::vmdk(format!("Failed to finish compression: {}", e)))
 }
 
+/// Compresses a batch of grains in parallel.
+///
+/// Uses the same settings as [`compress_grain`], so the output is identical
+/// to compressing each grain in turn.
+///
+/// # Arguments
+///
+/// * `grains` - The uncompressed grains.
+/// * `level` - Compression level (0-9, where 6 is default).
+/// * `algorithm` - Compression algorithm to use.
+///
+/// # Returns
+///
+/// The compressed grains, in the same order as `grains`.
+pub fn compress_grains(
+    grains: &[&[u8]],
+    level: u32,
+    algorithm: CompressionAlgorithm,
+) -> Result<Vec<Vec<u8>>> {
+    grains
+        .par_iter()
+        .map(|grain| match algorithm {
+            CompressionAlgorithm::Deflate => compress_grain(grain, level),
+        })
+        .collect()
+}
+
+/// Bytes examined per step by [`is_zero_grain`].
+const ZERO_SCAN_BLOCK: usize = 64;
+
+/// Checks whether a grain consists entirely of zero bytes.
+///
+/// Scans 64-byte blocks as eight OR-folded `u64` words, which the compiler
+/// lowers to wide vector compares. Bytes past the last full block go through
+/// [`is_zero_scalar`].
+///
+/// # Arguments
+///
+/// * `data` - The grain data to check.
+///
+/// # Returns
+///
+/// `true` if every byte is zero (including for an empty slice).
+pub fn is_zero_grain(data: &[u8]) -> bool {
+    let mut blocks = data.chunks_exact(ZERO_SCAN_BLOCK);
+
+    for block in &mut blocks {
+        let folded = block
+            .chunks_exact(8)
+            .fold(0u64, |acc, word| acc | u64::from_ne_bytes(word.try_into().unwrap()));
+        if folded != 0 {
+            return false;
+        }
+    }
+
+    is_zero_scalar(blocks.remainder())
+}
+
+/// Byte-at-a-time zero check.
+///
+/// Portable fallback for [`is_zero_grain`], and the reference it is tested
+/// against.
+pub fn is_zero_scalar(data: &[u8]) -> bool {
+    data.iter().all(|&b| b == 0)
+}
+
 /// A writer for creating streamOptimized VMDK files.
 ///
 /// This writer creates VMware-compatible VMDK files with:
@@ -278,6 +385,9 @@ pub fn compress_grain(data: &[u8], level: u32) -> Result<Vec<u8>> {
 /// - Markers for metadata sections
 /// - Footer with grain directory location
 ///
+/// The file is written strictly front to back, so any `Write` will do,
+/// including a pipe or a file being streamed into an archive.
+///
 /// # Example
 ///
 /// ```no_run
@@ -295,18 +405,30 @@ pub fn compress_grain(data: &[u8], level: u32) -> Result<Vec<u8>> {
 /// // Finish writing (writes grain tables, directory, footer)
 /// let _file = writer.finish().unwrap();
 /// ```
-pub struct StreamVmdkWriter<W: Write + Seek> {
+pub struct StreamVmdkWriter<W: Write> {
     writer: W,
     header: SparseExtentHeader,
     /// Current position in the file (in bytes).
     current_pos: u64,
     /// Map of grain index to sector offset where grain data was written.
-    grain_offsets: BTreeMap<u64, u64>,
+    grain_offsets: BTreeMap<u64, u32>,
     /// Grain size in bytes.
     grain_size_bytes: u64,
 }
 
-impl<W: Write + Seek> StreamVmdkWriter<W> {
+/// Checks that a grain size, in sectors, is one VMware accepts: a power of
+/// two of at least 8 sectors (4 KB).
+pub fn check_grain_size(grain_size: u64) -> Result<()> {
+    if grain_size < 8 || !grain_size.is_power_of_two() {
+        return Err(Error::vmdk(format!(
+            "Grain size of {} sectors is not a power of two of at least 8 sectors",
+            grain_size
+        )));
+    }
+    Ok(())
+}
+
+impl<W: Write> StreamVmdkWriter<W> {
     /// Creates a new StreamVmdkWriter.
     ///
     /// # Arguments
@@ -317,8 +439,25 @@ impl<W: Write + Seek> StreamVmdkWriter<W> {
     /// # Returns
     ///
     /// A `Result` containing the writer on success.
-    pub fn new(mut writer: W, capacity_bytes: u64) -> Result<Self> {
-        let header = SparseExtentHeader::new(capacity_bytes);
+    pub fn new(writer: W, capacity_bytes: u64) -> Result<Self> {
+        Self::with_grain_size(writer, capacity_bytes, DEFAULT_GRAIN_SIZE)
+    }
+
+    /// Creates a new StreamVmdkWriter with a non-default grain size.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - The underlying writer (file, buffer, etc.).
+    /// * `capacity_bytes` - Total disk capacity in bytes.
+    /// * `grain_size` - Grain size in sectors; see [`check_grain_size`].
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the writer on success.
+    pub fn with_grain_size(mut writer: W, capacity_bytes: u64, grain_size: u64) -> Result<Self> {
+        check_grain_size(grain_size)?;
+        let mut header = SparseExtentHeader::new(capacity_bytes);
+        header.grain_size = grain_size;
 
         // Write the header
         let header_bytes = header.to_bytes();
@@ -351,15 +490,18 @@ impl<W: Write + Seek> StreamVmdkWriter<W> {
         // Calculate grain index
         let grain_index = lba / self.header.grain_size;
 
+        // Validate sizes before anything is written for this grain
+        let compressed_size = grain_marker_size(compressed_data.len())?;
+        let grain_sector = grain_table_entry(self.current_pos / SECTOR_SIZE)?;
+
         // Write grain marker (12 bytes)
-        let marker = GrainMarker::new(lba, compressed_data.len() as u32);
+        let marker = GrainMarker::new(lba, compressed_size);
         self.writer
             .write_all(&marker.to_bytes())
             .map_err(|e| Error::vmdk(format!("Failed to write grain marker: {}", e)))?;
 
         // Record the offset where the grain data starts (after the marker)
         // The grain table entry points to the sector containing the grain marker
-        let grain_sector = self.current_pos / SECTOR_SIZE;
         self.grain_offsets.insert(grain_index, grain_sector);
 
         // Write compressed data
@@ -368,7 +510,7 @@ impl<W: Write + Seek> StreamVmdkWriter<W> {
             .map_err(|e| Error::vmdk(format!("Failed to write grain data: {}", e)))?;
 
         // Update position
-        self.current_pos += 12 + compressed_data.len() as u64;
+        self.current_pos += 12 + u64::from(compressed_size);
 
         // Pad to sector boundary
         let remainder = self.current_pos % SECTOR_SIZE;
@@ -384,6 +526,28 @@ impl<W: Write + Seek> StreamVmdkWriter<W> {
         Ok(())
     }
 
+    /// Compresses and writes a grain, unless it is all zeros.
+    ///
+    /// An all-zero grain is not written at all: its grain table entry stays
+    /// 0, which readers treat as an unallocated grain of zeros.
+    ///
+    /// # Arguments
+    ///
+    /// * `lba` - Logical block address (in sectors) of the grain.
+    /// * `data` - The uncompressed grain data.
+    /// * `level` - Compression level (0-9, where 6 is default).
+    ///
+    /// # Returns
+    ///
+    /// `true` if the grain was written, `false` if it was skipped.
+    pub fn write_grain_if_nonzero(&mut self, lba: u64, data: &[u8], level: u32) -> Result<bool> {
+        if is_zero_grain(data) {
+            return Ok(false);
+        }
+        self.write_grain(lba, &compress_grain(data, level)?)?;
+        Ok(true)
+    }
+
     /// Finishes writing the VMDK file.
     ///
     /// This writes the grain tables, grain directory, footer, and EOS marker.
@@ -393,54 +557,45 @@ impl<W: Write + Seek> StreamVmdkWriter<W> {
     /// The underlying writer on success.
     pub fn finish(mut self) -> Result<W> {
         // Calculate number of grain tables needed
-        let total_grains = (self.header.capacity + self.header.grain_size - 1) / self.header.grain_size;
-        let num_gts = (total_grains + GT_ENTRIES_PER_GT as u64 - 1) / GT_ENTRIES_PER_GT as u64;
-
-        // Write grain tables
-        let mut gt_offsets: Vec<u64> = Vec::with_capacity(num_gts as usize);
-
-        for gt_index in 0..num_gts {
-            let gt_start_grain = gt_index * GT_ENTRIES_PER_GT as u64;
-
-            // Build grain table entries
-            let mut gt_entries = vec![0u32; GT_ENTRIES_PER_GT as usize];
-            for (i, entry) in gt_entries.iter_mut().enumerate() {
-                let grain_index = gt_start_grain + i as u64;
-                if let Some(&offset) = self.grain_offsets.get(&grain_index) {
-                    *entry = offset as u32;
-                }
+        let total_grains = self.header.capacity.div_ceil(self.header.grain_size);
+        let num_gts = total_grains.div_ceil(GT_ENTRIES_PER_GT as u64);
+
+        // Write grain tables, reusing one buffer; empty tables are skipped
+        let entries_per_gt = GT_ENTRIES_PER_GT as u64;
+        let gt_size_sectors = (u64::from(GT_ENTRIES_PER_GT) * 4).div_ceil(SECTOR_SIZE);
+        let mut gt_offsets: Vec<u64> = vec![0; num_gts as usize];
+        let mut gt_bytes = vec![0u8; (gt_size_sectors * SECTOR_SIZE) as usize];
+
+        let mut grains = self.grain_offsets.iter().peekable();
+        while let Some(&(&first_grain, _)) = grains.peek() {
+            let gt_index = first_grain / entries_per_gt;
+            let gt_start_grain = gt_index * entries_per_gt;
+            if gt_index >= num_gts {
+                return Err(Error::vmdk(format!(
+                    "Grain {} is beyond the disk capacity of {} grains",
+                    first_grain, total_grains
+                )));
             }
 
-            // Check if this GT has any entries
-            let has_entries = gt_entries.iter().any(|&e| e != 0);
-            if !has_entries {
-                gt_offsets.push(0);
-                continue;
+            gt_bytes.fill(0);
+            while let Some((&grain_index, &offset)) =
+                grains.next_if(|(&grain_index, _)| grain_index < gt_start_grain + entries_per_gt)
+            {
+                let entry = ((grain_index - gt_start_grain) * 4) as usize;
+                gt_bytes[entry..entry + 4].copy_from_slice(&offset.to_le_bytes());
             }
 
             // Write grain table marker
-            let gt_size_sectors = (GT_ENTRIES_PER_GT * 4 + SECTOR_SIZE as u32 - 1) / SECTOR_SIZE as u32;
-            let gt_marker = Marker::new(MarkerType::GrainTable, gt_size_sectors as u64);
+            let gt_marker = Marker::new(MarkerType::GrainTable, gt_size_sectors);
             self.writer
                 .write_all(&gt_marker.to_bytes())
                 .map_err(|e| Error::vmdk(format!("Failed to write GT marker: {}", e)))?;
 
             // Record GT offset (sector after the marker)
             let gt_offset = (self.current_pos + SECTOR_SIZE) / SECTOR_SIZE;
-            gt_offsets.push(gt_offset);
+            gt_offsets[gt_index as usize] = gt_offset;
             self.current_pos += SECTOR_SIZE;
 
-            // Write grain table entries
-            let mut gt_bytes = Vec::with_capacity(GT_ENTRIES_PER_GT as usize * 4);
-            for entry in &gt_entries {
-                gt_bytes.extend_from_slice(&entry.to_le_bytes());
-            }
-
-            // Pad to sector boundary
-            while gt_bytes.len() % SECTOR_SIZE as usize != 0 {
-                gt_bytes.push(0);
-            }
-
             self.writer
                 .write_all(&gt_bytes)
                 .map_err(|e| Error::vmdk(format!("Failed to write grain table: {}", e)))?;
@@ -448,7 +603,7 @@ impl<W: Write + Seek> StreamVmdkWriter<W> {
         }
 
         // Write grain directory marker
-        let gd_size_sectors = (num_gts * 4 + SECTOR_SIZE - 1) / SECTOR_SIZE;
+        let gd_size_sectors = (num_gts * 4).div_ceil(SECTOR_SIZE);
         let gd_marker = Marker::new(MarkerType::GrainDirectory, gd_size_sectors);
         self.writer
             .write_all(&gd_marker.to_bytes())
@@ -458,16 +613,12 @@ impl<W: Write + Seek> StreamVmdkWriter<W> {
         let gd_offset = (self.current_pos + SECTOR_SIZE) / SECTOR_SIZE;
         self.current_pos += SECTOR_SIZE;
 
-        // Write grain directory entries
-        let mut gd_bytes = Vec::with_capacity(num_gts as usize * 4);
+        // Write grain directory entries, padded to a sector boundary
+        let mut gd_bytes = Vec::with_capacity((gd_size_sectors * SECTOR_SIZE) as usize);
         for &gt_offset in &gt_offsets {
-            gd_bytes.extend_from_slice(&(gt_offset as u32).to_le_bytes());
-        }
-
-        // Pad to sector boundary
-        while gd_bytes.len() % SECTOR_SIZE as usize != 0 {
-            gd_bytes.push(0);
+            gd_bytes.extend_from_slice(&grain_table_entry(gt_offset)?.to_le_bytes());
         }
+        gd_bytes.resize((gd_size_sectors * SECTOR_SIZE) as usize, 0);
 
         self.writer
             .write_all(&gd_bytes)
@@ -511,6 +662,11 @@ impl<W: Write + Seek> StreamVmdkWriter<W> {
     pub fn capacity_bytes(&self) -> u64 {
         self.header.capacity * SECTOR_SIZE
     }
+
+    /// Returns the number of bytes written so far.
+    pub fn bytes_written(&self) -> u64 {
+        self.current_pos
+    }
 }
 
 #[cfg(test)]
@@ -534,6 +690,21 @@ mod tests {
         assert_eq!(header.gd_offset, GD_AT_END);
     }
 
+    #[test]
+    fn test_writer_grain_size() {
+        let writer =
+            StreamVmdkWriter::with_grain_size(Cursor::new(Vec::new()), 1024 * 1024, 8).unwrap();
+        assert_eq!(writer.grain_size_bytes(), 4096);
+        let data = writer.finish().unwrap().into_inner();
+        let reader = crate::vmdk::StreamOptimizedReader::from_bytes(&data).unwrap();
+        assert_eq!(reader.grain_size_bytes(), 4096);
+
+        for grain_size in [0, 4, 96] {
+            assert!(StreamVmdkWriter::with_grain_size(Cursor::new(Vec::new()), 4096, grain_size)
+                .is_err());
+        }
+    }
+
     #[test]
     fn test_marker_to_bytes() {
         let marker = Marker::new(MarkerType::GrainTable, 4);
@@ -591,4 +762,17 @@ mod tests {
         let magic = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
         assert_eq!(magic, VMDK_MAGIC);
     }
+
+    #[test]
+    fn test_grain_marker_size_limits() {
+        assert_eq!(grain_marker_size(u32::MAX as usize).unwrap(), u32::MAX);
+        assert!(grain_marker_size(u32::MAX as usize + 1).is_err());
+    }
+
+    #[test]
+    fn test_grain_table_entry_limits() {
+        assert_eq!(grain_table_entry(u64::from(u32::MAX)).unwrap(), u32::MAX);
+        assert!(grain_table_entry(u64::from(u32::MAX) + 1).is_err());
+        assert!(grain_table_entry(u64::MAX).is_err());
+    }
 }
@@ -0,0 +1,330 @@
+//! StreamOptimized VMDK reader.
+//!
+//! This module is the read counterpart to
+//! [`StreamVmdkWriter`](super::stream::StreamVmdkWriter): it walks the markers
+//! of a streamOptimized VMDK and yields its grains decompressed, in LBA order.
+
+use crate::error::{Error, Result};
+use flate2::read::DeflateDecoder;
+use memmap2::Mmap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use super::stream::{MarkerType, SECTOR_SIZE, VMDK_MAGIC};
+
+/// Header flag set on VMDKs whose grains are compressed.
+const FLAG_COMPRESSED: u32 = 1 << 16;
+/// Header flag set on VMDKs that use stream markers.
+const FLAG_MARKERS: u32 = 1 << 17;
+
+/// Bytes the reader walks over.
+enum Backing<'a> {
+    Mapped(Mmap),
+    Borrowed(&'a [u8]),
+}
+
+impl Backing<'_> {
+    fn as_slice(&self) -> &[u8] {
+        match self {
+            Backing::Mapped(mmap) => mmap,
+            Backing::Borrowed(data) => data,
+        }
+    }
+}
+
+/// Location of one compressed grain within the file.
+#[derive(Debug, Clone, Copy)]
+struct GrainLocation {
+    lba: u64,
+    /// Byte offset of the compressed data (just past the grain marker).
+    offset: usize,
+    /// Length of the compressed data in bytes.
+    size: usize,
+}
+
+/// A grain read back from a streamOptimized VMDK.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecompressedGrain {
+    /// Logical block address of the grain (in sectors).
+    pub lba: u64,
+    /// The decompressed grain data.
+    pub data: Vec<u8>,
+}
+
+/// A reader for streamOptimized VMDK files.
+///
+/// Opening the reader walks every marker once, skipping grain tables, the
+/// grain directory and the footer, and indexes the grains it finds. Grains
+/// are decompressed lazily by [`grains`](Self::grains).
+pub struct StreamOptimizedReader<'a> {
+    backing: Backing<'a>,
+    capacity_bytes: u64,
+    grain_size_bytes: u64,
+    /// Grain locations sorted by LBA.
+    grains: Vec<GrainLocation>,
+}
+
+impl StreamOptimizedReader<'static> {
+    /// Opens a streamOptimized VMDK file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the VMDK file.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the reader on success.
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path).map_err(|e| Error::io(e, path))?;
+        let mmap = unsafe { Mmap::map(&file).map_err(|e| Error::io(e, path))? };
+        Self::new(Backing::Mapped(mmap))
+    }
+}
+
+impl<'a> StreamOptimizedReader<'a> {
+    /// Creates a reader over an in-memory streamOptimized VMDK.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The complete VMDK file contents.
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self> {
+        Self::new(Backing::Borrowed(data))
+    }
+
+    fn new(backing: Backing<'a>) -> Result<Self> {
+        let data = backing.as_slice();
+        if data.len() < SECTOR_SIZE as usize {
+            return Err(Error::vmdk("StreamOptimized header too short"));
+        }
+
+        let magic = read_u32(data, 0);
+        if magic != VMDK_MAGIC {
+            return Err(Error::vmdk(format!(
+                "Invalid VMDK magic: expected 0x{:X}, got 0x{:X}",
+                VMDK_MAGIC, magic
+            )));
+        }
+
+        let flags = read_u32(data, 8);
+        if flags & FLAG_COMPRESSED == 0 || flags & FLAG_MARKERS == 0 {
+            return Err(Error::vmdk(
+                "Not a streamOptimized VMDK: grains are not compressed with markers",
+            ));
+        }
+
+        let capacity_sectors = read_u64(data, 12);
+        let grain_size_sectors = read_u64(data, 20);
+        if grain_size_sectors == 0 {
+            return Err(Error::vmdk("Invalid streamOptimized VMDK: grain size is 0"));
+        }
+        let capacity_bytes = capacity_sectors.checked_mul(SECTOR_SIZE).ok_or_else(|| {
+            Error::vmdk(format!(
+                "StreamOptimized capacity of {} sectors overflows",
+                capacity_sectors
+            ))
+        })?;
+        let grain_size_bytes = grain_size_sectors.checked_mul(SECTOR_SIZE).ok_or_else(|| {
+            Error::vmdk(format!(
+                "StreamOptimized grain size of {} sectors overflows",
+                grain_size_sectors
+            ))
+        })?;
+
+        let grains = index_grains(data, capacity_sectors)?;
+
+        Ok(Self {
+            backing,
+            capacity_bytes,
+            grain_size_bytes,
+            grains,
+        })
+    }
+
+    /// Returns the virtual disk capacity in bytes.
+    pub fn capacity(&self) -> u64 {
+        self.capacity_bytes
+    }
+
+    /// Returns the grain size in bytes.
+    pub fn grain_size_bytes(&self) -> u64 {
+        self.grain_size_bytes
+    }
+
+    /// Returns the number of grains stored in the file.
+    pub fn grain_count(&self) -> usize {
+        self.grains.len()
+    }
+
+    /// Creates an iterator that decompresses the stored grains in LBA order.
+    ///
+    /// Unallocated grains are not yielded; they read as zeros.
+    pub fn grains(&self) -> StreamGrainIterator<'_> {
+        StreamGrainIterator {
+            data: self.backing.as_slice(),
+            capacity_bytes: self.capacity_bytes,
+            grain_size_bytes: self.grain_size_bytes,
+            locations: self.grains.iter(),
+        }
+    }
+}
+
+/// Iterator over the decompressed grains of a [`StreamOptimizedReader`].
+pub struct StreamGrainIterator<'r> {
+    data: &'r [u8],
+    capacity_bytes: u64,
+    grain_size_bytes: u64,
+    locations: std::slice::Iter<'r, GrainLocation>,
+}
+
+impl Iterator for StreamGrainIterator<'_> {
+    type Item = Result<DecompressedGrain>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let location = self.locations.next()?;
+        Some(self.decompress(location))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.locations.size_hint()
+    }
+}
+
+impl StreamGrainIterator<'_> {
+    fn decompress(&self, location: &GrainLocation) -> Result<DecompressedGrain> {
+        let compressed = &self.data[location.offset..location.offset + location.size];
+        let mut data = Vec::with_capacity(self.grain_size_bytes as usize);
+        DeflateDecoder::new(compressed)
+            .read_to_end(&mut data)
+            .map_err(|e| {
+                Error::vmdk(format!(
+                    "Failed to decompress grain at LBA {}: {}",
+                    location.lba, e
+                ))
+            })?;
+
+        // The LBA was checked against the capacity while indexing
+        if location.lba * SECTOR_SIZE + data.len() as u64 > self.capacity_bytes {
+            return Err(Error::vmdk(format!(
+                "Grain at LBA {} extends beyond the disk capacity of {} bytes",
+                location.lba, self.capacity_bytes
+            )));
+        }
+
+        Ok(DecompressedGrain {
+            lba: location.lba,
+            data,
+        })
+    }
+}
+
+/// Walk the markers from the first grain to the end-of-stream marker.
+fn index_grains(data: &[u8], capacity_sectors: u64) -> Result<Vec<GrainLocation>> {
+    let file_len = data.len() as u64;
+
+    // Grains follow the header, or the embedded descriptor when there is one
+    let overhead = std::cmp::max(read_u64(data, 64), 1);
+    let mut pos = overhead.saturating_mul(SECTOR_SIZE);
+    let mut grains = Vec::new();
+
+    loop {
+        if pos.checked_add(16).is_none_or(|end| end > file_len) {
+            return Err(Error::vmdk(format!(
+                "Missing end-of-stream marker: file ends at byte {}",
+                file_len
+            )));
+        }
+        let offset = pos as usize;
+        let value = read_u64(data, offset);
+        let size = u64::from(read_u32(data, offset + 8));
+
+        if size == 0 {
+            // Metadata marker: `value` is the number of sectors that follow
+            let marker_type = read_u32(data, offset + 12);
+            if marker_type == MarkerType::EndOfStream as u32 {
+                break;
+            }
+            if marker_type > MarkerType::Footer as u32 {
+                return Err(Error::vmdk(format!(
+                    "Unknown marker type {} at byte {}",
+                    marker_type, pos
+                )));
+            }
+            pos = value
+                .checked_add(1)
+                .and_then(|sectors| sectors.checked_mul(SECTOR_SIZE))
+                .and_then(|len| pos.checked_add(len))
+                .ok_or_else(|| Error::vmdk(format!("Marker at byte {} overflows", pos)))?;
+            continue;
+        }
+
+        // Grain marker: `value` is the grain's LBA
+        let lba = value;
+        if lba >= capacity_sectors {
+            return Err(Error::vmdk(format!(
+                "Grain at LBA {} is beyond the disk capacity of {} sectors",
+                lba, capacity_sectors
+            )));
+        }
+        let data_start = pos + 12;
+        if data_start + size > file_len {
+            return Err(Error::vmdk(format!(
+                "Grain at LBA {} extends beyond the end of the file",
+                lba
+            )));
+        }
+
+        grains.push(GrainLocation {
+            lba,
+            offset: data_start as usize,
+            size: size as usize,
+        });
+        pos = (data_start + size).div_ceil(SECTOR_SIZE) * SECTOR_SIZE;
+    }
+
+    grains.sort_by_key(|grain| grain.lba);
+    Ok(grains)
+}
+
+/// Read a little-endian `u32` at `offset`.
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+/// Read a little-endian `u64` at `offset`.
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vmdk::stream::{compress_grain, StreamVmdkWriter};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_grains_sorted_by_lba() {
+        let mut writer = StreamVmdkWriter::new(Cursor::new(Vec::new()), 1024 * 1024).unwrap();
+        for lba in [256u64, 0, 128] {
+            let data = vec![lba as u8 + 1; 65536];
+            writer.write_grain(lba, &compress_grain(&data, 6).unwrap()).unwrap();
+        }
+        let data = writer.finish().unwrap().into_inner();
+
+        let reader = StreamOptimizedReader::from_bytes(&data).unwrap();
+        let lbas: Vec<u64> = reader.grains().map(|grain| grain.unwrap().lba).collect();
+        assert_eq!(lbas, vec![0, 128, 256]);
+    }
+
+    #[test]
+    fn test_rejects_grain_beyond_capacity() {
+        let mut writer = StreamVmdkWriter::new(Cursor::new(Vec::new()), 65536).unwrap();
+        writer
+            .write_grain(128, &compress_grain(&[1u8; 512], 6).unwrap())
+            .unwrap();
+        let data = writer.finish().unwrap().into_inner();
+
+        let err = StreamOptimizedReader::from_bytes(&data).err().unwrap();
+        assert!(err.to_string().contains("beyond the disk capacity"));
+    }
+}
@@ -0,0 +1,105 @@
+//! StreamOptimized VMDK verification.
+//!
+//! This module walks a streamOptimized VMDK from start to finish, decompressing
+//! every grain to confirm the file is intact without extracting its contents.
+
+use crate::error::Result;
+use std::path::Path;
+
+use super::stream_reader::StreamOptimizedReader;
+
+/// Summary of a verified streamOptimized VMDK.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamVerifyReport {
+    /// Virtual disk capacity in bytes.
+    pub capacity_bytes: u64,
+    /// Grain size in bytes.
+    pub grain_size_bytes: u64,
+    /// Number of grains covering the whole capacity.
+    pub total_grains: u64,
+    /// Number of grains stored in the file.
+    pub allocated_grains: u64,
+    /// Number of grains not stored in the file, which read as zeros.
+    pub zero_grains: u64,
+}
+
+/// Verifies a streamOptimized VMDK by decompressing every grain.
+///
+/// The file is opened with a [`StreamOptimizedReader`], which checks every
+/// marker up to the end-of-stream marker, and each grain is then inflated.
+///
+/// # Arguments
+///
+/// * `path` - Path to the streamOptimized VMDK file.
+///
+/// # Returns
+///
+/// A `Result` containing the verification report, or a VMDK error describing
+/// the first problem found.
+pub fn verify_stream_vmdk(path: &Path) -> Result<StreamVerifyReport> {
+    verify_reader(&StreamOptimizedReader::open(path)?)
+}
+
+/// Decompresses every grain of an opened reader.
+fn verify_reader(reader: &StreamOptimizedReader<'_>) -> Result<StreamVerifyReport> {
+    let grain_size_bytes = reader.grain_size_bytes();
+    let total_grains = reader.capacity().div_ceil(grain_size_bytes);
+
+    let mut allocated_grains = 0u64;
+    for grain in reader.grains() {
+        let grain = grain?;
+        allocated_grains += (grain.data.len() as u64).div_ceil(grain_size_bytes);
+    }
+
+    Ok(StreamVerifyReport {
+        capacity_bytes: reader.capacity(),
+        grain_size_bytes,
+        total_grains,
+        allocated_grains,
+        zero_grains: total_grains.saturating_sub(allocated_grains),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vmdk::stream::{compress_grain, StreamVmdkWriter};
+    use std::io::Cursor;
+
+    /// Verifies an in-memory streamOptimized VMDK.
+    fn verify_stream_bytes(data: &[u8]) -> Result<StreamVerifyReport> {
+        verify_reader(&StreamOptimizedReader::from_bytes(data)?)
+    }
+
+    #[test]
+    fn test_verify_rejects_non_vmdk() {
+        let err = verify_stream_bytes(&[0u8; 1024]).unwrap_err();
+        assert!(err.to_string().contains("Invalid VMDK magic"));
+    }
+
+    #[test]
+    fn test_verify_detects_corrupt_grain() {
+        let mut writer = StreamVmdkWriter::new(Cursor::new(Vec::new()), 1024 * 1024).unwrap();
+        writer
+            .write_grain(0, &compress_grain(&[0x42u8; 65536], 6).unwrap())
+            .unwrap();
+        let mut data = writer.finish().unwrap().into_inner();
+
+        // Garble the compressed stream just after the grain marker
+        for byte in &mut data[524..540] {
+            *byte = 0xFF;
+        }
+
+        let err = verify_stream_bytes(&data).unwrap_err();
+        assert!(err.to_string().contains("LBA 0"), "{}", err);
+    }
+
+    #[test]
+    fn test_verify_detects_truncation() {
+        let writer = StreamVmdkWriter::new(Cursor::new(Vec::new()), 1024 * 1024).unwrap();
+        let data = writer.finish().unwrap().into_inner();
+
+        let err = verify_stream_bytes(&data[..data.len() - 512]).unwrap_err();
+        assert!(err.to_string().contains("end-of-stream"));
+    }
+}
@@ -1,10 +1,10 @@
 //! VMX file parsing.
 //!
 //! This module handles parsing VMware VMX configuration files to extract
-//! VM metadata and disk references.
+//! VM metadata and disk references, and writing configurations back out.
 
 use crate::error::{Error, Result};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::Path;
 
@@ -19,6 +19,30 @@ pub struct DiskConfig {
     pub unit: u32,
 }
 
+/// Configuration for a SCSI controller.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControllerConfig {
+    /// The controller name (e.g., "scsi0").
+    pub name: String,
+    /// Bus sharing mode for clustering: "virtual" or "physical".
+    ///
+    /// `None` when the bus is not shared.
+    pub shared_bus: Option<String>,
+    /// The adapter model (e.g., "lsilogic", "pvscsi", "buslogic"), lowercased.
+    ///
+    /// `None` when the VMX leaves it to the disk descriptors.
+    pub virtual_dev: Option<String>,
+}
+
+/// Configuration for a CD/DVD drive attached to the VM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CdromConfig {
+    /// The controller type and number (e.g., "ide1", "sata0").
+    pub controller: String,
+    /// The unit number on the controller.
+    pub unit: u32,
+}
+
 /// Configuration for a network adapter attached to the VM.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct NetworkConfig {
@@ -28,8 +52,82 @@ pub struct NetworkConfig {
     pub virtual_dev: Option<String>,
     /// The network name this adapter is connected to (e.g., "NAT", "Bridged").
     pub network_name: Option<String>,
+    /// Whether the adapter is connected when the VM powers on.
+    pub start_connected: bool,
+    /// How the adapter reaches the host (e.g., "bridged", "nat", "hostonly"), lowercased.
+    pub connection_type: Option<String>,
+    /// Whether receive-side scaling is enabled (vmxnet3), if set.
+    pub rss: Option<bool>,
+    /// Number of transmit/receive queue pairs (vmxnet3), if set.
+    pub num_queue_pairs: Option<u32>,
+}
+
+/// Firmware the VM boots with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FirmwareType {
+    /// Legacy BIOS, used when the VMX has no `firmware` key.
+    #[default]
+    Bios,
+    /// UEFI firmware (`firmware = "efi"`).
+    Efi,
+}
+
+impl FirmwareType {
+    /// Returns the value VMware uses for this firmware (`bios` or `efi`).
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FirmwareType::Bios => "bios",
+            FirmwareType::Efi => "efi",
+        }
+    }
+}
+
+/// Product details shown by the OVF's ProductSection.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProductInfo {
+    /// The product name (e.g., "Acme Firewall").
+    pub product: Option<String>,
+    /// The product vendor (e.g., "Acme Inc.").
+    pub vendor: Option<String>,
+    /// The short product version (e.g., "4.2").
+    pub version: Option<String>,
+    /// The full product version (e.g., "4.2.1-build-1234").
+    pub full_version: Option<String>,
 }
 
+/// VMware Tools settings carried over to the OVF as `vmw:ExtraConfig`.
+///
+/// Appliances rely on these to run their guest customization scripts after
+/// deployment. Matched case-insensitively.
+pub const GUEST_CUSTOMIZATION_KEYS: &[&str] = &[
+    "tools.syncTime",
+    "tools.upgrade.policy",
+    "tools.remindInstall",
+    "tools.guest.desktop.autolock",
+    "tools.guestlib.enableHostInfo",
+    "toolScripts.afterPowerOn",
+    "toolScripts.afterResume",
+    "toolScripts.beforePowerOff",
+    "toolScripts.beforeSuspend",
+];
+
+/// VMX keys present in VMs encrypted with a key provider.
+const ENCRYPTION_KEYS: &[&str] = &["encryption.keySafe", "encryption.data"];
+
+/// Name of a VM whose `displayName` is blank and whose VMX file name has no
+/// stem to use instead.
+pub const FALLBACK_DISPLAY_NAME: &str = "VM";
+
+/// Prefix of the NUMA placement settings, such as `numa.nodeAffinity`.
+const NUMA_PREFIX: &str = "numa.";
+
+/// Virtual socket layout, which together with the `numa.*` settings decides
+/// the NUMA topology the guest sees.
+const CORES_PER_SOCKET_KEY: &str = "cpuid.coresPerSocket";
+
+/// Prefix of the guest variables readable from inside the VM.
+const GUESTINFO_PREFIX: &str = "guestinfo.";
+
 /// Parsed VMX configuration containing VM settings.
 #[derive(Debug, Clone)]
 pub struct VmxConfig {
@@ -45,10 +143,165 @@ pub struct VmxConfig {
     pub disks: Vec<DiskConfig>,
     /// List of network adapter configurations.
     pub networks: Vec<NetworkConfig>,
+    /// List of CD/DVD drives on IDE or SATA controllers.
+    pub cdroms: Vec<CdromConfig>,
+    /// List of present SCSI controllers.
+    pub controllers: Vec<ControllerConfig>,
+    /// Firmware the VM boots with, from the `firmware` key.
+    pub firmware: FirmwareType,
+    /// File holding the firmware's variables, such as the EFI boot order,
+    /// from the `nvram` key.
+    pub nvram: Option<String>,
+    /// Free-form description of the VM, from the `annotation` key with its
+    /// `|XX` escapes decoded; empty if there is none.
+    pub annotation: String,
     /// Raw key-value pairs from the VMX file.
     pub raw: HashMap<String, String>,
 }
 
+impl VmxConfig {
+    /// Returns the virtual hardware version in OVF form (e.g. `vmx-21`).
+    ///
+    /// Taken from the `virtualHW.version` key; `None` if it is missing or
+    /// not a number.
+    pub fn hardware_version(&self) -> Option<String> {
+        self.raw
+            .get("virtualHW.version")
+            .and_then(|v| v.trim().parse::<u32>().ok())
+            .map(|v| format!("vmx-{}", v))
+    }
+
+    /// Returns the VM's BIOS UUID in canonical form
+    /// (e.g. `564d8f2c-1a4b-3e7d-9a0b-1c2d3e4f5061`).
+    ///
+    /// Taken from the `uuid.bios` key, which VMware writes as space-separated
+    /// hex bytes; `None` if it is missing or malformed.
+    pub fn uuid(&self) -> Option<String> {
+        let hex: String = self
+            .raw
+            .get("uuid.bios")?
+            .chars()
+            .filter(|c| *c != ' ' && *c != '-')
+            .collect::<String>()
+            .to_ascii_lowercase();
+        if hex.len() != 32 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        Some(format!(
+            "{}-{}-{}-{}-{}",
+            &hex[..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..]
+        ))
+    }
+
+    /// Returns the BIOS boot order, e.g. `["cdrom", "hdd"]`.
+    ///
+    /// Taken from the comma-separated `bios.bootOrder` key; empty if unset.
+    pub fn boot_order(&self) -> Vec<String> {
+        self.raw
+            .get("bios.bootOrder")
+            .map(|order| {
+                order
+                    .split(',')
+                    .map(|device| device.trim().to_ascii_lowercase())
+                    .filter(|device| !device.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns the product details to describe the appliance with.
+    ///
+    /// Taken from the `guestinfo.product.name`, `guestinfo.product.vendor`,
+    /// `guestinfo.product.version` and `guestinfo.product.fullVersion` keys,
+    /// matched case-insensitively; `None` if none of them is set.
+    pub fn product_info(&self) -> Option<ProductInfo> {
+        let get = |name: &str| {
+            let key = format!("{}product.{}", GUESTINFO_PREFIX, name);
+            self.raw
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(&key))
+                .map(|(_, value)| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let product = ProductInfo {
+            product: get("name"),
+            vendor: get("vendor"),
+            version: get("version"),
+            full_version: get("fullVersion"),
+        };
+        (product != ProductInfo::default()).then_some(product)
+    }
+
+    /// Returns the guest customization settings to preserve, sorted by key.
+    ///
+    /// Only keys in [`GUEST_CUSTOMIZATION_KEYS`] are included, plus the
+    /// `guestinfo.*` variables when `include_guestinfo` is set. Those are left
+    /// out by default because they often carry passwords or tokens.
+    pub fn guest_customization(&self, include_guestinfo: bool) -> Vec<(&str, &str)> {
+        let mut settings: Vec<(&str, &str)> = self
+            .raw
+            .iter()
+            .filter(|(key, _)| {
+                GUEST_CUSTOMIZATION_KEYS
+                    .iter()
+                    .any(|allowed| key.eq_ignore_ascii_case(allowed))
+                    || (include_guestinfo
+                        && key.len() > GUESTINFO_PREFIX.len()
+                        && key
+                            .get(..GUESTINFO_PREFIX.len())
+                            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(GUESTINFO_PREFIX)))
+            })
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        settings.sort();
+        settings
+    }
+
+    /// Returns true if the VM is encrypted with a key provider.
+    ///
+    /// Its disks can't be read without the key, so it can't be exported.
+    pub fn is_encrypted(&self) -> bool {
+        self.raw
+            .keys()
+            .any(|key| ENCRYPTION_KEYS.iter().any(|k| key.eq_ignore_ascii_case(k)))
+    }
+
+    /// Returns true if the VMX sets `displayName` to an empty or
+    /// whitespace-only value.
+    ///
+    /// [`parse_vmx`] names such VMs after the VMX file instead.
+    pub fn has_blank_display_name(&self) -> bool {
+        self.raw
+            .get("displayName")
+            .is_some_and(|name| name.trim().is_empty())
+    }
+
+    /// Returns the NUMA topology settings, sorted by key.
+    ///
+    /// These are the `numa.*` keys and `cpuid.coresPerSocket`, matched
+    /// case-insensitively.
+    pub fn numa_settings(&self) -> Vec<(&str, &str)> {
+        let mut settings: Vec<(&str, &str)> = self
+            .raw
+            .iter()
+            .filter(|(key, _)| {
+                key.eq_ignore_ascii_case(CORES_PER_SOCKET_KEY)
+                    || (key.len() > NUMA_PREFIX.len()
+                        && key
+                            .get(..NUMA_PREFIX.len())
+                            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(NUMA_PREFIX)))
+            })
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        settings.sort();
+        settings
+    }
+}
+
 /// Parse a VMX file and extract VM configuration.
 ///
 /// # Arguments
@@ -63,14 +316,94 @@ pub struct VmxConfig {
 ///
 /// Returns an error if the file cannot be read or if required fields are missing.
 pub fn parse_vmx(path: &Path) -> Result<VmxConfig> {
-    let content = fs::read_to_string(path).map_err(|e| Error::io(e, path))?;
-    parse_vmx_content(&content)
+    let content = read_vmx_text(path)?;
+    let mut config = parse_vmx_content(&content)?;
+    apply_display_name_fallback(&mut config, path);
+    Ok(config)
+}
+
+/// Parse a VMX file, rejecting values that can't be interpreted.
+///
+/// Like [`parse_vmx`], but an unparseable `memsize` or `numvcpus` is an
+/// error instead of falling back to a default.
+///
+/// # Arguments
+///
+/// * `path` - Path to the VMX file to parse.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or a numeric setting is invalid.
+pub fn parse_vmx_strict(path: &Path) -> Result<VmxConfig> {
+    let content = read_vmx_text(path)?;
+    let mut config = parse_vmx_content_with(&content, true)?;
+    apply_display_name_fallback(&mut config, path);
+    Ok(config)
+}
+
+/// Name a VM whose `displayName` is blank after its VMX file, or
+/// [`FALLBACK_DISPLAY_NAME`] if the file name has no stem either.
+///
+/// A blank name would otherwise give an empty OVF id and an OVA named
+/// `.ova`. See [`VmxConfig::has_blank_display_name`].
+fn apply_display_name_fallback(config: &mut VmxConfig, path: &Path) {
+    if config.has_blank_display_name() {
+        config.display_name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().trim().to_string())
+            .filter(|stem| !stem.is_empty())
+            .unwrap_or_else(|| FALLBACK_DISPLAY_NAME.to_string());
+    }
+}
+
+/// Read a VMX file as text, transcoding it to UTF-8 if needed.
+///
+/// A UTF-8 byte order mark is stripped, and files starting with a UTF-16
+/// byte order mark, as some Windows editors save them, are decoded as
+/// UTF-16. Files without a BOM must be UTF-8.
+///
+/// # Errors
+///
+/// Returns a VMX parse error if the content isn't valid in its encoding.
+fn read_vmx_text(path: &Path) -> Result<String> {
+    let bytes = fs::read(path).map_err(|e| Error::io(e, path))?;
+    let invalid = |encoding: &str| {
+        Error::vmx_parse(format!(
+            "VMX file '{}' is not valid {}",
+            path.display(),
+            encoding
+        ))
+    };
+
+    let utf16 = |data: &[u8], decode: fn([u8; 2]) -> u16| {
+        if !data.len().is_multiple_of(2) {
+            return Err(invalid("UTF-16"));
+        }
+        let units = data.chunks_exact(2).map(|pair| decode([pair[0], pair[1]]));
+        char::decode_utf16(units)
+            .collect::<std::result::Result<String, _>>()
+            .map_err(|_| invalid("UTF-16"))
+    };
+
+    match bytes.as_slice() {
+        [0xEF, 0xBB, 0xBF, rest @ ..] => {
+            String::from_utf8(rest.to_vec()).map_err(|_| invalid("UTF-8"))
+        }
+        [0xFF, 0xFE, rest @ ..] => utf16(rest, u16::from_le_bytes),
+        [0xFE, 0xFF, rest @ ..] => utf16(rest, u16::from_be_bytes),
+        _ => String::from_utf8(bytes).map_err(|_| invalid("UTF-8")),
+    }
 }
 
 /// Parse VMX content from a string.
 ///
 /// This is useful for testing without file I/O.
 fn parse_vmx_content(content: &str) -> Result<VmxConfig> {
+    parse_vmx_content_with(content, false)
+}
+
+/// Parse VMX content, failing on invalid numeric settings when `strict`.
+fn parse_vmx_content_with(content: &str, strict: bool) -> Result<VmxConfig> {
     let raw = parse_key_value_pairs(content);
 
     let display_name = raw
@@ -83,18 +416,25 @@ fn parse_vmx_content(content: &str) -> Result<VmxConfig> {
         .cloned()
         .unwrap_or_else(|| "other".to_string());
 
-    let memory_mb = raw
-        .get("memsize")
-        .and_then(|s| s.parse::<u32>().ok())
-        .unwrap_or(1024);
-
-    let num_cpus = raw
-        .get("numvcpus")
-        .and_then(|s| s.parse::<u32>().ok())
-        .unwrap_or(1);
+    let memory_mb = numeric_setting(&raw, "memsize", 1024, strict)?;
+    let num_cpus = numeric_setting(&raw, "numvcpus", 1, strict)?;
 
     let disks = extract_disks(&raw);
     let networks = extract_networks(&raw);
+    let cdroms = extract_cdroms(&raw);
+    let controllers = extract_controllers(&raw);
+    let firmware = match raw.get("firmware") {
+        Some(value) if value.trim().eq_ignore_ascii_case("efi") => FirmwareType::Efi,
+        _ => FirmwareType::Bios,
+    };
+    let nvram = raw
+        .get("nvram")
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+    let annotation = raw
+        .get("annotation")
+        .cloned()
+        .unwrap_or_default();
 
     Ok(VmxConfig {
         display_name,
@@ -103,15 +443,200 @@ fn parse_vmx_content(content: &str) -> Result<VmxConfig> {
         num_cpus,
         disks,
         networks,
+        cdroms,
+        controllers,
+        firmware,
+        nvram,
+        annotation,
         raw,
     })
 }
 
+/// Decode the `|XX` escapes VMware writes in values, where `XX` is the
+/// hex code of the byte, such as `|0A` for a newline and `|22` for a quote.
+///
+/// A `|` not followed by two hex digits is kept as is.
+fn decode_pipe_escapes(value: &str) -> String {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut rest = value.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let code = tail
+            .get(..2)
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (byte, code) {
+            (b'|', Some(code)) => {
+                bytes.push(code);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Escape the characters a quoted VMX value can't hold as `|XX`, the
+/// inverse of [`decode_pipe_escapes`].
+fn encode_pipe_escapes(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '|' | '"' | '\n' | '\r' => encoded.push_str(&format!("|{:02X}", c as u32)),
+            c => encoded.push(c),
+        }
+    }
+    encoded
+}
+
+/// Read a whole-number setting, falling back to `default` when it is absent.
+///
+/// An invalid value also falls back to `default`, unless `strict` is set.
+fn numeric_setting(
+    raw: &HashMap<String, String>,
+    key: &str,
+    default: u32,
+    strict: bool,
+) -> Result<u32> {
+    let Some(value) = raw.get(key) else {
+        return Ok(default);
+    };
+    match parse_whole_number(value) {
+        Some(number) => Ok(number),
+        None if strict => Err(Error::vmx_parse(format!(
+            "Invalid value for {}: \"{}\"",
+            key, value
+        ))),
+        None => Ok(default),
+    }
+}
+
+/// Parse a whole number, tolerating surrounding whitespace and a zero
+/// fraction (e.g. `" 2 "` or `"4096.0"`).
+fn parse_whole_number(value: &str) -> Option<u32> {
+    let value = value.trim();
+    let integer = match value.split_once('.') {
+        Some((integer, fraction)) => {
+            if fraction.is_empty() || fraction.bytes().any(|b| b != b'0') {
+                return None;
+            }
+            integer
+        }
+        None => value,
+    };
+    integer.parse::<u32>().ok()
+}
+
+/// Write a VMX configuration to a file.
+///
+/// # Arguments
+///
+/// * `config` - The configuration to write.
+/// * `path` - Path of the VMX file to create.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+pub fn write_vmx(config: &VmxConfig, path: &Path) -> Result<()> {
+    fs::write(path, vmx_to_string(config)).map_err(|e| Error::io(e, path))
+}
+
+/// Serialize a VMX configuration to VMX text.
+///
+/// Entries from `raw` are written first and then overridden by the typed
+/// fields, so parsing the output yields the same typed fields. Keys are
+/// written in sorted order. Device entries left in `raw` for devices no
+/// longer in the typed lists are written as-is.
+/// Quotes, line breaks and `|` in values are written as `|XX` escapes.
+pub fn vmx_to_string(config: &VmxConfig) -> String {
+    let mut entries: BTreeMap<String, String> = config
+        .raw
+        .iter()
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    let mut set = |key: String, value: String| {
+        entries.insert(key, value);
+    };
+
+    set(".encoding".to_string(), "UTF-8".to_string());
+    set("displayName".to_string(), config.display_name.clone());
+    set("guestOS".to_string(), config.guest_os.clone());
+    set("memsize".to_string(), config.memory_mb.to_string());
+    set("numvcpus".to_string(), config.num_cpus.to_string());
+    if config.firmware != FirmwareType::Bios || config.raw.contains_key("firmware") {
+        set("firmware".to_string(), config.firmware.as_str().to_string());
+    }
+    if let Some(nvram) = &config.nvram {
+        set("nvram".to_string(), nvram.clone());
+    }
+    if !config.annotation.is_empty() {
+        set("annotation".to_string(), config.annotation.clone());
+    }
+
+    for disk in &config.disks {
+        let prefix = format!("{}:{}", disk.controller, disk.unit);
+        set(format!("{}.present", disk.controller), "TRUE".to_string());
+        set(format!("{}.present", prefix), "TRUE".to_string());
+        set(format!("{}.fileName", prefix), disk.file_name.clone());
+    }
+
+    for controller in &config.controllers {
+        set(format!("{}.present", controller.name), "TRUE".to_string());
+        if let Some(shared_bus) = &controller.shared_bus {
+            set(format!("{}.sharedBus", controller.name), shared_bus.clone());
+        }
+        if let Some(virtual_dev) = &controller.virtual_dev {
+            set(format!("{}.virtualDev", controller.name), virtual_dev.clone());
+        }
+    }
+
+    for cdrom in &config.cdroms {
+        let prefix = format!("{}:{}", cdrom.controller, cdrom.unit);
+        set(format!("{}.present", cdrom.controller), "TRUE".to_string());
+        set(format!("{}.present", prefix), "TRUE".to_string());
+        if !config.raw.contains_key(&format!("{}.deviceType", prefix)) {
+            set(format!("{}.deviceType", prefix), "atapi-cdrom".to_string());
+        }
+    }
+
+    for network in &config.networks {
+        set(format!("{}.present", network.name), "TRUE".to_string());
+        if let Some(virtual_dev) = &network.virtual_dev {
+            set(format!("{}.virtualDev", network.name), virtual_dev.clone());
+        }
+        if let Some(network_name) = &network.network_name {
+            set(format!("{}.networkName", network.name), network_name.clone());
+        }
+        let start_connected = if network.start_connected { "TRUE" } else { "FALSE" };
+        set(format!("{}.startConnected", network.name), start_connected.to_string());
+        if let Some(connection_type) = &network.connection_type {
+            set(format!("{}.connectionType", network.name), connection_type.clone());
+        }
+        if let Some(rss) = network.rss {
+            let rss = if rss { "TRUE" } else { "FALSE" };
+            set(format!("{}.rss", network.name), rss.to_string());
+        }
+        if let Some(num_queue_pairs) = network.num_queue_pairs {
+            set(format!("{}.numqps", network.name), num_queue_pairs.to_string());
+        }
+    }
+
+    entries
+        .iter()
+        .map(|(key, value)| format!("{} = \"{}\"\n", key, encode_pipe_escapes(value)))
+        .collect()
+}
+
 /// Parse key-value pairs from VMX content.
 ///
 /// Handles both quoted and unquoted values:
 /// - `key = "value"` -> ("key", "value")
 /// - `key = value` -> ("key", "value")
+///
+/// `|XX` escapes in values are decoded, see [`decode_pipe_escapes`].
 fn parse_key_value_pairs(content: &str) -> HashMap<String, String> {
     let mut map = HashMap::new();
 
@@ -135,7 +660,7 @@ fn parse_key_value_pairs(content: &str) -> HashMap<String, String> {
                 value
             };
 
-            map.insert(key.to_string(), value.to_string());
+            map.insert(key.to_string(), decode_pipe_escapes(value));
         }
     }
 
@@ -166,40 +691,27 @@ fn extract_disks(raw: &HashMap<String, String>) -> Vec<DiskConfig> {
 
         // Parse the controller:unit prefix (e.g., "scsi0:0")
         let prefix = &key[..key.len() - ".fileName".len()];
-
-        // Check if it starts with a known controller type
-        let mut matched = false;
-        for ctrl_prefix in &controller_prefixes {
-            if prefix.starts_with(*ctrl_prefix) {
-                matched = true;
-                break;
-            }
-        }
-
-        if !matched {
+        let Some((controller, unit_str)) = prefix.split_once(':') else {
+            continue;
+        };
+        if !is_device_name(controller, &controller_prefixes) {
             continue;
         }
 
-        // Parse controller and unit from "scsi0:0" format
-        if let Some(colon_pos) = prefix.find(':') {
-            let controller = &prefix[..colon_pos];
-            let unit_str = &prefix[colon_pos + 1..];
-
-            if let Ok(unit) = unit_str.parse::<u32>() {
-                // Check if this disk is present
-                let present_key = format!("{}.present", prefix);
-                let is_present = raw
-                    .get(&present_key)
-                    .map(|v| v.eq_ignore_ascii_case("TRUE"))
-                    .unwrap_or(false);
-
-                if is_present {
-                    disks.push(DiskConfig {
-                        file_name: value.clone(),
-                        controller: controller.to_string(),
-                        unit,
-                    });
-                }
+        if let Ok(unit) = unit_str.parse::<u32>() {
+            // Check if this disk is present
+            let present_key = format!("{}.present", prefix);
+            let is_present = raw
+                .get(&present_key)
+                .map(|v| v.eq_ignore_ascii_case("TRUE"))
+                .unwrap_or(false);
+
+            if is_present {
+                disks.push(DiskConfig {
+                    file_name: value.clone(),
+                    controller: controller.to_string(),
+                    unit,
+                });
             }
         }
     }
@@ -214,23 +726,110 @@ fn extract_disks(raw: &HashMap<String, String>) -> Vec<DiskConfig> {
     disks
 }
 
+/// Extract SCSI controller configurations from the raw key-value pairs.
+///
+/// Looks for patterns like:
+/// - scsi0.present = "TRUE"
+/// - scsi1.sharedBus = "physical"
+/// - scsi0.virtualDev = "pvscsi"
+fn extract_controllers(raw: &HashMap<String, String>) -> Vec<ControllerConfig> {
+    let mut controllers = Vec::new();
+
+    for (key, value) in raw {
+        let Some(name) = key.strip_suffix(".present") else {
+            continue;
+        };
+        if !is_device_name(name, &["scsi"]) || !value.eq_ignore_ascii_case("TRUE") {
+            continue;
+        }
+
+        let shared_bus = raw
+            .get(&format!("{}.sharedBus", name))
+            .map(|v| v.to_ascii_lowercase())
+            .filter(|v| v != "none");
+        let virtual_dev = raw
+            .get(&format!("{}.virtualDev", name))
+            .map(|v| v.to_ascii_lowercase());
+
+        controllers.push(ControllerConfig {
+            name: name.to_string(),
+            shared_bus,
+            virtual_dev,
+        });
+    }
+
+    controllers.sort_by(|a, b| a.name.cmp(&b.name));
+    controllers
+}
+
+/// Extract CD/DVD drive configurations from the raw key-value pairs.
+///
+/// Looks for patterns like:
+/// - ide1:0.deviceType = "cdrom-image"
+/// - sata0:1.deviceType = "atapi-cdrom"
+fn extract_cdroms(raw: &HashMap<String, String>) -> Vec<CdromConfig> {
+    let mut cdroms = Vec::new();
+
+    for (key, value) in raw {
+        let Some(prefix) = key.strip_suffix(".deviceType") else {
+            continue;
+        };
+        if !matches!(
+            value.to_ascii_lowercase().as_str(),
+            "cdrom-image" | "cdrom-raw" | "atapi-cdrom"
+        ) {
+            continue;
+        }
+        let Some((controller, unit_str)) = prefix.split_once(':') else {
+            continue;
+        };
+        if !is_device_name(controller, &["ide", "sata"]) {
+            continue;
+        }
+        let Ok(unit) = unit_str.parse::<u32>() else {
+            continue;
+        };
+
+        let is_present = raw
+            .get(&format!("{}.present", prefix))
+            .is_some_and(|v| v.eq_ignore_ascii_case("TRUE"));
+        if is_present {
+            cdroms.push(CdromConfig {
+                controller: controller.to_string(),
+                unit,
+            });
+        }
+    }
+
+    cdroms.sort_by(|a, b| {
+        a.controller
+            .cmp(&b.controller)
+            .then_with(|| a.unit.cmp(&b.unit))
+    });
+
+    cdroms
+}
+
 /// Extract network configurations from the raw key-value pairs.
 ///
 /// Looks for patterns like:
 /// - ethernet0.present = "TRUE"
 /// - ethernet0.virtualDev = "e1000"
 /// - ethernet0.networkName = "NAT"
+/// - ethernet0.startConnected = "FALSE"
+/// - ethernet0.connectionType = "hostonly"
+/// - ethernet0.rss = "TRUE"
+/// - ethernet0.numqps = "8"
 fn extract_networks(raw: &HashMap<String, String>) -> Vec<NetworkConfig> {
     let mut networks = Vec::new();
     let mut network_names: Vec<String> = Vec::new();
 
     // First, find all present network adapters
     for (key, value) in raw {
-        if key.starts_with("ethernet")
-            && key.ends_with(".present")
-            && value.eq_ignore_ascii_case("TRUE")
-        {
-            let name = &key[..key.len() - ".present".len()];
+        let Some(name) = key.strip_suffix(".present") else {
+            continue;
+        };
+        if is_device_name(name, &["ethernet"]) && value.eq_ignore_ascii_case("TRUE") {
             network_names.push(name.to_string());
         }
     }
@@ -242,20 +841,48 @@ fn extract_networks(raw: &HashMap<String, String>) -> Vec<NetworkConfig> {
     for name in network_names {
         let virtual_dev_key = format!("{}.virtualDev", name);
         let network_name_key = format!("{}.networkName", name);
+        let start_connected_key = format!("{}.startConnected", name);
+        let connection_type_key = format!("{}.connectionType", name);
 
         let virtual_dev = raw.get(&virtual_dev_key).cloned();
         let network_name = raw.get(&network_name_key).cloned();
+        // Adapters start connected unless explicitly disabled
+        let start_connected = raw
+            .get(&start_connected_key)
+            .is_none_or(|v| !v.eq_ignore_ascii_case("FALSE"));
+        let connection_type = raw.get(&connection_type_key).map(|v| v.to_lowercase());
+        let rss = raw
+            .get(&format!("{}.rss", name))
+            .map(|v| v.eq_ignore_ascii_case("TRUE"));
+        let num_queue_pairs = raw
+            .get(&format!("{}.numqps", name))
+            .and_then(|v| parse_whole_number(v));
 
         networks.push(NetworkConfig {
             name,
             virtual_dev,
             network_name,
+            start_connected,
+            connection_type,
+            rss,
+            num_queue_pairs,
         });
     }
 
     networks
 }
 
+/// Whether `name` is one of the device `kinds` followed by its number (e.g. "scsi0").
+///
+/// Host-specific keys that merely share a prefix, such as
+/// `ethernet0.filter0.present` or `scsiHost:0.fileName`, are not devices.
+fn is_device_name(name: &str, kinds: &[&str]) -> bool {
+    kinds.iter().any(|kind| {
+        name.strip_prefix(kind)
+            .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -408,4 +1035,309 @@ mod tests {
         assert_eq!(config.disks.len(), 1);
         assert_eq!(config.networks.len(), 1);
     }
+
+    #[test]
+    fn test_hardware_version() {
+        let config = parse_vmx_content("virtualHW.version = \"19\"").unwrap();
+        assert_eq!(config.hardware_version(), Some("vmx-19".to_string()));
+
+        let config = parse_vmx_content("virtualHW.version = \"bogus\"").unwrap();
+        assert_eq!(config.hardware_version(), None);
+
+        let config = parse_vmx_content("").unwrap();
+        assert_eq!(config.hardware_version(), None);
+    }
+
+    #[test]
+    fn test_parse_numeric_settings_tolerant() {
+        let content = r#"
+            memsize = "4096.0"
+            numvcpus = " 2 "
+        "#;
+        let config = parse_vmx_content_with(content, true).unwrap();
+        assert_eq!(config.memory_mb, 4096);
+        assert_eq!(config.num_cpus, 2);
+    }
+
+    #[test]
+    fn test_parse_numeric_settings_invalid() {
+        let content = r#"
+            memsize = "abc"
+            numvcpus = "2.5"
+        "#;
+
+        // Lenient parsing falls back to the defaults
+        let config = parse_vmx_content(content).unwrap();
+        assert_eq!(config.memory_mb, 1024);
+        assert_eq!(config.num_cpus, 1);
+
+        let err = parse_vmx_content_with(content, true).unwrap_err();
+        assert!(matches!(err, Error::VmxParse { .. }));
+        assert!(err.to_string().contains("memsize"));
+    }
+
+    #[test]
+    fn test_parse_whole_number() {
+        assert_eq!(parse_whole_number("8"), Some(8));
+        assert_eq!(parse_whole_number("8.00"), Some(8));
+        assert_eq!(parse_whole_number("8."), None);
+        assert_eq!(parse_whole_number("-8"), None);
+        assert_eq!(parse_whole_number(""), None);
+    }
+
+    #[test]
+    fn test_uuid() {
+        let content = r#"
+            uuid.bios = "56 4d 8f 2c 1a 4b 3e 7d-9A 0B 1C 2D 3E 4F 50 61"
+        "#;
+        let config = parse_vmx_content(content).unwrap();
+        assert_eq!(
+            config.uuid().as_deref(),
+            Some("564d8f2c-1a4b-3e7d-9a0b-1c2d3e4f5061")
+        );
+    }
+
+    #[test]
+    fn test_uuid_missing_or_malformed() {
+        let config = parse_vmx_content("displayName = \"VM\"").unwrap();
+        assert_eq!(config.uuid(), None);
+
+        let config = parse_vmx_content("uuid.bios = \"56 4d 8f\"").unwrap();
+        assert_eq!(config.uuid(), None);
+
+        let config = parse_vmx_content(
+            "uuid.bios = \"zz 4d 8f 2c 1a 4b 3e 7d-9a 0b 1c 2d 3e 4f 50 61\"",
+        )
+        .unwrap();
+        assert_eq!(config.uuid(), None);
+    }
+
+    #[test]
+    fn test_parse_network_connection_type() {
+        let content = r#"
+            ethernet0.present = "TRUE"
+            ethernet0.connectionType = "hostonly"
+            ethernet1.present = "TRUE"
+            ethernet1.connectionType = "NAT"
+            ethernet2.present = "TRUE"
+        "#;
+        let config = parse_vmx_content(content).unwrap();
+
+        let types: Vec<Option<&str>> = config
+            .networks
+            .iter()
+            .map(|n| n.connection_type.as_deref())
+            .collect();
+        assert_eq!(types, vec![Some("hostonly"), Some("nat"), None]);
+    }
+
+    #[test]
+    fn test_parse_network_multiqueue() {
+        let content = r#"
+            ethernet0.present = "TRUE"
+            ethernet0.virtualDev = "vmxnet3"
+            ethernet0.rss = "TRUE"
+            ethernet0.numqps = "8"
+            ethernet1.present = "TRUE"
+        "#;
+        let config = parse_vmx_content(content).unwrap();
+
+        assert_eq!(config.networks[0].rss, Some(true));
+        assert_eq!(config.networks[0].num_queue_pairs, Some(8));
+        assert_eq!(config.networks[1].rss, None);
+        assert_eq!(config.networks[1].num_queue_pairs, None);
+    }
+
+    #[test]
+    fn test_parse_network_start_connected() {
+        let content = r#"
+            ethernet0.present = "TRUE"
+            ethernet0.startConnected = "FALSE"
+            ethernet1.present = "TRUE"
+            ethernet1.startConnected = "TRUE"
+            ethernet2.present = "TRUE"
+        "#;
+        let config = parse_vmx_content(content).unwrap();
+
+        let connected: Vec<bool> = config.networks.iter().map(|n| n.start_connected).collect();
+        assert_eq!(connected, vec![false, true, true]);
+    }
+
+    #[test]
+    fn test_parse_cdroms() {
+        let content = r#"
+            ide1:0.present = "TRUE"
+            ide1:0.deviceType = "cdrom-image"
+            ide1:0.fileName = "ubuntu.iso"
+            sata0:1.present = "TRUE"
+            sata0:1.deviceType = "atapi-cdrom"
+            sata0:2.present = "FALSE"
+            sata0:2.deviceType = "cdrom-raw"
+            scsi0:1.present = "TRUE"
+            scsi0:1.deviceType = "cdrom-image"
+        "#;
+        let config = parse_vmx_content(content).unwrap();
+
+        assert_eq!(
+            config.cdroms,
+            vec![
+                CdromConfig {
+                    controller: "ide1".to_string(),
+                    unit: 0,
+                },
+                CdromConfig {
+                    controller: "sata0".to_string(),
+                    unit: 1,
+                },
+            ]
+        );
+        assert!(config.disks.is_empty());
+    }
+
+    #[test]
+    fn test_guest_customization() {
+        let config = parse_vmx_content(
+            r#"
+            tools.syncTime = "TRUE"
+            toolScripts.afterPowerOn = "TRUE"
+            tools.unknownSetting = "1"
+            guestinfo.ovfEnv = "<Environment/>"
+            guestinfo.password = "hunter2"
+            guestinf€x = "1"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.guest_customization(false),
+            vec![("toolScripts.afterPowerOn", "TRUE"), ("tools.syncTime", "TRUE")]
+        );
+        assert_eq!(
+            config.guest_customization(true),
+            vec![
+                ("guestinfo.ovfEnv", "<Environment/>"),
+                ("guestinfo.password", "hunter2"),
+                ("toolScripts.afterPowerOn", "TRUE"),
+                ("tools.syncTime", "TRUE"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_numa_settings() {
+        let config = parse_vmx_content(
+            r#"
+            NUMA.nodeAffinity = "0,1"
+            cpuid.coresPerSocket = "2"
+            numa€ = "1"
+            numa = "1"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.numa_settings(),
+            vec![("NUMA.nodeAffinity", "0,1"), ("cpuid.coresPerSocket", "2")]
+        );
+    }
+
+    #[test]
+    fn test_boot_order() {
+        let config = parse_vmx_content("bios.bootOrder = \"CDROM, hdd\"").unwrap();
+        assert_eq!(config.boot_order(), vec!["cdrom", "hdd"]);
+
+        let config = parse_vmx_content("").unwrap();
+        assert!(config.boot_order().is_empty());
+    }
+
+    #[test]
+    fn test_parse_firmware() {
+        let config = parse_vmx_content("firmware = \"EFI\"").unwrap();
+        assert_eq!(config.firmware, FirmwareType::Efi);
+
+        let config = parse_vmx_content("firmware = \"bios\"").unwrap();
+        assert_eq!(config.firmware, FirmwareType::Bios);
+
+        let config = parse_vmx_content("").unwrap();
+        assert_eq!(config.firmware, FirmwareType::Bios);
+        assert!(!vmx_to_string(&config).contains("firmware"));
+    }
+
+    #[test]
+    fn test_parse_nvram() {
+        let config = parse_vmx_content("nvram = \"Test VM.nvram\"").unwrap();
+        assert_eq!(config.nvram.as_deref(), Some("Test VM.nvram"));
+        assert!(vmx_to_string(&config).contains("nvram = \"Test VM.nvram\"\n"));
+
+        let config = parse_vmx_content("nvram = \"\"").unwrap();
+        assert_eq!(config.nvram, None);
+    }
+
+    #[test]
+    fn test_parse_annotation() {
+        let config =
+            parse_vmx_content(r#"annotation = "Web server|0ABuilt by |22ops|22|0A100|7C200 |zz""#)
+                .unwrap();
+        assert_eq!(config.annotation, "Web server\nBuilt by \"ops\"\n100|200 |zz");
+        assert!(vmx_to_string(&config)
+            .contains("annotation = \"Web server|0ABuilt by |22ops|22|0A100|7C200 |7Czz\"\n"));
+
+        let config = parse_vmx_content("displayName = \"Test\"").unwrap();
+        assert_eq!(config.annotation, "");
+    }
+
+    #[test]
+    fn test_vmx_to_string_overrides_raw() {
+        let mut config = parse_vmx_content(
+            r#"
+            displayName = "Old"
+            tools.syncTime = "TRUE"
+        "#,
+        )
+        .unwrap();
+        config.display_name = "New".to_string();
+
+        let content = vmx_to_string(&config);
+        assert!(content.contains("displayName = \"New\"\n"));
+        assert!(content.contains("tools.syncTime = \"TRUE\"\n"));
+        assert!(!content.contains("Old"));
+    }
+
+    #[test]
+    fn test_parse_controllers_shared_bus() {
+        let content = r#"
+            scsi0.present = "TRUE"
+            scsi1.present = "TRUE"
+            scsi1.sharedBus = "physical"
+            scsi2.present = "TRUE"
+            scsi2.sharedBus = "none"
+            scsi3.present = "FALSE"
+            scsi0:0.present = "TRUE"
+        "#;
+        let config = parse_vmx_content(content).unwrap();
+
+        let controllers: Vec<(&str, Option<&str>)> = config
+            .controllers
+            .iter()
+            .map(|c| (c.name.as_str(), c.shared_bus.as_deref()))
+            .collect();
+        assert_eq!(
+            controllers,
+            vec![("scsi0", None), ("scsi1", Some("physical")), ("scsi2", None)]
+        );
+    }
+
+    #[test]
+    fn test_parse_controllers_virtual_dev() {
+        let content = r#"
+            scsi0.present = "TRUE"
+            scsi0.virtualDev = "PVSCSI"
+            scsi1.present = "TRUE"
+        "#;
+        let config = parse_vmx_content(content).unwrap();
+
+        assert_eq!(config.controllers[0].virtual_dev.as_deref(), Some("pvscsi"));
+        assert_eq!(config.controllers[1].virtual_dev, None);
+        assert!(vmx_to_string(&config).contains("scsi0.virtualDev = \"pvscsi\"\n"));
+    }
 }
@@ -30,11 +30,21 @@
 //! ```
 
 use ovatool_core::{
-    export_vm, get_vm_info, CompressionLevel, ExportOptions, ExportPhase, ExportProgress,
+    export_vm, export_vm_to_writer, export_vm_with_ovf, get_ova_info, get_vm_info, import_ova,
+    plan_export, CompressionLevel, DiskLayout, Error, ExportOptions, ExportPhase, ExportProgress,
+    OvaOrder,
 };
-use std::path::Path;
+use ovatool_core::diagnose::{diagnose, Severity};
+use ovatool_core::ova::compute_sha256;
+use ovatool_core::vmdk::{
+    compress_grain, CreateType, ExtentAccess, SparseVmdkWriter, StreamOptimizedReader,
+    StreamVmdkWriter,
+};
+use ovatool_core::vmx::{parse_vmx, FirmwareType};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use tempfile::NamedTempFile;
 
 /// Path to the test VM fixture directory.
@@ -439,7 +449,7 @@ fn test_get_vm_info() {
     // Verify disk info
     for disk in &info.disks {
         assert!(!disk.filename.is_empty(), "Disk filename should not be empty");
-        assert!(!disk.create_type.is_empty(), "Disk create_type should not be empty");
+        assert!(!disk.create_type.as_str().is_empty(), "Disk create_type should not be empty");
     }
 }
 
@@ -549,6 +559,40 @@ fn test_export_explicit_thread_count() {
     let _ = std::fs::remove_file(&output_path);
 }
 
+#[test]
+#[ignore] // Requires real VMX fixture
+fn test_export_phase_timings() {
+    require_fixture!();
+
+    let vmx_path = test_vmx_path();
+    let output_file = NamedTempFile::new().expect("Failed to create temp file");
+    let output_path = output_file.path().to_path_buf();
+
+    let started = std::time::Instant::now();
+    let report = export_vm(&vmx_path, &output_path, ExportOptions::default(), None)
+        .expect("Export should succeed");
+    let wall_time = started.elapsed();
+
+    // Every non-terminal phase is timed
+    for phase in [
+        ExportPhase::Parsing,
+        ExportPhase::Compressing,
+        ExportPhase::Writing,
+        ExportPhase::Finalizing,
+    ] {
+        let timing = report
+            .phase_timings
+            .get(&phase)
+            .unwrap_or_else(|| panic!("Missing timing for {}", phase));
+        assert!(!timing.is_zero(), "Timing for {} should be nonzero", phase);
+    }
+    assert!(!report.phase_timings.contains_key(&ExportPhase::Complete));
+
+    // Phases don't overlap, so they can't add up to more than the whole export
+    let total: std::time::Duration = report.phase_timings.values().sum();
+    assert!(total <= wall_time, "Phase timings exceed wall time");
+}
+
 // =============================================================================
 // Helper Functions
 // =============================================================================
@@ -595,7 +639,7 @@ fn extract_tar_filenames(data: &[u8]) -> Vec<String> {
         // Parse size and skip to next header
         if let Ok(size_str) = std::str::from_utf8(&data[pos + 124..pos + 135]) {
             if let Ok(size) = u64::from_str_radix(size_str.trim_matches('\0').trim(), 8) {
-                let content_blocks = (size + 511) / 512;
+                let content_blocks = size.div_ceil(512);
                 pos += 512 + (content_blocks * 512) as usize;
                 continue;
             }
@@ -634,13 +678,97 @@ fn extract_file_from_tar(data: &[u8], filename: &str) -> Option<Vec<u8>> {
         }
 
         // Move to next header
-        let content_blocks = (size + 511) / 512;
+        let content_blocks = size.div_ceil(512);
         pos += 512 + (content_blocks * 512) as usize;
     }
 
     None
 }
 
+/// A disk of a VM written by [`write_vm`].
+struct TestDisk {
+    /// Descriptor file name, as the VMX references it.
+    file_name: String,
+    /// The descriptor's `createType`.
+    create_type: &'static str,
+    /// The descriptor's extent lines, e.g. `RW 2048 FLAT "disk-flat.vmdk" 0`.
+    extents: Vec<String>,
+}
+
+impl TestDisk {
+    fn new(file_name: &str, create_type: &'static str, extents: &[&str]) -> Self {
+        Self {
+            file_name: file_name.to_string(),
+            create_type,
+            extents: extents.iter().map(|extent| extent.to_string()).collect(),
+        }
+    }
+
+    /// A monolithicFlat disk `<stem>.vmdk` of `sectors` sectors, whose
+    /// extent is `<stem>-flat.vmdk`.
+    fn flat(stem: &str, sectors: u64) -> Self {
+        Self {
+            file_name: format!("{}.vmdk", stem),
+            create_type: "monolithicFlat",
+            extents: vec![format!("RW {} FLAT \"{}-flat.vmdk\" 0", sectors, stem)],
+        }
+    }
+}
+
+/// Write `<name>.vmx` in `dir` for a VM named `display_name` with one SCSI
+/// disk per entry of `disks`, and a base disk descriptor for each disk that
+/// has extents. Extent data files are left to the caller.
+///
+/// # Returns
+///
+/// The path of the VMX file.
+fn write_vm(dir: &Path, name: &str, display_name: &str, disks: &[TestDisk]) -> PathBuf {
+    let mut vmx = format!(
+        "displayName = \"{}\"\nguestOS = \"ubuntu-64\"\nmemsize = \"512\"\nnumvcpus = \"1\"\n\
+         scsi0.present = \"TRUE\"\n",
+        display_name
+    );
+    for (unit, disk) in disks.iter().enumerate() {
+        vmx.push_str(&format!(
+            "scsi0:{unit}.present = \"TRUE\"\nscsi0:{unit}.fileName = \"{}\"\n",
+            disk.file_name
+        ));
+        if !disk.extents.is_empty() {
+            write_descriptor(dir, disk);
+        }
+    }
+
+    let vmx_path = dir.join(format!("{}.vmx", name));
+    std::fs::write(&vmx_path, vmx).unwrap();
+    vmx_path
+}
+
+/// Write the descriptor of a base disk, with CID `fffffffe`, in `dir`.
+fn write_descriptor(dir: &Path, disk: &TestDisk) {
+    let descriptor = format!(
+        "# Disk DescriptorFile\nversion=1\nCID=fffffffe\nparentCID=ffffffff\n\
+         createType=\"{}\"\n\n{}\n",
+        disk.create_type,
+        disk.extents.join("\n")
+    );
+    std::fs::write(dir.join(&disk.file_name), descriptor).unwrap();
+}
+
+/// Read the disk `name` back out of the OVA at `ova_path`, as the bytes of
+/// the whole virtual disk.
+fn read_exported_disk(ova_path: &Path, name: &str) -> Vec<u8> {
+    let ova_data = std::fs::read(ova_path).unwrap();
+    let vmdk = extract_file_from_tar(&ova_data, name).unwrap();
+    let reader = StreamOptimizedReader::from_bytes(&vmdk).unwrap();
+    let mut disk = vec![0u8; reader.capacity() as usize];
+    for grain in reader.grains() {
+        let grain = grain.unwrap();
+        let start = (grain.lba * 512) as usize;
+        disk[start..start + grain.data.len()].copy_from_slice(&grain.data);
+    }
+    disk
+}
+
 // =============================================================================
 // Tests That Don't Require Fixtures
 // =============================================================================
@@ -680,8 +808,11 @@ fn test_export_progress_percent() {
         phase: ExportPhase::Compressing,
         bytes_processed: 500,
         bytes_total: 1000,
+        compressed_bytes_written: 0,
+        compressed_bytes_total: 0,
         current_disk: 1,
         total_disks: 1,
+        elapsed: Duration::ZERO,
     };
 
     assert_eq!(progress.percent_complete(), 50.0);
@@ -699,8 +830,11 @@ fn test_export_progress_zero_total() {
         phase: ExportPhase::Parsing,
         bytes_processed: 0,
         bytes_total: 0,
+        compressed_bytes_written: 0,
+        compressed_bytes_total: 0,
         current_disk: 0,
         total_disks: 0,
+        elapsed: Duration::ZERO,
     };
 
     // Zero total should return 0% (not NaN or panic)
@@ -713,3 +847,1478 @@ fn test_compression_level_values() {
     assert_eq!(CompressionLevel::Balanced.to_zlib_level(), 6);
     assert_eq!(CompressionLevel::Max.to_zlib_level(), 9);
 }
+
+#[test]
+fn test_export_zero_size_disks_indeterminate_progress() {
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(
+        dir.path().join("empty.vmx"),
+        "displayName = \"Empty Disk VM\"\nscsi0.present = \"TRUE\"\n\
+         scsi0:0.present = \"TRUE\"\nscsi0:0.fileName = \"empty.vmdk\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("empty.vmdk"),
+        "# Disk DescriptorFile\nversion=1\nCID=fffffffe\nparentCID=ffffffff\n\
+         createType=\"monolithicFlat\"\n\nRW 0 FLAT \"empty-flat.vmdk\" 0\n",
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("empty-flat.vmdk"), b"").unwrap();
+
+    let updates: Arc<std::sync::Mutex<Vec<ExportProgress>>> = Arc::default();
+    let recorded = Arc::clone(&updates);
+    let callback: ovatool_core::ProgressCallback = Box::new(move |progress| {
+        recorded.lock().unwrap().push(progress);
+    });
+
+    let output_path = dir.path().join("output.ova");
+    let report = export_vm(
+        &dir.path().join("empty.vmx"),
+        &output_path,
+        ExportOptions::default(),
+        Some(callback),
+    )
+    .expect("Export should succeed");
+    assert!(
+        report.warnings.iter().any(|w| w.contains("Disk sizes could not be determined")),
+        "{:?}",
+        report.warnings
+    );
+
+    let updates = updates.lock().unwrap();
+    assert!(updates.iter().all(ExportProgress::is_indeterminate));
+    assert!(updates
+        .iter()
+        .filter(|progress| progress.phase != ExportPhase::Complete)
+        .all(|progress| progress.percent_complete() == 0.0));
+    assert_eq!(updates.last().unwrap().percent_complete(), 100.0);
+}
+
+// =============================================================================
+// Missing Disk Synthesis Tests
+// =============================================================================
+
+/// Create a VM directory whose descriptor references a flat extent that does not exist.
+fn create_vm_with_missing_flat_extent() -> tempfile::TempDir {
+    let dir = tempfile::TempDir::new().unwrap();
+    write_vm(dir.path(), "missing", "Missing Disk VM", &[TestDisk::flat("missing", 2048)]);
+    dir
+}
+
+#[test]
+fn test_export_missing_disk_fails_by_default() {
+    let dir = create_vm_with_missing_flat_extent();
+    let output_path = dir.path().join("output.ova");
+
+    let result = export_vm(
+        &dir.path().join("missing.vmx"),
+        &output_path,
+        ExportOptions::default(),
+        None,
+    );
+    assert!(result.is_err(), "Export should fail when the flat extent is missing");
+}
+
+#[test]
+fn test_export_missing_disk_synthesized() {
+    let dir = create_vm_with_missing_flat_extent();
+    let output_path = dir.path().join("output.ova");
+
+    let options = ExportOptions {
+        synthesize_missing_disks: true,
+        ..ExportOptions::default()
+    };
+    let report = export_vm(&dir.path().join("missing.vmx"), &output_path, options, None)
+        .expect("Export should succeed with synthesized disk");
+
+    // The substitution must be reported
+    assert_eq!(report.warnings.len(), 1);
+    assert!(report.warnings[0].contains("missing-flat.vmdk"));
+
+    let ova_data = std::fs::read(&output_path).unwrap();
+    let filenames = extract_tar_filenames(&ova_data);
+    assert!(filenames.contains(&"missing.vmdk".to_string()));
+
+    // Placeholder is a valid streamOptimized VMDK with the declared capacity
+    let vmdk = extract_file_from_tar(&ova_data, "missing.vmdk").unwrap();
+    assert_eq!(&vmdk[0..4], b"KDMV");
+    let capacity_sectors = u64::from_le_bytes(vmdk[12..20].try_into().unwrap());
+    assert_eq!(capacity_sectors, 2048);
+
+    let ovf = extract_file_from_tar(&ova_data, "Missing_Disk_VM.ovf").unwrap();
+    let ovf = String::from_utf8(ovf).unwrap();
+    assert!(ovf.contains("ovf:capacity=\"1048576\""));
+}
+
+// =============================================================================
+// Symlink Resolution Tests
+// =============================================================================
+
+/// Create a VM whose flat extent is a symlink into a separate "datastore" directory.
+#[cfg(unix)]
+fn create_vm_with_symlinked_disk() -> (tempfile::TempDir, tempfile::TempDir) {
+    let vm_dir = tempfile::TempDir::new().unwrap();
+    let datastore = tempfile::TempDir::new().unwrap();
+    write_vm(vm_dir.path(), "linked", "Linked VM", &[TestDisk::flat("linked", 2048)]);
+
+    let real_flat = datastore.path().join("linked-flat.vmdk");
+    std::fs::write(&real_flat, vec![0xA5u8; 1024 * 1024]).unwrap();
+    std::os::unix::fs::symlink(&real_flat, vm_dir.path().join("linked-flat.vmdk")).unwrap();
+
+    (vm_dir, datastore)
+}
+
+#[test]
+#[cfg(unix)]
+fn test_export_follows_symlinked_disk() {
+    let (vm_dir, _datastore) = create_vm_with_symlinked_disk();
+    let output_path = vm_dir.path().join("output.ova");
+
+    let options = ExportOptions::default();
+    assert!(options.follow_symlinks);
+
+    export_vm(&vm_dir.path().join("linked.vmx"), &output_path, options, None)
+        .expect("Export should follow the symlinked disk");
+
+    let ova_data = std::fs::read(&output_path).unwrap();
+    assert!(extract_tar_filenames(&ova_data).contains(&"linked.vmdk".to_string()));
+}
+
+#[test]
+#[cfg(unix)]
+fn test_export_rejects_symlinked_disk_when_disallowed() {
+    let (vm_dir, _datastore) = create_vm_with_symlinked_disk();
+    let output_path = vm_dir.path().join("output.ova");
+
+    let options = ExportOptions {
+        follow_symlinks: false,
+        ..ExportOptions::default()
+    };
+    let err = export_vm(&vm_dir.path().join("linked.vmx"), &output_path, options, None)
+        .expect_err("Export should refuse the symlinked disk");
+    assert!(err.to_string().contains("symbolic link"));
+}
+
+// =============================================================================
+// Custom OVF Tests
+// =============================================================================
+
+/// Create a VM with a small flat disk named `custom.vmdk`.
+fn create_vm_with_flat_disk() -> tempfile::TempDir {
+    let dir = tempfile::TempDir::new().unwrap();
+    write_vm(dir.path(), "custom", "Custom OVF VM", &[TestDisk::flat("custom", 2048)]);
+    std::fs::write(dir.path().join("custom-flat.vmdk"), vec![0x5Au8; 1024 * 1024]).unwrap();
+
+    dir
+}
+
+/// Minimal hand-written OVF referencing the given disk file.
+fn custom_ovf(href: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<Envelope xmlns="http://schemas.dmtf.org/ovf/envelope/1" xmlns:ovf="http://schemas.dmtf.org/ovf/envelope/1">
+  <!-- hand-crafted -->
+  <References>
+    <File ovf:href="{}" ovf:id="file1"/>
+  </References>
+  <DiskSection>
+    <Info>Virtual disks</Info>
+    <Disk ovf:capacity="1048576" ovf:diskId="vmdisk1" ovf:fileRef="file1"/>
+  </DiskSection>
+  <VirtualSystem ovf:id="custom">
+    <Info>Custom appliance</Info>
+  </VirtualSystem>
+</Envelope>
+"#,
+        href
+    )
+}
+
+#[test]
+fn test_export_with_custom_ovf_verbatim() {
+    let dir = create_vm_with_flat_disk();
+    let output_path = dir.path().join("output.ova");
+    let ovf = custom_ovf("custom.vmdk");
+
+    export_vm_with_ovf(
+        &dir.path().join("custom.vmx"),
+        &output_path,
+        ExportOptions::default(),
+        &ovf,
+        None,
+    )
+    .expect("Export with custom OVF should succeed");
+
+    let ova_data = std::fs::read(&output_path).unwrap();
+    let packaged = extract_file_from_tar(&ova_data, "Custom_OVF_VM.ovf").unwrap();
+    assert_eq!(packaged, ovf.as_bytes(), "Custom OVF should be packaged verbatim");
+    assert!(extract_tar_filenames(&ova_data).contains(&"custom.vmdk".to_string()));
+}
+
+#[test]
+fn test_export_with_custom_ovf_href_mismatch() {
+    let dir = create_vm_with_flat_disk();
+    let output_path = dir.path().join("output.ova");
+
+    let err = export_vm_with_ovf(
+        &dir.path().join("custom.vmx"),
+        &output_path,
+        ExportOptions::default(),
+        &custom_ovf("other.vmdk"),
+        None,
+    )
+    .expect_err("Mismatched hrefs should be rejected");
+    assert!(err.to_string().contains("other.vmdk"));
+}
+
+#[test]
+fn test_export_with_custom_ovf_malformed() {
+    let dir = create_vm_with_flat_disk();
+    let output_path = dir.path().join("output.ova");
+
+    let result = export_vm_with_ovf(
+        &dir.path().join("custom.vmx"),
+        &output_path,
+        ExportOptions::default(),
+        "<Envelope><References>",
+        None,
+    );
+    assert!(result.is_err(), "Malformed OVF should be rejected");
+}
+
+#[test]
+fn test_export_with_custom_ovf_validated() {
+    let dir = create_vm_with_flat_disk();
+    let output_path = dir.path().join("output.ova");
+
+    // The hand-written OVF has no hardware item attaching its disk
+    let err = export_vm_with_ovf(
+        &dir.path().join("custom.vmx"),
+        &output_path,
+        ExportOptions {
+            validate_ovf: true,
+            ..ExportOptions::default()
+        },
+        &custom_ovf("custom.vmdk"),
+        None,
+    )
+    .expect_err("Invalid OVF should be rejected");
+    assert!(err.to_string().contains("Disk 'vmdisk1' is not attached"), "{}", err);
+}
+
+#[test]
+fn test_export_guestinfo_opt_in() {
+    let dir = create_vm_with_flat_disk();
+    let vmx_path = dir.path().join("custom.vmx");
+    let mut vmx = std::fs::read_to_string(&vmx_path).unwrap();
+    vmx.push_str("toolScripts.afterPowerOn = \"TRUE\"\n");
+    vmx.push_str("guestinfo.admin.password = \"hunter2\"\n");
+    std::fs::write(&vmx_path, vmx).unwrap();
+
+    let packaged_ovf = |options: ExportOptions| {
+        let output_path = dir.path().join("output.ova");
+        export_vm(&vmx_path, &output_path, options, None).expect("Export should succeed");
+        let ova_data = std::fs::read(&output_path).unwrap();
+        String::from_utf8(extract_file_from_tar(&ova_data, "Custom_OVF_VM.ovf").unwrap()).unwrap()
+    };
+
+    let ovf = packaged_ovf(ExportOptions::default());
+    assert!(ovf.contains(
+        r#"<vmw:ExtraConfig ovf:required="false" vmw:key="toolScripts.afterPowerOn" vmw:value="TRUE"/>"#
+    ));
+    assert!(!ovf.contains("guestinfo"), "guestinfo must be excluded by default");
+    assert!(!ovf.contains("hunter2"));
+
+    let ovf = packaged_ovf(ExportOptions {
+        include_guestinfo: true,
+        ..ExportOptions::default()
+    });
+    assert!(ovf.contains(
+        r#"<vmw:ExtraConfig ovf:required="false" vmw:key="guestinfo.admin.password" vmw:value="hunter2"/>"#
+    ));
+}
+
+#[test]
+fn test_export_numa_opt_in() {
+    let dir = create_vm_with_flat_disk();
+    let vmx_path = dir.path().join("custom.vmx");
+    let mut vmx = std::fs::read_to_string(&vmx_path).unwrap();
+    vmx.push_str("numa.nodeAffinity = \"0,1\"\n");
+    vmx.push_str("cpuid.coresPerSocket = \"8\"\n");
+    std::fs::write(&vmx_path, vmx).unwrap();
+
+    let packaged_ovf = |options: ExportOptions| {
+        let output_path = dir.path().join("output.ova");
+        export_vm(&vmx_path, &output_path, options, None).expect("Export should succeed");
+        let ova_data = std::fs::read(&output_path).unwrap();
+        String::from_utf8(extract_file_from_tar(&ova_data, "Custom_OVF_VM.ovf").unwrap()).unwrap()
+    };
+
+    let ovf = packaged_ovf(ExportOptions::default());
+    assert!(!ovf.contains("numa."), "NUMA settings must be excluded by default");
+
+    let ovf = packaged_ovf(ExportOptions {
+        include_numa: true,
+        ..ExportOptions::default()
+    });
+    assert!(ovf.contains(
+        r#"<vmw:ExtraConfig ovf:required="false" vmw:key="numa.nodeAffinity" vmw:value="0,1"/>"#
+    ));
+    assert!(ovf.contains(
+        r#"<vmw:ExtraConfig ovf:required="false" vmw:key="cpuid.coresPerSocket" vmw:value="8"/>"#
+    ));
+}
+
+#[test]
+fn test_export_logical_manifest() {
+    let dir = create_vm_with_flat_disk();
+    let output_path = dir.path().join("output.ova");
+    let options = ExportOptions {
+        logical_manifest: true,
+        ..ExportOptions::default()
+    };
+
+    let report = export_vm(&dir.path().join("custom.vmx"), &output_path, options, None)
+        .expect("Export should succeed");
+
+    let flat = std::fs::read(dir.path().join("custom-flat.vmdk")).unwrap();
+    let expected = compute_sha256(&flat);
+    assert_eq!(report.disks[0].logical_sha256.as_deref(), Some(expected.as_str()));
+
+    let ova_data = std::fs::read(&output_path).unwrap();
+    let sidecar = extract_file_from_tar(&ova_data, "Custom_OVF_VM.logical.sha256").unwrap();
+    assert_eq!(
+        String::from_utf8(sidecar).unwrap(),
+        format!("SHA256(custom.vmdk)= {}\n", expected)
+    );
+
+    // The standard manifest still only digests the packaged files
+    let manifest = String::from_utf8(extract_file_from_tar(&ova_data, "manifest.mf").unwrap())
+        .unwrap();
+    assert!(!manifest.contains(&expected), "{}", manifest);
+}
+
+#[test]
+fn test_export_blank_display_name() {
+    let dir = create_vm_with_flat_disk();
+    let vmx = std::fs::read_to_string(dir.path().join("custom.vmx")).unwrap();
+    let vmx_path = dir.path().join("web-01.vmx");
+    std::fs::write(&vmx_path, vmx.replace("\"Custom OVF VM\"", "\"  \"")).unwrap();
+
+    let info = get_vm_info(&vmx_path).expect("Info should succeed");
+    assert_eq!(info.name, "web-01");
+
+    let output_path = dir.path().join("output.ova");
+    let report = export_vm(&vmx_path, &output_path, ExportOptions::default(), None)
+        .expect("Export should succeed");
+    assert!(
+        report.warnings.iter().any(|w| w.contains("displayName is empty")),
+        "{:?}",
+        report.warnings
+    );
+
+    let ova_data = std::fs::read(&output_path).unwrap();
+    let ovf = String::from_utf8(extract_file_from_tar(&ova_data, "web-01.ovf").unwrap()).unwrap();
+    assert!(ovf.contains("<ovf:VirtualSystem ovf:id=\"web-01\">"), "{}", ovf);
+    assert!(ovf.contains("<ovf:Name>web-01</ovf:Name>"), "{}", ovf);
+}
+
+#[test]
+fn test_export_os_type_override() {
+    let dir = create_vm_with_flat_disk();
+    let vmx_path = dir.path().join("custom.vmx");
+    let vmx = std::fs::read_to_string(&vmx_path).unwrap();
+    std::fs::write(&vmx_path, vmx.replace("ubuntu-64", "haiku-64")).unwrap();
+
+    let packaged_ovf = |options: ExportOptions| {
+        let output_path = dir.path().join("output.ova");
+        export_vm(&vmx_path, &output_path, options, None).expect("Export should succeed");
+        let ova_data = std::fs::read(&output_path).unwrap();
+        String::from_utf8(extract_file_from_tar(&ova_data, "Custom_OVF_VM.ovf").unwrap()).unwrap()
+    };
+
+    let ovf = packaged_ovf(ExportOptions::default());
+    assert!(ovf.contains(r#"vmw:osType="otherGuest""#), "{}", ovf);
+
+    let ovf = packaged_ovf(ExportOptions {
+        os_type_override: Some((102, "other5xLinux64Guest".to_string())),
+        ..ExportOptions::default()
+    });
+    assert!(ovf.contains(
+        r#"<ovf:OperatingSystemSection ovf:id="102" vmw:osType="other5xLinux64Guest">"#
+    ));
+    assert!(!ovf.contains("otherGuest\""));
+}
+
+#[test]
+fn test_export_extra_file_name_collision() {
+    let dir = create_vm_with_flat_disk();
+    let sidecar_dir = tempfile::TempDir::new().unwrap();
+    let clashing = sidecar_dir.path().join("custom.vmdk");
+    std::fs::write(&clashing, "not a disk").unwrap();
+
+    let output_path = dir.path().join("output.ova");
+    let options = ExportOptions {
+        extra_files: vec![clashing],
+        ..ExportOptions::default()
+    };
+    let err = export_vm(&dir.path().join("custom.vmx"), &output_path, options, None).unwrap_err();
+    assert!(matches!(err, Error::Validation { .. }), "{}", err);
+    assert!(err.to_string().contains("'custom.vmdk'"), "{}", err);
+}
+
+#[test]
+fn test_export_survives_panicking_callback() {
+    let dir = create_vm_with_three_disks();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&calls);
+    let callback: ovatool_core::ProgressCallback = Box::new(move |_progress| {
+        if counter.fetch_add(1, Ordering::SeqCst) == 2 {
+            panic!("progress bar went away");
+        }
+    });
+
+    let output_path = dir.path().join("output.ova");
+    let report = export_vm(
+        &dir.path().join("three.vmx"),
+        &output_path,
+        ExportOptions::default(),
+        Some(callback),
+    )
+    .expect("Export should succeed despite the callback");
+
+    // Updates after the panic were still delivered
+    assert!(calls.load(Ordering::SeqCst) > 3);
+    assert!(
+        report
+            .warnings
+            .iter()
+            .any(|w| w.contains("panicked on 1 update(s)") && w.contains("progress bar went away")),
+        "{:?}",
+        report.warnings
+    );
+
+    let file = std::fs::File::open(&output_path).unwrap();
+    let mut reader = ovatool_core::ova::OvaReader::new(file).unwrap();
+    assert!(reader.verify().unwrap().is_empty());
+}
+
+#[test]
+fn test_plan_export() {
+    let dir = create_vm_with_flat_disk();
+    let vmx_path = dir.path().join("custom.vmx");
+
+    let plan = plan_export(&vmx_path, &ExportOptions::default()).expect("Plan should succeed");
+    assert_eq!(plan.name, "Custom OVF VM");
+    assert!(plan.is_exportable());
+    assert!(plan.warnings.is_empty(), "{:?}", plan.warnings);
+    assert_eq!(plan.total_capacity_bytes, 1024 * 1024);
+    assert_eq!(plan.output_files, ["Custom_OVF_VM.ovf", "custom.vmdk", "manifest.mf"]);
+
+    let disk = &plan.disks[0];
+    assert_eq!(disk.create_type, CreateType::MonolithicFlat);
+    assert_eq!(disk.layout, DiskLayout::Flat);
+    assert!(!disk.split && !disk.delta);
+    let source_names: Vec<_> = disk
+        .source_paths
+        .iter()
+        .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(source_names, ["custom.vmdk", "custom-flat.vmdk"]);
+
+    // Nothing is written
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 3);
+}
+
+#[test]
+fn test_plan_export_missing_extent() {
+    let dir = create_vm_with_flat_disk();
+    let vmx_path = dir.path().join("custom.vmx");
+    std::fs::remove_file(dir.path().join("custom-flat.vmdk")).unwrap();
+
+    let plan = plan_export(&vmx_path, &ExportOptions::default()).expect("Plan should succeed");
+    assert!(!plan.is_exportable());
+    assert_eq!(plan.disks[0].layout, DiskLayout::Missing);
+    assert_eq!(plan.disks[0].capacity_bytes, 1024 * 1024);
+    assert_eq!(plan.warnings.len(), 1);
+    assert!(plan.warnings[0].contains("disk 'custom.vmdk'"), "{}", plan.warnings[0]);
+    assert!(plan.warnings[0].contains("custom-flat.vmdk' not found"), "{}", plan.warnings[0]);
+
+    // A placeholder would be exported instead
+    let options = ExportOptions {
+        synthesize_missing_disks: true,
+        ..ExportOptions::default()
+    };
+    let plan = plan_export(&vmx_path, &options).expect("Plan should succeed");
+    assert!(plan.is_exportable());
+    assert!(plan.warnings[0].contains("zero-filled placeholder"), "{}", plan.warnings[0]);
+}
+
+#[test]
+fn test_export_missing_flat_extent() {
+    let dir = create_vm_with_flat_disk();
+    let vmx_path = dir.path().join("custom.vmx");
+    std::fs::remove_file(dir.path().join("custom-flat.vmdk")).unwrap();
+
+    let err = export_vm(&vmx_path, &dir.path().join("output.ova"), ExportOptions::default(), None)
+        .expect_err("A missing flat extent should fail the export");
+    assert!(matches!(err, Error::Io { .. }), "{:?}", err);
+    let message = err.to_string();
+    assert!(message.contains("custom-flat.vmdk' of disk 'custom.vmdk' not found"), "{}", message);
+
+    // Inspecting the VM stays lenient
+    let info = get_vm_info(&vmx_path).expect("Info should succeed");
+    assert_eq!(info.disks.len(), 1);
+}
+
+#[test]
+fn test_export_unaligned_flat_extent() {
+    let dir = create_vm_with_flat_disk();
+    let vmx_path = dir.path().join("custom.vmx");
+    let flat_path = dir.path().join("custom-flat.vmdk");
+    std::fs::write(&flat_path, vec![0x5Au8; 1024 * 1024 - 100]).unwrap();
+
+    let err = export_vm(&vmx_path, &dir.path().join("output.ova"), ExportOptions::default(), None)
+        .expect_err("A flat extent of partial sectors should fail the export");
+    assert!(matches!(err, Error::Vmdk { .. }), "{:?}", err);
+    assert!(err.to_string().contains(&flat_path.display().to_string()), "{}", err);
+    assert!(err.to_string().contains("not a multiple of the 512-byte sector size"), "{}", err);
+
+    let plan = plan_export(&vmx_path, &ExportOptions::default()).expect("Plan should succeed");
+    assert!(!plan.is_exportable());
+    assert!(plan.warnings[0].contains("not a multiple"), "{}", plan.warnings[0]);
+}
+
+#[test]
+fn test_plan_export_unsupported_create_type() {
+    let dir = create_vm_with_flat_disk();
+    std::fs::write(
+        dir.path().join("custom.vmdk"),
+        "# Disk DescriptorFile\nversion=1\nCID=fffffffe\nparentCID=ffffffff\n\
+         createType=\"vmfsThin\"\n\nRW 2048 VMFS \"custom-flat.vmdk\"\n",
+    )
+    .unwrap();
+
+    let plan = plan_export(&dir.path().join("custom.vmx"), &ExportOptions::default())
+        .expect("Plan should succeed");
+    assert_eq!(plan.disks[0].create_type, CreateType::VmfsThin);
+    assert_eq!(plan.disks[0].layout, DiskLayout::Unsupported);
+    assert!(plan.warnings[0].contains("thin-provisioned VMFS disk"), "{}", plan.warnings[0]);
+}
+
+#[test]
+fn test_diagnose_reports_every_blocker() {
+    let dir = create_vm_with_flat_disk();
+    let vmx_path = dir.path().join("custom.vmx");
+    std::fs::write(
+        dir.path().join("custom.vmdk"),
+        "# Disk DescriptorFile\nversion=1\nCID=fffffffe\nparentCID=ffffffff\n\
+         createType=\"vmfsThin\"\n\nRW 2048 VMFS \"custom-flat.vmdk\"\n",
+    )
+    .unwrap();
+    let mut vmx = std::fs::read_to_string(&vmx_path).unwrap();
+    vmx.push_str("scsi0:1.present = \"TRUE\"\nscsi0:1.fileName = \"missing.vmdk\"\n");
+    std::fs::write(&vmx_path, vmx).unwrap();
+
+    let diagnostics = diagnose(&vmx_path).expect("Diagnosis should succeed");
+    let blockers: Vec<_> = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Blocker)
+        .collect();
+    assert_eq!(blockers.len(), 2, "{:?}", diagnostics);
+    assert_eq!(blockers[0].disk.as_deref(), Some("custom.vmdk"));
+    assert!(blockers[0].message.contains("thin-provisioned VMFS disk"), "{}", blockers[0]);
+    assert_eq!(blockers[1].disk.as_deref(), Some("missing.vmdk"));
+    assert!(blockers[1].message.contains("not found"), "{}", blockers[1]);
+
+    // Each blocker is reported once, not again as a warning
+    assert_eq!(diagnostics.len(), 2, "{:?}", diagnostics);
+}
+
+#[test]
+fn test_encrypted_vm_is_refused() {
+    let dir = create_vm_with_flat_disk();
+    let vmx_path = dir.path().join("custom.vmx");
+    let mut vmx = std::fs::read_to_string(&vmx_path).unwrap();
+    vmx.push_str("encryption.keySafe = \"vmware:key/list/(pair/(null/vmware:key/fqid))\"\n");
+    std::fs::write(&vmx_path, vmx).unwrap();
+
+    // Diagnosis, planning and export agree that the VM can't be exported
+    let diagnostics = diagnose(&vmx_path).expect("Diagnosis should succeed");
+    assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+    assert_eq!(diagnostics[0].severity, Severity::Blocker);
+    assert!(diagnostics[0].message.contains("encrypted"), "{}", diagnostics[0]);
+    let plan = plan_export(&vmx_path, &ExportOptions::default()).unwrap();
+    assert!(!plan.is_exportable());
+
+    let output_path = dir.path().join("output.ova");
+    let err = export_vm(&vmx_path, &output_path, ExportOptions::default(), None)
+        .expect_err("Encrypted VM should be refused");
+    assert!(matches!(err, Error::Unsupported { .. }), "{:?}", err);
+    assert!(!output_path.exists());
+}
+
+#[test]
+fn test_export_vm_to_writer() {
+    let dir = create_vm_with_flat_disk();
+    let vmx_path = dir.path().join("custom.vmx");
+
+    let mut cursor = std::io::Cursor::new(Vec::new());
+    export_vm_to_writer(&vmx_path, &mut cursor, ExportOptions::default(), None)
+        .expect("Export should succeed");
+    let ova_data = cursor.into_inner();
+
+    // The archive ends with two zero blocks, as a file export does
+    assert!(ova_data.len() >= 1024 && ova_data.len() % 512 == 0);
+    assert!(ova_data[ova_data.len() - 1024..].iter().all(|&b| b == 0));
+    let output_path = dir.path().join("output.ova");
+    export_vm(&vmx_path, &output_path, ExportOptions::default(), None)
+        .expect("Export should succeed");
+    assert_eq!(
+        extract_tar_filenames(&ova_data),
+        extract_tar_filenames(&std::fs::read(&output_path).unwrap())
+    );
+
+    let mut reader = ovatool_core::ova::OvaReader::new(std::io::Cursor::new(ova_data)).unwrap();
+    assert!(reader.verify().unwrap().is_empty());
+}
+
+#[test]
+fn test_export_vm_to_writer_dedup() {
+    let dir = create_vm_with_identical_disks();
+    let options = ExportOptions {
+        dedup_disks: true,
+        ..ExportOptions::default()
+    };
+
+    let mut cursor = std::io::Cursor::new(Vec::new());
+    export_vm_to_writer(&dir.path().join("twin.vmx"), &mut cursor, options, None)
+        .expect("Export should succeed");
+
+    // Nothing follows the end-of-archive marker
+    let end = cursor.position() as usize;
+    let ova_data = cursor.into_inner();
+    assert_eq!(ova_data.len(), end);
+    assert!(ova_data[end - 1024..].iter().all(|&b| b == 0));
+    let names = extract_tar_filenames(&ova_data);
+    assert!(!names.contains(&"base-b.vmdk".to_string()), "{:?}", names);
+}
+
+#[test]
+fn test_export_vm_to_writer_at_offset() {
+    let dir = create_vm_with_flat_disk();
+    let vmx_path = dir.path().join("custom.vmx");
+
+    // The archive is written after existing data, which must be left alone
+    let base = 4096;
+    let prefix: Vec<u8> = (0..base).map(|i| (i % 251) as u8).collect();
+    let mut cursor = std::io::Cursor::new(prefix.clone());
+    cursor.set_position(base as u64);
+    export_vm_to_writer(&vmx_path, &mut cursor, ExportOptions::default(), None)
+        .expect("Export should succeed");
+    let data = cursor.into_inner();
+
+    assert_eq!(data[..base], prefix[..]);
+    let mut reader =
+        ovatool_core::ova::OvaReader::new(std::io::Cursor::new(data[base..].to_vec())).unwrap();
+    assert!(reader.entries().iter().any(|entry| entry.name == "custom.vmdk"));
+    assert!(reader.verify().unwrap().is_empty());
+}
+
+#[test]
+fn test_export_icon() {
+    let dir = create_vm_with_flat_disk();
+    let icon_dir = tempfile::TempDir::new().unwrap();
+    let icon = icon_dir.path().join("logo.png");
+    std::fs::write(&icon, b"\x89PNG\r\n\x1a\nnot really").unwrap();
+
+    let output_path = dir.path().join("output.ova");
+    let options = ExportOptions {
+        icon: Some(icon.clone()),
+        ..ExportOptions::default()
+    };
+    export_vm(&dir.path().join("custom.vmx"), &output_path, options, None)
+        .expect("Export should succeed");
+
+    let file = std::fs::File::open(&output_path).unwrap();
+    let mut reader = ovatool_core::ova::OvaReader::new(file).unwrap();
+    assert_eq!(reader.read_file("logo.png").unwrap(), std::fs::read(&icon).unwrap());
+    assert!(reader.manifest_digests().unwrap().contains_key("logo.png"));
+    assert!(reader.verify().unwrap().is_empty());
+    let ovf = reader.read_ovf().unwrap();
+    assert!(ovf.contains(r#"<ovf:File ovf:href="logo.png" ovf:id="icon"/>"#), "{}", ovf);
+    assert!(ovf.contains(r#"<ovf:Icon ovf:fileRef="icon" ovf:mimeType="image/png"/>"#));
+
+    // Listing the icon among the extra files too packages it once
+    let options = ExportOptions {
+        icon: Some(icon.clone()),
+        extra_files: vec![icon.clone()],
+        ..ExportOptions::default()
+    };
+    export_vm(&dir.path().join("custom.vmx"), &output_path, options, None)
+        .expect("Export should succeed");
+    let ova_data = std::fs::read(&output_path).unwrap();
+    let names = extract_tar_filenames(&ova_data);
+    assert_eq!(names.iter().filter(|name| *name == "logo.png").count(), 1);
+
+    let options = ExportOptions {
+        icon: Some(icon_dir.path().join("logo.txt")),
+        ..ExportOptions::default()
+    };
+    let err = export_vm(&dir.path().join("custom.vmx"), &output_path, options, None).unwrap_err();
+    assert!(matches!(err, Error::Validation { .. }), "{}", err);
+}
+
+#[test]
+fn test_export_includes_nvram() {
+    let dir = create_vm_with_flat_disk();
+    let vmx_path = dir.path().join("custom.vmx");
+    let mut vmx = std::fs::read_to_string(&vmx_path).unwrap();
+    vmx.push_str("firmware = \"efi\"\nnvram = \"custom.nvram\"\n");
+    std::fs::write(&vmx_path, vmx).unwrap();
+    std::fs::write(dir.path().join("custom.nvram"), [0xEEu8; 8684]).unwrap();
+
+    let output_path = dir.path().join("output.ova");
+    let report = export_vm(&vmx_path, &output_path, ExportOptions::default(), None)
+        .expect("Export should succeed");
+    assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+
+    let ova_data = std::fs::read(&output_path).unwrap();
+    assert!(extract_tar_filenames(&ova_data).contains(&"custom.nvram".to_string()));
+    assert_eq!(extract_file_from_tar(&ova_data, "custom.nvram").unwrap(), vec![0xEEu8; 8684]);
+
+    let ovf = String::from_utf8(extract_file_from_tar(&ova_data, "Custom_OVF_VM.ovf").unwrap())
+        .unwrap();
+    assert!(ovf.contains(r#"<ovf:File ovf:href="custom.nvram" ovf:id="file2" ovf:size="8684"/>"#));
+    assert!(ovf.contains(r#"vmw:key="nvram" vmw:value="ovf:/file/file2"/>"#));
+}
+
+#[test]
+fn test_export_skips_missing_nvram() {
+    let dir = create_vm_with_flat_disk();
+    let vmx_path = dir.path().join("custom.vmx");
+    let mut vmx = std::fs::read_to_string(&vmx_path).unwrap();
+    vmx.push_str("nvram = \"custom.nvram\"\n");
+    std::fs::write(&vmx_path, vmx).unwrap();
+
+    let output_path = dir.path().join("output.ova");
+    let report = export_vm(&vmx_path, &output_path, ExportOptions::default(), None)
+        .expect("A missing NVRAM file should not fail the export");
+    assert!(report.warnings.iter().any(|w| w.contains("NVRAM")), "{:?}", report.warnings);
+
+    let ova_data = std::fs::read(&output_path).unwrap();
+    assert!(!extract_tar_filenames(&ova_data).contains(&"custom.nvram".to_string()));
+    let ovf = extract_file_from_tar(&ova_data, "Custom_OVF_VM.ovf").unwrap();
+    assert!(!String::from_utf8(ovf).unwrap().contains("nvram"));
+}
+
+#[test]
+fn test_export_ignores_flat_extent_slack() {
+    let dir = create_vm_with_flat_disk();
+
+    // The descriptor declares 1 MB; append slack the export must leave out
+    let flat_path = dir.path().join("custom-flat.vmdk");
+    let mut flat = std::fs::read(&flat_path).unwrap();
+    flat.extend_from_slice(&[0xFFu8; 64 * 1024]);
+    std::fs::write(&flat_path, flat).unwrap();
+
+    let output_path = dir.path().join("output.ova");
+    export_vm(&dir.path().join("custom.vmx"), &output_path, ExportOptions::default(), None)
+        .expect("Export should succeed");
+
+    let ova_data = std::fs::read(&output_path).unwrap();
+    let vmdk = extract_file_from_tar(&ova_data, "custom.vmdk").unwrap();
+    let reader = StreamOptimizedReader::from_bytes(&vmdk).unwrap();
+    assert_eq!(reader.capacity(), 1024 * 1024);
+    let mut exported = 0u64;
+    for grain in reader.grains() {
+        let grain = grain.unwrap();
+        assert!(grain.data.iter().all(|&b| b == 0x5A), "slack leaked into the export");
+        exported += grain.data.len() as u64;
+    }
+    assert_eq!(exported, 1024 * 1024);
+
+    let ovf = extract_file_from_tar(&ova_data, "Custom_OVF_VM.ovf").unwrap();
+    assert!(String::from_utf8(ovf).unwrap().contains(r#"ovf:capacity="1048576""#));
+}
+
+/// Write a hosted sparse extent whose header declares `capacity` bytes,
+/// filled with `fill`.
+fn write_sparse_extent(path: &Path, capacity: u64, fill: u8) {
+    let file = std::fs::File::create(path).unwrap();
+    let mut writer = SparseVmdkWriter::new(file, capacity).unwrap();
+    let grain_size = writer.grain_size_bytes();
+    let mut offset = 0;
+    while offset < capacity {
+        let len = grain_size.min(capacity - offset) as usize;
+        writer.write_grain(offset / 512, &vec![fill; len]).unwrap();
+        offset += grain_size;
+    }
+    writer.finish().unwrap();
+}
+
+/// Create a VM with a twoGbMaxExtentSparse disk split over three extents.
+///
+/// The first extent's header declares more than the descriptor gives it and
+/// the last, smaller extent's header declares less.
+fn create_vm_with_split_sparse_disk() -> tempfile::TempDir {
+    let dir = tempfile::TempDir::new().unwrap();
+    let extents = [
+        r#"RW 2048 SPARSE "split-s001.vmdk""#,
+        r#"RW 2048 SPARSE "split-s002.vmdk""#,
+        r#"RW 600 SPARSE "split-s003.vmdk""#,
+    ];
+    let disk = TestDisk::new("split.vmdk", "twoGbMaxExtentSparse", &extents);
+    write_vm(dir.path(), "split", "Split VM", &[disk]);
+
+    write_sparse_extent(&dir.path().join("split-s001.vmdk"), 1024 * 1024 + 65536, 0x11);
+    write_sparse_extent(&dir.path().join("split-s002.vmdk"), 1024 * 1024, 0x22);
+    write_sparse_extent(&dir.path().join("split-s003.vmdk"), 256 * 1024, 0x33);
+
+    dir
+}
+
+#[test]
+fn test_export_split_sparse_disk() {
+    let dir = create_vm_with_split_sparse_disk();
+    let output_path = dir.path().join("output.ova");
+    export_vm(&dir.path().join("split.vmx"), &output_path, ExportOptions::default(), None)
+        .expect("Export should succeed");
+
+    let mut expected = vec![0x11u8; 1024 * 1024];
+    expected.extend_from_slice(&[0x22u8; 1024 * 1024]);
+    expected.extend_from_slice(&[0x33u8; 256 * 1024]);
+    expected.resize(2 * 1024 * 1024 + 600 * 512, 0);
+
+    let exported = read_exported_disk(&output_path, "split.vmdk");
+    assert_eq!(exported.len(), expected.len());
+    assert!(exported == expected, "extents were not joined at their declared sizes");
+}
+
+/// Create a VM with a twoGbMaxExtentFlat disk split over three extents.
+///
+/// The first extent file has slack past the sectors the descriptor gives it
+/// and the last is shorter than its extent line.
+fn create_vm_with_split_flat_disk() -> tempfile::TempDir {
+    let dir = tempfile::TempDir::new().unwrap();
+    let disk = TestDisk::new(
+        "splitflat.vmdk",
+        "twoGbMaxExtentFlat",
+        &[
+            r#"RW 2048 FLAT "splitflat-f001.vmdk" 0"#,
+            r#"RW 2048 FLAT "splitflat-f002.vmdk" 0"#,
+            r#"RW 600 FLAT "splitflat-f003.vmdk" 0"#,
+        ],
+    );
+    write_vm(dir.path(), "splitflat", "Split Flat VM", &[disk]);
+
+    let extents = [(1024 * 1024 + 65536, 0x11u8), (1024 * 1024, 0x22), (256 * 1024, 0x33)];
+    for (i, (len, fill)) in extents.into_iter().enumerate() {
+        let path = dir.path().join(format!("splitflat-f{:03}.vmdk", i + 1));
+        std::fs::write(path, vec![fill; len]).unwrap();
+    }
+
+    dir
+}
+
+#[test]
+fn test_export_split_flat_disk() {
+    let dir = create_vm_with_split_flat_disk();
+    let output_path = dir.path().join("output.ova");
+    export_vm(&dir.path().join("splitflat.vmx"), &output_path, ExportOptions::default(), None)
+        .expect("Export should succeed");
+
+    let mut expected = vec![0x11u8; 1024 * 1024];
+    expected.extend_from_slice(&[0x22u8; 1024 * 1024]);
+    expected.extend_from_slice(&[0x33u8; 256 * 1024]);
+    expected.resize(2 * 1024 * 1024 + 600 * 512, 0);
+
+    let ova_data = std::fs::read(&output_path).unwrap();
+    let vmdk = extract_file_from_tar(&ova_data, "splitflat.vmdk").unwrap();
+    let reader = StreamOptimizedReader::from_bytes(&vmdk).unwrap();
+    assert_eq!(reader.capacity(), expected.len() as u64);
+    let mut exported = vec![0u8; expected.len()];
+    for grain in reader.grains() {
+        let grain = grain.unwrap();
+        let start = (grain.lba * 512) as usize;
+        exported[start..start + grain.data.len()].copy_from_slice(&grain.data);
+    }
+    assert!(exported == expected, "flat extents were not joined at their declared sizes");
+}
+
+/// Create a VM running on a snapshot: a sparse delta over a flat base disk.
+///
+/// The base disk is 1 MB of 0x11 and the delta overwrites its third and
+/// sixth 64 KB grains with 0x22.
+fn create_vm_with_snapshot() -> tempfile::TempDir {
+    let dir = tempfile::TempDir::new().unwrap();
+    let delta = TestDisk::new("snap-000001.vmdk", "twoGbMaxExtentSparse", &[]);
+    write_vm(dir.path(), "snap", "Snapshot VM", &[delta]);
+
+    let base_extent = r#"RW 2048 FLAT "snap-flat.vmdk" 0"#;
+    let base = TestDisk::new("snap.vmdk", "monolithicFlat", &[base_extent]);
+    write_descriptor(dir.path(), &base);
+    std::fs::write(dir.path().join("snap-flat.vmdk"), vec![0x11u8; 1024 * 1024]).unwrap();
+
+    std::fs::write(
+        dir.path().join("snap-000001.vmdk"),
+        r#"# Disk DescriptorFile
+version=1
+CID=5678ef01
+parentCID=fffffffe
+parentFileNameHint="C:\VMs\Snapshot VM\snap.vmdk"
+createType="twoGbMaxExtentSparse"
+
+RW 2048 SPARSE "snap-000001-s001.vmdk"
+"#,
+    )
+    .unwrap();
+
+    let file = std::fs::File::create(dir.path().join("snap-000001-s001.vmdk")).unwrap();
+    let mut writer = SparseVmdkWriter::new(file, 1024 * 1024).unwrap();
+    for grain_index in [2u64, 5] {
+        writer.write_grain(grain_index * 128, &[0x22u8; 65536]).unwrap();
+    }
+    writer.finish().unwrap();
+
+    dir
+}
+
+#[test]
+fn test_export_flattens_snapshot_delta() {
+    let dir = create_vm_with_snapshot();
+    let output_path = dir.path().join("output.ova");
+    export_vm(&dir.path().join("snap.vmx"), &output_path, ExportOptions::default(), None)
+        .expect("Export should succeed");
+
+    let mut expected = vec![0x11u8; 1024 * 1024];
+    for grain_index in [2usize, 5] {
+        expected[grain_index * 65536..(grain_index + 1) * 65536].fill(0x22);
+    }
+
+    let exported = read_exported_disk(&output_path, "snap-000001.vmdk");
+    assert_eq!(exported.len(), expected.len());
+    assert!(exported == expected, "delta was not layered over its parent");
+}
+
+#[test]
+fn test_export_snapshot_rejects_modified_parent() {
+    let dir = create_vm_with_snapshot();
+    let base = dir.path().join("snap.vmdk");
+    let content = std::fs::read_to_string(&base).unwrap();
+    std::fs::write(&base, content.replace("\nCID=fffffffe", "\nCID=0badcafe")).unwrap();
+
+    let output_path = dir.path().join("output.ova");
+    let err = export_vm(&dir.path().join("snap.vmx"), &output_path, ExportOptions::default(), None)
+        .expect_err("Export should refuse a parent with the wrong CID");
+    assert!(matches!(err, Error::Vmdk { .. }), "{}", err);
+    assert!(err.to_string().contains("modified after the snapshot"), "{}", err);
+}
+
+#[test]
+fn test_export_snapshot_without_parent() {
+    let dir = create_vm_with_snapshot();
+    std::fs::remove_file(dir.path().join("snap.vmdk")).unwrap();
+
+    let output_path = dir.path().join("output.ova");
+    let err = export_vm(&dir.path().join("snap.vmx"), &output_path, ExportOptions::default(), None)
+        .expect_err("Export should refuse a delta without its parent");
+    assert!(matches!(err, Error::Vmdk { .. }), "{}", err);
+    let message = err.to_string();
+    assert!(message.contains("'snap-000001.vmdk' is a snapshot delta"), "{}", message);
+    assert!(message.contains("Consolidate the VM's snapshots"), "{}", message);
+    assert!(message.contains("base disk (probably 'snap.vmdk')"), "{}", message);
+}
+
+/// Create a VM whose descriptor references a streamOptimized extent holding
+/// 0x33 in its second 64 KB grain.
+fn create_vm_with_stream_extent() -> tempfile::TempDir {
+    let dir = tempfile::TempDir::new().unwrap();
+    let extent = r#"RW 2048 SPARSE "stream-s001.vmdk""#;
+    let disk = TestDisk::new("stream.vmdk", "streamOptimized", &[extent]);
+    write_vm(dir.path(), "stream", "Stream VM", &[disk]);
+
+    let file = std::fs::File::create(dir.path().join("stream-s001.vmdk")).unwrap();
+    let mut writer = StreamVmdkWriter::new(file, 1024 * 1024).unwrap();
+    writer.write_grain(128, &compress_grain(&[0x33u8; 65536], 6).unwrap()).unwrap();
+    writer.finish().unwrap();
+
+    dir
+}
+
+#[test]
+fn test_export_stream_optimized_descriptor() {
+    let dir = create_vm_with_stream_extent();
+    let output_path = dir.path().join("output.ova");
+    export_vm(&dir.path().join("stream.vmx"), &output_path, ExportOptions::default(), None)
+        .expect("Export should succeed");
+
+    let ova_data = std::fs::read(&output_path).unwrap();
+    let vmdk = extract_file_from_tar(&ova_data, "stream.vmdk").unwrap();
+    let reader = StreamOptimizedReader::from_bytes(&vmdk).unwrap();
+    assert_eq!(reader.capacity(), 1024 * 1024);
+    let grains: Vec<_> = reader.grains().map(|grain| grain.unwrap()).collect();
+    assert_eq!(grains.len(), 1);
+    assert_eq!(grains[0].lba, 128);
+    assert_eq!(grains[0].data, vec![0x33u8; 65536]);
+
+    let file = std::fs::File::open(&output_path).unwrap();
+    let mut ova = ovatool_core::ova::OvaReader::new(file).unwrap();
+    assert!(ova.verify().unwrap().is_empty());
+}
+
+#[test]
+fn test_export_stream_optimized_descriptor_rejects_hosted_extent() {
+    let dir = create_vm_with_stream_extent();
+    write_sparse_extent(&dir.path().join("stream-s001.vmdk"), 1024 * 1024, 0x33);
+
+    let output_path = dir.path().join("output.ova");
+    let options = ExportOptions::default();
+    let err = export_vm(&dir.path().join("stream.vmx"), &output_path, options, None)
+        .expect_err("A hosted sparse extent is not a stream file");
+    assert!(err.to_string().contains("not a stream file"), "{}", err);
+}
+
+/// Create a VM whose two disks have identical contents.
+fn create_vm_with_identical_disks() -> tempfile::TempDir {
+    let dir = tempfile::TempDir::new().unwrap();
+    let disks = [TestDisk::flat("base-a", 2048), TestDisk::flat("base-b", 2048)];
+    write_vm(dir.path(), "twin", "Twin Disks", &disks);
+
+    for name in ["base-a", "base-b"] {
+        std::fs::write(dir.path().join(format!("{}-flat.vmdk", name)), vec![0x5Au8; 1024 * 1024])
+            .unwrap();
+    }
+
+    dir
+}
+
+#[test]
+fn test_export_dedup_identical_disks() {
+    let dir = create_vm_with_identical_disks();
+
+    for parallel_disks in [false, true] {
+        let output_path = dir.path().join("output.ova");
+        let options = ExportOptions {
+            dedup_disks: true,
+            parallel_disks,
+            ..ExportOptions::default()
+        };
+        let written = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = Arc::clone(&written);
+        let callback = Box::new(move |progress: ExportProgress| {
+            sink.lock().unwrap().push(progress.compressed_bytes_written)
+        });
+
+        let report = export_vm(&dir.path().join("twin.vmx"), &output_path, options, Some(callback))
+            .expect("Export should succeed");
+        assert_eq!(report.disks[1].file_name, "base-b.vmdk");
+        assert_eq!(report.disks[1].compressed_bytes, report.disks[0].compressed_bytes);
+
+        // The duplicate is never written, so nothing is rolled back
+        let written = written.lock().unwrap();
+        assert!(written.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", written);
+        assert_eq!(*written.last().unwrap(), report.disks[0].compressed_bytes);
+
+        let ova_data = std::fs::read(&output_path).unwrap();
+        let names = extract_tar_filenames(&ova_data);
+        assert!(names.contains(&"base-a.vmdk".to_string()));
+        assert!(!names.contains(&"base-b.vmdk".to_string()), "{:?}", names);
+
+        let ovf = extract_file_from_tar(&ova_data, "Twin_Disks.ovf").unwrap();
+        let ovf = String::from_utf8(ovf).unwrap();
+        assert_eq!(ovf.matches("<ovf:File ").count(), 1);
+        assert_eq!(ovf.matches(r#"ovf:fileRef="file1""#).count(), 2, "{}", ovf);
+        assert!(ovf.contains(r#"ovf:diskId="vmdisk2""#));
+    }
+}
+
+#[test]
+fn test_export_identical_disks_kept_without_dedup() {
+    let dir = create_vm_with_identical_disks();
+    let output_path = dir.path().join("output.ova");
+
+    export_vm(&dir.path().join("twin.vmx"), &output_path, ExportOptions::default(), None)
+        .expect("Export should succeed");
+
+    let names = extract_tar_filenames(&std::fs::read(&output_path).unwrap());
+    assert!(names.contains(&"base-a.vmdk".to_string()));
+    assert!(names.contains(&"base-b.vmdk".to_string()));
+}
+
+#[test]
+fn test_export_per_disk_compression() {
+    let dir = create_vm_with_identical_disks();
+    let vmx_path = dir.path().join("twin.vmx");
+
+    for parallel_disks in [false, true] {
+        let output_path = dir.path().join("output.ova");
+        let options = ExportOptions {
+            per_disk_compression: Some(vec![CompressionLevel::Fast, CompressionLevel::Max]),
+            parallel_disks,
+            ..ExportOptions::default()
+        };
+        let report = export_vm(&vmx_path, &output_path, options, None)
+            .expect("Export should succeed");
+        let levels: Vec<_> = report.disks.iter().map(|disk| disk.compression).collect();
+        assert_eq!(levels, vec![Some(CompressionLevel::Fast), Some(CompressionLevel::Max)]);
+
+        // The disks hold the same data, so only the level tells them apart
+        let ova_data = std::fs::read(&output_path).unwrap();
+        let fast = extract_file_from_tar(&ova_data, "base-a.vmdk").unwrap();
+        let max = extract_file_from_tar(&ova_data, "base-b.vmdk").unwrap();
+        assert_ne!(fast, max, "parallel_disks: {}", parallel_disks);
+    }
+
+    let options = ExportOptions {
+        per_disk_compression: Some(vec![CompressionLevel::Fast]),
+        ..ExportOptions::default()
+    };
+    let err = export_vm(&vmx_path, &dir.path().join("output.ova"), options, None).unwrap_err();
+    assert!(matches!(err, Error::Validation { .. }), "{}", err);
+}
+
+/// Create a VM with three disks of different sizes and contents.
+fn create_vm_with_three_disks() -> tempfile::TempDir {
+    let dir = tempfile::TempDir::new().unwrap();
+    let sectors = [4096u64, 2048, 8192];
+    let disks: Vec<TestDisk> = sectors
+        .iter()
+        .enumerate()
+        .map(|(unit, &sectors)| TestDisk::flat(&format!("disk{}", unit), sectors))
+        .collect();
+    write_vm(dir.path(), "three", "Three Disks", &disks);
+
+    for (unit, sectors) in sectors.into_iter().enumerate() {
+        let data: Vec<u8> = (0..sectors * 512)
+            .map(|i| ((i / 4096) as u8).wrapping_mul(unit as u8 + 3))
+            .collect();
+        std::fs::write(dir.path().join(format!("disk{}-flat.vmdk", unit)), data).unwrap();
+    }
+
+    dir
+}
+
+#[test]
+fn test_export_parallel_disks() {
+    let dir = create_vm_with_three_disks();
+    let vmx_path = dir.path().join("three.vmx");
+
+    let sequential_path = dir.path().join("sequential.ova");
+    export_vm(&vmx_path, &sequential_path, ExportOptions::default(), None)
+        .expect("Sequential export should succeed");
+
+    let parallel_path = dir.path().join("parallel.ova");
+    let options = ExportOptions {
+        parallel_disks: true,
+        end_to_end_verify: true,
+        ..ExportOptions::default()
+    };
+    let report = export_vm(&vmx_path, &parallel_path, options, None)
+        .expect("Parallel export should succeed");
+    assert_eq!(report.disks.len(), 3);
+    assert!(report.disks.iter().all(|disk| disk.logical_sha256.is_some()));
+
+    let sequential = std::fs::read(&sequential_path).unwrap();
+    let parallel = std::fs::read(&parallel_path).unwrap();
+    assert_eq!(extract_tar_filenames(&parallel), extract_tar_filenames(&sequential));
+    for unit in 0..3 {
+        let name = format!("disk{}.vmdk", unit);
+        let disk = extract_file_from_tar(&parallel, &name).unwrap();
+        assert_eq!(disk, extract_file_from_tar(&sequential, &name).unwrap(), "{}", name);
+
+        let reader = StreamOptimizedReader::from_bytes(&disk).unwrap();
+        let source = std::fs::read(dir.path().join(format!("disk{}-flat.vmdk", unit))).unwrap();
+        assert_eq!(reader.capacity(), source.len() as u64);
+    }
+
+    let file = std::fs::File::open(&parallel_path).unwrap();
+    let mut reader = ovatool_core::ova::OvaReader::new(file).unwrap();
+    assert!(reader.verify().unwrap().is_empty());
+}
+
+#[test]
+fn test_export_parallel_disks_progress() {
+    let dir = create_vm_with_three_disks();
+    let updates: Arc<std::sync::Mutex<Vec<ExportProgress>>> = Arc::default();
+    let recorded = Arc::clone(&updates);
+    let callback: ovatool_core::ProgressCallback = Box::new(move |progress| {
+        recorded.lock().unwrap().push(progress);
+    });
+
+    let options = ExportOptions {
+        parallel_disks: true,
+        chunk_size: 64 * 1024,
+        progress_interval: Duration::ZERO,
+        ..ExportOptions::default()
+    };
+    export_vm(
+        &dir.path().join("three.vmx"),
+        &dir.path().join("output.ova"),
+        options,
+        Some(callback),
+    )
+    .expect("Parallel export should succeed");
+
+    // Progress covers all disks, never goes backwards and ends at the total
+    let updates = updates.lock().unwrap();
+    let total = updates[0].bytes_total;
+    assert!(updates
+        .windows(2)
+        .all(|pair| pair[0].bytes_processed <= pair[1].bytes_processed));
+    assert!(updates
+        .iter()
+        .any(|progress| progress.bytes_processed > 0 && progress.bytes_processed < total));
+    assert_eq!(updates.last().unwrap().bytes_processed, total);
+    assert_eq!(updates.last().unwrap().phase, ExportPhase::Complete);
+}
+
+#[test]
+fn test_import_round_trip() {
+    let dir = create_vm_with_three_disks();
+    let vmx_path = dir.path().join("three.vmx");
+    // An EFI VM with a NIC must come back bootable and connected
+    let mut vmx = std::fs::read_to_string(&vmx_path).unwrap();
+    vmx.push_str(
+        "firmware = \"efi\"\nethernet0.present = \"TRUE\"\n\
+         ethernet0.virtualDev = \"vmxnet3\"\nethernet0.networkName = \"Lab\"\n\
+         ethernet0.startConnected = \"TRUE\"\nethernet0.connectionType = \"bridged\"\n",
+    );
+    std::fs::write(&vmx_path, vmx).unwrap();
+    let output_path = dir.path().join("output.ova");
+    export_vm(&vmx_path, &output_path, ExportOptions::default(), None)
+        .expect("Export should succeed");
+
+    let import_dir = dir.path().join("imported");
+    let report = import_ova(&output_path, &import_dir).expect("Import should succeed");
+    assert_eq!(report.vmx_path, import_dir.join("Three_Disks.vmx"));
+    assert_eq!(report.disk_paths.len(), 3);
+    assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+
+    let original = get_vm_info(&vmx_path).unwrap();
+    let imported = get_vm_info(&report.vmx_path).unwrap();
+    assert_eq!(imported.name, original.name);
+    assert_eq!(imported.guest_os, original.guest_os);
+    assert_eq!(imported.memory_mb, original.memory_mb);
+    assert_eq!(imported.cpus, original.cpus);
+    // The OVF always declares a hardware version, defaulting if the VMX has none
+    let packaged = get_ova_info(&output_path).unwrap();
+    assert_eq!(imported.hardware_version, packaged.hardware_version);
+    assert_eq!(imported.total_disk_size, original.total_disk_size);
+    let original_config = parse_vmx(&vmx_path).unwrap();
+    let imported_config = parse_vmx(&report.vmx_path).unwrap();
+    assert_eq!(imported_config.firmware, FirmwareType::Efi);
+    assert_eq!(imported_config.networks, original_config.networks);
+    assert_eq!(imported_config.networks.len(), 1);
+    for (unit, disk) in imported.disks.iter().enumerate() {
+        assert_eq!(disk.filename, format!("disk{}.vmdk", unit));
+        assert_eq!(disk.create_type, CreateType::MonolithicFlat);
+
+        let flat_name = format!("disk{}-flat.vmdk", unit);
+        let data = std::fs::read(import_dir.join(&flat_name)).unwrap();
+        assert_eq!(data, std::fs::read(dir.path().join(&flat_name)).unwrap(), "{}", flat_name);
+    }
+    assert!(!import_dir.join("disk0.vmdk.stream").exists());
+
+    // The imported VM exports again
+    export_vm(&report.vmx_path, &dir.path().join("again.ova"), ExportOptions::default(), None)
+        .expect("Imported VM should export");
+}
+
+#[test]
+fn test_export_ova_order() {
+    let dir = create_vm_with_three_disks();
+    let vmx_path = dir.path().join("three.vmx");
+    for (order, expected) in [
+        (
+            OvaOrder::OvfFirst,
+            ["Three_Disks.ovf", "disk0.vmdk", "disk1.vmdk", "disk2.vmdk", "manifest.mf"],
+        ),
+        (
+            OvaOrder::OvfLast,
+            ["disk0.vmdk", "disk1.vmdk", "disk2.vmdk", "Three_Disks.ovf", "manifest.mf"],
+        ),
+    ] {
+        let output_path = dir.path().join("output.ova");
+        let options = ExportOptions {
+            ova_order: order,
+            ..ExportOptions::default()
+        };
+        export_vm(&vmx_path, &output_path, options, None).expect("Export should succeed");
+
+        let ova_data = std::fs::read(&output_path).unwrap();
+        assert_eq!(extract_tar_filenames(&ova_data), expected, "{:?}", order);
+
+        let file = std::fs::File::open(&output_path).unwrap();
+        let mut reader = ovatool_core::ova::OvaReader::new(file).unwrap();
+        assert!(reader.verify().unwrap().is_empty(), "{:?}", order);
+        let ovf = reader.read_ovf().unwrap();
+        assert!(ovf.trim_end().ends_with("</ovf:Envelope>"), "{:?}", order);
+        assert!(ovf.contains("ovf:href=\"disk2.vmdk\""), "{:?}", order);
+    }
+}
+
+// ============================================================================
+// OVA Inspection Tests
+// ============================================================================
+
+#[test]
+fn test_hardware_version_round_trip() {
+    let dir = create_vm_with_flat_disk();
+    let vmx_path = dir.path().join("custom.vmx");
+    let mut vmx = std::fs::read_to_string(&vmx_path).unwrap();
+    vmx.push_str("virtualHW.version = \"19\"\n");
+    std::fs::write(&vmx_path, vmx).unwrap();
+
+    let vm_info = get_vm_info(&vmx_path).unwrap();
+    assert_eq!(vm_info.hardware_version.as_deref(), Some("vmx-19"));
+
+    let output_path = dir.path().join("output.ova");
+    export_vm(&vmx_path, &output_path, ExportOptions::default(), None)
+        .expect("Export should succeed");
+
+    let ova_info = get_ova_info(&output_path).expect("OVA should be readable");
+    assert_eq!(ova_info.hardware_version.as_deref(), Some("vmx-19"));
+    assert_eq!(ova_info.name, "Custom OVF VM");
+    assert_eq!(ova_info.cpus, 1);
+    assert_eq!(ova_info.memory_mb, 512);
+    assert_eq!(ova_info.disks.len(), 1);
+    assert_eq!(ova_info.disks[0].filename, "custom.vmdk");
+    assert_eq!(ova_info.disks[0].size_bytes, 1024 * 1024);
+    assert_eq!(ova_info.disks[0].create_type, CreateType::StreamOptimized);
+}
+
+#[test]
+fn test_get_ova_info_not_an_ova() {
+    let dir = create_vm_with_flat_disk();
+    let result = get_ova_info(&dir.path().join("custom.vmx"));
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_export_display_name_override() {
+    let dir = create_vm_with_flat_disk();
+    let output_path = dir.path().join("output.ova");
+    let options = ExportOptions {
+        display_name_override: Some("Web Server".to_string()),
+        ..ExportOptions::default()
+    };
+    export_vm(&dir.path().join("custom.vmx"), &output_path, options, None)
+        .expect("Export should succeed");
+
+    let ova_data = std::fs::read(&output_path).unwrap();
+    assert!(!extract_tar_filenames(&ova_data).contains(&"Custom_OVF_VM.ovf".to_string()));
+    let ovf = String::from_utf8(extract_file_from_tar(&ova_data, "Web_Server.ovf").unwrap())
+        .unwrap();
+    assert!(ovf.contains("<ovf:Name>Web Server</ovf:Name>"), "{}", ovf);
+    assert!(ovf.contains(r#"<ovf:VirtualSystem ovf:id="Web_Server">"#), "{}", ovf);
+    assert!(!ovf.contains("Custom OVF VM"), "{}", ovf);
+}
+
+#[test]
+fn test_export_scsi_model_from_descriptor() {
+    let dir = create_vm_with_flat_disk();
+    let descriptor_path = dir.path().join("custom.vmdk");
+    let mut descriptor = std::fs::read_to_string(&descriptor_path).unwrap();
+    descriptor.push_str("\nddb.adapterType = \"buslogic\"\n");
+    std::fs::write(&descriptor_path, descriptor).unwrap();
+
+    let output_path = dir.path().join("output.ova");
+    export_vm(&dir.path().join("custom.vmx"), &output_path, ExportOptions::default(), None)
+        .expect("Export should succeed");
+
+    let ova_data = std::fs::read(&output_path).unwrap();
+    let ovf = String::from_utf8(extract_file_from_tar(&ova_data, "Custom_OVF_VM.ovf").unwrap())
+        .unwrap();
+    assert!(ovf.contains("<rasd:ResourceSubType>buslogic</rasd:ResourceSubType>"), "{}", ovf);
+}
+
+#[test]
+fn test_export_read_only_extent() {
+    let dir = create_vm_with_flat_disk();
+    let descriptor_path = dir.path().join("custom.vmdk");
+    let descriptor = std::fs::read_to_string(&descriptor_path).unwrap();
+    std::fs::write(&descriptor_path, descriptor.replace("RW 2048", "RDONLY 2048")).unwrap();
+
+    let info = get_vm_info(&dir.path().join("custom.vmx")).unwrap();
+    assert_eq!(info.disks[0].access, ExtentAccess::ReadOnly);
+    assert!(info.disks[0].deletable);
+
+    let output_path = dir.path().join("output.ova");
+    let report = export_vm(
+        &dir.path().join("custom.vmx"),
+        &output_path,
+        ExportOptions::default(),
+        None,
+    )
+    .expect("Export should succeed");
+    assert_eq!(report.warnings.len(), 1, "{:?}", report.warnings);
+    assert!(
+        report.warnings[0].contains("custom.vmdk") && report.warnings[0].contains("RDONLY"),
+        "{:?}",
+        report.warnings
+    );
+}
+
+#[test]
+fn test_export_not_deletable_disk() {
+    let dir = create_vm_with_flat_disk();
+    let descriptor_path = dir.path().join("custom.vmdk");
+    let descriptor = std::fs::read_to_string(&descriptor_path).unwrap();
+    std::fs::write(&descriptor_path, descriptor + "ddb.deletable = \"false\"\n").unwrap();
+
+    // Not being deletable doesn't make the disk read-only
+    let info = get_vm_info(&dir.path().join("custom.vmx")).unwrap();
+    assert_eq!(info.disks[0].access, ExtentAccess::ReadWrite);
+    assert!(!info.disks[0].deletable);
+
+    let report = export_vm(
+        &dir.path().join("custom.vmx"),
+        &dir.path().join("output.ova"),
+        ExportOptions::default(),
+        None,
+    )
+    .expect("Export should succeed");
+    assert_eq!(report.warnings.len(), 1, "{:?}", report.warnings);
+    assert!(report.warnings[0].contains("not deletable"), "{:?}", report.warnings);
+}
+
+#[test]
+fn test_export_grain_size() {
+    let dir = create_vm_with_flat_disk();
+    let output_path = dir.path().join("output.ova");
+    let options = ExportOptions {
+        grain_size: 4096,
+        ..ExportOptions::default()
+    };
+    export_vm(&dir.path().join("custom.vmx"), &output_path, options, None)
+        .expect("Export should succeed");
+
+    let ova_data = std::fs::read(&output_path).unwrap();
+    let disk = extract_file_from_tar(&ova_data, "custom.vmdk").unwrap();
+    let reader = StreamOptimizedReader::from_bytes(&disk).unwrap();
+    assert_eq!(reader.grain_size_bytes(), 4096);
+    assert_eq!(reader.capacity(), 1024 * 1024);
+
+    let options = ExportOptions {
+        grain_size: 6000,
+        ..ExportOptions::default()
+    };
+    let err = export_vm(&dir.path().join("custom.vmx"), &output_path, options, None).unwrap_err();
+    assert!(matches!(err, Error::Validation { .. }), "{}", err);
+}
+
+#[test]
+fn test_export_rejects_bad_adaptive_threshold() {
+    let dir = create_vm_with_flat_disk();
+    let output_path = dir.path().join("output.ova");
+    for threshold in [0.0, -1.0, f64::NAN] {
+        let options = ExportOptions::builder()
+            .compression(CompressionLevel::Adaptive)
+            .adaptive_ratio_threshold(threshold)
+            .build();
+        let err =
+            export_vm(&dir.path().join("custom.vmx"), &output_path, options, None).unwrap_err();
+        assert!(matches!(err, Error::Validation { .. }), "{}", err);
+    }
+}
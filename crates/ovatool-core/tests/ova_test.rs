@@ -1,9 +1,11 @@
 //! Integration tests for OVA TAR writer with SHA256 manifest.
 
 use ovatool_core::ova::{
-    compute_sha256, create_tar_header_with_mtime, OvaWriter, Sha256Writer,
+    compute_sha256, create_tar_header_with_mtime, ManifestAlgorithm, ManifestSigner, OvaReader,
+    OvaWriter, Sha256Writer,
 };
 use std::io::{Cursor, Write};
+use std::sync::Arc;
 
 #[test]
 fn test_sha256_computation() {
@@ -132,6 +134,44 @@ fn test_manifest_format() {
     assert!(manifest_str.contains(&vmdk_hash));
 }
 
+#[test]
+fn test_manifest_algorithms() {
+    let cases = [
+        (ManifestAlgorithm::Sha1, "SHA1", 40),
+        (ManifestAlgorithm::Sha256, "SHA256", 64),
+        (ManifestAlgorithm::Sha512, "SHA512", 128),
+    ];
+    for (algorithm, prefix, hex_len) in cases {
+        let mut writer = OvaWriter::new(Cursor::new(Vec::new()))
+            .unwrap()
+            .with_manifest_algorithm(algorithm);
+        writer.add_file("test.ovf", b"OVF content").unwrap();
+        let mut stream = writer.add_file_streaming_unsized("test.vmdk").unwrap();
+        stream.write_all(b"VMDK data").unwrap();
+        let stream_digest = stream.finish().unwrap();
+        let data = writer.finish().unwrap().into_inner();
+
+        let manifest = String::from_utf8(extract_file_content(&data, "manifest.mf").unwrap())
+            .unwrap();
+        let lines: Vec<&str> = manifest.lines().collect();
+        assert_eq!(lines.len(), 2, "{}", manifest);
+        for (line, name) in lines.iter().zip(["test.ovf", "test.vmdk"]) {
+            let digest = line
+                .strip_prefix(&format!("{}({})= ", prefix, name))
+                .unwrap_or_else(|| panic!("{} line expected, got {}", prefix, line));
+            assert_eq!(digest.len(), hex_len, "{}", line);
+        }
+        assert!(lines[0].ends_with(&algorithm.digest(b"OVF content")));
+        assert!(lines[1].ends_with(&stream_digest));
+        assert_eq!(stream_digest, algorithm.digest(b"VMDK data"));
+
+        // The reader checks each line with the algorithm it names
+        let mut reader = OvaReader::new(Cursor::new(data)).unwrap();
+        assert!(reader.verify().unwrap().is_empty());
+        reader.verify_ovf_digest().unwrap();
+    }
+}
+
 #[test]
 fn test_streaming_file_write() {
     let buffer = Cursor::new(Vec::new());
@@ -232,6 +272,193 @@ fn test_long_filename() {
     assert!(files.contains(&long_name));
 }
 
+/// Write a small OVA with an OVF and two disk images.
+fn write_sample_ova() -> Vec<u8> {
+    let mut ova = OvaWriter::new(Cursor::new(Vec::new())).unwrap();
+    ova.add_file("vm.ovf", b"<Envelope/>").unwrap();
+    ova.add_file("disk1.vmdk", &[0x11u8; 1500]).unwrap();
+    ova.add_file("disk2.vmdk", &[0x22u8; 700]).unwrap();
+    ova.finish().unwrap().into_inner()
+}
+
+#[test]
+fn test_reader_extracts_and_verifies() {
+    let mut reader = OvaReader::new(Cursor::new(write_sample_ova())).unwrap();
+
+    let entries: Vec<(&str, u64)> = reader
+        .entries()
+        .iter()
+        .map(|entry| (entry.name.as_str(), entry.size))
+        .collect();
+    assert_eq!(entries[..3], [("vm.ovf", 11), ("disk1.vmdk", 1500), ("disk2.vmdk", 700)]);
+    assert_eq!(reader.entries()[1].offset, 1024 + 512);
+
+    let mut extracted = Vec::new();
+    assert_eq!(reader.extract_file("disk1.vmdk", &mut extracted).unwrap(), 1500);
+    assert_eq!(extracted, vec![0x11u8; 1500]);
+    assert!(reader.extract_file("missing.vmdk", &mut Vec::new()).is_err());
+
+    assert!(reader.verify().unwrap().is_empty());
+}
+
+#[test]
+fn test_reader_verify_reports_mismatches() {
+    let mut data = write_sample_ova();
+    let offset = OvaReader::new(Cursor::new(data.clone())).unwrap().entries()[2].offset;
+    data[offset as usize] ^= 0xFF;
+
+    let mut reader = OvaReader::new(Cursor::new(data)).unwrap();
+    let mismatches = reader.verify().unwrap();
+    assert_eq!(mismatches.len(), 1);
+    assert_eq!(mismatches[0].name, "disk2.vmdk");
+    assert_eq!(mismatches[0].algorithm, ManifestAlgorithm::Sha256);
+    assert_eq!(mismatches[0].expected, compute_sha256(&[0x22u8; 700]));
+    assert!(mismatches[0].actual.is_some());
+}
+
+#[test]
+fn test_reader_verify_reports_missing_file() {
+    let mut ova = OvaWriter::new(Cursor::new(Vec::new())).unwrap();
+    let digest = ManifestAlgorithm::Sha512.digest(b"ghost");
+    let manifest = format!("SHA512(ghost.vmdk)= {}\n", digest);
+    ova.add_file("vm.mf", manifest.as_bytes()).unwrap();
+    let data = ova.finish().unwrap().into_inner();
+
+    let mut reader = OvaReader::new(Cursor::new(data)).unwrap();
+    let mismatches = reader.verify().unwrap();
+    assert_eq!(mismatches.len(), 1);
+    assert_eq!(mismatches[0].name, "ghost.vmdk");
+    assert_eq!(mismatches[0].algorithm, ManifestAlgorithm::Sha512);
+    assert_eq!(mismatches[0].expected, digest);
+    assert_eq!(mismatches[0].actual, None);
+}
+
+#[test]
+fn test_long_filenames_round_trip() {
+    // 120 bytes with a '/' fits the USTAR prefix and name fields
+    let split_name = format!("{}/{}.vmdk", "d".repeat(60), "s".repeat(54));
+    // 200 bytes without a '/' needs a PAX header
+    let pax_name = format!("{}.vmdk", "x".repeat(195));
+    assert_eq!(split_name.len(), 120);
+    assert_eq!(pax_name.len(), 200);
+
+    let mut ova = OvaWriter::new(Cursor::new(Vec::new())).unwrap();
+    ova.add_file("vm.ovf", b"<Envelope/>").unwrap();
+    ova.add_file(&split_name, &[0x11u8; 1500]).unwrap();
+    let mut entry = ova.add_file_streaming_unsized(&pax_name).unwrap();
+    entry.write_all(&[0x22u8; 700]).unwrap();
+    entry.finish().unwrap();
+    let data = ova.finish().unwrap().into_inner();
+
+    let mut reader = OvaReader::new(Cursor::new(data)).unwrap();
+    let entries: Vec<(&str, u64)> = reader
+        .entries()
+        .iter()
+        .map(|entry| (entry.name.as_str(), entry.size))
+        .collect();
+    assert_eq!(
+        entries[..3],
+        [("vm.ovf", 11), (split_name.as_str(), 1500), (pax_name.as_str(), 700)]
+    );
+    assert_eq!(reader.read_file(&split_name).unwrap(), vec![0x11u8; 1500]);
+    assert_eq!(reader.read_file(&pax_name).unwrap(), vec![0x22u8; 700]);
+    assert!(reader.verify().unwrap().is_empty());
+}
+
+/// Stand-in signer whose "signature" is a keyed SHA256 of the manifest.
+#[derive(Debug)]
+struct TestSigner;
+
+impl TestSigner {
+    const CERT: &'static str =
+        "-----BEGIN CERTIFICATE-----\nTEST\n-----END CERTIFICATE-----\n";
+
+    fn signature(manifest: &[u8]) -> Vec<u8> {
+        let keyed = [b"test-key".as_slice(), manifest].concat();
+        compute_sha256(&keyed).into_bytes()
+    }
+}
+
+impl ManifestSigner for TestSigner {
+    fn sign(&self, manifest: &[u8]) -> ovatool_core::Result<Vec<u8>> {
+        Ok(Self::signature(manifest))
+    }
+
+    fn certificate_pem(&self) -> String {
+        Self::CERT.to_string()
+    }
+}
+
+#[test]
+fn test_signed_ova_order_and_certificate() {
+    let mut ova = OvaWriter::new(Cursor::new(Vec::new()))
+        .unwrap()
+        .with_signer(Arc::new(TestSigner));
+    ova.add_file("vm.ovf", b"<Envelope/>").unwrap();
+    ova.add_file("disk1.vmdk", &[0x11u8; 1500]).unwrap();
+    let data = ova.finish().unwrap().into_inner();
+
+    assert_eq!(
+        extract_tar_filenames(&data),
+        vec!["vm.ovf", "disk1.vmdk", "manifest.mf", "manifest.cert"]
+    );
+    assert!(data[data.len() - 1024..].iter().all(|&b| b == 0), "end marker is last");
+
+    // The manifest covers the other files but not the certificate
+    let manifest = extract_file_content(&data, "manifest.mf").unwrap();
+    let manifest_text = String::from_utf8(manifest.clone()).unwrap();
+    assert_eq!(manifest_text.lines().count(), 2);
+    assert!(!manifest_text.contains("manifest.cert"));
+
+    // The certificate signs the manifest bytes
+    let cert = String::from_utf8(extract_file_content(&data, "manifest.cert").unwrap()).unwrap();
+    let (signature_line, pem) = cert.split_once('\n').unwrap();
+    let signature_hex = signature_line.strip_prefix("SHA256(manifest.mf)= ").unwrap();
+    let expected_hex: String = TestSigner::signature(&manifest)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    assert_eq!(signature_hex, expected_hex);
+    assert_eq!(pem, TestSigner::CERT);
+
+    let mut reader = OvaReader::new(Cursor::new(data)).unwrap();
+    assert!(reader.verify().unwrap().is_empty());
+}
+
+/// Stand-in signer that signs a SHA1 digest of the manifest.
+#[derive(Debug)]
+struct Sha1Signer;
+
+impl ManifestSigner for Sha1Signer {
+    fn sign(&self, manifest: &[u8]) -> ovatool_core::Result<Vec<u8>> {
+        Ok(ManifestAlgorithm::Sha1.digest(manifest).into_bytes())
+    }
+
+    fn digest_algorithm(&self) -> ManifestAlgorithm {
+        ManifestAlgorithm::Sha1
+    }
+
+    fn certificate_pem(&self) -> String {
+        TestSigner::CERT.to_string()
+    }
+}
+
+#[test]
+fn test_certificate_names_signer_digest_algorithm() {
+    // The signature line follows the signer, not the manifest's algorithm
+    let mut ova = OvaWriter::new(Cursor::new(Vec::new()))
+        .unwrap()
+        .with_manifest_algorithm(ManifestAlgorithm::Sha512)
+        .with_signer(Arc::new(Sha1Signer));
+    ova.add_file("vm.ovf", b"<Envelope/>").unwrap();
+    let data = ova.finish().unwrap().into_inner();
+
+    let manifest = String::from_utf8(extract_file_content(&data, "manifest.mf").unwrap()).unwrap();
+    assert!(manifest.starts_with("SHA512(vm.ovf)= "), "{}", manifest);
+    let cert = String::from_utf8(extract_file_content(&data, "manifest.cert").unwrap()).unwrap();
+    assert!(cert.starts_with("SHA1(manifest.mf)= "), "{}", cert);
+}
+
 // Helper functions for tests
 
 fn extract_tar_filenames(data: &[u8]) -> Vec<String> {
@@ -258,7 +485,7 @@ fn extract_tar_filenames(data: &[u8]) -> Vec<String> {
         // Parse size and skip to next header
         if let Ok(size_str) = std::str::from_utf8(&data[pos + 124..pos + 135]) {
             if let Ok(size) = u64::from_str_radix(size_str.trim_matches('\0').trim(), 8) {
-                let content_blocks = (size + 511) / 512;
+                let content_blocks = size.div_ceil(512);
                 pos += 512 + (content_blocks * 512) as usize;
                 continue;
             }
@@ -292,7 +519,7 @@ fn extract_file_content(data: &[u8], filename: &str) -> Option<Vec<u8>> {
             return Some(data[content_start..content_end].to_vec());
         }
 
-        let content_blocks = (size + 511) / 512;
+        let content_blocks = size.div_ceil(512);
         pos += 512 + (content_blocks * 512) as usize;
     }
 
@@ -1,7 +1,12 @@
 //! Integration tests for OVF XML builder.
 
-use ovatool_core::ovf::{DiskInfo, OvfBuilder};
-use ovatool_core::vmx::{DiskConfig, NetworkConfig, VmxConfig};
+use ovatool_core::ovf::{
+    parse_file_references, validate_ovf, DiskFormat, DiskInfo, OvfBuilder, OvfCollectionBuilder,
+    OvfInfoTexts, OvfLayout,
+};
+use ovatool_core::vmx::{
+    CdromConfig, ControllerConfig, DiskConfig, FirmwareType, NetworkConfig, VmxConfig,
+};
 use std::collections::HashMap;
 
 /// Create a test VMX configuration for use in tests.
@@ -20,7 +25,16 @@ fn create_test_config() -> VmxConfig {
             name: "ethernet0".to_string(),
             virtual_dev: Some("e1000".to_string()),
             network_name: Some("NAT".to_string()),
+            start_connected: true,
+            connection_type: None,
+            rss: None,
+            num_queue_pairs: None,
         }],
+        cdroms: vec![],
+        controllers: vec![],
+        firmware: FirmwareType::Bios,
+        nvram: None,
+        annotation: String::new(),
         raw: HashMap::new(),
     }
 }
@@ -265,6 +279,135 @@ fn test_ovf_network_section() {
     );
 }
 
+#[test]
+fn test_ovf_network_start_disconnected() {
+    let mut config = create_test_config();
+    config.networks[0].start_connected = false;
+    let disks = create_test_disks();
+
+    let ovf = OvfBuilder::new(&config).build(&disks).expect("Failed to build OVF");
+
+    let item_start = ovf.find("<rasd:ResourceSubType>e1000").unwrap();
+    let item_start = ovf[..item_start].rfind("<ovf:Item>").unwrap();
+    let item_end = item_start + ovf[item_start..].find("</ovf:Item>").unwrap();
+    let item = &ovf[item_start..item_end];
+    assert!(
+        item.contains("<rasd:AutomaticAllocation>false</rasd:AutomaticAllocation>"),
+        "Disconnected NIC should not be allocated at power-on"
+    );
+}
+
+#[test]
+fn test_ovf_network_host_only() {
+    let mut config = create_test_config();
+    config.networks[0].network_name = Some("HostOnly".to_string());
+    config.networks[0].connection_type = Some("hostonly".to_string());
+    let disks = create_test_disks();
+
+    let ovf = OvfBuilder::new(&config).build(&disks).expect("Failed to build OVF");
+
+    assert!(
+        ovf.contains("<ovf:Description>The HostOnly network (host-only)</ovf:Description>"),
+        "Network description should mention the connection type"
+    );
+    let item_start = ovf.find("<rasd:ResourceSubType>e1000").unwrap();
+    let item_end = item_start + ovf[item_start..].find("</ovf:Item>").unwrap();
+    assert!(
+        ovf[item_start..item_end]
+            .contains(r#"<vmw:Config ovf:required="false" vmw:key="connectionType" vmw:value="hostonly"/>"#),
+        "Adapter should carry its connection type"
+    );
+}
+
+#[test]
+fn test_ovf_virtual_system_id_uses_uuid() {
+    let disks = create_test_disks();
+    let build = |name: &str, uuid: &str| {
+        let mut config = create_test_config();
+        config.display_name = name.to_string();
+        config.raw.insert("uuid.bios".to_string(), uuid.to_string());
+        OvfBuilder::new(&config).build(&disks).expect("Failed to build OVF")
+    };
+
+    // Both names sanitize to "Web_Server_"
+    let first = build("Web Server!", "56 4d 00 00 00 00 00 00-00 00 00 00 00 00 00 01");
+    let second = build("Web Server?", "56 4d 00 00 00 00 00 00-00 00 00 00 00 00 00 02");
+
+    assert!(first.contains(r#"<ovf:VirtualSystem ovf:id="564d0000-0000-0000-0000-000000000001">"#));
+    assert!(second.contains(r#"<ovf:VirtualSystem ovf:id="564d0000-0000-0000-0000-000000000002">"#));
+    assert!(first.contains("<ovf:Name>Web Server!</ovf:Name>"));
+    assert!(second.contains("<ovf:Name>Web Server?</ovf:Name>"));
+}
+
+#[test]
+fn test_ovf_virtual_system_id_without_uuid() {
+    let mut config = create_test_config();
+    config.display_name = "Web Server!".to_string();
+    let disks = create_test_disks();
+
+    let ovf = OvfBuilder::new(&config).build(&disks).expect("Failed to build OVF");
+    assert!(ovf.contains(r#"<ovf:VirtualSystem ovf:id="Web_Server_">"#));
+}
+
+#[test]
+fn test_ovf_network_multiqueue_config() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let vmx_path = dir.path().join("fast-nic.vmx");
+    std::fs::write(
+        &vmx_path,
+        r#"displayName = "FastNic"
+ethernet0.present = "TRUE"
+ethernet0.virtualDev = "vmxnet3"
+ethernet0.rss = "TRUE"
+ethernet0.numqps = "4"
+"#,
+    )
+    .unwrap();
+    let config = ovatool_core::vmx::parse_vmx(&vmx_path).unwrap();
+    let disks = create_test_disks();
+
+    let ovf = OvfBuilder::new(&config).build(&disks).expect("Failed to build OVF");
+
+    let item_start = ovf.find("<rasd:ResourceSubType>vmxnet3").unwrap();
+    let item_end = item_start + ovf[item_start..].find("</ovf:Item>").unwrap();
+    let item = &ovf[item_start..item_end];
+    assert!(item.contains(r#"<vmw:Config ovf:required="false" vmw:key="rss" vmw:value="true"/>"#));
+    assert!(item.contains(r#"<vmw:Config ovf:required="false" vmw:key="numqps" vmw:value="4"/>"#));
+}
+
+#[test]
+fn test_ovf_network_without_multiqueue_config() {
+    let config = create_test_config();
+    let disks = create_test_disks();
+
+    let ovf = OvfBuilder::new(&config).build(&disks).expect("Failed to build OVF");
+    assert!(!ovf.contains("vmw:key=\"rss\""));
+    assert!(!ovf.contains("vmw:key=\"numqps\""));
+}
+
+#[test]
+fn test_ovf_efi_firmware() {
+    let mut config = create_test_config();
+    config.firmware = FirmwareType::Efi;
+    let disks = create_test_disks();
+
+    let ovf = OvfBuilder::new(&config).build(&disks).expect("Failed to build OVF");
+    let firmware = r#"<vmw:Config ovf:required="false" vmw:key="firmware" vmw:value="efi"/>"#;
+    let firmware_pos = ovf.find(firmware).expect("EFI firmware should be declared");
+    let last_item_end = ovf.rfind("</ovf:Item>").unwrap();
+    let section_end = ovf.find("</ovf:VirtualHardwareSection>").unwrap();
+    assert!(last_item_end < firmware_pos && firmware_pos < section_end);
+}
+
+#[test]
+fn test_ovf_bios_firmware_omitted() {
+    let config = create_test_config();
+    let disks = create_test_disks();
+
+    let ovf = OvfBuilder::new(&config).build(&disks).expect("Failed to build OVF");
+    assert!(!ovf.contains("vmw:key=\"firmware\""));
+}
+
 #[test]
 fn test_ovf_xml_declaration() {
     let config = create_test_config();
@@ -403,3 +546,599 @@ fn test_ovf_scsi_controller() {
         "OVF should contain lsilogic controller type"
     );
 }
+
+#[test]
+fn test_ovf_pvscsi_controller() {
+    let mut config = create_test_config();
+    config.controllers.push(ControllerConfig {
+        name: "scsi0".to_string(),
+        shared_bus: None,
+        virtual_dev: Some("pvscsi".to_string()),
+    });
+    let disks = create_test_disks();
+
+    let ovf = OvfBuilder::new(&config).build(&disks).expect("Failed to build OVF");
+
+    assert!(ovf.contains("<rasd:ResourceSubType>VirtualSCSI</rasd:ResourceSubType>"));
+    assert!(!ovf.contains("lsilogic"));
+}
+
+#[test]
+fn test_ovf_nvme_disk_controller() {
+    let mut config = create_test_config();
+    config.disks[0].controller = "nvme0".to_string();
+    let disks = create_test_disks();
+
+    let ovf = OvfBuilder::new(&config).build(&disks).expect("Failed to build OVF");
+
+    // No SCSI controller; the NVMe controller follows the disk (4) and NIC (5)
+    assert!(!ovf.contains("SCSI Controller"), "{}", ovf);
+    let nvme_pos = ovf.find("<rasd:ElementName>NVMe Controller 0</rasd:ElementName>").unwrap();
+    let nvme_item = &ovf[nvme_pos..nvme_pos + ovf[nvme_pos..].find("</ovf:Item>").unwrap()];
+    assert!(nvme_item.contains("<rasd:InstanceID>6</rasd:InstanceID>"));
+    assert!(nvme_item.contains(">vmware.nvme.controller</rasd:ResourceSubType>"));
+    assert!(nvme_item.contains("<rasd:ResourceType>20</rasd:ResourceType>"));
+
+    let disk_pos = ovf.find("<rasd:ElementName>Hard Disk 1</rasd:ElementName>").unwrap();
+    let disk_item = &ovf[disk_pos..disk_pos + ovf[disk_pos..].find("</ovf:Item>").unwrap()];
+    assert!(disk_item.contains("<rasd:Parent>6</rasd:Parent>"));
+}
+
+#[test]
+fn test_ovf_mixed_ide_and_scsi_disks() {
+    let mut config = create_test_config();
+    config.disks.insert(
+        0,
+        DiskConfig {
+            file_name: "TestVM_ide.vmdk".to_string(),
+            controller: "ide0".to_string(),
+            unit: 1,
+        },
+    );
+    let disks: Vec<DiskInfo> = (1..=2)
+        .map(|i| DiskInfo {
+            id: format!("vmdisk{}", i),
+            file_ref: format!("file{}", i),
+            capacity_bytes: 1024 * 1024 * 1024,
+            file_size_bytes: 1024 * 1024,
+        })
+        .collect();
+
+    let ovf = OvfBuilder::new(&config).build(&disks).expect("Failed to build OVF");
+    let item = |name: &str| {
+        let pos = ovf.find(&format!("<rasd:ElementName>{}</rasd:ElementName>", name)).unwrap();
+        let start = ovf[..pos].rfind("<ovf:Item").unwrap();
+        ovf[start..pos + ovf[pos..].find("</ovf:Item>").unwrap()].to_string()
+    };
+
+    // One controller per distinct VMX controller
+    let scsi = item("SCSI Controller 0");
+    assert!(scsi.contains("<rasd:InstanceID>3</rasd:InstanceID>"));
+    assert!(scsi.contains("<rasd:ResourceType>6</rasd:ResourceType>"));
+    let ide = item("IDE Controller 0");
+    assert!(ide.contains("<rasd:InstanceID>7</rasd:InstanceID>"), "{}", ide);
+    assert!(ide.contains("<rasd:ResourceType>5</rasd:ResourceType>"));
+
+    let ide_disk = item("Hard Disk 1");
+    assert!(ide_disk.contains("<rasd:Parent>7</rasd:Parent>"), "{}", ide_disk);
+    assert!(ide_disk.contains("<rasd:AddressOnParent>1</rasd:AddressOnParent>"));
+    let scsi_disk = item("Hard Disk 2");
+    assert!(scsi_disk.contains("<rasd:Parent>3</rasd:Parent>"), "{}", scsi_disk);
+    assert!(scsi_disk.contains("<rasd:AddressOnParent>0</rasd:AddressOnParent>"));
+
+    let mut instance_ids: Vec<&str> = ovf
+        .split("<rasd:InstanceID>")
+        .skip(1)
+        .map(|rest| &rest[..rest.find('<').unwrap()])
+        .collect();
+    let count = instance_ids.len();
+    instance_ids.sort();
+    instance_ids.dedup();
+    assert_eq!(instance_ids.len(), count, "InstanceIDs should be unique");
+}
+
+#[test]
+fn test_ovf_storage_policy_omitted_by_default() {
+    let config = create_test_config();
+    let builder = OvfBuilder::new(&config);
+    let disks = create_test_disks();
+
+    let ovf = builder.build(&disks).expect("Failed to build OVF");
+
+    assert!(
+        !ovf.contains("StorageGroupSection"),
+        "OVF should not contain a storage group without a policy"
+    );
+    assert!(
+        !ovf.contains("vmw:StorageSection"),
+        "OVF should not reference a storage group without a policy"
+    );
+}
+
+#[test]
+fn test_ovf_storage_policy() {
+    let config = create_test_config();
+    let builder =
+        OvfBuilder::new(&config).with_storage_policy(Some("Gold & Replicated".to_string()));
+    let disks = create_test_disks();
+
+    let ovf = builder.build(&disks).expect("Failed to build OVF");
+
+    // Policy is declared once at envelope level
+    assert!(
+        ovf.contains(
+            "<vmw:StorageGroupSection ovf:required=\"false\" vmw:id=\"group1\" vmw:name=\"Gold &amp; Replicated\">"
+        ),
+        "OVF should declare the storage group with the escaped policy name"
+    );
+
+    // Referenced by the VM and by the disk
+    assert_eq!(
+        ovf.matches("<vmw:StorageSection ovf:required=\"false\" vmw:group=\"group1\">")
+            .count(),
+        2,
+        "VM and disk should both reference the storage group"
+    );
+    assert!(ovf.contains("Storage policy for this disk"));
+
+    // Section must come before the VirtualSystem
+    let group_pos = ovf.find("vmw:StorageGroupSection").unwrap();
+    let vs_pos = ovf.find("<ovf:VirtualSystem").unwrap();
+    assert!(group_pos < vs_pos);
+}
+
+#[test]
+fn test_ovf_custom_vm_description() {
+    let config = create_test_config();
+    let disks = create_test_disks();
+
+    let ovf = OvfBuilder::new(&config)
+        .with_description("Build agent <v2>")
+        .build(&disks)
+        .expect("Failed to build OVF");
+
+    let vs_start = ovf.find("<ovf:VirtualSystem").unwrap();
+    let vs_info = &ovf[vs_start..ovf[vs_start..].find("</ovf:Info>").unwrap() + vs_start];
+    assert!(
+        vs_info.ends_with("<ovf:Info>Build agent &lt;v2&gt;"),
+        "VirtualSystem Info should hold the escaped description"
+    );
+    assert!(!ovf.contains("<ovf:Info>A virtual machine</ovf:Info>"));
+
+    // Other sections keep their defaults
+    assert!(ovf.contains("<ovf:Info>Virtual hardware requirements</ovf:Info>"));
+}
+
+#[test]
+fn test_ovf_custom_info_texts() {
+    let config = create_test_config();
+    let disks = create_test_disks();
+    let info = OvfInfoTexts {
+        disk_section: "Informations sur les disques".to_string(),
+        virtual_hardware: "Configuration matérielle".to_string(),
+        ..OvfInfoTexts::default()
+    };
+
+    let ovf = OvfBuilder::new(&config)
+        .with_info_texts(info)
+        .build(&disks)
+        .expect("Failed to build OVF");
+
+    assert!(ovf.contains("<ovf:Info>Informations sur les disques</ovf:Info>"));
+    assert!(ovf.contains("<ovf:Info>Configuration matérielle</ovf:Info>"));
+    assert!(ovf.contains("<ovf:Info>Network configuration</ovf:Info>"));
+    assert!(ovf.contains("<ovf:Info>A virtual machine</ovf:Info>"));
+}
+
+#[test]
+fn test_ovf_product_section() {
+    let mut config = create_test_config();
+    for (key, value) in [
+        ("guestinfo.product.name", "Acme Firewall"),
+        ("guestinfo.product.vendor", "Acme & Sons"),
+        ("guestinfo.product.version", "4.2"),
+        ("guestinfo.product.fullVersion", "4.2.1-build-1234"),
+    ] {
+        config.raw.insert(key.to_string(), value.to_string());
+    }
+
+    let ovf = OvfBuilder::new(&config)
+        .build(&create_test_disks())
+        .expect("Failed to build OVF");
+
+    let start = ovf.find("<ovf:ProductSection>").expect("ProductSection should be present");
+    let end = ovf.find("</ovf:ProductSection>").unwrap();
+    let section = &ovf[start..end];
+    assert!(section.contains("<ovf:Info>Information about the installed software</ovf:Info>"));
+    assert!(section.contains("<ovf:Product>Acme Firewall</ovf:Product>"));
+    assert!(section.contains("<ovf:Vendor>Acme &amp; Sons</ovf:Vendor>"));
+    assert!(section.contains("<ovf:Version>4.2</ovf:Version>"));
+    assert!(section.contains("<ovf:FullVersion>4.2.1-build-1234</ovf:FullVersion>"));
+
+    // Inside the VirtualSystem, ahead of the hardware
+    assert!(start > ovf.find("<ovf:VirtualSystem ").unwrap());
+    assert!(end < ovf.find("<ovf:VirtualHardwareSection>").unwrap());
+}
+
+#[test]
+fn test_ovf_product_section_omitted() {
+    let config = create_test_config();
+
+    let ovf = OvfBuilder::new(&config)
+        .build(&create_test_disks())
+        .expect("Failed to build OVF");
+    assert!(!ovf.contains("ProductSection"));
+}
+
+#[test]
+fn test_ovf_icon() {
+    let config = create_test_config();
+
+    let ovf = OvfBuilder::new(&config)
+        .with_icon("logo.png", "image/png")
+        .build(&create_test_disks())
+        .expect("Failed to build OVF");
+
+    // An icon alone is enough for a ProductSection
+    assert!(ovf.contains("<ovf:File ovf:href=\"logo.png\" ovf:id=\"icon\"/>"));
+    let start = ovf.find("<ovf:ProductSection>").expect("ProductSection should be present");
+    let end = ovf.find("</ovf:ProductSection>").unwrap();
+    let section = &ovf[start..end];
+    assert!(section.contains(r#"<ovf:Icon ovf:fileRef="icon" ovf:mimeType="image/png"/>"#));
+    assert!(!section.contains("<ovf:Product>"));
+}
+
+#[test]
+fn test_ovf_annotation_section() {
+    let mut config = create_test_config();
+    config.annotation = "Web server\nOwner: <ops> & \"infra\"".to_string();
+
+    let ovf = OvfBuilder::new(&config)
+        .build(&create_test_disks())
+        .expect("Failed to build OVF");
+
+    let start = ovf.find("<ovf:AnnotationSection>").expect("AnnotationSection should be present");
+    let end = ovf.find("</ovf:AnnotationSection>").unwrap();
+    let section = &ovf[start..end];
+    assert!(section.contains("<ovf:Info>A human-readable annotation</ovf:Info>"));
+    assert!(section.contains(
+        "<ovf:Annotation>Web server\nOwner: &lt;ops&gt; &amp; &quot;infra&quot;</ovf:Annotation>"
+    ));
+    assert!(start > ovf.find("<ovf:VirtualSystem ").unwrap());
+    assert!(end < ovf.find("<ovf:VirtualHardwareSection>").unwrap());
+
+    config.annotation = String::new();
+    let ovf = OvfBuilder::new(&config)
+        .build(&create_test_disks())
+        .expect("Failed to build OVF");
+    assert!(!ovf.contains("AnnotationSection"));
+}
+
+#[test]
+fn test_ovf_eula_section() {
+    let config = create_test_config();
+    let disks = create_test_disks();
+
+    let ovf = OvfBuilder::new(&config)
+        .with_eula("Terms & conditions: use <at your own risk>")
+        .build(&disks)
+        .expect("Failed to build OVF");
+
+    assert!(ovf.contains(
+        "<ovf:License>Terms &amp; conditions: use &lt;at your own risk&gt;</ovf:License>"
+    ));
+
+    // The section belongs to the VirtualSystem, ahead of its hardware
+    let eula = ovf.find("<ovf:EulaSection>").expect("EULA section missing");
+    assert!(eula > ovf.find("<ovf:VirtualSystem").unwrap());
+    assert!(eula < ovf.find("<ovf:VirtualHardwareSection").unwrap());
+}
+
+#[test]
+fn test_ovf_no_eula_by_default() {
+    let config = create_test_config();
+    let disks = create_test_disks();
+
+    let ovf = OvfBuilder::new(&config).build(&disks).expect("Failed to build OVF");
+    assert!(!ovf.contains("EulaSection"));
+}
+
+#[test]
+fn test_ovf_cdrom_boot_order() {
+    let mut config = create_test_config();
+    config.cdroms.push(CdromConfig {
+        controller: "ide1".to_string(),
+        unit: 0,
+    });
+    config
+        .raw
+        .insert("bios.bootOrder".to_string(), "cdrom,hdd".to_string());
+    let disks = create_test_disks();
+
+    let ovf = OvfBuilder::new(&config).build(&disks).expect("Failed to build OVF");
+
+    // IDE controller and CD drive follow the disk (4) and NIC (5)
+    assert!(ovf.contains("<rasd:ElementName>IDE Controller 1</rasd:ElementName>"));
+    let cd_pos = ovf.find("<rasd:ElementName>CD/DVD Drive 1</rasd:ElementName>").unwrap();
+    let cd_item = &ovf[cd_pos..cd_pos + ovf[cd_pos..].find("</ovf:Item>").unwrap()];
+    assert!(cd_item.contains("<rasd:InstanceID>7</rasd:InstanceID>"));
+    assert!(cd_item.contains("<rasd:Parent>6</rasd:Parent>"));
+    assert!(cd_item.contains("<rasd:ResourceType>15</rasd:ResourceType>"));
+
+    // CD boots before the disk
+    let cd_boot = ovf
+        .find("<vmw:BootOrderSection vmw:instanceId=\"7\" vmw:type=\"cdrom\">")
+        .expect("CD drive should be in the boot order");
+    let disk_boot = ovf
+        .find("<vmw:BootOrderSection vmw:instanceId=\"4\" vmw:type=\"disk\">")
+        .expect("Disk should be in the boot order");
+    assert!(cd_boot < disk_boot, "CD should precede disks in boot order");
+}
+
+#[test]
+fn test_ovf_no_boot_order_without_bios_setting() {
+    let mut config = create_test_config();
+    config.cdroms.push(CdromConfig {
+        controller: "sata0".to_string(),
+        unit: 1,
+    });
+    let disks = create_test_disks();
+
+    let ovf = OvfBuilder::new(&config).build(&disks).expect("Failed to build OVF");
+
+    assert!(ovf.contains("<rasd:ResourceSubType>vmware.sata.ahci</rasd:ResourceSubType>"));
+    assert!(!ovf.contains("BootOrderSection"));
+}
+
+#[test]
+fn test_ovf_two_nics_on_same_network() {
+    let mut config = create_test_config();
+    config.networks = ["ethernet0", "ethernet1"]
+        .iter()
+        .map(|name| NetworkConfig {
+            name: name.to_string(),
+            virtual_dev: Some("vmxnet3".to_string()),
+            network_name: Some("VM Network".to_string()),
+            start_connected: true,
+            connection_type: None,
+            rss: None,
+            num_queue_pairs: None,
+        })
+        .collect();
+    let disks = create_test_disks();
+
+    let ovf = OvfBuilder::new(&config).build(&disks).expect("Failed to build OVF");
+
+    assert_eq!(
+        ovf.matches("<ovf:Network ovf:name=\"VM Network\">").count(),
+        1,
+        "NetworkSection should declare the shared network once"
+    );
+    assert_eq!(
+        ovf.matches("<rasd:Connection>VM Network</rasd:Connection>").count(),
+        2,
+        "Each NIC should keep its own adapter item"
+    );
+    assert!(ovf.contains("<rasd:ElementName>Network Adapter 2</rasd:ElementName>"));
+}
+
+/// Flatten an XML document into its non-whitespace events.
+fn xml_events(xml: &str) -> Vec<String> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    let mut events = Vec::new();
+    loop {
+        match reader.read_event().expect("OVF should be well-formed") {
+            quick_xml::events::Event::Eof => break,
+            quick_xml::events::Event::Text(text) => {
+                let text = text.unescape().unwrap();
+                if !text.trim().is_empty() {
+                    events.push(format!("Text({})", text));
+                }
+            }
+            quick_xml::events::Event::Start(e) | quick_xml::events::Event::Empty(e) => {
+                let attrs: Vec<String> = e
+                    .attributes()
+                    .map(|a| {
+                        let a = a.unwrap();
+                        format!(
+                            "{}={}",
+                            String::from_utf8_lossy(a.key.as_ref()),
+                            a.unescape_value().unwrap()
+                        )
+                    })
+                    .collect();
+                events.push(format!(
+                    "<{} {:?}>",
+                    String::from_utf8_lossy(e.name().as_ref()),
+                    attrs
+                ));
+            }
+            event => events.push(format!("{:?}", event)),
+        }
+    }
+    events
+}
+
+#[test]
+fn test_ovf_compact_layout() {
+    let mut config = create_test_config();
+    config.display_name = "Test <&> \"VM\"".to_string();
+    let disks = create_test_disks();
+
+    let pretty = OvfBuilder::new(&config).build(&disks).expect("Failed to build OVF");
+    let compact = OvfBuilder::new(&config)
+        .with_layout(OvfLayout::Compact)
+        .build(&disks)
+        .expect("Failed to build OVF");
+
+    assert_ne!(pretty, compact);
+    assert!(compact.len() < pretty.len());
+    assert!(!compact.contains('\n'));
+    assert!(!compact.contains(">  <"));
+
+    // Same elements, attributes, and escaped values in both layouts
+    assert_eq!(xml_events(&pretty), xml_events(&compact));
+    assert!(compact.contains("<ovf:Name>Test &lt;&amp;&gt; &quot;VM&quot;</ovf:Name>"));
+}
+
+#[test]
+fn test_ovf_shared_bus_controller() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let vmx_path = dir.path().join("cluster.vmx");
+    std::fs::write(
+        &vmx_path,
+        r#"displayName = "ClusterNode"
+scsi0.present = "TRUE"
+scsi0:0.present = "TRUE"
+scsi0:0.fileName = "os.vmdk"
+scsi1.present = "TRUE"
+scsi1.sharedBus = "physical"
+scsi1:0.present = "TRUE"
+scsi1:0.fileName = "quorum.vmdk"
+"#,
+    )
+    .unwrap();
+    let config = ovatool_core::vmx::parse_vmx(&vmx_path).unwrap();
+    let disks: Vec<DiskInfo> = (1..=2)
+        .map(|i| DiskInfo {
+            id: format!("vmdisk{}", i),
+            file_ref: format!("file{}", i),
+            capacity_bytes: 1024 * 1024,
+            file_size_bytes: 4096,
+        })
+        .collect();
+
+    let ovf = OvfBuilder::new(&config).build(&disks).expect("Failed to build OVF");
+
+    let item_for = |element_name: &str| {
+        let pos = ovf.find(element_name).unwrap();
+        let start = ovf[..pos].rfind("<ovf:Item").unwrap();
+        let end = pos + ovf[pos..].find("</ovf:Item>").unwrap();
+        ovf[start..end].to_string()
+    };
+
+    let scsi1 = item_for("<rasd:ElementName>SCSI Controller 1</rasd:ElementName>");
+    assert!(
+        scsi1.contains(
+            "<vmw:Config ovf:required=\"false\" vmw:key=\"sharedBus\" vmw:value=\"physicalSharing\"/>"
+        ),
+        "Shared bus mode should reach the scsi1 controller item"
+    );
+
+    // scsi0 is not shared
+    let scsi0 = item_for("<rasd:ElementName>SCSI Controller 0</rasd:ElementName>");
+    assert!(!scsi0.contains("sharedBus"));
+
+    // The quorum disk hangs off the shared controller
+    let scsi1_id = scsi1
+        .split("<rasd:InstanceID>")
+        .nth(1)
+        .and_then(|rest| rest.split('<').next())
+        .unwrap()
+        .to_string();
+    let quorum = item_for("<rasd:ElementName>Hard Disk 2</rasd:ElementName>");
+    assert!(quorum.contains(&format!("<rasd:Parent>{}</rasd:Parent>", scsi1_id)));
+    let os_disk = item_for("<rasd:ElementName>Hard Disk 1</rasd:ElementName>");
+    assert!(os_disk.contains("<rasd:Parent>3</rasd:Parent>"));
+}
+
+#[test]
+fn test_ovf_virtual_system_collection() {
+    // Two VMs whose disks share a file name and disk ids
+    let web = create_test_config();
+    let mut db = create_test_config();
+    db.display_name = "Database".to_string();
+    db.networks[0].network_name = Some("Backend".to_string());
+
+    let builder = OvfCollectionBuilder::new("Web Appliance")
+        .add_system(&web, create_test_disks())
+        .add_system(&db, create_test_disks());
+    let ovf = builder.build().expect("Failed to build OVF");
+
+    assert!(ovf.contains("<ovf:VirtualSystemCollection ovf:id=\"Web_Appliance\">"));
+    assert!(ovf.contains("<ovf:Name>Web Appliance</ovf:Name>"));
+    assert_eq!(ovf.matches("<ovf:VirtualSystem ovf:id=").count(), 2);
+    assert!(ovf.contains("<ovf:VirtualSystem ovf:id=\"TestVM\">"));
+    assert!(ovf.contains("<ovf:VirtualSystem ovf:id=\"Database\">"));
+
+    // File references are namespaced per VM and don't collide
+    let mut references = parse_file_references(&ovf).unwrap();
+    assert_eq!(references, vec!["TestVM-TestVM.vmdk", "Database-TestVM.vmdk"]);
+    assert_eq!(builder.file_names().unwrap(), references);
+    references.dedup();
+    assert_eq!(references.len(), 2);
+
+    assert!(ovf.contains("ovf:diskId=\"TestVM-vmdisk1\" ovf:fileRef=\"TestVM-file1\""));
+    assert!(ovf.contains("ovf:diskId=\"Database-vmdisk1\" ovf:fileRef=\"Database-file1\""));
+    assert!(ovf.contains("<rasd:HostResource>ovf:/disk/Database-vmdisk1</rasd:HostResource>"));
+
+    // Both VMs' networks are declared, each once
+    assert_eq!(ovf.matches("<ovf:Network ovf:name=\"NAT\">").count(), 1);
+    assert_eq!(ovf.matches("<ovf:Network ovf:name=\"Backend\">").count(), 1);
+}
+
+#[test]
+fn test_ovf_virtual_system_collection_rejects_duplicate_names() {
+    let config = create_test_config();
+    let result = OvfCollectionBuilder::new("Appliance")
+        .add_system(&config, create_test_disks())
+        .add_system(&config, create_test_disks())
+        .build();
+    assert!(result.is_err());
+
+    assert!(OvfCollectionBuilder::new("Empty").build().is_err());
+}
+
+#[test]
+fn test_ovf_disk_format_uri() {
+    let config = create_test_config();
+    let disks = create_test_disks();
+
+    let stream = OvfBuilder::new(&config).build(&disks).unwrap();
+    assert!(stream.contains(
+        "ovf:format=\"http://www.vmware.com/interfaces/specifications/vmdk.html#streamOptimized\""
+    ));
+
+    let sparse = OvfBuilder::new(&config)
+        .with_disk_format(DiskFormat::Sparse)
+        .build(&disks)
+        .unwrap();
+    assert!(sparse.contains(
+        "ovf:format=\"http://www.vmware.com/interfaces/specifications/vmdk.html#sparse\""
+    ));
+    assert!(!sparse.contains("#streamOptimized"));
+}
+
+#[test]
+fn test_ovf_validate() {
+    let mut config = create_test_config();
+    config.firmware = FirmwareType::Efi;
+    let disks = create_test_disks();
+    let builder = OvfBuilder::new(&config)
+        .with_nvram_file(Some(("TestVM.nvram".to_string(), 8684)))
+        .with_icon("logo.png", "image/png");
+    assert_eq!(builder.validate(&disks).unwrap(), Vec::<String>::new());
+
+    let collection = OvfCollectionBuilder::new("Appliance")
+        .add_system(&config, create_test_disks())
+        .build()
+        .unwrap();
+    assert_eq!(validate_ovf(&collection).unwrap(), Vec::<String>::new());
+
+    // Give the memory item the CPU's InstanceID
+    let ovf = builder.build(&disks).unwrap();
+    let broken = ovf.replacen(
+        "<rasd:InstanceID>2</rasd:InstanceID>",
+        "<rasd:InstanceID>1</rasd:InstanceID>",
+        1,
+    );
+    assert_ne!(broken, ovf);
+    let issues = validate_ovf(&broken).unwrap();
+    assert_eq!(issues, vec!["InstanceID 1 is used by more than one item"]);
+
+    // Detach the disk from its hardware item
+    let start = ovf.find("<rasd:HostResource>").unwrap();
+    let broken = ovf.replacen(&ovf[start..ovf[start..].find('\n').unwrap() + start], "", 1);
+    let issues = validate_ovf(&broken).unwrap();
+    assert_eq!(issues, vec!["Disk 'vmdisk1' is not attached by any hardware item"]);
+}
@@ -1,8 +1,10 @@
 //! Integration tests for the parallel processing pipeline.
 
 use ovatool_core::pipeline::{
-    CompressionLevel, Pipeline, PipelineConfig, PipelineProgress, ProgressTracker,
+    AdaptiveCompressor, CompressionLevel, Pipeline, PipelineConfig, PipelineProgress,
+    ProgressTracker, ADAPTIVE_SAMPLE_CHUNKS,
 };
+use ovatool_core::vmdk::compress_grain;
 
 #[test]
 fn test_pipeline_processes_chunks() {
@@ -77,6 +79,69 @@ fn test_compression_level_variants() {
     assert_eq!(CompressionLevel::Fast.to_zlib_level(), 1);
     assert_eq!(CompressionLevel::Balanced.to_zlib_level(), 6);
     assert_eq!(CompressionLevel::Max.to_zlib_level(), 9);
+    assert_eq!(CompressionLevel::Adaptive.to_zlib_level(), 9);
+}
+
+/// Pseudo-random bytes that deflate can't shrink.
+fn incompressible_chunk(seed: u64, len: usize) -> Vec<u8> {
+    let mut state = seed | 1;
+    (0..len)
+        .map(|_| {
+            // xorshift64
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect()
+}
+
+/// Feed `ADAPTIVE_SAMPLE_CHUNKS` chunks through a compressor at its current level.
+fn sample(compressor: &mut AdaptiveCompressor, chunk: impl Fn(u64) -> Vec<u8>) {
+    for i in 0..ADAPTIVE_SAMPLE_CHUNKS {
+        let data = chunk(i as u64);
+        let compressed = compress_grain(&data, compressor.effective_level()).unwrap();
+        compressor.record(data.len() as u64, compressed.len() as u64);
+    }
+}
+
+#[test]
+fn test_adaptive_compression_drops_level_for_incompressible_data() {
+    let mut compressor = AdaptiveCompressor::new(CompressionLevel::Adaptive);
+    assert_eq!(compressor.effective_level(), 9);
+    assert_eq!(compressor.remaining_samples(), ADAPTIVE_SAMPLE_CHUNKS);
+
+    sample(&mut compressor, |i| incompressible_chunk(i, 65536));
+    assert_eq!(compressor.remaining_samples(), 0);
+    assert_eq!(compressor.effective_level(), 1);
+}
+
+#[test]
+fn test_adaptive_compression_keeps_level_for_compressible_data() {
+    let mut compressor = AdaptiveCompressor::new(CompressionLevel::Adaptive);
+    sample(&mut compressor, |i| vec![i as u8; 65536]);
+    assert_eq!(compressor.effective_level(), 9);
+}
+
+#[test]
+fn test_adaptive_compression_threshold_is_configurable() {
+    // Incompressible data grows slightly, so only a threshold above 1 keeps it at level 9
+    let mut compressor = AdaptiveCompressor::with_threshold(CompressionLevel::Adaptive, 1.5);
+    sample(&mut compressor, |i| incompressible_chunk(i, 65536));
+    assert_eq!(compressor.effective_level(), 9);
+
+    // A threshold below the ratio of compressible data drops it to level 1
+    let mut compressor = AdaptiveCompressor::with_threshold(CompressionLevel::Adaptive, 0.0001);
+    sample(&mut compressor, |i| vec![i as u8; 65536]);
+    assert_eq!(compressor.effective_level(), 1);
+}
+
+#[test]
+fn test_fixed_compression_level_is_not_sampled() {
+    let mut compressor = AdaptiveCompressor::new(CompressionLevel::Max);
+    assert_eq!(compressor.remaining_samples(), 0);
+    sample(&mut compressor, |i| incompressible_chunk(i, 65536));
+    assert_eq!(compressor.effective_level(), 9);
 }
 
 #[test]
@@ -88,6 +153,30 @@ fn test_pipeline_config_accessors() {
     assert_eq!(pipeline.compression_level(), 9);
 }
 
+#[test]
+fn test_cloned_pipeline_shares_config() {
+    let config = PipelineConfig::new(4096, CompressionLevel::Fast, 2);
+    let pipeline = Pipeline::new(config);
+    let clone = pipeline.clone();
+
+    assert_eq!(clone.config().chunk_size, 4096);
+    assert_eq!(clone.compression(), CompressionLevel::Fast);
+    assert_eq!(clone.num_threads(), 2);
+
+    let chunks: Vec<Vec<u8>> = (0..8u8).map(|i| vec![i; 16]).collect();
+    let sum = |_idx: usize, data: Vec<u8>| Ok(data.iter().map(|&b| b as u32).sum::<u32>());
+    let expected: Vec<u32> = (0..8u32).map(|i| i * 16).collect();
+
+    // Both handles run work on the same pool, including at the same time
+    let (original_results, clone_results) = std::thread::scope(|scope| {
+        let original = scope.spawn(|| pipeline.process(chunks.clone(), sum));
+        let cloned = scope.spawn(|| clone.process(chunks.clone(), sum));
+        (original.join().unwrap(), cloned.join().unwrap())
+    });
+    assert_eq!(original_results.unwrap(), expected);
+    assert_eq!(clone_results.unwrap(), expected);
+}
+
 #[test]
 fn test_progress_tracking_integration() {
     let config = PipelineConfig::new(1024, CompressionLevel::Balanced, 2);
@@ -0,0 +1,641 @@
+//! Integration tests for exporting from custom disk sources.
+
+use ovatool_core::ova::compute_sha256;
+use ovatool_core::source::ZeroDiskSource;
+use ovatool_core::vmdk::{SparseVmdkReader, StreamOptimizedReader};
+use ovatool_core::vmx::{DiskConfig, FirmwareType, VmxConfig};
+use ovatool_core::{
+    export_sources, CapacityRounding, ChangedBlockMap, CompressionLevel, DiskFormat, DiskSource,
+    Error, ExportOptions, ExportPhase, ExportProgress, Result,
+};
+use std::collections::HashMap;
+use std::io::Read;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// In-memory disk image used as a stand-in for a remote backend.
+struct MemoryDiskSource {
+    data: Vec<u8>,
+}
+
+impl DiskSource for MemoryDiskSource {
+    fn size(&self) -> u64 {
+        self.data.len() as u64
+    }
+
+    fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        let start = std::cmp::min(offset as usize, self.data.len());
+        let end = std::cmp::min(start + len, self.data.len());
+        Ok(self.data[start..end].to_vec())
+    }
+}
+
+/// Disk image generated on demand, so its size isn't limited by memory.
+struct PatternDiskSource {
+    size: u64,
+}
+
+impl DiskSource for PatternDiskSource {
+    fn size(&self) -> u64 {
+        self.size
+    }
+
+    fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        let end = std::cmp::min(offset + len as u64, self.size);
+        Ok((offset..end).map(|i| (i / 4096 % 251) as u8 + 1).collect())
+    }
+}
+
+/// Create a single-disk VM configuration.
+fn create_test_config() -> VmxConfig {
+    VmxConfig {
+        display_name: "MemoryVM".to_string(),
+        guest_os: "ubuntu-64".to_string(),
+        memory_mb: 1024,
+        num_cpus: 1,
+        disks: vec![DiskConfig {
+            file_name: "memory.vmdk".to_string(),
+            controller: "scsi0".to_string(),
+            unit: 0,
+        }],
+        networks: vec![],
+        cdroms: vec![],
+        controllers: vec![],
+        firmware: FirmwareType::Bios,
+        nvram: None,
+        annotation: String::new(),
+        raw: HashMap::new(),
+    }
+}
+
+/// Extract file content from a TAR archive by filename.
+fn extract_file_from_tar(data: &[u8], filename: &str) -> Option<Vec<u8>> {
+    let mut pos = 0;
+
+    while pos + 512 <= data.len() {
+        // Check for end of archive
+        if data[pos..pos + 512].iter().all(|&b| b == 0) {
+            break;
+        }
+
+        // Extract filename
+        let name_end = data[pos..pos + 100]
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(100);
+        let name = std::str::from_utf8(&data[pos..pos + name_end]).ok()?;
+
+        // Parse size
+        let size_str = std::str::from_utf8(&data[pos + 124..pos + 135]).ok()?;
+        let size = u64::from_str_radix(size_str.trim_matches('\0').trim(), 8).ok()?;
+
+        if name == filename {
+            let content_start = pos + 512;
+            let content_end = content_start + size as usize;
+            return Some(data[content_start..content_end].to_vec());
+        }
+
+        // Move to next header
+        let content_blocks = size.div_ceil(512);
+        pos += 512 + (content_blocks * 512) as usize;
+    }
+
+    None
+}
+
+#[test]
+fn test_export_from_memory_source() {
+    // 1 MB synthetic disk with a recognizable pattern
+    let data: Vec<u8> = (0..1024 * 1024).map(|i| (i % 251) as u8).collect();
+    let source = MemoryDiskSource { data: data.clone() };
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let output_path = dir.path().join("memory.ova");
+    let options = ExportOptions {
+        chunk_size: 256 * 1024,
+        ..ExportOptions::default()
+    };
+
+    let report = export_sources(
+        &create_test_config(),
+        vec![Box::new(source)],
+        &output_path,
+        options,
+        None,
+    )
+    .expect("Export from memory source should succeed");
+    assert!(report.warnings.is_empty());
+    assert_eq!(report.phase_timings.len(), 4);
+
+    let ova_data = std::fs::read(&output_path).unwrap();
+
+    // Disk is a streamOptimized VMDK with the source's capacity
+    let vmdk = extract_file_from_tar(&ova_data, "memory.vmdk").expect("Disk should be in OVA");
+    assert_eq!(&vmdk[0..4], b"KDMV");
+    let capacity_sectors = u64::from_le_bytes(vmdk[12..20].try_into().unwrap());
+    assert_eq!(capacity_sectors * 512, data.len() as u64);
+
+    // The first grain follows the header and holds the start of the source data
+    let grain_start = 512;
+    assert_eq!(u64::from_le_bytes(vmdk[grain_start..grain_start + 8].try_into().unwrap()), 0);
+    let compressed_len =
+        u32::from_le_bytes(vmdk[grain_start + 8..grain_start + 12].try_into().unwrap()) as usize;
+    let mut decoder =
+        flate2::read::DeflateDecoder::new(&vmdk[grain_start + 12..grain_start + 12 + compressed_len]);
+    let mut decompressed = Vec::new();
+    decoder.read_to_end(&mut decompressed).unwrap();
+    assert_eq!(&decompressed[..], &data[..decompressed.len()]);
+
+    let ovf = extract_file_from_tar(&ova_data, "MemoryVM.ovf").expect("OVF should be in OVA");
+    let ovf = String::from_utf8(ovf).unwrap();
+    assert!(ovf.contains("ovf:capacity=\"1048576\""));
+}
+
+#[test]
+fn test_export_all_zero_disk_is_small() {
+    // The source doesn't report its zeros as unallocated, so they are read
+    let source = MemoryDiskSource {
+        data: vec![0u8; 16 * 1024 * 1024],
+    };
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let output_path = dir.path().join("zeros.ova");
+    let options = ExportOptions {
+        chunk_size: 1024 * 1024,
+        ..ExportOptions::default()
+    };
+    let report = export_sources(
+        &create_test_config(),
+        vec![Box::new(source)],
+        &output_path,
+        options,
+        None,
+    )
+    .expect("Export should succeed");
+    assert_eq!(report.disks[0].bytes_read, 16 * 1024 * 1024);
+
+    let ova_data = std::fs::read(&output_path).unwrap();
+    let vmdk = extract_file_from_tar(&ova_data, "memory.vmdk").expect("Disk should be in OVA");
+    assert!(vmdk.len() < 4 * 1024, "{} bytes", vmdk.len());
+    assert_eq!(StreamOptimizedReader::from_bytes(&vmdk).unwrap().grain_count(), 0);
+}
+
+#[test]
+fn test_capacity_rounding_policies() {
+    const GIB: u64 = 1024 * 1024 * 1024;
+    let capacity = 10 * GIB + 123;
+
+    for (rounding, expected) in [
+        (CapacityRounding::None, capacity),
+        (CapacityRounding::Mb, 10 * GIB + 1024 * 1024),
+        (CapacityRounding::Gb, 11 * GIB),
+    ] {
+        assert_eq!(rounding.round(capacity), expected);
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let output_path = dir.path().join("rounded.ova");
+        let options = ExportOptions {
+            capacity_rounding: rounding,
+            ..ExportOptions::default()
+        };
+        let report = export_sources(
+            &create_test_config(),
+            vec![Box::new(ZeroDiskSource::new(capacity))],
+            &output_path,
+            options,
+            None,
+        )
+        .expect("Export should succeed");
+        assert_eq!(report.disks[0].capacity_bytes, expected, "{:?}", rounding);
+
+        let ova_data = std::fs::read(&output_path).unwrap();
+        let ovf = extract_file_from_tar(&ova_data, "MemoryVM.ovf").unwrap();
+        let ovf = String::from_utf8(ovf).unwrap();
+        assert!(ovf.contains(&format!("ovf:capacity=\"{}\"", expected)), "{:?}", rounding);
+
+        if rounding != CapacityRounding::None {
+            let vmdk = extract_file_from_tar(&ova_data, "memory.vmdk").unwrap();
+            let reader = StreamOptimizedReader::from_bytes(&vmdk).unwrap();
+            assert_eq!(reader.capacity(), expected);
+        }
+    }
+}
+
+/// Collect the LBAs of all grain markers in a streamOptimized VMDK.
+fn grain_lbas(vmdk: &[u8]) -> Vec<u64> {
+    let mut lbas = Vec::new();
+    let mut pos = 512; // Grains start right after the header
+
+    while pos + 12 <= vmdk.len() {
+        let lba = u64::from_le_bytes(vmdk[pos..pos + 8].try_into().unwrap());
+        let size = u32::from_le_bytes(vmdk[pos + 8..pos + 12].try_into().unwrap()) as usize;
+        if size == 0 {
+            // Metadata marker: grains are done
+            break;
+        }
+        lbas.push(lba);
+        pos += (12 + size).div_ceil(512) * 512;
+    }
+
+    lbas
+}
+
+#[test]
+fn test_export_changed_blocks_only() {
+    const GRAIN: usize = 64 * 1024;
+
+    // Six grains of non-zero data, except grain 3 which was zeroed
+    let mut data = vec![0x11u8; 6 * GRAIN];
+    data[3 * GRAIN..4 * GRAIN].fill(0);
+    let source = MemoryDiskSource { data };
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let output_path = dir.path().join("delta.ova");
+    let mut options = ExportOptions {
+        end_to_end_verify: true,
+        ..ExportOptions::default()
+    };
+    // Grain 1, and a few bytes of grain 3, all within one default-size chunk
+    let mut changed = ChangedBlockMap::from_grain_ranges(GRAIN as u64, std::iter::once(1..2));
+    changed.add(3 * GRAIN as u64 + 100, 10);
+    options
+        .changed_blocks_only
+        .insert("memory.vmdk".to_string(), changed);
+
+    let report = export_sources(
+        &create_test_config(),
+        vec![Box::new(source)],
+        &output_path,
+        options,
+        None,
+    )
+    .expect("Incremental export should succeed");
+    assert!(
+        report.warnings.iter().any(|w| w.contains("'memory.vmdk' holds only its changed blocks")),
+        "{:?}",
+        report.warnings
+    );
+
+    let ova_data = std::fs::read(&output_path).unwrap();
+    let vmdk = extract_file_from_tar(&ova_data, "memory.vmdk").unwrap();
+
+    // Only grains 1 and 3 are allocated, grain 3 as explicit zeros; capacity
+    // still covers the whole disk
+    let grain_sectors = (GRAIN / 512) as u64;
+    assert_eq!(grain_lbas(&vmdk), vec![grain_sectors, 3 * grain_sectors]);
+    let capacity_sectors = u64::from_le_bytes(vmdk[12..20].try_into().unwrap());
+    assert_eq!(capacity_sectors, 6 * grain_sectors);
+}
+
+#[test]
+fn test_export_round_trips_through_sparse_reader() {
+    const GRAIN: usize = 64 * 1024;
+
+    // Five chunks of patterned data with a zeroed grain, ending on a partial grain
+    let mut data: Vec<u8> = (0..5 * 256 * 1024 + 1000).map(|i| (i % 251) as u8).collect();
+    data[3 * GRAIN..4 * GRAIN].fill(0);
+    let source = MemoryDiskSource { data: data.clone() };
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let output_path = dir.path().join("roundtrip.ova");
+    let options = ExportOptions {
+        chunk_size: 256 * 1024,
+        ..ExportOptions::default()
+    };
+    export_sources(
+        &create_test_config(),
+        vec![Box::new(source)],
+        &output_path,
+        options,
+        None,
+    )
+    .expect("Export should succeed");
+
+    let ova_data = std::fs::read(&output_path).unwrap();
+    let vmdk = extract_file_from_tar(&ova_data, "memory.vmdk").unwrap();
+
+    // One grain per 64 KB, at consecutive grain LBAs, skipping the zeroed one
+    let grain_sectors = (GRAIN / 512) as u64;
+    let expected_lbas: Vec<u64> = (0..data.len().div_ceil(GRAIN) as u64)
+        .filter(|&grain| grain != 3)
+        .map(|grain| grain * grain_sectors)
+        .collect();
+    let mut lbas = grain_lbas(&vmdk);
+    lbas.sort_unstable();
+    assert_eq!(lbas, expected_lbas);
+
+    // The capacity is whole sectors, so the trailing partial sector is dropped
+    let vmdk_path = dir.path().join("roundtrip.vmdk");
+    std::fs::write(&vmdk_path, &vmdk).unwrap();
+    let reader = SparseVmdkReader::open(&vmdk_path).expect("Output should open as sparse");
+    let capacity = data.len() / 512 * 512;
+    assert_eq!(reader.capacity(), capacity as u64);
+    assert!(reader.read_at(0, capacity).unwrap() == data[..capacity]);
+}
+
+#[test]
+fn test_export_streams_disk_larger_than_buffers() {
+    const MIB: u64 = 1024 * 1024;
+    let source = PatternDiskSource { size: 128 * MIB };
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let output_path = dir.path().join("large.ova");
+    let options = ExportOptions {
+        compression: CompressionLevel::Fast,
+        chunk_size: MIB as usize,
+        num_threads: 2,
+        ..ExportOptions::default()
+    };
+    let report = export_sources(
+        &create_test_config(),
+        vec![Box::new(source)],
+        &output_path,
+        options,
+        None,
+    )
+    .expect("Export should succeed");
+
+    // Two chunks per thread are in flight, plus their compressed grains
+    assert_eq!(report.disks[0].bytes_read, 128 * MIB);
+    assert!(report.peak_memory_bytes > 0);
+    assert!(report.peak_memory_bytes <= 8 * MIB, "{}", report.peak_memory_bytes);
+
+    let ova_data = std::fs::read(&output_path).unwrap();
+    let vmdk = extract_file_from_tar(&ova_data, "memory.vmdk").unwrap();
+    assert_eq!(vmdk.len() as u64, report.disks[0].compressed_bytes);
+    let reader = StreamOptimizedReader::from_bytes(&vmdk).unwrap();
+    assert_eq!(reader.grain_count() as u64, 128 * MIB / (64 * 1024));
+}
+
+#[test]
+fn test_export_end_to_end_verify() {
+    // Patterned data with a zeroed stretch, ending on a partial grain
+    let mut data: Vec<u8> = (0..3 * 256 * 1024 + 1000).map(|i| (i % 251) as u8).collect();
+    data[100_000..400_000].fill(0);
+    let source = MemoryDiskSource { data: data.clone() };
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let output_path = dir.path().join("verified.ova");
+    let options = ExportOptions {
+        chunk_size: 256 * 1024,
+        end_to_end_verify: true,
+        ..ExportOptions::default()
+    };
+    let report = export_sources(
+        &create_test_config(),
+        vec![Box::new(source)],
+        &output_path,
+        options,
+        None,
+    )
+    .expect("Verified export should succeed");
+
+    let expected = compute_sha256(&data);
+    assert_eq!(report.disks[0].logical_sha256.as_deref(), Some(expected.as_str()));
+}
+
+#[test]
+fn test_export_sources_count_mismatch() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let output_path = dir.path().join("mismatch.ova");
+
+    let result = export_sources(
+        &create_test_config(),
+        vec![],
+        &output_path,
+        ExportOptions::default(),
+        None,
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_progress_interval_throttles_callbacks() {
+    const GRAIN: usize = 4096;
+    const GRAINS: usize = 2048;
+
+    let source = MemoryDiskSource {
+        data: vec![0x22u8; GRAIN * GRAINS],
+    };
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let output_path = dir.path().join("throttled.ova");
+    let options = ExportOptions {
+        chunk_size: GRAIN,
+        progress_interval: Duration::from_millis(100),
+        ..ExportOptions::default()
+    };
+
+    let callbacks = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&callbacks);
+
+    export_sources(
+        &create_test_config(),
+        vec![Box::new(source)],
+        &output_path,
+        options,
+        Some(Box::new(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        })),
+    )
+    .expect("Export should succeed");
+
+    let count = callbacks.load(Ordering::SeqCst);
+    assert!(count > 0, "Phase changes should still be reported");
+    assert!(
+        count < GRAINS / 10,
+        "Expected far fewer callbacks than grains, got {}",
+        count
+    );
+}
+
+#[test]
+fn test_max_disk_size_rejects_large_disk() {
+    let source = MemoryDiskSource {
+        data: vec![0u8; 1024 * 1024],
+    };
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let output_path = dir.path().join("too-big.ova");
+    let options = ExportOptions {
+        max_disk_bytes: Some(512 * 1024),
+        ..ExportOptions::default()
+    };
+
+    let err = export_sources(
+        &create_test_config(),
+        vec![Box::new(source)],
+        &output_path,
+        options,
+        None,
+    )
+    .expect_err("Disk over the limit should be rejected");
+
+    assert!(matches!(err, Error::Validation { .. }));
+    assert!(err.to_string().contains("memory.vmdk"));
+    assert!(!output_path.exists(), "Nothing should be written");
+}
+
+#[test]
+fn test_max_disk_size_allows_disk_at_limit() {
+    let source = MemoryDiskSource {
+        data: vec![0u8; 1024 * 1024],
+    };
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let output_path = dir.path().join("at-limit.ova");
+    let options = ExportOptions {
+        max_disk_bytes: Some(1024 * 1024),
+        ..ExportOptions::default()
+    };
+
+    export_sources(
+        &create_test_config(),
+        vec![Box::new(source)],
+        &output_path,
+        options,
+        None,
+    )
+    .expect("Disk at the limit should be accepted");
+}
+
+#[test]
+fn test_progress_reports_uncompressed_and_compressed_totals() {
+    let data: Vec<u8> = (0..1024 * 1024).map(|i| (i % 7) as u8).collect();
+    let source = MemoryDiskSource { data };
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let output_path = dir.path().join("progress.ova");
+    let options = ExportOptions {
+        chunk_size: 64 * 1024,
+        ..ExportOptions::default()
+    };
+
+    let updates: Arc<Mutex<Vec<ExportProgress>>> = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&updates);
+
+    export_sources(
+        &create_test_config(),
+        vec![Box::new(source)],
+        &output_path,
+        options,
+        Some(Box::new(move |progress| sink.lock().unwrap().push(progress))),
+    )
+    .expect("Export should succeed");
+
+    let updates = updates.lock().unwrap();
+    let last = updates.last().unwrap();
+    assert_eq!(last.phase, ExportPhase::Complete);
+    assert_eq!(last.bytes_processed, last.bytes_total);
+    assert_eq!(last.bytes_total, 1024 * 1024);
+    assert!(last.compressed_bytes_total > 0);
+    assert_eq!(last.compressed_bytes_written, last.compressed_bytes_total);
+
+    // The compressed total starts at the uncompressed size, is estimated
+    // from the ratio so far while the disk is written, and is exact once
+    // every disk is written
+    assert_eq!(updates[0].compressed_bytes_total, last.bytes_total);
+    let finalizing = updates
+        .iter()
+        .find(|progress| progress.phase == ExportPhase::Finalizing)
+        .unwrap();
+    assert_eq!(finalizing.compressed_bytes_total, last.compressed_bytes_total);
+    let compressing: Vec<&ExportProgress> = updates
+        .iter()
+        .filter(|progress| progress.phase == ExportPhase::Compressing)
+        .collect();
+    assert!(compressing.iter().all(|progress| {
+        progress.compressed_bytes_total >= progress.compressed_bytes_written
+            && progress.compressed_bytes_total > 0
+    }));
+    assert!(compressing
+        .windows(2)
+        .all(|pair| pair[0].compressed_bytes_written <= pair[1].compressed_bytes_written));
+    let midway = compressing
+        .iter()
+        .find(|progress| progress.bytes_processed == last.bytes_total / 2)
+        .expect("Progress should be reported after each chunk");
+    assert!(midway.compressed_bytes_written > 0);
+    assert!(midway.compressed_bytes_total < last.bytes_total);
+    assert!(midway.compressed_bytes_written < last.compressed_bytes_written);
+}
+
+#[test]
+fn test_export_zero_capacity_sparse_disk() {
+    // Hosted sparse header declaring a capacity of 0 sectors
+    let mut header = vec![0u8; 1024];
+    header[0..4].copy_from_slice(b"KDMV");
+    header[4..8].copy_from_slice(&1u32.to_le_bytes());
+    header[20..28].copy_from_slice(&128u64.to_le_bytes()); // grain size
+    header[44..48].copy_from_slice(&512u32.to_le_bytes()); // GTEs per GT
+    header[56..64].copy_from_slice(&1u64.to_le_bytes()); // GD offset
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let sparse_path = dir.path().join("empty-sparse.vmdk");
+    std::fs::write(&sparse_path, &header).unwrap();
+    let source = SparseVmdkReader::open(&sparse_path).expect("Zero capacity should open");
+    assert_eq!(source.capacity(), 0);
+
+    let output_path = dir.path().join("empty.ova");
+    let report = export_sources(
+        &create_test_config(),
+        vec![Box::new(source)],
+        &output_path,
+        ExportOptions::default(),
+        None,
+    )
+    .expect("Zero-capacity export should succeed");
+    assert_eq!(report.disks[0].capacity_bytes, 0);
+    assert_eq!(report.disks[0].bytes_read, 0);
+
+    // The disk is an empty but well-formed streamOptimized VMDK
+    let ova_data = std::fs::read(&output_path).unwrap();
+    let vmdk = extract_file_from_tar(&ova_data, "memory.vmdk").expect("Disk should be in OVA");
+    let reader = StreamOptimizedReader::from_bytes(&vmdk).expect("VMDK should be readable");
+    assert_eq!(reader.capacity(), 0);
+    assert_eq!(reader.grain_count(), 0);
+
+    let ovf = extract_file_from_tar(&ova_data, "MemoryVM.ovf").unwrap();
+    assert!(String::from_utf8(ovf).unwrap().contains("ovf:capacity=\"0\""));
+}
+
+#[test]
+fn test_export_sparse_disk_format() {
+    const GRAIN: usize = 64 * 1024;
+
+    // Data in grains 0 and 2, zeros elsewhere
+    let mut data = vec![0u8; 4 * GRAIN];
+    data[..GRAIN].fill(0x33);
+    data[2 * GRAIN + 100] = 0x44;
+    let source = MemoryDiskSource { data: data.clone() };
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let output_path = dir.path().join("sparse.ova");
+    let options = ExportOptions {
+        disk_format: DiskFormat::Sparse,
+        ..ExportOptions::default()
+    };
+
+    export_sources(
+        &create_test_config(),
+        vec![Box::new(source)],
+        &output_path,
+        options,
+        None,
+    )
+    .expect("Sparse export should succeed");
+
+    let ova_data = std::fs::read(&output_path).unwrap();
+    let ovf = extract_file_from_tar(&ova_data, "MemoryVM.ovf").unwrap();
+    assert!(String::from_utf8(ovf).unwrap().contains(DiskFormat::Sparse.format_uri()));
+
+    // The disk really is a hosted sparse VMDK holding the source data
+    let vmdk = extract_file_from_tar(&ova_data, "memory.vmdk").unwrap();
+    assert!(StreamOptimizedReader::from_bytes(&vmdk).is_err());
+    let vmdk_path = dir.path().join("memory.vmdk");
+    std::fs::write(&vmdk_path, &vmdk).unwrap();
+    let reader = SparseVmdkReader::open(&vmdk_path).unwrap();
+    assert_eq!(reader.capacity(), data.len() as u64);
+    assert_eq!(reader.read_at(0, data.len()).unwrap(), data);
+}
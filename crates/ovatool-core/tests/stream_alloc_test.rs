@@ -0,0 +1,71 @@
+//! Allocation counts for the streamOptimized VMDK writer.
+//!
+//! Kept in its own test binary because it installs a counting global
+//! allocator. Only allocations made on the counting thread are tallied, so
+//! the test harness doesn't skew the numbers.
+
+use ovatool_core::vmdk::stream::{
+    compress_grain, StreamVmdkWriter, DEFAULT_GRAIN_SIZE, GT_ENTRIES_PER_GT, SECTOR_SIZE,
+};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::io::Cursor;
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Number of allocations made on this thread so far.
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+#[test]
+fn test_finish_allocations_do_not_scale_with_grain_tables() {
+    // 1000 grain tables' worth of capacity with only two grains written
+    const GRAIN_TABLES: u64 = 1000;
+    let grain_bytes = DEFAULT_GRAIN_SIZE * SECTOR_SIZE;
+    let capacity = GRAIN_TABLES * u64::from(GT_ENTRIES_PER_GT) * grain_bytes;
+
+    // Pre-sized so writes into the buffer never reallocate
+    let buffer = Cursor::new(Vec::with_capacity(1024 * 1024));
+    let mut writer = StreamVmdkWriter::new(buffer, capacity).unwrap();
+    let compressed = compress_grain(&vec![0x5Au8; grain_bytes as usize], 1).unwrap();
+    writer.write_grain(0, &compressed).unwrap();
+    writer
+        .write_grain((capacity / SECTOR_SIZE) - DEFAULT_GRAIN_SIZE, &compressed)
+        .unwrap();
+
+    let before = allocations();
+    let vmdk = writer.finish().unwrap().into_inner();
+    let during_finish = allocations() - before;
+
+    assert!(
+        during_finish < 50,
+        "finish() made {} allocations for {} grain tables",
+        during_finish,
+        GRAIN_TABLES
+    );
+    assert_eq!(&vmdk[0..4], b"KDMV");
+}
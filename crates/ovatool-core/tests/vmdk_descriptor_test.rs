@@ -1,6 +1,6 @@
 //! Integration tests for VMDK descriptor parsing.
 
-use ovatool_core::vmdk::descriptor::{parse_descriptor, ExtentType};
+use ovatool_core::vmdk::descriptor::{parse_descriptor, CreateType, ExtentAccess, ExtentType};
 
 const SPLIT_SPARSE_DESCRIPTOR: &str = r#"
 # Disk DescriptorFile
@@ -45,7 +45,7 @@ ddb.adapterType = "lsilogic"
 fn test_parse_create_type() {
     let descriptor =
         parse_descriptor(MONOLITHIC_FLAT_DESCRIPTOR).expect("Failed to parse descriptor");
-    assert_eq!(descriptor.create_type, "monolithicFlat");
+    assert_eq!(descriptor.create_type, CreateType::MonolithicFlat);
 }
 
 #[test]
@@ -127,11 +127,57 @@ fn test_parse_hw_version() {
 
 // Tests for split sparse (twoGbMaxExtentSparse) VMDK descriptors
 
+#[test]
+fn test_parse_sparse_extent_without_offset() {
+    // CRLF line endings and trailing blanks after the filename
+    let content = "# Disk DescriptorFile\r\nversion=1\r\ncreateType=\"monolithicSparse\"\r\n\r\n\
+                   RW 12345 SPARSE \"disk.vmdk\" \t\r\n";
+    let descriptor = parse_descriptor(content).expect("Failed to parse descriptor");
+
+    assert_eq!(descriptor.extents.len(), 1);
+    let extent = &descriptor.extents[0];
+    assert_eq!(extent.size_sectors, 12345);
+    assert_eq!(extent.extent_type, ExtentType::Sparse);
+    assert_eq!(extent.filename, "disk.vmdk");
+    assert_eq!(extent.offset, 0);
+}
+
+#[test]
+fn test_create_type_parse() {
+    for (value, expected) in [
+        ("monolithicFlat", CreateType::MonolithicFlat),
+        ("monolithicSparse", CreateType::MonolithicSparse),
+        ("twoGbMaxExtentSparse", CreateType::TwoGbMaxExtentSparse),
+        ("twoGbMaxExtentFlat", CreateType::TwoGbMaxExtentFlat),
+        ("streamOptimized", CreateType::StreamOptimized),
+        ("vmfs", CreateType::Vmfs),
+        ("vmfsThin", CreateType::VmfsThin),
+        ("vmfsPreallocated", CreateType::VmfsPreallocated),
+        ("vmfsEagerZeroedThick", CreateType::VmfsEagerZeroedThick),
+        ("vmfsSparse", CreateType::VmfsSparse),
+        ("vmfsRaw", CreateType::VmfsRaw),
+        ("vmfsRawDeviceMap", CreateType::VmfsRawDeviceMap),
+        ("vmfsPassthroughRawDeviceMap", CreateType::VmfsPassthroughRawDeviceMap),
+        ("seSparse", CreateType::SeSparse),
+    ] {
+        assert_eq!(CreateType::parse(value), expected, "{}", value);
+        assert_eq!(expected.as_str(), value);
+        assert_eq!(expected.is_vmfs(), value.starts_with("vmfs"), "{}", value);
+    }
+
+    // Case doesn't matter, and anything else is kept as written
+    assert_eq!(CreateType::parse("STREAMOPTIMIZED"), CreateType::StreamOptimized);
+    let custom = CreateType::parse("fullDevice");
+    assert_eq!(custom, CreateType::Unknown("fullDevice".to_string()));
+    assert_eq!(custom.to_string(), "fullDevice");
+    assert!(!custom.is_vmfs());
+}
+
 #[test]
 fn test_parse_split_sparse_create_type() {
     let descriptor =
         parse_descriptor(SPLIT_SPARSE_DESCRIPTOR).expect("Failed to parse descriptor");
-    assert_eq!(descriptor.create_type, "twoGbMaxExtentSparse");
+    assert_eq!(descriptor.create_type, CreateType::TwoGbMaxExtentSparse);
 }
 
 #[test]
@@ -193,3 +239,144 @@ fn test_split_sparse_all_extents_are_sparse() {
         assert_eq!(extent.extent_type, ExtentType::Sparse);
     }
 }
+
+#[test]
+fn test_parse_descriptor_with_bom() {
+    let content = format!("\u{FEFF}{}", MONOLITHIC_FLAT_DESCRIPTOR.trim_start());
+    let desc = parse_descriptor(&content).expect("Failed to parse BOM-prefixed descriptor");
+
+    assert_eq!(desc.create_type, CreateType::MonolithicFlat);
+    assert_eq!(desc.extents.len(), 1);
+    assert_eq!(desc.extents[0].extent_type, ExtentType::Flat);
+    assert_eq!(desc.extents[0].filename, "TestVM-flat.vmdk");
+}
+
+#[test]
+fn test_parse_descriptor_with_bom_before_key() {
+    // BOM directly in front of the first key must not corrupt the key name
+    let content = "\u{FEFF}createType=\"monolithicSparse\"\nRW 2048 SPARSE \"disk.vmdk\"\n";
+    let desc = parse_descriptor(content).expect("Failed to parse descriptor");
+
+    assert_eq!(desc.create_type, CreateType::MonolithicSparse);
+    assert_eq!(desc.extents.len(), 1);
+    assert_eq!(desc.extents[0].size_sectors, 2048);
+}
+
+#[test]
+fn test_parse_descriptor_with_leading_whitespace() {
+    let content = format!("\n\n   \t\n  {}", MONOLITHIC_FLAT_DESCRIPTOR.trim_start());
+    let desc = parse_descriptor(&content).expect("Failed to parse descriptor");
+
+    assert_eq!(desc.create_type, CreateType::MonolithicFlat);
+    assert_eq!(desc.cid, 0xfffffffe);
+    assert_eq!(desc.extents.len(), 1);
+}
+
+/// Build a single-extent VMFS descriptor with the given create type.
+fn vmfs_descriptor(create_type: &str) -> String {
+    format!(
+        "# Disk DescriptorFile\nversion=1\nCID=fffffffe\nparentCID=ffffffff\ncreateType=\"{}\"\n\nRW 41943040 VMFS \"disk-flat.vmdk\"\n",
+        create_type
+    )
+}
+
+#[test]
+fn test_parse_vmfs_preallocated() {
+    let desc = parse_descriptor(&vmfs_descriptor("vmfsPreallocated"))
+        .expect("Failed to parse descriptor");
+
+    assert_eq!(desc.create_type, CreateType::VmfsPreallocated);
+    assert_eq!(desc.extents[0].extent_type, ExtentType::Vmfs);
+    assert_eq!(desc.disk_size_bytes(), 20 * 1024 * 1024 * 1024);
+
+    let guidance = desc.vmfs_clone_guidance().expect("VMFS disks need guidance");
+    assert!(guidance.contains("'vmfsPreallocated'"));
+    assert!(guidance.contains("lazy-zeroed"));
+    assert!(guidance.contains("clone it to a hosted format"));
+}
+
+#[test]
+fn test_parse_vmfs_eager_zeroed_thick() {
+    let desc = parse_descriptor(&vmfs_descriptor("vmfsEagerZeroedThick"))
+        .expect("Failed to parse descriptor");
+
+    assert_eq!(desc.extents[0].extent_type, ExtentType::Vmfs);
+
+    let guidance = desc.vmfs_clone_guidance().expect("VMFS disks need guidance");
+    assert!(guidance.contains("'vmfsEagerZeroedThick'"));
+    assert!(guidance.contains("eager-zeroed"));
+    assert!(guidance.contains("clone it to a hosted format"));
+
+    // The two thick variants are told apart
+    let lazy = parse_descriptor(&vmfs_descriptor("vmfsPreallocated")).unwrap();
+    assert_ne!(lazy.vmfs_clone_guidance(), Some(guidance));
+}
+
+#[test]
+fn test_hosted_descriptor_has_no_vmfs_guidance() {
+    let desc = parse_descriptor(MONOLITHIC_FLAT_DESCRIPTOR).unwrap();
+    assert_eq!(desc.vmfs_clone_guidance(), None);
+}
+
+#[test]
+fn test_malformed_cid_tolerated_on_base_disk() {
+    let content = MONOLITHIC_FLAT_DESCRIPTOR.replace("CID=fffffffe", "CID=fffffffe-copy");
+    let descriptor = parse_descriptor(&content).expect("Base disk should still parse");
+
+    assert_eq!(descriptor.cid, 0);
+    assert_eq!(descriptor.parent_cid, 0xffffffff);
+    assert!(!descriptor.is_delta());
+    assert_eq!(descriptor.warnings.len(), 1);
+    assert!(descriptor.warnings[0].contains("fffffffe-copy"), "{:?}", descriptor.warnings);
+}
+
+#[test]
+fn test_malformed_cid_rejected_on_delta_disk() {
+    let content = MONOLITHIC_FLAT_DESCRIPTOR
+        .replace("CID=fffffffe", "CID=zz12")
+        .replace("parentCID=ffffffff", "parentCID=1a2b3c4d");
+    let err = parse_descriptor(&content).unwrap_err();
+    assert!(err.to_string().contains("invalid CID: zz12"), "{}", err);
+}
+
+#[test]
+fn test_parse_read_only_extent() {
+    let content = MONOLITHIC_FLAT_DESCRIPTOR.replace("RW 838860800", "RDONLY 838860800");
+    let descriptor = parse_descriptor(&content).unwrap();
+
+    assert_eq!(descriptor.extents[0].access, "RDONLY");
+    assert_eq!(descriptor.extents[0].access_mode(), ExtentAccess::ReadOnly);
+    assert!(descriptor.extents[0].is_read_only());
+    assert_eq!(descriptor.access_mode(), ExtentAccess::ReadOnly);
+    assert!(descriptor.deletable);
+
+    let writable = parse_descriptor(MONOLITHIC_FLAT_DESCRIPTOR).unwrap();
+    assert!(writable.deletable);
+    assert_eq!(writable.access_mode(), ExtentAccess::ReadWrite);
+}
+
+#[test]
+fn test_parse_extent_access_modes() {
+    assert_eq!(ExtentAccess::parse("rw"), Some(ExtentAccess::ReadWrite));
+    assert_eq!(ExtentAccess::parse("NOACCESS"), Some(ExtentAccess::NoAccess));
+    assert_eq!(ExtentAccess::parse("RO"), None);
+
+    // The disk reports its most restrictive extent
+    let content = MONOLITHIC_FLAT_DESCRIPTOR.replace(
+        "RW 838860800 FLAT \"TestVM-flat.vmdk\" 0",
+        "RW 838860800 FLAT \"TestVM-flat.vmdk\" 0\nNOACCESS 2048 FLAT \"TestVM-2.vmdk\" 0\n\
+         RDONLY 2048 FLAT \"TestVM-3.vmdk\" 0",
+    );
+    let descriptor = parse_descriptor(&content).unwrap();
+    assert_eq!(descriptor.extents.len(), 3);
+    assert_eq!(descriptor.access_mode(), ExtentAccess::NoAccess);
+}
+
+#[test]
+fn test_parse_not_deletable() {
+    let content = format!("{}ddb.deletable = \"false\"\n", MONOLITHIC_FLAT_DESCRIPTOR);
+    let descriptor = parse_descriptor(&content).unwrap();
+
+    assert!(!descriptor.deletable);
+    assert_eq!(descriptor.access_mode(), ExtentAccess::ReadWrite);
+}
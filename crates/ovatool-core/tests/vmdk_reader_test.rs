@@ -54,8 +54,8 @@ fn test_reader_last_chunk_size() {
     assert_eq!(chunks.len(), 5, "Expected 5 chunks for 1MB+100 bytes file");
 
     // First 4 chunks should be 256KB
-    for i in 0..4 {
-        assert_eq!(chunks[i].len(), CHUNK_256KB, "Chunk {} should be 256KB", i);
+    for (i, chunk) in chunks.iter().take(4).enumerate() {
+        assert_eq!(chunk.len(), CHUNK_256KB, "Chunk {} should be 256KB", i);
     }
 
     // Last chunk should be 100 bytes
@@ -1,8 +1,8 @@
 //! Integration tests for StreamOptimized VMDK writer.
 
 use ovatool_core::vmdk::stream::{
-    compress_grain, SparseExtentHeader, StreamVmdkWriter, DEFAULT_GRAIN_SIZE, SECTOR_SIZE,
-    VMDK_MAGIC,
+    compress_grain, compress_grains, is_zero_grain, is_zero_scalar, CompressionAlgorithm, SparseExtentHeader,
+    StreamVmdkWriter, DEFAULT_GRAIN_SIZE, GT_ENTRIES_PER_GT, SECTOR_SIZE, VMDK_MAGIC,
 };
 use std::io::Cursor;
 
@@ -172,3 +172,193 @@ fn test_compress_grain_random_data() {
         "Decompressed data should match original"
     );
 }
+
+#[test]
+fn test_compress_grains_matches_sequential() {
+    // Grains with different content and sizes so reordering would show
+    let grains: Vec<Vec<u8>> = (0..32u32)
+        .map(|i| {
+            (0..(4096 + i as usize * 97))
+                .map(|j| ((j as u32 * 31 + i * 7) % 251) as u8)
+                .collect()
+        })
+        .collect();
+    let grain_refs: Vec<&[u8]> = grains.iter().map(|g| g.as_slice()).collect();
+
+    let batch = compress_grains(&grain_refs, 6, CompressionAlgorithm::Deflate)
+        .expect("Batch compression failed");
+
+    let sequential: Vec<Vec<u8>> = grains
+        .iter()
+        .map(|g| compress_grain(g, 6).unwrap())
+        .collect();
+    assert_eq!(batch, sequential);
+    assert!(compress_grains(&[], 6, CompressionAlgorithm::default()).unwrap().is_empty());
+}
+
+#[test]
+fn test_is_zero_grain_aligned_and_unaligned() {
+    const GRAIN: usize = 64 * 1024;
+    let buffer = vec![0u8; GRAIN + 64];
+
+    // Every starting offset within a block, with lengths hitting every remainder
+    for start in 0..16 {
+        for len in [0, 1, 7, 63, 64, 65, 4095, GRAIN] {
+            let slice = &buffer[start..start + len];
+            assert!(is_zero_grain(slice), "start {} len {}", start, len);
+            assert_eq!(is_zero_grain(slice), is_zero_scalar(slice));
+        }
+    }
+}
+
+#[test]
+fn test_is_zero_grain_detects_single_nonzero_byte() {
+    const GRAIN: usize = 64 * 1024;
+
+    for start in [0, 3] {
+        let mut buffer = vec![0u8; GRAIN + start];
+        let grain_positions = (0..GRAIN).step_by(509).chain([0, 63, 64, GRAIN - 1]);
+
+        for pos in grain_positions {
+            buffer[start + pos] = 0x01;
+            let slice = &buffer[start..];
+            assert!(!is_zero_grain(slice), "missed byte at {} (start {})", pos, start);
+            assert!(!is_zero_scalar(slice));
+            buffer[start + pos] = 0;
+        }
+    }
+}
+
+#[test]
+fn test_verify_reports_grain_counts() {
+    use ovatool_core::vmdk::verify_stream_vmdk;
+
+    let grain_bytes = (DEFAULT_GRAIN_SIZE * SECTOR_SIZE) as usize;
+    let capacity = 16 * grain_bytes as u64;
+
+    let file = tempfile::NamedTempFile::new().unwrap();
+    let mut writer =
+        StreamVmdkWriter::new(file.reopen().unwrap(), capacity).expect("Failed to create writer");
+    for grain in [0u64, 3, 15] {
+        let data = vec![grain as u8 + 1; grain_bytes];
+        let compressed = compress_grain(&data, 6).unwrap();
+        writer
+            .write_grain(grain * DEFAULT_GRAIN_SIZE, &compressed)
+            .expect("Failed to write grain");
+    }
+    writer.finish().expect("Failed to finish writer");
+
+    let report = verify_stream_vmdk(file.path()).expect("VMDK should verify");
+    assert_eq!(report.capacity_bytes, capacity);
+    assert_eq!(report.grain_size_bytes, grain_bytes as u64);
+    assert_eq!(report.total_grains, 16);
+    assert_eq!(report.allocated_grains, 3);
+    assert_eq!(report.zero_grains, 13);
+}
+
+#[test]
+fn test_stream_reader_round_trip() {
+    use ovatool_core::vmdk::StreamOptimizedReader;
+
+    let grain_bytes = (DEFAULT_GRAIN_SIZE * SECTOR_SIZE) as usize;
+    let originals: Vec<(u64, Vec<u8>)> = (0..5u64)
+        .map(|grain| {
+            let data: Vec<u8> = (0..grain_bytes)
+                .map(|i| ((i as u64 * (grain + 3)) % 251) as u8)
+                .collect();
+            (grain * 2 * DEFAULT_GRAIN_SIZE, data)
+        })
+        .collect();
+
+    let buffer = Cursor::new(Vec::new());
+    let mut writer =
+        StreamVmdkWriter::new(buffer, 10 * grain_bytes as u64).expect("Failed to create writer");
+    for (lba, data) in &originals {
+        let compressed = compress_grain(data, 6).unwrap();
+        writer.write_grain(*lba, &compressed).expect("Failed to write grain");
+    }
+    let vmdk = writer.finish().expect("Failed to finish writer").into_inner();
+
+    let reader = StreamOptimizedReader::from_bytes(&vmdk).expect("Failed to open VMDK");
+    assert_eq!(reader.capacity(), 10 * grain_bytes as u64);
+    assert_eq!(reader.grain_count(), originals.len());
+
+    let grains: Vec<(u64, Vec<u8>)> = reader
+        .grains()
+        .map(|grain| {
+            let grain = grain.expect("Grain should decompress");
+            (grain.lba, grain.data)
+        })
+        .collect();
+    assert_eq!(grains, originals);
+}
+
+#[test]
+fn test_writer_skips_empty_grain_tables() {
+    use ovatool_core::vmdk::StreamOptimizedReader;
+
+    // Three grain tables' worth of grains; only the first and last are used
+    let grains_per_gt = u64::from(GT_ENTRIES_PER_GT);
+    let grain_bytes = DEFAULT_GRAIN_SIZE * SECTOR_SIZE;
+    let capacity = 3 * grains_per_gt * grain_bytes;
+    let used_grains = [0u64, 5, 2 * grains_per_gt + 7];
+
+    let mut writer = StreamVmdkWriter::new(Cursor::new(Vec::new()), capacity).unwrap();
+    for &grain in &used_grains {
+        let data = vec![grain as u8 + 1; grain_bytes as usize];
+        let compressed = compress_grain(&data, 1).unwrap();
+        writer.write_grain(grain * DEFAULT_GRAIN_SIZE, &compressed).unwrap();
+    }
+    let vmdk = writer.finish().unwrap().into_inner();
+
+    // The footer's grain directory points at tables 0 and 2 only
+    let footer = &vmdk[vmdk.len() - 1024..vmdk.len() - 512];
+    let gd_offset = u64::from_le_bytes(footer[56..64].try_into().unwrap()) * SECTOR_SIZE;
+    let gd: Vec<u32> = vmdk[gd_offset as usize..gd_offset as usize + 12]
+        .chunks_exact(4)
+        .map(|entry| u32::from_le_bytes(entry.try_into().unwrap()))
+        .collect();
+    assert_ne!(gd[0], 0);
+    assert_eq!(gd[1], 0);
+    assert_ne!(gd[2], 0);
+
+    // Grain table 2 maps its grain to the right place
+    let gt2 = gd[2] as usize * SECTOR_SIZE as usize;
+    assert_ne!(u32::from_le_bytes(vmdk[gt2 + 28..gt2 + 32].try_into().unwrap()), 0);
+
+    let reader = StreamOptimizedReader::from_bytes(&vmdk).unwrap();
+    let lbas: Vec<u64> = reader.grains().map(|grain| grain.unwrap().lba).collect();
+    let expected: Vec<u64> = used_grains.iter().map(|g| g * DEFAULT_GRAIN_SIZE).collect();
+    assert_eq!(lbas, expected);
+}
+
+#[test]
+fn test_writer_rejects_grain_beyond_capacity() {
+    let grain_bytes = DEFAULT_GRAIN_SIZE * SECTOR_SIZE;
+    let mut writer = StreamVmdkWriter::new(Cursor::new(Vec::new()), grain_bytes).unwrap();
+    let compressed = compress_grain(&[1u8; 512], 1).unwrap();
+    writer
+        .write_grain(u64::from(GT_ENTRIES_PER_GT) * DEFAULT_GRAIN_SIZE, &compressed)
+        .unwrap();
+    assert!(writer.finish().is_err());
+}
+
+#[test]
+fn test_writer_skips_all_zero_grains() {
+    let grain_bytes = DEFAULT_GRAIN_SIZE * SECTOR_SIZE;
+    let capacity = 256 * 1024 * 1024;
+    let zeros = vec![0u8; grain_bytes as usize];
+
+    let mut writer = StreamVmdkWriter::new(Cursor::new(Vec::new()), capacity).unwrap();
+    for grain in 0..capacity / grain_bytes {
+        let written = writer
+            .write_grain_if_nonzero(grain * DEFAULT_GRAIN_SIZE, &zeros, 6)
+            .unwrap();
+        assert!(!written);
+    }
+    assert!(writer.write_grain_if_nonzero(0, &[7u8; 512], 6).unwrap());
+    let vmdk = writer.finish().unwrap().into_inner();
+
+    // Header, one grain, one grain table, the directory, footer and markers
+    assert!(vmdk.len() < 8 * 1024, "{} bytes", vmdk.len());
+}
@@ -1,6 +1,12 @@
 //! Integration tests for VMX parsing.
 
-use ovatool_core::vmx::parse_vmx;
+use ovatool_core::error::Error;
+use ovatool_core::vmx::{
+    parse_vmx, write_vmx, CdromConfig, DiskConfig, FirmwareType, NetworkConfig, VmxConfig,
+    FALLBACK_DISPLAY_NAME,
+};
+use proptest::prelude::*;
+use std::collections::HashMap;
 use std::path::Path;
 
 fn fixture_path() -> &'static Path {
@@ -54,6 +60,7 @@ fn test_parse_vmx_networks() {
     assert_eq!(network.name, "ethernet0");
     assert_eq!(network.virtual_dev, Some("e1000".to_string()));
     assert_eq!(network.network_name, Some("NAT".to_string()));
+    assert!(network.start_connected, "Adapters start connected by default");
 }
 
 #[test]
@@ -65,3 +72,246 @@ fn test_parse_vmx_raw_values() {
     assert_eq!(config.raw.get("virtualHW.version"), Some(&"21".to_string()));
     assert_eq!(config.raw.get(".encoding"), Some(&"UTF-8".to_string()));
 }
+
+#[test]
+fn test_parse_vmx_ignores_host_specific_keys() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("host.vmx");
+    std::fs::write(
+        &path,
+        r#"displayName = "Host Keys"
+powerType.powerOff = "soft"
+powerType.powerOn = "hard"
+powerType.suspend = "soft"
+powerType.reset = "soft"
+autostart = "poweron"
+autostop = "softpoweroff"
+autostart.delay = "120"
+ethernet0.filter0.name = "dvfilter-generic-vmware"
+ethernet0.filter0.present = "TRUE"
+ethernetHost.present = "TRUE"
+scsiHost:0.present = "TRUE"
+scsiHost:0.fileName = "host.vmdk"
+sata0:0.backup.present = "TRUE"
+sata0:0.backup.fileName = "backup.vmdk"
+"#,
+    )
+    .unwrap();
+
+    let config = parse_vmx(&path).expect("Failed to parse VMX");
+    assert!(config.disks.is_empty(), "{:?}", config.disks);
+    assert!(config.networks.is_empty(), "{:?}", config.networks);
+    assert!(config.cdroms.is_empty());
+    assert!(config.controllers.is_empty());
+
+    // Kept verbatim for ExtraConfig passthrough
+    assert_eq!(config.raw.get("powerType.powerOff"), Some(&"soft".to_string()));
+    assert_eq!(config.raw.get("autostart"), Some(&"poweron".to_string()));
+    assert_eq!(config.raw.get("autostart.delay"), Some(&"120".to_string()));
+    assert_eq!(
+        config.raw.get("ethernet0.filter0.present"),
+        Some(&"TRUE".to_string())
+    );
+}
+
+#[test]
+fn test_parse_vmx_utf16_with_bom() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("utf16.vmx");
+    let text = "displayName = \"Café\"\r\nmemsize = \"2048\"\r\nnumvcpus = \"4\"\r\n";
+    let mut content = vec![0xFF, 0xFE];
+    content.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+    std::fs::write(&path, content).unwrap();
+
+    let config = parse_vmx(&path).expect("Failed to parse UTF-16 VMX");
+    assert_eq!(config.display_name, "Café");
+    assert_eq!(config.memory_mb, 2048);
+    assert_eq!(config.num_cpus, 4);
+
+    // A UTF-8 BOM is skipped rather than read into the first key
+    std::fs::write(&path, [b"\xEF\xBB\xBF".as_slice(), text.as_bytes()].concat()).unwrap();
+    assert_eq!(parse_vmx(&path).unwrap().display_name, "Café");
+}
+
+#[test]
+fn test_parse_vmx_blank_display_name() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("web-01.vmx");
+    std::fs::write(&path, "displayName = \" \"\nmemsize = \"512\"\n").unwrap();
+    assert_eq!(parse_vmx(&path).unwrap().display_name, "web-01");
+
+    // With no usable file stem either, the fixed fallback name is used
+    let path = dir.path().join(" .vmx");
+    std::fs::write(&path, "displayName = \"\"\n").unwrap();
+    assert_eq!(parse_vmx(&path).unwrap().display_name, FALLBACK_DISPLAY_NAME);
+}
+
+#[test]
+fn test_parse_vmx_invalid_encoding() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("latin1.vmx");
+    std::fs::write(&path, b"displayName = \"Caf\xE9\"\n").unwrap();
+
+    let err = parse_vmx(&path).expect_err("Invalid UTF-8 should be rejected");
+    assert!(matches!(err, Error::VmxParse { .. }), "{:?}", err);
+    assert!(err.to_string().contains("not valid UTF-8"), "{}", err);
+}
+
+// ============================================================================
+// Round-trip property tests
+// ============================================================================
+
+/// VMX value, including the quotes, pipes and line breaks that are written
+/// as `|XX` escapes.
+fn vmx_value() -> impl Strategy<Value = String> {
+    "[A-Za-z0-9 _.,()|\"\n-]{1,24}"
+}
+
+/// Disks on distinct controller slots, in the order the parser sorts them.
+fn disks() -> impl Strategy<Value = Vec<DiskConfig>> {
+    let slot = (prop::sample::select(vec!["ide0", "nvme0", "sata0", "scsi0"]), 0u32..16);
+    prop::collection::btree_map(slot, "[a-z0-9-]{1,12}", 0..5).prop_map(|slots| {
+        slots
+            .into_iter()
+            .map(|((controller, unit), stem)| DiskConfig {
+                file_name: format!("{}.vmdk", stem),
+                controller: controller.to_string(),
+                unit,
+            })
+            .collect()
+    })
+}
+
+/// CD/DVD drives on controllers that don't hold disks.
+fn cdroms() -> impl Strategy<Value = Vec<CdromConfig>> {
+    let slot = (prop::sample::select(vec!["ide1", "sata1"]), 0u32..2);
+    prop::collection::btree_set(slot, 0..3).prop_map(|slots| {
+        slots
+            .into_iter()
+            .map(|(controller, unit)| CdromConfig {
+                controller: controller.to_string(),
+                unit,
+            })
+            .collect()
+    })
+}
+
+/// Network adapters `ethernet0`..`ethernet9` (single digits sort numerically).
+fn networks() -> impl Strategy<Value = Vec<NetworkConfig>> {
+    let adapter = (
+        prop::option::of(prop::sample::select(vec!["e1000", "e1000e", "vmxnet3"])),
+        prop::option::of(vmx_value()),
+        any::<bool>(),
+        prop::option::of(prop::sample::select(vec!["bridged", "nat", "hostonly"])),
+        prop::option::of(any::<bool>()),
+        prop::option::of(1u32..=32),
+    );
+    prop::collection::btree_map(0u8..10, adapter, 0..4).prop_map(|adapters| {
+        adapters
+            .into_iter()
+            .map(
+                |(
+                    index,
+                    (virtual_dev, network_name, start_connected, connection_type, rss, num_queue_pairs),
+                )| {
+                    NetworkConfig {
+                        name: format!("ethernet{}", index),
+                        virtual_dev: virtual_dev.map(str::to_string),
+                        network_name,
+                        start_connected,
+                        connection_type: connection_type.map(str::to_string),
+                        rss,
+                        num_queue_pairs,
+                    }
+                },
+            )
+            .collect()
+    })
+}
+
+fn vmx_config() -> impl Strategy<Value = VmxConfig> {
+    (
+        // Blank display names are replaced by the file name when parsed
+        vmx_value().prop_filter("blank display name", |name| !name.trim().is_empty()),
+        "[a-z0-9-]{1,16}",
+        1u32..1_048_576,
+        1u32..256,
+        disks(),
+        networks(),
+        cdroms(),
+        prop_oneof![Just(FirmwareType::Bios), Just(FirmwareType::Efi)],
+        "[a-zA-Z0-9 |\"\n]{0,32}",
+    )
+        .prop_map(
+            |(
+                display_name,
+                guest_os,
+                memory_mb,
+                num_cpus,
+                disks,
+                networks,
+                cdroms,
+                firmware,
+                annotation,
+            )| {
+                VmxConfig {
+                    display_name,
+                    guest_os,
+                    memory_mb,
+                    num_cpus,
+                    disks,
+                    networks,
+                    cdroms,
+                    controllers: vec![],
+                    firmware,
+                    nvram: None,
+                    annotation,
+                    raw: HashMap::new(),
+                }
+            },
+        )
+}
+
+proptest! {
+    #[test]
+    fn prop_write_then_parse_round_trips(config in vmx_config()) {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("roundtrip.vmx");
+
+        write_vmx(&config, &path).unwrap();
+        let parsed = parse_vmx(&path).unwrap();
+
+        prop_assert_eq!(&parsed.display_name, &config.display_name);
+        prop_assert_eq!(&parsed.guest_os, &config.guest_os);
+        prop_assert_eq!(parsed.memory_mb, config.memory_mb);
+        prop_assert_eq!(parsed.num_cpus, config.num_cpus);
+        prop_assert_eq!(&parsed.disks, &config.disks);
+        prop_assert_eq!(&parsed.networks, &config.networks);
+        prop_assert_eq!(&parsed.cdroms, &config.cdroms);
+        prop_assert_eq!(parsed.firmware, config.firmware);
+        prop_assert_eq!(&parsed.annotation, &config.annotation);
+    }
+
+    #[test]
+    fn prop_serialized_values_parse_back(
+        config in vmx_config(),
+        extra in prop::collection::hash_map(
+            "custom\\.[a-zA-Z0-9]{1,12}",
+            "(\\PC|[\"|\r\n]){0,32}",
+            0..8,
+        ),
+    ) {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("values.vmx");
+        let config = VmxConfig { raw: extra.clone(), ..config };
+
+        write_vmx(&config, &path).unwrap();
+        let parsed = parse_vmx(&path).unwrap();
+
+        for (key, value) in &extra {
+            prop_assert_eq!(parsed.raw.get(key), Some(value));
+        }
+        prop_assert_eq!(&parsed.display_name, &config.display_name);
+        prop_assert_eq!(&parsed.networks, &config.networks);
+    }
+}